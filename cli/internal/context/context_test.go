@@ -159,7 +159,7 @@ func Test_populateExternalDeps_NoTransitiveDepsWithoutLockfile(t *testing.T) {
 
 	pm, err := packagemanager.GetPackageManager("pnpm")
 	assert.NilError(t, err)
-	pm.UnmarshalLockfile = func(rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
+	pm.UnmarshalLockfile = func(_readRepoFile packagemanager.RepoFileReader, _rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
 		return nil, errors.New("bad lockfile")
 	}
 	context := Context{
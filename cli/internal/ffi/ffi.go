@@ -169,11 +169,8 @@ func PreviousContent(gitRoot, fromCommit, filePath string) ([]byte, error) {
 }
 
 // TransitiveDeps returns the transitive external deps for all provided workspaces
-func TransitiveDeps(content []byte, packageManager string, workspaces map[string]map[string]string, resolutions map[string]string) (map[string]*ffi_proto.LockfilePackageList, error) {
-	var additionalData *ffi_proto.AdditionalBerryData
-	if resolutions != nil {
-		additionalData = &ffi_proto.AdditionalBerryData{Resolutions: resolutions}
-	}
+func TransitiveDeps(content []byte, packageManager string, workspaces map[string]map[string]string, resolutions map[string]string, yarnrc []byte) (map[string]*ffi_proto.LockfilePackageList, error) {
+	additionalData := additionalBerryData(resolutions, yarnrc)
 	flatWorkspaces := make(map[string]*ffi_proto.PackageDependencyList)
 	for workspace, deps := range workspaces {
 		packageDependencyList := make([]*ffi_proto.PackageDependency, len(deps))
@@ -210,6 +207,14 @@ func TransitiveDeps(content []byte, packageManager string, workspaces map[string
 	return dependencies.GetDependencies(), nil
 }
 
+// additionalBerryData holds the package.json resolutions and the contents of .yarnrc.yml of a berry repo
+func additionalBerryData(resolutions map[string]string, yarnrc []byte) *ffi_proto.AdditionalBerryData {
+	if resolutions == nil && yarnrc == nil {
+		return nil
+	}
+	return &ffi_proto.AdditionalBerryData{Resolutions: resolutions, Yarnrc: yarnrc}
+}
+
 func toPackageManager(packageManager string) ffi_proto.PackageManager {
 	switch packageManager {
 	case "npm":
@@ -226,11 +231,8 @@ func toPackageManager(packageManager string) ffi_proto.PackageManager {
 }
 
 // Subgraph returns the contents of a lockfile subgraph
-func Subgraph(packageManager string, content []byte, workspaces []string, packages []string, resolutions map[string]string) ([]byte, error) {
-	var additionalData *ffi_proto.AdditionalBerryData
-	if resolutions != nil {
-		additionalData = &ffi_proto.AdditionalBerryData{Resolutions: resolutions}
-	}
+func Subgraph(packageManager string, content []byte, workspaces []string, packages []string, resolutions map[string]string, yarnrc []byte) ([]byte, error) {
+	additionalData := additionalBerryData(resolutions, yarnrc)
 	req := ffi_proto.SubgraphRequest{
 		Contents:       content,
 		Workspaces:     workspaces,
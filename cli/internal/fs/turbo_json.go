@@ -113,6 +113,27 @@ type rawTask struct {
 	Env            []string             `json:"env,omitempty"`
 	PassThroughEnv []string             `json:"passThroughEnv,omitempty"`
 	DotEnv         []string             `json:"dotEnv,omitempty"`
+
+	// Validated, but not yet supported when running tasks, see unsupportedTaskFields
+	Timeout    json.RawMessage `json:"timeout,omitempty"`
+	MaxRetries json.RawMessage `json:"maxRetries,omitempty"`
+	Cpus       json.RawMessage `json:"cpus,omitempty"`
+}
+
+// unsupportedTaskFields lists the task fields in task that are accepted in
+// turbo.json but aren't acted on when running tasks
+func (task *rawTask) unsupportedTaskFields() []string {
+	var fields []string
+	if task.Timeout != nil {
+		fields = append(fields, "timeout")
+	}
+	if task.MaxRetries != nil {
+		fields = append(fields, "maxRetries")
+	}
+	if task.Cpus != nil {
+		fields = append(fields, "cpus")
+	}
+	return fields
 }
 
 // taskDefinitionHashable exists as a definition for PristinePipeline, which is used down
@@ -482,6 +503,10 @@ func (btd *BookkeepingTaskDefinition) UnmarshalJSON(data []byte) error {
 	btd.definedFields = util.Set{}
 	btd.experimentalFields = util.Set{}
 
+	for _, field := range task.unsupportedTaskFields() {
+		log.Printf("[WARNING] \"%v\" is not supported when running tasks yet and will have no effect", field)
+	}
+
 	if task.Outputs != nil {
 		var inclusions []string
 		var exclusions []string
@@ -1,6 +1,7 @@
 package fs
 
 import (
+	"encoding/json"
 	"os"
 	"reflect"
 	"sort"
@@ -488,6 +489,18 @@ func Test_TaskOutputsSort(t *testing.T) {
 	assert.True(t, cmp.DeepEqual(taskOutputs, TaskOutputs{Inclusions: []string{"bar", "foo/**"}, Exclusions: []string{".hidden/**", "special-file"}})().Success())
 }
 
+func Test_UnsupportedTaskFields(t *testing.T) {
+	task := rawTask{}
+	err := json.Unmarshal([]byte(`{"outputs": ["dist/**"], "timeout": "90s", "cpus": 2}`), &task)
+	assert.NoError(t, err)
+	assert.Equal(t, []string{"timeout", "cpus"}, task.unsupportedTaskFields())
+
+	task = rawTask{}
+	err = json.Unmarshal([]byte(`{"outputs": ["dist/**"]}`), &task)
+	assert.NoError(t, err)
+	assert.Empty(t, task.unsupportedTaskFields())
+}
+
 // Helpers
 func validateOutput(t *testing.T, turboJSON *TurboJSON, expectedPipeline Pipeline) {
 	t.Helper()
@@ -11,6 +11,7 @@ import (
 type BerryLockfile struct {
 	contents    []byte
 	resolutions map[string]string
+	yarnrc      []byte
 }
 
 // BerryDependencyMetaEntry Structure for holding if a package is optional or not
@@ -37,11 +38,11 @@ func (l *BerryLockfile) Subgraph(workspacePackages []turbopath.AnchoredSystemPat
 	for i, workspace := range workspacePackages {
 		workspaces[i] = workspace.ToUnixPath().ToString()
 	}
-	contents, err := ffi.Subgraph("berry", l.contents, workspaces, packages, l.resolutions)
+	contents, err := ffi.Subgraph("berry", l.contents, workspaces, packages, l.resolutions, l.yarnrc)
 	if err != nil {
 		return nil, err
 	}
-	return &BerryLockfile{contents: contents, resolutions: l.resolutions}, nil
+	return &BerryLockfile{contents: contents, resolutions: l.resolutions, yarnrc: l.yarnrc}, nil
 }
 
 // Encode encode the lockfile representation and write it to the given writer
@@ -63,9 +64,10 @@ func (l *BerryLockfile) Patches() []turbopath.AnchoredUnixPath {
 	return patches
 }
 
-// DecodeBerryLockfile Takes the contents of a berry lockfile and returns a struct representation
-func DecodeBerryLockfile(contents []byte, resolutions map[string]string) (*BerryLockfile, error) {
-	return &BerryLockfile{contents: contents, resolutions: resolutions}, nil
+// DecodeBerryLockfile Takes the contents of a berry lockfile and returns a struct representation.
+// yarnrc is the contents of .yarnrc.yml, if there is one, used for packageExtensions.
+func DecodeBerryLockfile(contents []byte, resolutions map[string]string, yarnrc []byte) (*BerryLockfile, error) {
+	return &BerryLockfile{contents: contents, resolutions: resolutions, yarnrc: yarnrc}, nil
 }
 
 // GlobalChange checks if there are any differences between lockfiles that would completely invalidate
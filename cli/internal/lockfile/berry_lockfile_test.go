@@ -9,7 +9,7 @@ import (
 
 func Test_BerryPatches(t *testing.T) {
 	contents := getRustFixture(t, "berry.lock")
-	lf, err := DecodeBerryLockfile(contents, nil)
+	lf, err := DecodeBerryLockfile(contents, nil, nil)
 	assert.NilError(t, err)
 	patches := lf.Patches()
 	assert.DeepEqual(t, patches, []turbopath.AnchoredUnixPath{".yarn/patches/lodash-npm-4.17.21-6382451519.patch"})
@@ -17,7 +17,7 @@ func Test_BerryPatches(t *testing.T) {
 
 func Test_EmptyBerryPatches(t *testing.T) {
 	contents := getRustFixture(t, "minimal-berry.lock")
-	lf, err := DecodeBerryLockfile(contents, nil)
+	lf, err := DecodeBerryLockfile(contents, nil, nil)
 	assert.NilError(t, err)
 	patches := lf.Patches()
 	assert.Assert(t, patches == nil)
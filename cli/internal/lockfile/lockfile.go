@@ -74,16 +74,16 @@ func AllTransitiveClosures(
 ) (map[turbopath.AnchoredUnixPath]mapset.Set, error) {
 	// We special case as Rust implementations have their own dep crawl
 	if lf, ok := lockFile.(*NpmLockfile); ok {
-		return rustTransitiveDeps(lf.contents, "npm", workspaces, nil)
+		return rustTransitiveDeps(lf.contents, "npm", workspaces, nil, nil)
 	}
 	if lf, ok := lockFile.(*BerryLockfile); ok {
-		return rustTransitiveDeps(lf.contents, "berry", workspaces, lf.resolutions)
+		return rustTransitiveDeps(lf.contents, "berry", workspaces, lf.resolutions, lf.yarnrc)
 	}
 	if lf, ok := lockFile.(*PnpmLockfile); ok {
-		return rustTransitiveDeps(lf.contents, "pnpm", workspaces, nil)
+		return rustTransitiveDeps(lf.contents, "pnpm", workspaces, nil, nil)
 	}
 	if lf, ok := lockFile.(*YarnLockfile); ok {
-		return rustTransitiveDeps(lf.contents, "yarn", workspaces, nil)
+		return rustTransitiveDeps(lf.contents, "yarn", workspaces, nil, nil)
 	}
 
 	g := new(errgroup.Group)
@@ -172,12 +172,12 @@ func transitiveClosureHelper(
 	}
 }
 
-func rustTransitiveDeps(content []byte, packageManager string, workspaces map[turbopath.AnchoredUnixPath]map[string]string, resolutions map[string]string) (map[turbopath.AnchoredUnixPath]mapset.Set, error) {
+func rustTransitiveDeps(content []byte, packageManager string, workspaces map[turbopath.AnchoredUnixPath]map[string]string, resolutions map[string]string, yarnrc []byte) (map[turbopath.AnchoredUnixPath]mapset.Set, error) {
 	processedWorkspaces := make(map[string]map[string]string, len(workspaces))
 	for workspacePath, workspace := range workspaces {
 		processedWorkspaces[workspacePath.ToString()] = workspace
 	}
-	workspaceDeps, err := ffi.TransitiveDeps(content, packageManager, processedWorkspaces, resolutions)
+	workspaceDeps, err := ffi.TransitiveDeps(content, packageManager, processedWorkspaces, resolutions, yarnrc)
 	if err != nil {
 		return nil, err
 	}
@@ -40,7 +40,7 @@ func (p *PnpmLockfile) Subgraph(workspacePackages []turbopath.AnchoredSystemPath
 	for i, workspace := range workspacePackages {
 		workspaces[i] = workspace.ToUnixPath().ToString()
 	}
-	contents, err := ffi.Subgraph("pnpm", p.contents, workspaces, packages, nil)
+	contents, err := ffi.Subgraph("pnpm", p.contents, workspaces, packages, nil, nil)
 	if err != nil {
 		return nil, err
 	}
@@ -34,7 +34,7 @@ func (l *YarnLockfile) Subgraph(workspacePackages []turbopath.AnchoredSystemPath
 	for i, workspace := range workspacePackages {
 		workspaces[i] = workspace.ToUnixPath().ToString()
 	}
-	contents, err := ffi.Subgraph("yarn", l.contents, workspaces, packages, nil)
+	contents, err := ffi.Subgraph("yarn", l.contents, workspaces, packages, nil, nil)
 	if err != nil {
 		return nil, err
 	}
@@ -1,7 +1,9 @@
 package packagemanager
 
 import (
+	"errors"
 	"fmt"
+	"os"
 	"strings"
 
 	"github.com/vercel/turbo/cli/internal/fs"
@@ -42,7 +44,7 @@ var nodejsBerry = PackageManager{
 		return true, nil
 	},
 
-	UnmarshalLockfile: func(rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
+	UnmarshalLockfile: func(readRepoFile RepoFileReader, rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
 		var resolutions map[string]string
 		if untypedResolutions, ok := rootPackageJSON.RawJSON["resolutions"]; ok {
 			if untypedResolutions, ok := untypedResolutions.(map[string]interface{}); ok {
@@ -55,7 +57,13 @@ var nodejsBerry = PackageManager{
 			}
 		}
 
-		return lockfile.DecodeBerryLockfile(contents, resolutions)
+		// packageExtensions are configured in .yarnrc.yml
+		yarnrc, err := readRepoFile(".yarnrc.yml")
+		if err != nil && !errors.Is(err, os.ErrNotExist) {
+			return nil, fmt.Errorf("reading .yarnrc.yml: %w", err)
+		}
+
+		return lockfile.DecodeBerryLockfile(contents, resolutions, yarnrc)
 	},
 
 	prunePatches: func(pkgJSON *fs.PackageJSON, patches []turbopath.AnchoredUnixPath) error {
@@ -42,7 +42,7 @@ var nodejsNpm = PackageManager{
 		return true, nil
 	},
 
-	UnmarshalLockfile: func(_rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
+	UnmarshalLockfile: func(_readRepoFile RepoFileReader, _rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
 		return lockfile.DecodeNpmLockfile(contents)
 	},
 }
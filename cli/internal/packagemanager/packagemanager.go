@@ -15,6 +15,9 @@ import (
 	"github.com/vercel/turbo/cli/internal/turbopath"
 )
 
+// RepoFileReader reads a file at a path relative to the repository root
+type RepoFileReader func(path string) ([]byte, error)
+
 // PackageManager is an abstraction across package managers
 type PackageManager struct {
 	// The descriptive name of the Package Manager.
@@ -52,7 +55,7 @@ type PackageManager struct {
 	canPrune func(cwd turbopath.AbsoluteSystemPath) (bool, error)
 
 	// Read a lockfile for a given package manager
-	UnmarshalLockfile func(rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error)
+	UnmarshalLockfile func(readRepoFile RepoFileReader, rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error)
 
 	// Prune the given pkgJSON to only include references to the given patches
 	prunePatches func(pkgJSON *fs.PackageJSON, patches []turbopath.AnchoredUnixPath) error
@@ -131,7 +134,10 @@ func (pm PackageManager) ReadLockfile(projectDirectory turbopath.AbsoluteSystemP
 	if err != nil {
 		return nil, fmt.Errorf("reading %s: %w", pm.Lockfile, err)
 	}
-	lf, err := pm.UnmarshalLockfile(rootPackageJSON, contents)
+	readRepoFile := func(path string) ([]byte, error) {
+		return projectDirectory.UntypedJoin(path).ReadFile()
+	}
+	lf, err := pm.UnmarshalLockfile(readRepoFile, rootPackageJSON, contents)
 	if err != nil {
 		return nil, errors.Wrapf(err, "error in %v", pm.Lockfile)
 	}
@@ -98,7 +98,7 @@ var nodejsPnpm = PackageManager{
 		return true, nil
 	},
 
-	UnmarshalLockfile: func(_rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
+	UnmarshalLockfile: func(_readRepoFile RepoFileReader, _rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
 		return lockfile.DecodePnpmLockfile(contents)
 	},
 
@@ -30,7 +30,7 @@ var nodejsPnpm6 = PackageManager{
 		return true, nil
 	},
 
-	UnmarshalLockfile: func(_rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
+	UnmarshalLockfile: func(_readRepoFile RepoFileReader, _rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
 		return lockfile.DecodePnpmLockfile(contents)
 	},
 }
@@ -69,7 +69,7 @@ var nodejsYarn = PackageManager{
 		return true, nil
 	},
 
-	UnmarshalLockfile: func(_rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
+	UnmarshalLockfile: func(_readRepoFile RepoFileReader, _rootPackageJSON *fs.PackageJSON, contents []byte) (lockfile.Lockfile, error) {
 		return lockfile.DecodeYarnLockfile(contents)
 	},
 }
@@ -12,6 +12,7 @@ import (
 	"github.com/pkg/errors"
 	"github.com/vercel/turbo/cli/internal/context"
 	"github.com/vercel/turbo/cli/internal/lockfile"
+	"github.com/vercel/turbo/cli/internal/packagemanager"
 	"github.com/vercel/turbo/cli/internal/scm"
 	scope_filter "github.com/vercel/turbo/cli/internal/scope/filter"
 	"github.com/vercel/turbo/cli/internal/turbopath"
@@ -276,7 +277,7 @@ func getChangesFromLockfile(scm scm.SCM, ctx *context.Context, changedFiles []st
 		// unable to reconstruct old lockfile, assume everything changed
 		return nil, true
 	}
-	prevLockfile, err := ctx.PackageManager.UnmarshalLockfile(ctx.WorkspaceInfos.PackageJSONs[util.RootPkgName], prevContents)
+	prevLockfile, err := ctx.PackageManager.UnmarshalLockfile(previousContentReader(scm, fromRef), ctx.WorkspaceInfos.PackageJSONs[util.RootPkgName], prevContents)
 	if err != nil {
 		// unable to parse old lockfile, assume everything changed
 		return nil, true
@@ -290,6 +291,19 @@ func getChangesFromLockfile(scm scm.SCM, ctx *context.Context, changedFiles []st
 	return additionalPkgs, false
 }
 
+// previousContentReader reads repository files as they were at fromRef
+func previousContentReader(scm scm.SCM, fromRef string) packagemanager.RepoFileReader {
+	return func(path string) ([]byte, error) {
+		contents, err := scm.PreviousContent(fromRef, path)
+		if err != nil {
+			// The lockfile was already read at fromRef, so the ref is valid
+			// and a failure here means the file didn't exist there.
+			return nil, fmt.Errorf("%v at %v: %w", path, fromRef, os.ErrNotExist)
+		}
+		return contents, nil
+	}
+}
+
 func getDefaultGlobalDeps() []string {
 	// include turbo.json and root package.json as implicit global dependencies
 	defaultGlobalDeps := []string{
@@ -1,6 +1,7 @@
 package scope
 
 import (
+	"errors"
 	"fmt"
 	"io"
 	"os"
@@ -510,7 +511,7 @@ func TestResolvePackages(t *testing.T) {
 			for _, path := range systemSeparatorChanged {
 				scm.contents[path] = nil
 			}
-			readLockfile := func(_rootPackageJSON *fs.PackageJSON, content []byte) (lockfile.Lockfile, error) {
+			readLockfile := func(_readRepoFile packagemanager.RepoFileReader, _rootPackageJSON *fs.PackageJSON, content []byte) (lockfile.Lockfile, error) {
 				return tc.prevLockfile, nil
 			}
 			pkgInferenceRoot, err := resolvePackageInferencePath(tc.inferPkgPath)
@@ -551,3 +552,24 @@ func TestResolvePackages(t *testing.T) {
 		})
 	}
 }
+
+func TestPreviousContentReader(t *testing.T) {
+	scm := &mockSCM{
+		contents: map[string][]byte{
+			".yarnrc.yml": []byte("packageExtensions: {}\n"),
+		},
+	}
+	readRepoFile := previousContentReader(scm, "HEAD~1")
+
+	contents, err := readRepoFile(".yarnrc.yml")
+	if err != nil {
+		t.Fatalf("reading .yarnrc.yml: %v", err)
+	}
+	if string(contents) != "packageExtensions: {}\n" {
+		t.Errorf("contents: got %q", contents)
+	}
+
+	if _, err := readRepoFile("missing.yml"); !errors.Is(err, os.ErrNotExist) {
+		t.Errorf("missing file: expected os.ErrNotExist, got %v", err)
+	}
+}
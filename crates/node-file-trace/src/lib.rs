@@ -328,15 +328,14 @@ pub async fn start(
     if let Some(cache) = cache {
         use tokio::time::timeout;
         use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
-        use turbo_tasks_rocksdb::RocksDbPersistedGraph;
+        use turbo_tasks_redb::RedbPersistedGraph;
 
-        run(
-            &args,
+        return run(
+            args.clone(),
             || {
                 let start = Instant::now();
-                let backend = MemoryBackendWithPersistedGraph::new(
-                    RocksDbPersistedGraph::new(cache).unwrap(),
-                );
+                let backend =
+                    MemoryBackendWithPersistedGraph::new(RedbPersistedGraph::new(cache).unwrap());
                 let tt = TurboTasks::new(backend);
                 let elapsed = start.elapsed();
                 println!("restored cache {}", FormatDuration(elapsed));
@@ -368,9 +367,10 @@ pub async fn start(
                 let elapsed = start.elapsed();
                 println!("writing cache {}", FormatDuration(elapsed));
             },
+            module_options,
+            resolve_options,
         )
         .await;
-        return;
     }
 
     run(
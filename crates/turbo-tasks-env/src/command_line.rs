@@ -1,6 +1,8 @@
 use std::env;
 
+use anyhow::Result;
 use indexmap::IndexMap;
+use turbo_tasks::recording::record_or_replay;
 
 use crate::{EnvMapVc, ProcessEnv, ProcessEnvVc, GLOBAL_ENV_LOCK};
 
@@ -25,7 +27,8 @@ fn env_snapshot() -> IndexMap<String, String> {
 #[turbo_tasks::value_impl]
 impl ProcessEnv for CommandLineProcessEnv {
     #[turbo_tasks::function]
-    fn read_all(&self) -> EnvMapVc {
-        EnvMapVc::cell(env_snapshot())
+    async fn read_all(&self) -> Result<EnvMapVc> {
+        let env = record_or_replay("env", "command_line", async { Ok(env_snapshot()) }).await?;
+        Ok(EnvMapVc::cell(env))
     }
 }
@@ -1,7 +1,11 @@
 #![feature(min_specialization)]
 
 use anyhow::Result;
-use turbo_tasks::primitives::{OptionStringVc, StringVc};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{
+    primitives::{OptionStringVc, StringVc},
+    recording::record_or_replay,
+};
 use turbo_tasks_fs::FileSystemPathVc;
 use turbopack_core::issue::{Issue, IssueSeverityVc, IssueVc};
 
@@ -35,32 +39,61 @@ impl HttpResponseBodyVc {
     }
 }
 
+/// A response or error in the form it's recorded by [record_or_replay].
+#[derive(Serialize, Deserialize)]
+enum RecordedFetch {
+    Response {
+        status: u16,
+        body: Vec<u8>,
+    },
+    Error {
+        kind: FetchErrorKind,
+        detail: String,
+    },
+}
+
 #[turbo_tasks::function]
 pub async fn fetch(url: StringVc, user_agent: OptionStringVc) -> Result<FetchResultVc> {
     let url = &*url.await?;
     let user_agent = &*user_agent.await?;
-    let client = reqwest::Client::new();
-
-    let mut builder = client.get(url);
-    if let Some(user_agent) = user_agent {
-        builder = builder.header("User-Agent", user_agent);
-    }
-
-    let response = builder.send().await.and_then(|r| r.error_for_status());
-    match response {
-        Ok(response) => {
-            let status = response.status().as_u16();
-            let body = response.bytes().await?.to_vec();
+    let key = match user_agent {
+        Some(user_agent) => format!("{url} ({user_agent})"),
+        None => url.clone(),
+    };
+    let recorded = record_or_replay("fetch", key, async {
+        let client = reqwest::Client::new();
+
+        let mut builder = client.get(url);
+        if let Some(user_agent) = user_agent {
+            builder = builder.header("User-Agent", user_agent);
+        }
 
-            Ok(FetchResultVc::cell(Ok(HttpResponse {
-                status,
-                body: HttpResponseBodyVc::cell(HttpResponseBody(body)),
+        let response = builder.send().await.and_then(|r| r.error_for_status());
+        Ok(match response {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let body = response.bytes().await?.to_vec();
+                RecordedFetch::Response { status, body }
             }
-            .cell())))
+            Err(err) => RecordedFetch::Error {
+                kind: FetchErrorKind::from_reqwest_error(&err),
+                detail: err.to_string(),
+            },
+        })
+    })
+    .await?;
+
+    match recorded {
+        RecordedFetch::Response { status, body } => Ok(FetchResultVc::cell(Ok(HttpResponse {
+            status,
+            body: HttpResponseBodyVc::cell(HttpResponseBody(body)),
+        }
+        .cell()))),
+        RecordedFetch::Error { kind, detail } => Ok(FetchResultVc::cell(Err(FetchError {
+            detail: StringVc::cell(detail),
+            url: StringVc::cell(url.to_owned()),
+            kind: kind.into(),
         }
-        Err(err) => Ok(FetchResultVc::cell(Err(FetchError::from_reqwest_error(
-            &err, url,
-        )
         .cell()))),
     }
 }
@@ -81,9 +114,9 @@ pub struct FetchError {
     pub detail: StringVc,
 }
 
-impl FetchError {
-    fn from_reqwest_error(error: &reqwest::Error, url: &str) -> FetchError {
-        let kind = if error.is_connect() {
+impl FetchErrorKind {
+    fn from_reqwest_error(error: &reqwest::Error) -> FetchErrorKind {
+        if error.is_connect() {
             FetchErrorKind::Connect
         } else if error.is_timeout() {
             FetchErrorKind::Timeout
@@ -91,12 +124,6 @@ impl FetchError {
             FetchErrorKind::Status(status.as_u16())
         } else {
             FetchErrorKind::Other
-        };
-
-        FetchError {
-            detail: StringVc::cell(error.to_string()),
-            url: StringVc::cell(url.to_owned()),
-            kind: kind.into(),
         }
     }
 }
@@ -57,6 +57,7 @@ use tracing::{instrument, Level};
 use turbo_tasks::{
     mark_stateful,
     primitives::{BoolVc, OptionStringVc, StringReadRef, StringVc},
+    recording::record_or_replay,
     spawn_thread,
     trace::TraceRawVcs,
     CompletionVc, InvalidationReason, Invalidator, ValueToString, ValueToStringVc,
@@ -200,6 +201,12 @@ impl DiskFileSystem {
         Ok(())
     }
 
+    /// Identifies a path of this file system in a recording. It's relative to
+    /// the root, so recordings can be replayed on other machines.
+    fn recording_key(&self, fs_path: &FileSystemPath) -> String {
+        format!("{}:{}", self.name, fs_path.path)
+    }
+
     async fn lock_path(&self, full_path: &Path) -> PathLockGuard<'_> {
         let lock1 = self.invalidation_lock.read().await;
         let lock2 = self.mutex_map.lock(full_path.to_path_buf()).await;
@@ -538,12 +545,19 @@ impl FileSystem for DiskFileSystem {
         self.register_invalidator(&full_path)?;
 
         let _lock = self.lock_path(&full_path).await;
-        let content = match retry_future(|| File::from_path(full_path.clone())).await {
-            Ok(file) => FileContent::new(file),
-            Err(e) if e.kind() == ErrorKind::NotFound => FileContent::NotFound,
-            Err(e) => {
-                bail!(anyhow!(e).context(format!("reading file {}", full_path.display())))
+        let file = record_or_replay("fs.read", self.recording_key(&*fs_path.await?), async {
+            match retry_future(|| File::from_path(full_path.clone())).await {
+                Ok(file) => Ok(Some(RecordedFile(file))),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => {
+                    bail!(anyhow!(e).context(format!("reading file {}", full_path.display())))
+                }
             }
+        })
+        .await?;
+        let content = match file {
+            Some(RecordedFile(file)) => FileContent::new(file),
+            None => FileContent::NotFound,
         };
         Ok(content.cell())
     }
@@ -554,50 +568,72 @@ impl FileSystem for DiskFileSystem {
         self.register_dir_invalidator(&full_path)?;
         let fs_path = fs_path.await?;
 
-        // we use the sync std function here as it's a lot faster (600%) in
-        // node-file-trace
-        let read_dir = match retry_blocking(&full_path, |path| std::fs::read_dir(path)).await {
-            Ok(dir) => dir,
-            Err(e)
-                if e.kind() == ErrorKind::NotFound
-                    || e.kind() == ErrorKind::NotADirectory
-                    || e.kind() == ErrorKind::InvalidFilename =>
-            {
-                return Ok(DirectoryContentVc::not_found());
-            }
-            Err(e) => {
-                bail!(anyhow!(e).context(format!("reading dir {}", full_path.display())))
-            }
-        };
-
-        let entries = read_dir
-            .filter_map(|r| {
-                let e = match r {
-                    Ok(e) => e,
-                    Err(err) => return Some(Err(err.into())),
+        let key = self.recording_key(&fs_path);
+        // Entries are recorded by their file name, path relative to the root and type
+        let entries: Option<Vec<(String, String, FileSystemEntryType)>> =
+            record_or_replay("fs.read_dir", key, async {
+                // we use the sync std function here as it's a lot faster (600%) in
+                // node-file-trace
+                let read_dir = match retry_blocking(&full_path, |path| std::fs::read_dir(path))
+                    .await
+                {
+                    Ok(dir) => dir,
+                    Err(e)
+                        if e.kind() == ErrorKind::NotFound
+                            || e.kind() == ErrorKind::NotADirectory
+                            || e.kind() == ErrorKind::InvalidFilename =>
+                    {
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        bail!(anyhow!(e).context(format!("reading dir {}", full_path.display())))
+                    }
                 };
 
-                let path = e.path();
+                read_dir
+                    .filter_map(|r| {
+                        let e = match r {
+                            Ok(e) => e,
+                            Err(err) => return Some(Err(err.into())),
+                        };
 
-                // we filter out any non unicode names and paths without the same root here
-                let file_name = path.file_name()?.to_str()?.to_string();
-                let path_to_root = sys_to_unix(path.strip_prefix(&self.root).ok()?.to_str()?);
+                        let path = e.path();
 
-                let fs_path =
-                    FileSystemPathVc::new_normalized(fs_path.fs, path_to_root.to_string());
+                        // we filter out any non unicode names and paths without the same root
+                        // here
+                        let file_name = path.file_name()?.to_str()?.to_string();
+                        let path_to_root =
+                            sys_to_unix(path.strip_prefix(&self.root).ok()?.to_str()?);
 
-                let entry = match e.file_type() {
-                    Ok(t) if t.is_file() => DirectoryEntry::File(fs_path),
-                    Ok(t) if t.is_dir() => DirectoryEntry::Directory(fs_path),
-                    Ok(t) if t.is_symlink() => DirectoryEntry::Symlink(fs_path),
-                    Ok(_) => DirectoryEntry::Other(fs_path),
-                    Err(err) => return Some(Err(err.into())),
-                };
+                        let file_type = match e.file_type() {
+                            Ok(t) => t.into(),
+                            Err(err) => return Some(Err(err.into())),
+                        };
 
-                Some(anyhow::Ok((file_name, entry)))
+                        Some(anyhow::Ok((file_name, path_to_root.to_string(), file_type)))
+                    })
+                    .collect::<Result<_>>()
+                    .map(Some)
+                    .with_context(|| format!("reading directory item in {}", full_path.display()))
             })
-            .collect::<Result<_>>()
-            .with_context(|| format!("reading directory item in {}", full_path.display()))?;
+            .await?;
+
+        let Some(entries) = entries else {
+            return Ok(DirectoryContentVc::not_found());
+        };
+        let entries = entries
+            .into_iter()
+            .map(|(file_name, path_to_root, file_type)| {
+                let fs_path = FileSystemPathVc::new_normalized(fs_path.fs, path_to_root);
+                let entry = match file_type {
+                    FileSystemEntryType::File => DirectoryEntry::File(fs_path),
+                    FileSystemEntryType::Directory => DirectoryEntry::Directory(fs_path),
+                    FileSystemEntryType::Symlink => DirectoryEntry::Symlink(fs_path),
+                    _ => DirectoryEntry::Other(fs_path),
+                };
+                (file_name, entry)
+            })
+            .collect();
 
         Ok(DirectoryContentVc::new(entries))
     }
@@ -608,80 +644,84 @@ impl FileSystem for DiskFileSystem {
         self.register_invalidator(&full_path)?;
 
         let _lock = self.lock_path(&full_path).await;
-        let link_path = match retry_future(|| fs::read_link(&full_path)).await {
-            Ok(res) => res,
-            Err(_) => return Ok(LinkContent::NotFound.cell()),
-        };
-        let is_link_absolute = link_path.is_absolute();
-
-        let mut file = link_path.clone();
-        if !is_link_absolute {
-            if let Some(normalized_linked_path) = full_path.parent().and_then(|p| {
-                normalize_path(&sys_to_unix(p.join(&file).to_string_lossy().as_ref()))
-            }) {
-                #[cfg(target_family = "windows")]
-                {
-                    file = PathBuf::from(normalized_linked_path);
-                }
-                // `normalize_path` stripped the leading `/` of the path
-                // add it back here or the `strip_prefix` will return `Err`
-                #[cfg(not(target_family = "windows"))]
-                {
-                    file = PathBuf::from(format!("/{normalized_linked_path}"));
+        let key = self.recording_key(&*fs_path.await?);
+        let content = record_or_replay("fs.read_link", key, async {
+            let link_path = match retry_future(|| fs::read_link(&full_path)).await {
+                Ok(res) => res,
+                Err(_) => return Ok(LinkContent::NotFound),
+            };
+            let is_link_absolute = link_path.is_absolute();
+
+            let mut file = link_path.clone();
+            if !is_link_absolute {
+                if let Some(normalized_linked_path) = full_path.parent().and_then(|p| {
+                    normalize_path(&sys_to_unix(p.join(&file).to_string_lossy().as_ref()))
+                }) {
+                    #[cfg(target_family = "windows")]
+                    {
+                        file = PathBuf::from(normalized_linked_path);
+                    }
+                    // `normalize_path` stripped the leading `/` of the path
+                    // add it back here or the `strip_prefix` will return `Err`
+                    #[cfg(not(target_family = "windows"))]
+                    {
+                        file = PathBuf::from(format!("/{normalized_linked_path}"));
+                    }
+                } else {
+                    return Ok(LinkContent::Invalid);
                 }
-            } else {
-                return Ok(LinkContent::Invalid.cell());
             }
-        }
 
-        // strip the root from the path, it serves two purpose
-        // 1. ensure the linked path is under the root
-        // 2. strip the root path if the linked path is absolute
-        //
-        // we use `dunce::simplify` to strip a potential UNC prefix on windows, on any
-        // other OS this gets compiled away
-        let result = simplified(&file).strip_prefix(simplified(Path::new(&self.root)));
-
-        let relative_to_root_path = match result {
-            Ok(file) => PathBuf::from(sys_to_unix(&file.to_string_lossy()).as_ref()),
-            Err(_) => return Ok(LinkContent::Invalid.cell()),
-        };
+            // strip the root from the path, it serves two purpose
+            // 1. ensure the linked path is under the root
+            // 2. strip the root path if the linked path is absolute
+            //
+            // we use `dunce::simplify` to strip a potential UNC prefix on windows, on any
+            // other OS this gets compiled away
+            let result = simplified(&file).strip_prefix(simplified(Path::new(&self.root)));
+
+            let relative_to_root_path = match result {
+                Ok(file) => PathBuf::from(sys_to_unix(&file.to_string_lossy()).as_ref()),
+                Err(_) => return Ok(LinkContent::Invalid),
+            };
 
-        let (target, file_type) = if is_link_absolute {
-            let target_string = relative_to_root_path.to_string_lossy().to_string();
-            (
-                target_string.clone(),
-                FileSystemPathVc::new_normalized(fs_path.fs(), target_string)
-                    .get_type()
-                    .await?,
-            )
-        } else {
-            let link_path_string_cow = link_path.to_string_lossy();
-            let link_path_unix = sys_to_unix(&link_path_string_cow);
-            (
-                link_path_unix.to_string(),
-                fs_path
-                    .parent()
-                    .join(link_path_unix.as_ref())
-                    .get_type()
-                    .await?,
-            )
-        };
+            let (target, file_type) = if is_link_absolute {
+                let target_string = relative_to_root_path.to_string_lossy().to_string();
+                (
+                    target_string.clone(),
+                    FileSystemPathVc::new_normalized(fs_path.fs(), target_string)
+                        .get_type()
+                        .await?,
+                )
+            } else {
+                let link_path_string_cow = link_path.to_string_lossy();
+                let link_path_unix = sys_to_unix(&link_path_string_cow);
+                (
+                    link_path_unix.to_string(),
+                    fs_path
+                        .parent()
+                        .join(link_path_unix.as_ref())
+                        .get_type()
+                        .await?,
+                )
+            };
 
-        Ok(LinkContent::Link {
-            target,
-            link_type: {
-                let mut link_type = Default::default();
-                if link_path.is_absolute() {
-                    link_type |= LinkType::ABSOLUTE;
-                }
-                if matches!(&*file_type, FileSystemEntryType::Directory) {
-                    link_type |= LinkType::DIRECTORY;
-                }
-                link_type
-            },
-        }
-        .cell())
+            Ok(LinkContent::Link {
+                target,
+                link_type: {
+                    let mut link_type = Default::default();
+                    if link_path.is_absolute() {
+                        link_type |= LinkType::ABSOLUTE;
+                    }
+                    if matches!(&*file_type, FileSystemEntryType::Directory) {
+                        link_type |= LinkType::DIRECTORY;
+                    }
+                    link_type
+                },
+            })
+        })
+        .await?;
+        Ok(content.cell())
     }
 
     #[turbo_tasks::function]
@@ -845,11 +885,16 @@ impl FileSystem for DiskFileSystem {
         self.register_invalidator(&full_path)?;
 
         let _lock = self.lock_path(&full_path).await;
-        let meta = retry_future(|| fs::metadata(full_path.clone()))
-            .await
-            .with_context(|| format!("reading metadata for {}", full_path.display()))?;
+        let key = self.recording_key(&*fs_path.await?);
+        let meta: FileMeta = record_or_replay("fs.metadata", key, async {
+            let meta = retry_future(|| fs::metadata(full_path.clone()))
+                .await
+                .with_context(|| format!("reading metadata for {}", full_path.display()))?;
+            Ok(meta.into())
+        })
+        .await?;
 
-        Ok(FileMetaVc::cell(meta.into()))
+        Ok(FileMetaVc::cell(meta))
     }
 }
 
@@ -1691,6 +1736,37 @@ impl File {
     }
 }
 
+/// A [File] in the form it's recorded by [record_or_replay]. Unlike the
+/// [Rope] serialization, the content is recorded as bytes, so it doesn't need
+/// to be valid UTF-8.
+struct RecordedFile(File);
+
+impl Serialize for RecordedFile {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+        let mut state = serializer.serialize_struct("RecordedFile", 2)?;
+        state.serialize_field("meta", &self.0.meta)?;
+        let content = self.0.content.to_bytes().map_err(Error::custom)?;
+        state.serialize_field("content", &*content)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RecordedFile {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Recorded {
+            meta: FileMeta,
+            content: Vec<u8>,
+        }
+        let Recorded { meta, content } = Recorded::deserialize(deserializer)?;
+        Ok(RecordedFile(File {
+            meta,
+            content: Rope::from(content),
+        }))
+    }
+}
+
 mod mime_option_serde {
     use std::{fmt, str::FromStr};
 
@@ -0,0 +1,161 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use criterion::{BenchmarkId, Criterion};
+use turbo_tasks::{get_invalidator, Invalidator, NothingVc, TryJoinIterExt, TurboTasks};
+use turbo_tasks_malloc::TurboMalloc;
+use turbo_tasks_memory::{GcPolicy, MemoryBackend};
+
+use super::register;
+
+const MB: usize = 1024 * 1024;
+
+/// The number of modules of the app. Every module holds [CONTENT_SIZE] bytes
+/// of intermediate data, so the app needs more memory than the GC budget.
+const MODULES: u32 = 5000;
+const CONTENT_SIZE: usize = 16 * 1024;
+
+static VERSION: AtomicU32 = AtomicU32::new(0);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+/// Measures the latency of an incremental update of a large app, with and
+/// without garbage collection under memory pressure. With a well behaved GC
+/// policy the latency stays flat, since only unused data is collected.
+///
+/// Run it with
+/// `TURBOPACK_BENCH_STRESS=yes cargo bench -p turbo-tasks-memory --bench mod --
+/// incremental_update`. Criterion reports
+/// `turbo_tasks_memory_gc/incremental_update/gc` and `.../no gc` next to each
+/// other, and the `gc` time should be within the noise of the `no gc` time.
+/// Pass `--save-baseline <name>` and `--baseline <name>` after the `--` to
+/// compare a change to the GC policy against an earlier run.
+///
+/// The app is computed once per variant, so the samples measure the steady
+/// state of repeated updates. Every update checks the result of the app, and
+/// the `gc` variant checks that GC actually freed memory, so neither variant
+/// can get faster by skipping work.
+pub fn incremental_update(c: &mut Criterion) {
+    if matches!(
+        std::env::var("TURBOPACK_BENCH_STRESS").ok().as_deref(),
+        None | Some("") | Some("no") | Some("false")
+    ) {
+        return;
+    }
+
+    register();
+
+    let mut group = c.benchmark_group("turbo_tasks_memory_gc");
+    group.sample_size(20);
+
+    for gc in [false, true] {
+        let name = if gc { "gc" } else { "no gc" };
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .on_thread_stop(TurboMalloc::thread_stop)
+            .build()
+            .unwrap();
+        // The budget is relative to the memory that is already in use, and a
+        // lot smaller than the memory the app needs.
+        let base = TurboMalloc::memory_usage();
+        let policy = gc.then(|| GcPolicy {
+            soft_limit: base + 16 * MB,
+            hard_limit: base + 48 * MB,
+            ..GcPolicy::from_memory_limit(usize::MAX)
+        });
+        let tt = TurboTasks::new(MemoryBackend::with_gc_policy(policy));
+        rt.block_on(async {
+            let root = tt.spawn_root_task(|| {
+                Box::pin(async {
+                    app().await?;
+                    Ok(NothingVc::new().into())
+                })
+            });
+            tt.wait_task_completion(root, true).await.unwrap();
+            tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+        });
+
+        group.bench_function(BenchmarkId::new("incremental_update", name), |b| {
+            b.to_async(&rt).iter(|| async {
+                let version = VERSION.fetch_add(1, Ordering::SeqCst) + 1;
+                let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+                invalidator.invalidate();
+                // Like a request to the dev server, which waits for the update
+                let summary = tt
+                    .run_once(async { Ok(*app().strongly_consistent().await?) })
+                    .await
+                    .unwrap();
+                // A stale result would mean the update was lost, e.g. because GC
+                // dropped the dependency on an emptied cell
+                assert_eq!(summary, expected_summary(version));
+            });
+        });
+
+        if let Some(metrics) = tt.backend().gc_metrics() {
+            // Otherwise the gc variant measures the same as no gc
+            assert!(
+                metrics.freed > 0,
+                "the app should exceed the GC budget, but GC freed nothing: {metrics:?}"
+            );
+        }
+    }
+}
+
+/// The summary [app] computes when the first module is at `version`.
+fn expected_summary(version: u32) -> u64 {
+    (0..MODULES)
+        .map(|i| {
+            let version = if i == 0 { version } else { 0 };
+            (i ^ version) as u8 as u64 * CONTENT_SIZE as u64
+        })
+        .sum()
+}
+
+#[turbo_tasks::value(transparent)]
+struct Version(u32);
+
+#[turbo_tasks::value(transparent)]
+struct Content(Vec<u8>);
+
+#[turbo_tasks::value(transparent)]
+struct Summary(u64);
+
+/// Only the first module changes between updates.
+#[turbo_tasks::function]
+fn module_source(i: u32) -> VersionVc {
+    if i == 0 {
+        *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+        VersionVc::cell(VERSION.load(Ordering::SeqCst))
+    } else {
+        VersionVc::cell(0)
+    }
+}
+
+/// Large intermediate data, which is only read to compute the summary.
+#[turbo_tasks::function]
+async fn module_content(i: u32) -> Result<ContentVc> {
+    let version = *module_source(i).await?;
+    Ok(ContentVc::cell(vec![(i ^ version) as u8; CONTENT_SIZE]))
+}
+
+#[turbo_tasks::function]
+async fn module_summary(i: u32) -> Result<SummaryVc> {
+    let content = module_content(i).await?;
+    Ok(SummaryVc::cell(
+        content.iter().map(|&byte| byte as u64).sum::<u64>(),
+    ))
+}
+
+#[turbo_tasks::function]
+async fn app() -> Result<SummaryVc> {
+    let summaries = (0..MODULES)
+        .map(|i| async move { Ok(*module_summary(i).await?) })
+        .try_join()
+        .await?;
+    Ok(SummaryVc::cell(summaries.into_iter().sum()))
+}
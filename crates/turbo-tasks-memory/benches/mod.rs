@@ -2,16 +2,21 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 
+pub(crate) mod gc;
 pub(crate) mod scope_stress;
 pub(crate) mod stress;
 
 criterion_group!(
     name = turbo_tasks_memory_stress;
     config = Criterion::default();
-    targets = stress::fibonacci, scope_stress::scope_stress
+    targets = stress::fibonacci, scope_stress::scope_stress, gc::incremental_update
 );
 criterion_main!(turbo_tasks_memory_stress);
 
+/// Counts allocations, so garbage collection can react to memory pressure.
+#[global_allocator]
+static ALLOC: turbo_tasks_malloc::TurboMalloc = turbo_tasks_malloc::TurboMalloc;
+
 pub fn register() {
     turbo_tasks::register();
     include!(concat!(env!("OUT_DIR"), "/register_benches.rs"));
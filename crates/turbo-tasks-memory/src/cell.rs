@@ -129,6 +129,21 @@ impl Cell {
         listener
     }
 
+    /// Gives up recomputing the cell, e.g. because the execution was
+    /// cancelled. Waiting readers are woken up to trigger a new recomputation.
+    pub fn cancel_recompute(&mut self) {
+        if let Cell::Recomputing {
+            dependent_tasks,
+            event,
+        } = self
+        {
+            event.notify(usize::MAX);
+            *self = Cell::TrackedValueless {
+                dependent_tasks: take(dependent_tasks),
+            };
+        }
+    }
+
     /// Read the content of the cell when avaiable. Registers the reader as
     /// dependent task. Will trigger recomputation is no content is
     /// available.
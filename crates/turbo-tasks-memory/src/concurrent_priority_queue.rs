@@ -79,6 +79,26 @@ impl<K: Hash + Eq, V: Ord + Clone + Debug, H: BuildHasher + Clone>
         inner.push(key, value);
     }
 
+    pub fn get(&self, key: &K) -> Option<V> {
+        let inner = self.shard(key);
+        inner.get_priority(key).cloned()
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let mut inner = self.shard(key);
+        inner.remove(key).map(|(_, value)| value)
+    }
+
+    /// Updates the values of all items in the queue.
+    pub fn update_all(&self, mut update: impl FnMut(&mut V)) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock();
+            for (_, value) in shard.iter_mut() {
+                update(value);
+            }
+        }
+    }
+
     pub fn upsert_with(
         &self,
         key: K,
@@ -1,11 +1,14 @@
 use std::{
-    cmp::Reverse,
+    cmp::{min, Reverse},
     collections::HashMap,
+    ops::AddAssign,
     time::{Duration, Instant},
 };
 
 use concurrent_queue::ConcurrentQueue;
 use nohash_hasher::BuildNoHashHasher;
+use parking_lot::Mutex;
+use serde::Serialize;
 use turbo_tasks::{small_duration::SmallDuration, TaskId, TurboTasksBackendApi};
 
 use crate::{concurrent_priority_queue::ConcurrentPriorityQueue, MemoryBackend};
@@ -49,10 +52,13 @@ pub enum GcPriority {
 }
 
 /// Statistics about actions performed during garbage collection.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GcStats {
     /// How many tasks were unloaded.
     pub unloaded: usize,
+    /// How many tasks were not unloaded because of the rate limit.
+    pub unload_rate_limited: usize,
     /// How many unused cells were emptied.
     pub empty_unused: usize,
     /// How many unused cells were emptied (on the fast path).
@@ -70,6 +76,196 @@ pub struct GcStats {
     pub no_gc_possible: usize,
 }
 
+impl AddAssign<&GcStats> for GcStats {
+    fn add_assign(&mut self, other: &GcStats) {
+        self.unloaded += other.unloaded;
+        self.unload_rate_limited += other.unload_rate_limited;
+        self.empty_unused += other.empty_unused;
+        self.empty_unused_fast += other.empty_unused_fast;
+        self.empty_cells += other.empty_cells;
+        self.priority_updated += other.priority_updated;
+        self.priority_updated_fast += other.priority_updated_fast;
+        self.no_gc_needed += other.no_gc_needed;
+        self.no_gc_possible += other.no_gc_possible;
+    }
+}
+
+/// Configures when and how much garbage collection runs, based on the memory
+/// usage counted by turbo-tasks-malloc.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcPolicy {
+    /// Above this memory usage, garbage collection runs when idle and
+    /// incrementally during execution. The further the usage is above it, the
+    /// more is collected per run.
+    pub soft_limit: usize,
+    /// At this memory usage, runs ignore the rate limits and are repeated
+    /// until the usage is below it again.
+    pub hard_limit: usize,
+    /// The minimum time between two runs during execution below the hard
+    /// limit. It's increased while runs don't free any memory.
+    pub min_interval: Duration,
+    /// The maximum number of tasks unloaded per second below the hard limit.
+    /// Unloaded tasks need to recompute when they are needed again, so
+    /// unloading too eagerly causes thrashing.
+    pub max_unloads_per_second: usize,
+}
+
+impl GcPolicy {
+    /// The policy used for a single memory limit. It starts collecting at 3/4
+    /// of the limit and ignores the rate limits from 7/8 of it.
+    pub fn from_memory_limit(memory_limit: usize) -> Self {
+        Self {
+            soft_limit: memory_limit / 4 * 3,
+            hard_limit: memory_limit / 8 * 7,
+            min_interval: Duration::from_millis(100),
+            max_unloads_per_second: 10_000,
+        }
+    }
+
+    /// How much work garbage collection should do at the given memory usage,
+    /// as a factor between 0 and 255. 0 means no collection is needed.
+    pub fn collect_factor(&self, usage: usize) -> u8 {
+        if usage < self.soft_limit {
+            return 0;
+        }
+        if usage >= self.hard_limit {
+            return MAX_COLLECT_FACTOR;
+        }
+        let range = (self.hard_limit - self.soft_limit) as u128;
+        let factor = (usage - self.soft_limit) as u128 * u8::MAX as u128 / range;
+        factor.clamp(1, MAX_COLLECT_FACTOR as u128) as u8
+    }
+}
+
+/// The collect factor is capped to keep the pauses during execution short,
+/// and to collect the tasks that are cheapest to recompute first. Collecting
+/// everything at once would also empty the cells that active tasks read, which
+/// need to be recomputed on the next update.
+const MAX_COLLECT_FACTOR: u8 = u8::MAX / 8;
+
+/// The interval is doubled up to this many times while runs don't free memory.
+const MAX_BACKOFF: u32 = 4;
+
+/// Metrics about garbage collection since the backend was created.
+#[derive(Default, Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcMetrics {
+    /// How often garbage collection ran.
+    pub runs: usize,
+    /// How many of the runs happened while idle.
+    pub idle_runs: usize,
+    /// How many of the runs happened at the hard limit.
+    pub hard_limit_runs: usize,
+    /// How often a run was skipped because of the minimum interval.
+    pub throttled_runs: usize,
+    /// The memory usage at the start of the last run.
+    pub memory_usage: usize,
+    /// The memory freed by all runs.
+    pub freed: usize,
+    /// The time spent in all runs.
+    pub duration: Duration,
+    /// The actions performed by all runs.
+    pub stats: GcStats,
+}
+
+/// Applies the rate limits of a [GcPolicy] and collects [GcMetrics].
+pub struct GcScheduler {
+    state: Mutex<GcSchedulerState>,
+}
+
+struct GcSchedulerState {
+    running: bool,
+    last_run: Option<Instant>,
+    backoff: u32,
+    unload_tokens: usize,
+    unload_tokens_refilled: Instant,
+    metrics: GcMetrics,
+}
+
+impl GcScheduler {
+    pub fn new(policy: &GcPolicy) -> Self {
+        Self {
+            state: Mutex::new(GcSchedulerState {
+                running: false,
+                last_run: None,
+                backoff: 0,
+                unload_tokens: policy.max_unloads_per_second,
+                unload_tokens_refilled: Instant::now(),
+                metrics: GcMetrics::default(),
+            }),
+        }
+    }
+
+    /// Decides if a run may start now. Returns the number of tasks it may
+    /// unload, or [None] when the run should be skipped.
+    pub fn start(
+        &self,
+        policy: &GcPolicy,
+        usage: usize,
+        idle: bool,
+        now: Instant,
+    ) -> Option<usize> {
+        let mut state = self.state.lock();
+        if state.running {
+            return None;
+        }
+        let at_hard_limit = usage >= policy.hard_limit;
+        if !idle && !at_hard_limit {
+            if let Some(last_run) = state.last_run {
+                if now.duration_since(last_run) < policy.min_interval * (1 << state.backoff) {
+                    state.metrics.throttled_runs += 1;
+                    return None;
+                }
+            }
+        }
+        let elapsed = now.duration_since(state.unload_tokens_refilled);
+        let refill = (elapsed.as_secs_f64() * policy.max_unloads_per_second as f64) as usize;
+        if refill > 0 {
+            state.unload_tokens = min(
+                policy.max_unloads_per_second,
+                state.unload_tokens.saturating_add(refill),
+            );
+            state.unload_tokens_refilled = now;
+        }
+        state.running = true;
+        state.last_run = Some(now);
+        state.metrics.runs += 1;
+        if idle {
+            state.metrics.idle_runs += 1;
+        }
+        state.metrics.memory_usage = usage;
+        if at_hard_limit {
+            state.metrics.hard_limit_runs += 1;
+            Some(usize::MAX)
+        } else {
+            Some(state.unload_tokens)
+        }
+    }
+
+    /// Records the result of a run started with [GcScheduler::start].
+    pub fn finish(&self, stats: Option<&GcStats>, freed: usize, duration: Duration) {
+        let mut state = self.state.lock();
+        state.running = false;
+        if let Some(stats) = stats {
+            state.unload_tokens = state.unload_tokens.saturating_sub(stats.unloaded);
+            state.metrics.stats += stats;
+        }
+        // Back off while collecting doesn't help, to avoid wasting time on
+        // tasks that are still in use.
+        state.backoff = if freed == 0 {
+            min(state.backoff + 1, MAX_BACKOFF)
+        } else {
+            0
+        };
+        state.metrics.freed += freed;
+        state.metrics.duration += duration;
+    }
+
+    pub fn metrics(&self) -> GcMetrics {
+        self.state.lock().metrics.clone()
+    }
+}
+
 /// State about garbage collection for a task.
 #[derive(Debug, Default)]
 pub struct GcTaskState {
@@ -151,6 +347,7 @@ impl GcQueue {
     pub fn run_gc(
         &self,
         factor: u8,
+        mut unload_budget: usize,
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> Option<(GcPriority, usize, GcStats)> {
@@ -178,6 +375,7 @@ impl GcQueue {
                     &mut task_duration_cache,
                     &mut scope_active_cache,
                     &mut stats,
+                    &mut unload_budget,
                     backend,
                     turbo_tasks,
                 )
@@ -224,3 +422,69 @@ pub fn to_exp_u8(value: u64) -> u8 {
         .unwrap_or(0x7000_0000_0000_0000)
         .trailing_zeros() as u8
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: usize = 1024 * 1024;
+
+    fn policy() -> GcPolicy {
+        GcPolicy {
+            soft_limit: 100 * MB,
+            hard_limit: 200 * MB,
+            min_interval: Duration::from_millis(100),
+            max_unloads_per_second: 10,
+        }
+    }
+
+    #[test]
+    fn collect_factor_scales_between_limits() {
+        let policy = policy();
+        assert_eq!(policy.collect_factor(50 * MB), 0);
+        assert_eq!(policy.collect_factor(100 * MB), 1);
+        assert_eq!(policy.collect_factor(110 * MB), 25);
+        assert_eq!(policy.collect_factor(199 * MB), MAX_COLLECT_FACTOR);
+        assert_eq!(policy.collect_factor(200 * MB), MAX_COLLECT_FACTOR);
+    }
+
+    #[test]
+    fn scheduler_rate_limits_below_hard_limit() {
+        let policy = policy();
+        let scheduler = GcScheduler::new(&policy);
+        let start = Instant::now();
+        let usage = 150 * MB;
+
+        assert_eq!(scheduler.start(&policy, usage, false, start), Some(10));
+        // Only one run at a time
+        assert_eq!(scheduler.start(&policy, usage, true, start), None);
+        let stats = GcStats {
+            unloaded: 10,
+            ..Default::default()
+        };
+        scheduler.finish(Some(&stats), 0, Duration::ZERO);
+
+        // Throttled by the interval, which doubled since nothing was freed
+        let later = start + Duration::from_millis(150);
+        assert_eq!(scheduler.start(&policy, usage, false, later), None);
+        // Idle runs are not throttled, but the unload budget is used up
+        let budget = scheduler.start(&policy, usage, true, later).unwrap();
+        assert!(budget < 10);
+        scheduler.finish(None, MB, Duration::ZERO);
+
+        // The hard limit ignores all limits
+        assert_eq!(
+            scheduler.start(&policy, 200 * MB, false, later),
+            Some(usize::MAX)
+        );
+        scheduler.finish(None, MB, Duration::ZERO);
+
+        let metrics = scheduler.metrics();
+        assert_eq!(metrics.runs, 3);
+        assert_eq!(metrics.idle_runs, 1);
+        assert_eq!(metrics.hard_limit_runs, 1);
+        assert_eq!(metrics.throttled_runs, 1);
+        assert_eq!(metrics.freed, 2 * MB);
+        assert_eq!(metrics.stats.unloaded, 10);
+    }
+}
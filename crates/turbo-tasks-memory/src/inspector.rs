@@ -0,0 +1,413 @@
+//! Queries to inspect the live task graph of a [MemoryBackend], e.g. from a
+//! dev server endpoint. All results are serializable to JSON. Invalidations
+//! and recomputations are only recorded after
+//! [MemoryBackend::enable_inspector].
+//!
+//! The propagation of invalidations can also be traced, see
+//! [INVALIDATION_TRACE_TARGET].
+
+use std::{
+    collections::HashSet,
+    mem::{replace, take},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use nohash_hasher::BuildNoHashHasher;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{field::Empty, trace, trace_span, Level, Span};
+use turbo_tasks::{util::StaticOrArc, InvalidationReason, TaskId};
+
+use crate::{stats::ReferenceType, MemoryBackend};
+
+/// The maximum number of steps followed when explaining an invalidation.
+const MAX_INVALIDATION_CHAIN: usize = 32;
+
+/// Tracing target of the invalidation propagation trace. Enable it with
+/// `turbo_tasks_memory::invalidation=trace` to record which reason dirtied
+/// which tasks and whether their recomputation cascaded.
+pub const INVALIDATION_TRACE_TARGET: &str = "turbo_tasks_memory::invalidation";
+
+fn invalidation_trace_enabled() -> bool {
+    tracing::enabled!(target: INVALIDATION_TRACE_TARGET, Level::TRACE)
+}
+
+#[derive(Clone)]
+enum Cause {
+    External(Option<StaticOrArc<dyn InvalidationReason>>),
+    Dependency(TaskId),
+}
+
+struct Invalidation {
+    cause: Cause,
+    /// The task hasn't finished an execution since it was invalidated.
+    pending: bool,
+    /// The span the recomputation of the task is traced in. It's a child of
+    /// the span of the cause.
+    parent_span: Span,
+    /// The span of the current recomputation, while it's running.
+    recompute_span: Span,
+    /// The number of tasks the current recomputation has invalidated.
+    invalidated: usize,
+}
+
+/// Records why tasks were invalidated and how long their recomputation took.
+///
+/// Invalidations are kept until the task is unloaded while the inspector is
+/// enabled. Otherwise they're only kept for the invalidation trace, until the
+/// recomputation finishes.
+pub(crate) struct InspectorLog {
+    enabled: AtomicBool,
+    invalidations: DashMap<TaskId, Invalidation, BuildNoHashHasher<TaskId>>,
+    recomputations: Mutex<Recomputations>,
+}
+
+#[derive(Default)]
+struct Recomputations {
+    current: Vec<(TaskId, Duration)>,
+    last_update: Vec<(TaskId, Duration)>,
+}
+
+impl InspectorLog {
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            invalidations: DashMap::default(),
+            recomputations: Mutex::new(Recomputations::default()),
+        }
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Release);
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// Whether invalidations need to be recorded, for the inspector or the
+    /// invalidation trace.
+    fn is_recording(&self) -> bool {
+        self.is_enabled() || invalidation_trace_enabled()
+    }
+
+    pub fn invalidated_externally(
+        &self,
+        task: TaskId,
+        reason: Option<StaticOrArc<dyn InvalidationReason>>,
+        backend: &MemoryBackend,
+    ) {
+        if !self.is_recording() {
+            return;
+        }
+        let parent_span = if invalidation_trace_enabled() {
+            match &reason {
+                Some(reason) => trace_span!(
+                    target: INVALIDATION_TRACE_TARGET,
+                    parent: None,
+                    "invalidation",
+                    name = %reason
+                ),
+                None => trace_span!(
+                    target: INVALIDATION_TRACE_TARGET,
+                    parent: None,
+                    "invalidation",
+                    name = "unknown reason"
+                ),
+            }
+        } else {
+            Span::none()
+        };
+        self.record(task, Cause::External(reason), parent_span, backend);
+    }
+
+    pub fn invalidated_by(&self, task: TaskId, cause: TaskId, backend: &MemoryBackend) {
+        if !self.is_recording() {
+            return;
+        }
+        let parent_span = match self.invalidations.get_mut(&cause) {
+            Some(mut invalidation) if !invalidation.recompute_span.is_disabled() => {
+                invalidation.invalidated += 1;
+                invalidation.recompute_span.clone()
+            }
+            _ => Span::none(),
+        };
+        self.record(task, Cause::Dependency(cause), parent_span, backend);
+    }
+
+    fn record(&self, task: TaskId, cause: Cause, parent_span: Span, backend: &MemoryBackend) {
+        if !parent_span.is_disabled() {
+            let description = backend.with_task(task, |task| task.get_description());
+            trace!(
+                target: INVALIDATION_TRACE_TARGET,
+                parent: &parent_span,
+                name = %description,
+                "invalidated"
+            );
+        }
+        self.invalidations.insert(
+            task,
+            Invalidation {
+                cause,
+                pending: true,
+                parent_span,
+                recompute_span: Span::none(),
+                invalidated: 0,
+            },
+        );
+    }
+
+    /// Returns the span to trace the execution of the task in, when it
+    /// recomputes an invalidated task and the invalidation trace is enabled.
+    pub fn execution_started(&self, task: TaskId, backend: &MemoryBackend) -> Option<Span> {
+        if !invalidation_trace_enabled() {
+            return None;
+        }
+        let mut invalidation = self.invalidations.get_mut(&task)?;
+        if !invalidation.pending || invalidation.parent_span.is_disabled() {
+            return None;
+        }
+        let description = backend.with_task(task, |task| task.get_description());
+        let span = trace_span!(
+            target: INVALIDATION_TRACE_TARGET,
+            parent: &invalidation.parent_span,
+            "recompute",
+            name = %description,
+            outcome = Empty,
+            invalidated = Empty
+        );
+        invalidation.recompute_span = span.clone();
+        invalidation.invalidated = 0;
+        Some(span)
+    }
+
+    pub fn execution_completed(&self, task: TaskId, duration: Duration) {
+        if !self.is_recording() {
+            return;
+        }
+        let recomputed = self
+            .invalidations
+            .get_mut(&task)
+            .map_or(false, |mut invalidation| {
+                let span = replace(&mut invalidation.recompute_span, Span::none());
+                if !span.is_disabled() {
+                    // A recomputation that didn't invalidate any task produced equal
+                    // values and stops the propagation.
+                    let outcome = if invalidation.invalidated == 0 {
+                        "unchanged"
+                    } else {
+                        "cascaded"
+                    };
+                    span.record("outcome", outcome);
+                    span.record("invalidated", invalidation.invalidated);
+                }
+                take(&mut invalidation.pending)
+            });
+        if !self.is_enabled() {
+            self.invalidations.remove(&task);
+        } else if recomputed {
+            self.recomputations.lock().current.push((task, duration));
+        }
+    }
+
+    pub fn task_unloaded(&self, task: TaskId) {
+        self.invalidations.remove(&task);
+    }
+
+    /// Called when all scheduled work has finished. Recomputations recorded
+    /// since the previous update become the ones of the last update.
+    pub fn update_finished(&self) {
+        let mut recomputations = self.recomputations.lock();
+        if !recomputations.current.is_empty() {
+            recomputations.last_update = take(&mut recomputations.current);
+        }
+    }
+
+    fn cause(&self, task: TaskId) -> Option<Cause> {
+        self.invalidations
+            .get(&task)
+            .map(|invalidation| invalidation.cause.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSummary {
+    pub id: TaskId,
+    pub description: String,
+    pub function: Option<&'static str>,
+    pub state: String,
+    pub active: bool,
+    pub last_duration_ms: f64,
+    pub executions: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDetails {
+    #[serde(flatten)]
+    pub summary: TaskSummary,
+    pub dependencies: Vec<TaskSummary>,
+    pub dependents: Vec<TaskSummary>,
+    pub children: Vec<TaskSummary>,
+    pub inputs: Vec<TaskSummary>,
+    /// Why the task was last invalidated, starting with the task itself and
+    /// followed by the tasks that caused it, up to an external reason.
+    pub invalidation: Vec<InvalidationStep>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum InvalidationStep {
+    /// The task was invalidated because a task it depends on has changed.
+    #[serde(rename_all = "camelCase")]
+    Dependency {
+        task: TaskId,
+        changed_by: TaskSummary,
+    },
+    /// The task was invalidated by an external event, e.g. a file change.
+    #[serde(rename_all = "camelCase")]
+    External {
+        task: TaskId,
+        reason: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recomputation {
+    pub task: TaskSummary,
+    pub duration_ms: f64,
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl MemoryBackend {
+    /// Starts recording why tasks are invalidated and how long their
+    /// recomputations take, for [MemoryBackend::inspect_task] and
+    /// [MemoryBackend::inspect_slowest_recomputations].
+    pub fn enable_inspector(&self) {
+        self.inspector_log.enable();
+    }
+
+    pub fn inspect_task_summary(&self, id: TaskId) -> TaskSummary {
+        self.with_task(id, |task| {
+            let info = task.get_stats_info(self);
+            TaskSummary {
+                id,
+                description: task.get_description(),
+                function: task.get_function_name(),
+                state: task.get_state_description(),
+                active: info.active,
+                last_duration_ms: duration_ms(info.last_duration),
+                executions: info.executions,
+            }
+        })
+    }
+
+    /// Searches cached tasks whose function name contains `query`, ignoring
+    /// case. The slowest tasks come first.
+    pub fn inspect_search(&self, query: &str, limit: usize) -> Vec<TaskSummary> {
+        let query = query.to_lowercase();
+        let mut results = Vec::new();
+        self.with_all_cached_tasks(|id| {
+            let matches = self.with_task(id, |task| {
+                task.get_function_name()
+                    .map_or(false, |name| name.to_lowercase().contains(&query))
+            });
+            if matches {
+                results.push(self.inspect_task_summary(id));
+            }
+        });
+        results.sort_by(|a, b| {
+            b.last_duration_ms
+                .total_cmp(&a.last_duration_ms)
+                .then(a.id.cmp(&b.id))
+        });
+        results.truncate(limit);
+        results
+    }
+
+    /// Returns the task with its edges in both directions and the chain of
+    /// events that caused its last invalidation. Returns [None] for unknown
+    /// task ids.
+    pub fn inspect_task(&self, id: TaskId) -> Option<TaskDetails> {
+        self.memory_tasks.get(*id)?;
+        let mut dependencies = Vec::new();
+        let mut children = Vec::new();
+        let mut inputs = Vec::new();
+        let references = self.with_task(id, |task| task.get_stats_references().tasks);
+        // A task can be referenced multiple times, e.g. by reading its output and cells
+        let mut seen = HashSet::new();
+        for (ty, task) in references {
+            if !seen.insert((ty, task)) {
+                continue;
+            }
+            let list = match ty {
+                ReferenceType::Child => &mut children,
+                ReferenceType::Dependency => &mut dependencies,
+                ReferenceType::Input => &mut inputs,
+            };
+            list.push(self.inspect_task_summary(task));
+        }
+        let mut dependents = self.with_task(id, |task| task.get_dependents());
+        dependents.sort();
+        Some(TaskDetails {
+            summary: self.inspect_task_summary(id),
+            dependencies,
+            dependents: dependents
+                .into_iter()
+                .map(|task| self.inspect_task_summary(task))
+                .collect(),
+            children,
+            inputs,
+            invalidation: self.inspect_invalidation(id),
+        })
+    }
+
+    /// Follows the recorded invalidation causes from `id` back to the
+    /// external event that started it.
+    pub fn inspect_invalidation(&self, id: TaskId) -> Vec<InvalidationStep> {
+        let mut steps = Vec::new();
+        let mut current = id;
+        while steps.len() < MAX_INVALIDATION_CHAIN {
+            match self.inspector_log.cause(current) {
+                Some(Cause::Dependency(cause)) => {
+                    steps.push(InvalidationStep::Dependency {
+                        task: current,
+                        changed_by: self.inspect_task_summary(cause),
+                    });
+                    current = cause;
+                }
+                Some(Cause::External(reason)) => {
+                    steps.push(InvalidationStep::External {
+                        task: current,
+                        reason: reason.map(|reason| reason.to_string()),
+                    });
+                    break;
+                }
+                None => break,
+            }
+        }
+        steps
+    }
+
+    /// Returns the slowest recomputations of invalidated tasks during the last
+    /// update, slowest first.
+    pub fn inspect_slowest_recomputations(&self, limit: usize) -> Vec<Recomputation> {
+        let mut recomputations = self.inspector_log.recomputations.lock().last_update.clone();
+        recomputations.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        recomputations
+            .into_iter()
+            .take(limit)
+            .map(|(task, duration)| Recomputation {
+                task: self.inspect_task_summary(task),
+                duration_ms: duration_ms(duration),
+            })
+            .collect()
+    }
+}
@@ -11,15 +11,19 @@ mod cell;
 mod concurrent_priority_queue;
 mod count_hash_set;
 mod gc;
+pub mod inspector;
 mod map_guard;
 mod memory_backend;
 mod memory_backend_with_pg;
 mod output;
 mod priority_pair;
+mod priority_scheduler;
 pub mod scope;
+pub mod state_snapshot;
 pub mod stats;
 mod task;
 pub mod viz;
 
+pub use gc::{GcMetrics, GcPolicy, GcStats};
 pub use memory_backend::MemoryBackend;
 pub use memory_backend_with_pg::MemoryBackendWithPersistedGraph;
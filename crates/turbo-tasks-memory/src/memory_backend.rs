@@ -1,7 +1,6 @@
 use std::{
     borrow::{Borrow, Cow},
     cell::RefCell,
-    cmp::min,
     collections::VecDeque,
     future::Future,
     hash::{BuildHasher, BuildHasherDefault, Hash},
@@ -22,20 +21,23 @@ use tokio::task::futures::TaskLocalFuture;
 use tracing::{trace_span, Instrument};
 use turbo_tasks::{
     backend::{
-        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionSpec,
+        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionSpec, TaskPriority,
         TransientTaskType,
     },
     event::EventListener,
     primitives::RawVcSetVc,
-    util::{IdFactory, NoMoveVec},
-    CellId, RawVc, TaskId, TraitTypeId, TurboTasksBackendApi, Unused,
+    util::{IdFactory, NoMoveVec, StaticOrArc},
+    CellId, InvalidationReason, RawVc, SharedReference, TaskId, TraitTypeId, TurboTasksBackendApi,
+    Unused,
 };
 
 use crate::{
     cell::RecomputingCell,
-    gc::GcQueue,
+    gc::{GcMetrics, GcPolicy, GcQueue, GcScheduler},
+    inspector::InspectorLog,
     output::Output,
     priority_pair::PriorityPair,
+    priority_scheduler::PriorityScheduler,
     scope::{TaskScope, TaskScopeId},
     task::{
         run_add_to_scope_queue, run_remove_from_scope_queue, Task, TaskDependency,
@@ -44,17 +46,23 @@ use crate::{
 };
 
 pub struct MemoryBackend {
-    memory_tasks: NoMoveVec<Task, 13>,
+    pub(crate) memory_tasks: NoMoveVec<Task, 13>,
     memory_task_scopes: NoMoveVec<TaskScope>,
     scope_id_factory: IdFactory<TaskScopeId>,
     pub(crate) initial_scope: TaskScopeId,
     backend_jobs: NoMoveVec<Job>,
     backend_job_id_factory: IdFactory<BackendJobId>,
     task_cache: DashMap<Arc<PersistentTaskType>, TaskId, BuildHasherDefault<FxHasher>>,
-    memory_limit: usize,
+    gc_policy: Option<GcPolicy>,
     gc_queue: Option<GcQueue>,
+    gc_scheduler: Option<GcScheduler>,
     idle_gc_active: AtomicBool,
+    /// Cell contents restored from a [crate::state_snapshot::StateSnapshot],
+    /// that replace the next update of the cell.
+    pub(crate) restored_cells: DashMap<(TaskId, CellId), SharedReference>,
     scope_add_remove_priority: PriorityPair,
+    pub(crate) priority_scheduler: PriorityScheduler,
+    pub(crate) inspector_log: InspectorLog,
 }
 
 impl Default for MemoryBackend {
@@ -64,7 +72,18 @@ impl Default for MemoryBackend {
 }
 
 impl MemoryBackend {
+    /// Creates a backend that collects garbage when the memory usage gets
+    /// close to `memory_limit`. Pass `usize::MAX` to disable garbage
+    /// collection.
     pub fn new(memory_limit: usize) -> Self {
+        Self::with_gc_policy(
+            (memory_limit != usize::MAX).then(|| GcPolicy::from_memory_limit(memory_limit)),
+        )
+    }
+
+    /// Creates a backend that collects garbage according to `gc_policy`, or
+    /// never when it's [None].
+    pub fn with_gc_policy(gc_policy: Option<GcPolicy>) -> Self {
         let memory_task_scopes = NoMoveVec::new();
         let scope_id_factory = IdFactory::new();
         let initial_scope: TaskScopeId = scope_id_factory.get();
@@ -79,10 +98,14 @@ impl MemoryBackend {
             backend_jobs: NoMoveVec::new(),
             backend_job_id_factory: IdFactory::new(),
             task_cache: DashMap::default(),
-            memory_limit,
-            gc_queue: (memory_limit != usize::MAX).then(GcQueue::new),
+            gc_queue: gc_policy.map(|_| GcQueue::new()),
+            gc_scheduler: gc_policy.as_ref().map(GcScheduler::new),
+            gc_policy,
             idle_gc_active: AtomicBool::new(false),
+            restored_cells: DashMap::new(),
             scope_add_remove_priority: PriorityPair::new(),
+            priority_scheduler: PriorityScheduler::new(),
+            inspector_log: InspectorLog::new(),
         }
     }
 
@@ -239,43 +262,65 @@ impl MemoryBackend {
     }
 
     pub fn run_gc(&self, idle: bool, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
-        if let Some(gc_queue) = &self.gc_queue {
-            const MAX_COLLECT_FACTOR: u8 = u8::MAX / 8;
-
-            let mem_limit = self.memory_limit;
-
-            let usage = turbo_tasks_malloc::TurboMalloc::memory_usage();
-            let target = if idle {
-                mem_limit * 3 / 4
-            } else {
-                mem_limit * 7 / 8
-            };
-            if usage < target {
+        let (Some(policy), Some(gc_queue), Some(gc_scheduler)) =
+            (&self.gc_policy, &self.gc_queue, &self.gc_scheduler)
+        else {
+            return;
+        };
+        let mut usage = turbo_tasks_malloc::TurboMalloc::memory_usage();
+        loop {
+            let collect_factor = policy.collect_factor(usage);
+            if collect_factor == 0 {
                 if idle {
                     // Always run propagation when idle
-                    gc_queue.run_gc(0, self, turbo_tasks);
+                    gc_queue.run_gc(0, 0, self, turbo_tasks);
+                    self.idle_gc_active.store(false, Ordering::Release);
                 }
                 return;
             }
 
-            let collect_factor = min(
-                MAX_COLLECT_FACTOR as usize,
-                (usage - target) * u8::MAX as usize / (mem_limit - target),
-            ) as u8;
+            let start = Instant::now();
+            let Some(unload_budget) = gc_scheduler.start(policy, usage, idle, start) else {
+                if idle {
+                    self.idle_gc_active.store(false, Ordering::Release);
+                }
+                return;
+            };
+            let collected = gc_queue.run_gc(collect_factor, unload_budget, self, turbo_tasks);
+            let new_usage = turbo_tasks_malloc::TurboMalloc::memory_usage();
+            let freed = usage.saturating_sub(new_usage);
+            gc_scheduler.finish(
+                collected.as_ref().map(|(_, _, stats)| stats),
+                freed,
+                start.elapsed(),
+            );
 
-            let collected = gc_queue.run_gc(collect_factor, self, turbo_tasks);
+            // At the hard limit, continue with the next cheapest tasks until the usage is
+            // below it again, instead of collecting everything at once.
+            if new_usage >= policy.hard_limit && collected.is_some() && freed > 0 {
+                usage = new_usage;
+                continue;
+            }
 
             if idle {
-                if let Some((_collected, _count, _stats)) = collected {
+                // Continue while there is work left and it helps. Otherwise the next idle
+                // phase starts over.
+                if collected.is_some() && freed > 0 {
                     let job = self.create_backend_job(Job::GarbageCollection);
                     turbo_tasks.schedule_backend_background_job(job);
                 } else {
                     self.idle_gc_active.store(false, Ordering::Release);
                 }
             }
+            return;
         }
     }
 
+    /// Returns metrics about garbage collection, when it's enabled.
+    pub fn gc_metrics(&self) -> Option<GcMetrics> {
+        self.gc_scheduler.as_ref().map(GcScheduler::metrics)
+    }
+
     pub(crate) fn get_or_create_read_task_collectibles_task(
         &self,
         task_id: TaskId,
@@ -346,6 +391,23 @@ impl MemoryBackend {
         task: Task,
         root_scoped: bool,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> TaskId {
+        let result_task =
+            self.insert_fresh_task(task_cache, key, new_id, task, root_scoped, turbo_tasks);
+        self.connect_task_child(parent_task, result_task, turbo_tasks);
+        result_task
+    }
+
+    /// Inserts a fresh task into the task cache, or returns the cached task
+    /// when there is one for `key` already.
+    fn insert_fresh_task<K: Eq + Hash, H: BuildHasher + Clone>(
+        &self,
+        task_cache: &DashMap<K, TaskId, H>,
+        key: K,
+        new_id: Unused<TaskId>,
+        task: Task,
+        root_scoped: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> TaskId {
         let new_id = new_id.into();
         // Safety: We have a fresh task id that nobody knows about yet
@@ -369,10 +431,32 @@ impl MemoryBackend {
                 *entry.get()
             }
         };
-        self.connect_task_child(parent_task, result_task, turbo_tasks);
         result_task
     }
 
+    /// Gets or creates a persistent task without connecting it to a parent
+    /// task. It's connected once a task calls it.
+    pub(crate) fn get_or_create_unconnected_task(
+        &self,
+        mut task_type: PersistentTaskType,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> TaskId {
+        if let Some(task) = self.task_cache.get(&task_type) {
+            return *task;
+        }
+        task_type.shrink_to_fit();
+        let task_type = Arc::new(task_type);
+        let id = turbo_tasks.get_fresh_task_id();
+        let task = Task::new_persistent(
+            // Safety: That task will hold the value, but we are still in
+            // control of the task
+            *unsafe { id.get_unchecked() },
+            task_type.clone(),
+            turbo_tasks.stats_type(),
+        );
+        self.insert_fresh_task(&self.task_cache, task_type, id, task, false, turbo_tasks)
+    }
+
     fn lookup_and_connect_task<K: Hash + Eq, Q, H: BuildHasher + Clone>(
         &self,
         parent_task: TaskId,
@@ -394,6 +478,7 @@ impl MemoryBackend {
 
 impl Backend for MemoryBackend {
     fn idle_start(&self, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
+        self.inspector_log.update_finished();
         if self
             .idle_gc_active
             .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
@@ -405,6 +490,7 @@ impl Backend for MemoryBackend {
     }
 
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
+        self.inspector_log.invalidated_externally(task, None, self);
         self.with_task(task, |task| task.invalidate(self, turbo_tasks));
     }
 
@@ -414,6 +500,32 @@ impl Backend for MemoryBackend {
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) {
         for task in tasks.into_iter() {
+            self.inspector_log.invalidated_externally(task, None, self);
+            self.with_task(task, |task| {
+                task.invalidate(self, turbo_tasks);
+            });
+        }
+    }
+
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        reason: StaticOrArc<dyn InvalidationReason>,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        self.inspector_log
+            .invalidated_externally(task, Some(reason), self);
+        self.with_task(task, |task| task.invalidate(self, turbo_tasks));
+    }
+
+    fn invalidate_tasks_caused_by(
+        &self,
+        tasks: Vec<TaskId>,
+        cause: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        for task in tasks.into_iter() {
+            self.inspector_log.invalidated_by(task, cause, self);
             self.with_task(task, |task| {
                 task.invalidate(self, turbo_tasks);
             });
@@ -439,7 +551,11 @@ impl Backend for MemoryBackend {
         task: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> Option<TaskExecutionSpec> {
-        self.with_task(task, |task| task.execute(self, turbo_tasks))
+        let mut spec = self.with_task(task, |task| task.execute(self, turbo_tasks))?;
+        if let Some(span) = self.inspector_log.execution_started(task, self) {
+            spec.future = Box::pin(spec.future.instrument(span));
+        }
+        Some(spec)
     }
 
     fn task_execution_result(
@@ -465,6 +581,7 @@ impl Backend for MemoryBackend {
             task.execution_completed(duration, instant, stateful, self, turbo_tasks)
         });
         if !reexecute {
+            self.inspector_log.execution_completed(task_id, duration);
             self.run_gc(false, turbo_tasks);
             if let Some(gc_queue) = &self.gc_queue {
                 gc_queue.task_executed(task_id, duration);
@@ -473,6 +590,40 @@ impl Backend for MemoryBackend {
         reexecute
     }
 
+    fn task_execution_cancelled(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> bool {
+        self.with_task(task, |task| task.execution_cancelled(self, turbo_tasks))
+    }
+
+    fn root_task_cancelled(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        self.with_task(task, |task| task.root_cancelled(self, turbo_tasks))
+    }
+
+    fn try_admit_task_execution(
+        &self,
+        task: TaskId,
+        priority: TaskPriority,
+        _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> Result<TaskPriority, EventListener> {
+        self.priority_scheduler.try_admit(task, priority)
+    }
+
+    fn release_task_execution(
+        &self,
+        task: TaskId,
+        priority: TaskPriority,
+        _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        self.priority_scheduler.release(task, priority)
+    }
+
     fn try_read_task_output(
         &self,
         task: TaskId,
@@ -536,6 +687,7 @@ impl Backend for MemoryBackend {
                         if schedule {
                             task.recompute(self, turbo_tasks);
                         }
+                        self.priority_scheduler.boost(task_id);
                         Ok(Err(listener))
                     }
                 }
@@ -572,6 +724,7 @@ impl Backend for MemoryBackend {
                     if schedule {
                         task.recompute(self, turbo_tasks);
                     }
+                    self.priority_scheduler.boost(task_id);
                     Ok(Err(listener))
                 }
             }
@@ -621,6 +774,14 @@ impl Backend for MemoryBackend {
         content: CellContent,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) {
+        let content = if self.restored_cells.is_empty() {
+            content
+        } else {
+            match self.restored_cells.remove(&(task, index)) {
+                Some((_, restored)) => CellContent(Some(restored)),
+                None => content,
+            }
+        };
         self.with_task(task, |task| {
             task.with_cell_mut(index, |cell| cell.assign(content, turbo_tasks))
         })
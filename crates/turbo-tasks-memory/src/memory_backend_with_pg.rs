@@ -1,13 +1,13 @@
 use std::{
     borrow::Cow,
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, HashSet},
     fmt::Debug,
     future::Future,
     mem::{replace, take},
     pin::Pin,
     sync::{
         atomic::{AtomicU32, AtomicUsize, Ordering},
-        Mutex, MutexGuard,
+        Arc, Mutex, MutexGuard,
     },
     time::{Duration, Instant},
 };
@@ -39,6 +39,8 @@ enum TaskType {
     Persistent(PersistentTaskType),
     Root(RootTaskFn),
     Once(Mutex<Pin<Box<dyn Future<Output = Result<RawVc>> + Send + 'static>>>),
+    /// Reads the collectibles of a task and all its transitive children
+    ReadCollectibles(TaskId, TraitTypeId),
 }
 
 impl Debug for TaskType {
@@ -47,6 +49,11 @@ impl Debug for TaskType {
             Self::Persistent(ty) => f.debug_tuple("Persistent").field(ty).finish(),
             Self::Root(_) => f.debug_tuple("Root").finish(),
             Self::Once(_) => f.debug_tuple("Once").finish(),
+            Self::ReadCollectibles(task, trait_type) => f
+                .debug_tuple("ReadCollectibles")
+                .field(task)
+                .field(trait_type)
+                .finish(),
         }
     }
 }
@@ -71,6 +78,10 @@ struct MemoryTaskState {
     output_dependent: AutoSet<TaskId, BuildNoHashHasher<TaskId>>,
     dependencies: AutoSet<RawVc>,
     children: AutoSet<TaskId, BuildNoHashHasher<TaskId>>,
+    collectibles: HashMap<(TraitTypeId, RawVc), i32>,
+    /// Read collectibles tasks that need to be invalidated when the
+    /// collectibles or children of this task change
+    collectibles_dependent: AutoSet<TaskId, BuildNoHashHasher<TaskId>>,
     event: Event,
     event_cells: Event,
 }
@@ -86,6 +97,8 @@ impl MemoryTaskState {
             output_dependent: Default::default(),
             dependencies: Default::default(),
             children: Default::default(),
+            collectibles: Default::default(),
+            collectibles_dependent: Default::default(),
             event: Event::new(move || format!("MemoryTaskState({task})::event")),
             event_cells: Event::new(move || format!("MemoryTaskState({task})::event_cells")),
         }
@@ -145,6 +158,7 @@ pub struct MemoryBackendWithPersistedGraph<P: PersistedGraph + 'static> {
     persist_capacity: AtomicUsize,
     persist_job: BackendJobId,
     partial_lookups: DashMap<PersistentTaskType, bool>,
+    read_collectibles_tasks: DashMap<(TaskId, TraitTypeId), TaskId>,
     #[cfg(feature = "unsafe_once_map")]
     partial_lookup: turbo_tasks::util::OnceConcurrentlyMap<PersistentTaskType, bool>,
     #[cfg(not(feature = "unsafe_once_map"))]
@@ -172,6 +186,7 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
             persist_capacity: AtomicUsize::new(num_cpus::get()),
             persist_job,
             partial_lookups: DashMap::new(),
+            read_collectibles_tasks: DashMap::new(),
             #[cfg(feature = "unsafe_once_map")]
             partial_lookup: turbo_tasks::util::OnceConcurrentlyMap::new(),
             #[cfg(not(feature = "unsafe_once_map"))]
@@ -274,6 +289,12 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
                     output_dependent: AutoSet::default(),
                     dependencies: data.dependencies.into_iter().collect(),
                     children: data.children.into_iter().collect(),
+                    collectibles: data
+                        .collectibles
+                        .into_iter()
+                        .map(|(trait_type, collectible, count)| ((trait_type, collectible), count))
+                        .collect(),
+                    collectibles_dependent: AutoSet::default(),
                     need_persist: Default::default(),
                     has_changes: Default::default(),
                     event: Event::new(move || format!("MemoryTaskState({task})::event")),
@@ -776,6 +797,7 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
                                 ref children,
                                 ref dependencies,
                                 ref cells,
+                                ref collectibles,
                                 ..
                             }),
                         ..
@@ -813,6 +835,12 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
                                                 .map(|(k, (s, _))| (*k, s.clone()))
                                                 .collect(),
                                             output: *output,
+                                            collectibles: collectibles
+                                                .iter()
+                                                .map(|(&(trait_type, collectible), &count)| {
+                                                    (trait_type, collectible, count)
+                                                })
+                                                .collect(),
                                         };
                                         let externally_active =
                                             task_info.active_parents.load(Ordering::Acquire) > 0;
@@ -925,6 +953,159 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
             }
         }
     }
+
+    fn update_collectible_count(
+        &self,
+        trait_type: TraitTypeId,
+        collectible: RawVc,
+        by: i32,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+    ) {
+        let (mut state, _) = self.mem_state_mut(task, turbo_tasks);
+        let mem_state = state.memory.as_mut().unwrap();
+        let count = mem_state
+            .collectibles
+            .entry((trait_type, collectible))
+            .or_default();
+        *count += by;
+        if *count == 0 {
+            mem_state.collectibles.remove(&(trait_type, collectible));
+        }
+        mem_state.has_changes = true;
+        let dependent = take(&mut mem_state.collectibles_dependent);
+        drop(state);
+        if !dependent.is_empty() {
+            turbo_tasks.schedule_notify_tasks_set(&dependent);
+        }
+    }
+
+    fn get_or_create_read_collectibles_task(
+        &self,
+        task: TaskId,
+        trait_type: TraitTypeId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+    ) -> TaskId {
+        if let Some(read_task) = self.read_collectibles_tasks.get(&(task, trait_type)) {
+            let read_task = *read_task;
+            self.connect(reader, read_task, turbo_tasks);
+            return read_task;
+        }
+
+        let read_task = turbo_tasks.get_fresh_task_id();
+        let read_task = read_task.into();
+        let new_task = Task {
+            active_parents: AtomicU32::new(1),
+            task_state: Mutex::new(TaskState {
+                scheduled: true,
+                memory: Some(MemoryTaskState::new(
+                    read_task,
+                    TaskFreshness::NeverExecuted,
+                )),
+                persisted: None,
+                active: true,
+                ..Default::default()
+            }),
+            task_type: TaskType::ReadCollectibles(task, trait_type),
+        };
+        // SAFETY: It's a fresh task id
+        unsafe {
+            self.tasks.insert(*read_task, new_task);
+        }
+        match self.read_collectibles_tasks.entry((task, trait_type)) {
+            Entry::Occupied(e) => {
+                let existing_task = *e.into_ref();
+                // SAFETY: We are still the only owner of this task and id
+                unsafe {
+                    self.tasks.remove(*read_task);
+                    let read_task = Unused::new_unchecked(read_task);
+                    turbo_tasks.reuse_task_id(read_task);
+                }
+                self.connect(reader, existing_task, turbo_tasks);
+                existing_task
+            }
+            Entry::Vacant(e) => {
+                e.insert(read_task);
+                self.only_known_to_memory_tasks.insert(read_task);
+                #[cfg(feature = "log_scheduled_tasks")]
+                println!("schedule({read_task}) in get_or_create_read_collectibles_task");
+                turbo_tasks.schedule(read_task);
+                self.connect_already_counted(reader, read_task, turbo_tasks);
+                read_task
+            }
+        }
+    }
+
+    /// Sums up the collectibles of a task and all its transitive children.
+    /// Returns a listener when one of the tasks hasn't finished yet.
+    fn read_collectibles(
+        &self,
+        read_task: TaskId,
+        task: TaskId,
+        trait_type: TraitTypeId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+    ) -> Result<AutoSet<RawVc>, EventListener> {
+        let mut counts: HashMap<RawVc, i32> = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut queue = vec![task];
+        while let Some(task) = queue.pop() {
+            if !visited.insert(task) {
+                continue;
+            }
+            let (mut state, task_info) = self.mem_state_mut(task, turbo_tasks);
+            if let TaskType::ReadCollectibles(..) = task_info.task_type {
+                // Read tasks are connected as children of their readers, but
+                // never emit collectibles themselves
+                continue;
+            }
+            let TaskState {
+                ref mut scheduled,
+                ref mut memory,
+                ..
+            } = *state;
+            let mem_state = memory.as_mut().unwrap();
+            if mem_state.freshness != TaskFreshness::Done {
+                let listener = mem_state.event.listen();
+                if !*scheduled {
+                    *scheduled = true;
+                    #[cfg(feature = "log_scheduled_tasks")]
+                    println!("schedule({task}) in read_collectibles");
+                    turbo_tasks.schedule(task);
+                }
+                return Err(listener);
+            }
+            mem_state.collectibles_dependent.insert(read_task);
+            for (&(collectible_trait_type, collectible), &count) in mem_state.collectibles.iter() {
+                if collectible_trait_type == trait_type {
+                    *counts.entry(collectible).or_default() += count;
+                }
+            }
+            queue.extend(mem_state.children.iter().copied());
+        }
+        Ok(counts
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(collectible, _)| collectible)
+            .collect())
+    }
+
+    async fn execute_read_collectibles(
+        read_task: TaskId,
+        task: TaskId,
+        trait_type: TraitTypeId,
+        turbo_tasks: Arc<dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>>,
+    ) -> Result<RawVc> {
+        let backend = turbo_tasks.backend();
+        let collectibles = loop {
+            // Only read the collectibles when all tasks have finished
+            match backend.read_collectibles(read_task, task, trait_type, &*turbo_tasks) {
+                Ok(collectibles) => break collectibles,
+                Err(listener) => listener.await,
+            }
+        };
+        Ok(RawVcSetVc::cell(collectibles).into())
+    }
 }
 
 impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
@@ -1073,7 +1254,12 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
         mem_state.freshness = TaskFreshness::NeverExecuted;
         let deps = take(&mut mem_state.dependencies);
         let children = take(&mut mem_state.children);
+        mem_state.collectibles.clear();
+        let collectibles_dependent = take(&mut mem_state.collectibles_dependent);
         drop(state);
+        if !collectibles_dependent.is_empty() {
+            turbo_tasks.schedule_notify_tasks_set(&collectibles_dependent);
+        }
         for dep in deps {
             let (mut state, _) = self.mem_state_mut(dep.get_task_id(), turbo_tasks);
             let mem_state = state.memory.as_mut().unwrap();
@@ -1101,6 +1287,9 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
                     Box::pin(async { Err(anyhow::anyhow!("Once task can only be executed once")) }),
                 )
             }
+            &TaskType::ReadCollectibles(read_task, trait_type) => Box::pin(
+                Self::execute_read_collectibles(task, read_task, trait_type, turbo_tasks.pin()),
+            ),
         };
         Some(TaskExecutionSpec { future })
     }
@@ -1138,6 +1327,37 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
         }
     }
 
+    fn task_execution_cancelled(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+    ) -> bool {
+        let (mut state, task_info) = self.mem_state_mut(task, turbo_tasks);
+        let TaskState {
+            ref mut scheduled,
+            ref mut memory,
+            active,
+            ..
+        } = *state;
+        let mem_state = memory.as_mut().unwrap();
+        if matches!(task_info.task_type, TaskType::Root(_) | TaskType::Once(_)) {
+            mem_state.output = Some(Err(SharedError::new(anyhow!(
+                "Task execution was cancelled"
+            ))));
+            mem_state.freshness = TaskFreshness::Done;
+        } else if active {
+            // Active tasks are still needed, keep it scheduled
+            return true;
+        } else {
+            mem_state.freshness = TaskFreshness::Dirty;
+        }
+        *scheduled = false;
+        // Readers are woken up and schedule the task again when needed
+        mem_state.event.notify(usize::MAX);
+        mem_state.event_cells.notify(usize::MAX);
+        false
+    }
+
     fn task_execution_completed(
         &self,
         task: TaskId,
@@ -1320,7 +1540,10 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
             ..
         } = *state;
         let mem_state = memory.as_mut().unwrap();
-        if mem_state.freshness == TaskFreshness::NeverExecuted {
+        // Cells created by a task that is still executing can be read already
+        if mem_state.freshness == TaskFreshness::NeverExecuted
+            && !mem_state.cells.contains_key(&index)
+        {
             if !*scheduled {
                 *scheduled = true;
                 #[cfg(feature = "log_scheduled_tasks")]
@@ -1430,32 +1653,34 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
 
     fn read_task_collectibles(
         &self,
-        _task: TaskId,
-        _trait_id: TraitTypeId,
-        _reader: TaskId,
-        _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+        task: TaskId,
+        trait_id: TraitTypeId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
     ) -> RawVcSetVc {
-        todo!()
+        let read_task =
+            self.get_or_create_read_collectibles_task(task, trait_id, reader, turbo_tasks);
+        RawVc::TaskOutput(read_task).into()
     }
 
     fn emit_collectible(
         &self,
-        _trait_id: TraitTypeId,
-        _collectible: RawVc,
-        _task: TaskId,
-        _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+        trait_id: TraitTypeId,
+        collectible: RawVc,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
     ) {
-        todo!()
+        self.update_collectible_count(trait_id, collectible, 1, task, turbo_tasks);
     }
 
     fn unemit_collectible(
         &self,
-        _trait_id: TraitTypeId,
-        _collectible: RawVc,
-        _task: TaskId,
-        _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+        trait_id: TraitTypeId,
+        collectible: RawVc,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
     ) {
-        todo!()
+        self.update_collectible_count(trait_id, collectible, -1, task, turbo_tasks);
     }
 
     fn update_task_cell(
@@ -1559,11 +1784,11 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
 
     fn connect_task(
         &self,
-        _task: TaskId,
-        _parent_task: TaskId,
-        _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
+        task: TaskId,
+        parent_task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
     ) {
-        todo!()
+        self.connect(parent_task, task, turbo_tasks);
     }
 
     fn create_transient_task(
@@ -1641,6 +1866,14 @@ impl<'a, P: PersistedGraph> PersistedGraphApi for MemoryBackendPersistedGraphApi
             _ => panic!("lookup_task_type should only be used for PersistentTaskType"),
         }
     }
+
+    fn try_lookup_task_type(&self, id: TaskId) -> Option<&PersistentTaskType> {
+        let task = self.backend.tasks.get(*id)?;
+        match &task.task_type {
+            TaskType::Persistent(ty) => Some(ty),
+            _ => None,
+        }
+    }
 }
 
 impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use dashmap::DashSet;
+use nohash_hasher::BuildNoHashHasher;
+use turbo_tasks::{
+    backend::TaskPriority,
+    current_task_priority,
+    event::{Event, EventListener},
+    TaskId,
+};
+
+use crate::concurrent_priority_queue::ConcurrentPriorityQueue;
+
+/// A task waiting for admission, or boosted before it asked for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Waiting {
+    /// Whether an execution waits for the task. Holding it back would also
+    /// block that execution, and everything waiting for that execution, so
+    /// it's admitted regardless of priority.
+    awaited: bool,
+    priority: TaskPriority,
+}
+
+/// Holds back task executions while executions with a higher priority are
+/// running, so interactive work preempts eager background work.
+///
+/// A task that an execution waits for is admitted right away, with the
+/// priority of that execution if it's higher. Otherwise a lower priority
+/// execution that was admitted before a higher priority one could wait
+/// forever for a task that is held back, and block the higher priority
+/// execution waiting for it in turn.
+pub struct PriorityScheduler {
+    /// The number of admitted executions per priority.
+    running: [AtomicUsize; TaskPriority::ALL.len()],
+    /// Tasks with an admitted execution.
+    admitted: DashSet<TaskId, BuildNoHashHasher<TaskId>>,
+    waiting: ConcurrentPriorityQueue<TaskId, Waiting, BuildNoHashHasher<TaskId>>,
+    event: Event,
+}
+
+impl PriorityScheduler {
+    pub fn new() -> Self {
+        Self {
+            running: Default::default(),
+            admitted: DashSet::default(),
+            waiting: ConcurrentPriorityQueue::new(),
+            event: Event::new(|| "PriorityScheduler::event".to_string()),
+        }
+    }
+
+    fn has_running_above(&self, priority: TaskPriority) -> bool {
+        TaskPriority::ALL
+            .iter()
+            .filter(|&&p| p > priority)
+            .any(|&p| self.running[p as usize].load(Ordering::Acquire) != 0)
+    }
+
+    fn can_admit(&self, waiting: Waiting) -> bool {
+        waiting.awaited || !self.has_running_above(waiting.priority)
+    }
+
+    /// Records that `task` waits for admission, merging with any boost it
+    /// already received.
+    fn merge(&self, task: TaskId, waiting: Waiting) {
+        self.waiting.upsert_with(
+            task,
+            || waiting,
+            |existing| {
+                existing.awaited |= waiting.awaited;
+                existing.priority = existing.priority.max(waiting.priority);
+            },
+        );
+    }
+
+    pub fn try_admit(
+        &self,
+        task: TaskId,
+        priority: TaskPriority,
+    ) -> Result<TaskPriority, EventListener> {
+        let mut waiting = Waiting {
+            awaited: false,
+            priority,
+        };
+        loop {
+            if let Some(boosted) = self.waiting.remove(&task) {
+                waiting.awaited |= boosted.awaited;
+                waiting.priority = waiting.priority.max(boosted.priority);
+            }
+            if self.can_admit(waiting) {
+                self.running[waiting.priority as usize].fetch_add(1, Ordering::AcqRel);
+                self.admitted.insert(task);
+                // A boost between the removal above and admitting the task
+                // has nothing left to do
+                self.waiting.remove(&task);
+                return Ok(waiting.priority);
+            }
+            let listener = self.event.listen();
+            self.merge(task, waiting);
+            // A boost or release might have happened before we started listening
+            let current = self.waiting.get(&task).unwrap_or(waiting);
+            if !self.can_admit(current) {
+                return Err(listener);
+            }
+        }
+    }
+
+    pub fn release(&self, task: TaskId, priority: TaskPriority) {
+        self.admitted.remove(&task);
+        // A boost that raced with the execution has nothing left to do, and
+        // must not carry over to the next execution of the task
+        self.waiting.remove(&task);
+        if self.running[priority as usize].fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.event.notify(usize::MAX);
+        }
+    }
+
+    /// Admits the task with at least the priority of the current execution,
+    /// which is about to wait for it.
+    pub fn boost(&self, task: TaskId) {
+        if let Some(priority) = current_task_priority() {
+            // A running task can't be held back anymore
+            if self.admitted.contains(&task) {
+                return;
+            }
+            self.merge(
+                task,
+                Waiting {
+                    awaited: true,
+                    priority,
+                },
+            );
+            // The task might have been admitted before the boost was recorded
+            if self.admitted.contains(&task) {
+                self.waiting.remove(&task);
+            }
+            self.event.notify(usize::MAX);
+        }
+    }
+
+    /// Admits all waiting tasks with at least the priority of the current
+    /// execution. Used when it waits for a whole scope to settle.
+    pub fn boost_all(&self) {
+        if let Some(current) = current_task_priority() {
+            self.waiting.update_all(|waiting| {
+                waiting.awaited = true;
+                waiting.priority = waiting.priority.max(current);
+            });
+            self.event.notify(usize::MAX);
+        }
+    }
+}
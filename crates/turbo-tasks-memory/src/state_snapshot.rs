@@ -0,0 +1,230 @@
+//! Snapshots of the [turbo_tasks::State] held by stateful tasks, to resume
+//! with the same state after a restart.
+//!
+//! A snapshot contains the serializable cells of all stateful tasks. Task ids
+//! referenced by the cells, e.g. by Vcs stored in a state, are replaced by the
+//! [PersistentTaskType] of the task, so they can be mapped to the tasks of
+//! the new [MemoryBackend]. After restoring, the first time a stateful task
+//! writes one of its cells the restored content is used instead.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{
+    backend::PersistentTaskType, registry, with_task_id_mapping, CellId, IdMapping,
+    SharedReference, TaskId, TurboTasksBackendApi,
+};
+
+use crate::MemoryBackend;
+
+/// The serializable cells of all stateful tasks. See
+/// [MemoryBackend::snapshot_state].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct StateSnapshot {
+    /// The types of the stateful tasks and of all tasks referenced by their
+    /// cells. Task ids are serialized as indices into this list.
+    tasks: Vec<serde_json::Value>,
+    cells: Vec<SnapshotCell>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SnapshotCell {
+    /// The index of the task in [StateSnapshot::tasks].
+    task: usize,
+    cell: CellId,
+    content: serde_json::Value,
+}
+
+impl StateSnapshot {
+    /// The number of cells in the snapshot.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+/// Maps task ids to indices into [StateSnapshot::tasks] while taking a
+/// snapshot.
+#[derive(Default)]
+struct SnapshotTaskIds {
+    indices: RefCell<HashMap<TaskId, usize>>,
+    tasks: RefCell<Vec<TaskId>>,
+}
+
+impl SnapshotTaskIds {
+    fn get(&self, index: usize) -> Option<TaskId> {
+        self.tasks.borrow().get(index).copied()
+    }
+}
+
+impl IdMapping<TaskId> for SnapshotTaskIds {
+    fn forward(&self, id: TaskId) -> usize {
+        *self.indices.borrow_mut().entry(id).or_insert_with(|| {
+            let mut tasks = self.tasks.borrow_mut();
+            tasks.push(id);
+            tasks.len() - 1
+        })
+    }
+
+    fn backward(&self, _id: usize) -> TaskId {
+        unreachable!("snapshots are only serialized")
+    }
+}
+
+/// Maps indices into [StateSnapshot::tasks] to tasks of the restoring backend,
+/// creating the tasks on demand.
+struct RestoreTaskIds<'a> {
+    backend: &'a MemoryBackend,
+    turbo_tasks: &'a dyn TurboTasksBackendApi<MemoryBackend>,
+    tasks: &'a [serde_json::Value],
+    ids: RefCell<Vec<Option<TaskId>>>,
+    error: RefCell<Option<anyhow::Error>>,
+}
+
+impl<'a> RestoreTaskIds<'a> {
+    fn restore_task(&self, index: usize) -> Result<TaskId> {
+        if let Some(id) = self.ids.borrow().get(index).copied().flatten() {
+            return Ok(id);
+        }
+        let task_type = self
+            .tasks
+            .get(index)
+            .ok_or_else(|| anyhow!("unknown task {index} in state snapshot"))?;
+        // Referenced tasks are restored recursively while deserializing. They
+        // were created before the referencing task, so there are no cycles.
+        let task_type: PersistentTaskType = serde_json::from_value(task_type.clone())
+            .with_context(|| format!("restoring task {index} from state snapshot"))?;
+        let id = self
+            .backend
+            .get_or_create_unconnected_task(task_type, self.turbo_tasks);
+        self.ids.borrow_mut()[index] = Some(id);
+        Ok(id)
+    }
+}
+
+impl<'a> IdMapping<TaskId> for RestoreTaskIds<'a> {
+    fn forward(&self, _id: TaskId) -> usize {
+        unreachable!("snapshots are only deserialized")
+    }
+
+    fn backward(&self, index: usize) -> TaskId {
+        self.restore_task(index).unwrap_or_else(|err| {
+            self.error.borrow_mut().get_or_insert(err);
+            // Never used, restoring fails with the error
+            TaskId::from(0)
+        })
+    }
+}
+
+impl MemoryBackend {
+    /// Takes a snapshot of the cells of all stateful tasks that hold a
+    /// serializable value. Fails when these cells reference transient tasks,
+    /// or tasks with non-serializable inputs.
+    pub fn snapshot_state(&self) -> Result<StateSnapshot> {
+        let ids = SnapshotTaskIds::default();
+        with_task_id_mapping(&ids, || {
+            let mut cells = Vec::new();
+            self.with_all_cached_tasks(|id| {
+                let Some(stateful_cells) = self.with_task(id, |task| task.get_stateful_cells())
+                else {
+                    return;
+                };
+                for (cell, content) in stateful_cells {
+                    if !is_serializable(&content) {
+                        continue;
+                    }
+                    let task = ids.forward(id);
+                    cells.push((task, cell, content));
+                }
+            });
+            let cells = cells
+                .into_iter()
+                .map(|(task, cell, content)| {
+                    Ok(SnapshotCell {
+                        task,
+                        cell,
+                        content: serde_json::to_value(&content)
+                            .with_context(|| format!("serializing {cell} of stateful task"))?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            // Serializing task types can reference more tasks
+            let mut tasks = Vec::new();
+            while let Some(id) = ids.get(tasks.len()) {
+                let Some(task_type) =
+                    self.with_task(id, |task| task.get_persistent_task_type().cloned())
+                else {
+                    bail!(
+                        "{} is referenced by a stateful task, but it isn't persistent",
+                        self.with_task(id, |task| task.get_description())
+                    );
+                };
+                tasks.push(
+                    serde_json::to_value(&*task_type)
+                        .with_context(|| format!("serializing {task_type}"))?,
+                );
+            }
+            Ok(StateSnapshot { tasks, cells })
+        })
+    }
+
+    /// Restores a snapshot taken with [MemoryBackend::snapshot_state]. Must be
+    /// called before the stateful tasks are executed. On their first
+    /// execution, they resume with the restored cell contents.
+    pub fn restore_state(
+        &self,
+        snapshot: StateSnapshot,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> Result<()> {
+        let ids = RestoreTaskIds {
+            backend: self,
+            turbo_tasks,
+            tasks: &snapshot.tasks,
+            ids: RefCell::new(vec![None; snapshot.tasks.len()]),
+            error: RefCell::new(None),
+        };
+        let cells = with_task_id_mapping(&ids, || {
+            snapshot
+                .cells
+                .iter()
+                .map(
+                    |SnapshotCell {
+                         task,
+                         cell,
+                         content,
+                     }| {
+                        let task = ids.restore_task(*task)?;
+                        // SharedReference borrows the name of the value type, so
+                        // it can't be deserialized from a Value
+                        let content: SharedReference = serde_json::from_str(&content.to_string())
+                            .with_context(|| {
+                            format!("restoring {cell} of stateful task from state snapshot")
+                        })?;
+                        Ok((task, *cell, content))
+                    },
+                )
+                .collect::<Result<Vec<_>>>()
+        })?;
+        if let Some(err) = ids.error.into_inner() {
+            return Err(err);
+        }
+        for (task, cell, content) in cells {
+            self.restored_cells.insert((task, cell), content);
+        }
+        Ok(())
+    }
+}
+
+fn is_serializable(content: &SharedReference) -> bool {
+    match content {
+        SharedReference(Some(ty), arc) => registry::get_value_type(*ty)
+            .any_as_serializable(arc)
+            .is_some(),
+        SharedReference(None, _) => false,
+    }
+}
@@ -15,19 +15,19 @@ use std::{
     time::{Duration, Instant},
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use auto_hash_map::{AutoMap, AutoSet};
 use nohash_hasher::BuildNoHashHasher;
 use parking_lot::{Mutex, RwLock};
 use stats::TaskStats;
 use tokio::task_local;
 use turbo_tasks::{
-    backend::{PersistentTaskType, TaskExecutionSpec},
+    backend::{CellContent, PersistentTaskType, TaskExecutionSpec},
     event::{Event, EventListener},
     get_invalidator,
     primitives::{RawVcSet, RawVcSetVc},
-    registry, CellId, Invalidator, RawVc, StatsType, TaskId, TraitTypeId, TryJoinIterExt,
-    TurboTasksBackendApi, ValueTypeId,
+    registry, CellId, Invalidator, RawVc, SharedReference, StatsType, TaskId, TraitTypeId,
+    TryJoinIterExt, TurboTasksBackendApi, ValueTypeId,
 };
 
 use crate::{
@@ -636,6 +636,34 @@ impl Task {
         None
     }
 
+    pub(crate) fn get_persistent_task_type(&self) -> Option<&Arc<PersistentTaskType>> {
+        if let TaskType::Persistent(ty) = &self.ty {
+            Some(ty)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the content of all cells of a stateful task, or [None] when the
+    /// task is not stateful.
+    pub(crate) fn get_stateful_cells(&self) -> Option<Vec<(CellId, SharedReference)>> {
+        let mut state = self.state();
+        let state = state.as_full()?;
+        if !state.stateful {
+            return None;
+        }
+        let mut cells = Vec::new();
+        for (&type_id, list) in state.cells.iter() {
+            for (index, cell) in list.iter().enumerate() {
+                if let CellContent(Some(content)) = cell.read_own_content_untracked() {
+                    let index = index as u32;
+                    cells.push((CellId { type_id, index }, content));
+                }
+            }
+        }
+        Some(cells)
+    }
+
     pub(crate) fn get_description(&self) -> String {
         Self::format_description(&TaskTypeForDescription::from(&self.ty), self.id)
     }
@@ -1049,6 +1077,91 @@ impl Task {
         schedule_task
     }
 
+    /// Called when the execution was dropped because its cancellation token
+    /// has been cancelled. Root and once tasks finish with an error. Other
+    /// tasks become dirty, unless they are still needed by an active scope.
+    /// Returns true when the task needs to be scheduled again.
+    #[must_use]
+    pub(crate) fn execution_cancelled(
+        &self,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> bool {
+        let mut schedule_task = false;
+        let dependencies = DEPENDENCIES_TO_TRACK.with(|deps| deps.take());
+        {
+            let mut state = self.full_state_mut();
+            let (event, counted_as_unfinished) = match state.state_type {
+                InProgress {
+                    ref mut event,
+                    count_as_finished,
+                } => (event.take(), !count_as_finished),
+                InProgressDirty { ref mut event } => (event.take(), true),
+                Dirty { .. } | Scheduled { .. } | Done { .. } => {
+                    panic!(
+                        "Task execution cancelled in unexpected state {}",
+                        Task::state_string(&state)
+                    )
+                }
+            };
+            if matches!(self.ty, TaskType::Root(_) | TaskType::Once(_)) {
+                state
+                    .output
+                    .error(anyhow!("Task execution was cancelled"), turbo_tasks);
+                state.state_type = Done {
+                    dependencies: Default::default(),
+                };
+                if counted_as_unfinished {
+                    for scope in state.scopes.iter() {
+                        backend.with_scope(scope, |scope| {
+                            scope.decrement_unfinished_tasks(backend);
+                        })
+                    }
+                }
+                event.notify(usize::MAX);
+            } else {
+                if !counted_as_unfinished {
+                    for scope in state.scopes.iter() {
+                        backend.with_scope(scope, |scope| {
+                            scope.increment_unfinished_tasks(backend);
+                        })
+                    }
+                }
+                if self.scopes_dirty_or_active(false, &state.scopes, backend) {
+                    // Someone else still needs the task
+                    state.state_type = Scheduled { event };
+                    schedule_task = true;
+                } else {
+                    let description = self.get_event_description();
+                    state.state_type = Dirty {
+                        event: Event::new(move || format!("TaskState({})::event", description())),
+                    };
+                    // Readers are woken up and schedule the task again when needed
+                    for cells in state.cells.values_mut() {
+                        for cell in cells.iter_mut() {
+                            cell.cancel_recompute();
+                        }
+                    }
+                    event.notify(usize::MAX);
+                }
+            }
+        }
+        if !dependencies.is_empty() {
+            self.clear_dependencies(dependencies, backend);
+        }
+        schedule_task
+    }
+
+    /// Called when the cancellation token of a root or once task has been
+    /// cancelled. The task and its children no longer keep the scope active.
+    pub(crate) fn root_cancelled(
+        &self,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        self.remove_root_or_initial_scope(backend, turbo_tasks);
+    }
+
     /// When any scope is active it returns true. When no scope is active it
     /// returns false and adds the tasks to all scopes as dirty task.
     /// When `increment_unfinished` is true it will also increment the
@@ -1158,8 +1271,18 @@ impl Task {
                     clear_dependencies = take(dependencies);
                     // add to dirty lists and potentially schedule
                     let description = self.get_event_description();
-                    let active =
-                        force_schedule || self.scopes_dirty_or_active(true, &state.scopes, backend);
+                    let active = if force_schedule {
+                        // The execution is counted as unfinished like any other, since it
+                        // will be counted as finished when it completes
+                        for scope in state.scopes.iter() {
+                            backend.with_scope(scope, |scope| {
+                                scope.increment_unfinished_tasks(backend);
+                            })
+                        }
+                        true
+                    } else {
+                        self.scopes_dirty_or_active(true, &state.scopes, backend)
+                    };
                     if active {
                         state.state_type = Scheduled {
                             event: Event::new(move || {
@@ -2003,6 +2126,28 @@ impl Task {
         }
     }
 
+    pub(crate) fn get_state_description(&self) -> String {
+        match self.state() {
+            TaskMetaStateReadGuard::Full(state) => Self::state_string(&state),
+            TaskMetaStateReadGuard::Partial(_) => "unloaded (partial)".to_string(),
+            TaskMetaStateReadGuard::Unloaded(_) => "unloaded".to_string(),
+        }
+    }
+
+    /// Returns the tasks that read the output or a cell of this task.
+    pub(crate) fn get_dependents(&self) -> Vec<TaskId> {
+        let mut dependents = HashSet::with_hasher(BuildNoHashHasher::<TaskId>::default());
+        if let TaskMetaStateReadGuard::Full(state) = self.state() {
+            dependents.extend(state.output.dependent_tasks().iter().copied());
+            for cells in state.cells.values() {
+                for cell in cells.iter() {
+                    dependents.extend(cell.dependent_tasks().iter().copied());
+                }
+            }
+        }
+        dependents.into_iter().collect()
+    }
+
     fn state_string(state: &TaskState) -> String {
         let mut state_str = match state.state_type {
             Scheduled { .. } => "scheduled".to_string(),
@@ -2150,6 +2295,8 @@ impl Task {
                     }
                     None
                 }) {
+                    // Tasks held back for their priority could be part of the scope
+                    backend.priority_scheduler.boost_all();
                     return Ok(Err(listener));
                 }
             } else {
@@ -2174,11 +2321,16 @@ impl Task {
                     })
                 }
                 drop(state);
+                backend.priority_scheduler.boost(self.id);
                 Ok(Err(listener))
             }
-            Scheduled { ref event }
-            | InProgress { ref event, .. }
-            | InProgressDirty { ref event } => {
+            Scheduled { ref event } => {
+                let listener = event.listen_with_note(note);
+                drop(state);
+                backend.priority_scheduler.boost(self.id);
+                Ok(Err(listener))
+            }
+            InProgress { ref event, .. } | InProgressDirty { ref event } => {
                 let listener = event.listen_with_note(note);
                 drop(state);
                 Ok(Err(listener))
@@ -2254,7 +2406,10 @@ impl Task {
             // unfinished tasks anymore.
             match read_collectibles_and_children(&*turbo_tasks) {
                 Ok(r) => break r,
-                Err(listener) => listener.await,
+                Err(listener) => {
+                    turbo_tasks.backend().priority_scheduler.boost_all();
+                    listener.await
+                }
             }
         };
         let backend = turbo_tasks.backend();
@@ -2373,6 +2528,7 @@ impl Task {
         task_duration_cache: &mut HashMap<TaskId, Duration, BuildNoHashHasher<TaskId>>,
         scope_active_cache: &mut HashMap<TaskScopeId, bool, BuildNoHashHasher<TaskScopeId>>,
         stats: &mut GcStats,
+        unload_budget: &mut usize,
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> Option<GcPriority> {
@@ -2571,8 +2727,15 @@ impl Task {
                                 total_compute_duration: total_compute_duration_u8,
                             };
                             if new_priority <= max_priority {
+                                if *unload_budget == 0 {
+                                    // Keep the task queued until the rate limit allows unloading
+                                    // it
+                                    stats.unload_rate_limited += 1;
+                                    return Some(new_priority);
+                                }
                                 // Unload task
                                 if self.unload(state, backend, turbo_tasks) {
+                                    *unload_budget -= 1;
                                     stats.unloaded += 1;
                                     return None;
                                 } else {
@@ -2593,13 +2756,12 @@ impl Task {
                                 age: Reverse(age),
                             };
                             if new_priority <= max_priority {
-                                // Empty cells
-                                let cells = take(&mut state.cells);
-                                for cells in cells.into_values() {
-                                    for mut cell in cells {
-                                        if cell.has_value() {
-                                            cells_to_drop.extend(cell.gc_content());
-                                        }
+                                // Empty cells. They stay in place to keep track of their
+                                // dependent tasks, which need to be invalidated when the
+                                // cell is assigned again.
+                                for cells in state.cells.values_mut() {
+                                    for cell in cells.iter_mut() {
+                                        cells_to_drop.extend(cell.gc_content());
                                     }
                                 }
                                 stats.empty_cells += 1;
@@ -2730,6 +2892,7 @@ impl Task {
             }
         }
         // Task is now dirty, so we can safely unload it
+        backend.inspector_log.task_unloaded(self.id);
 
         let mut state = full_state.into_inner();
         let old_state = replace(
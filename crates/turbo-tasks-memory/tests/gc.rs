@@ -0,0 +1,102 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, Invalidator, TryJoinIterExt, TurboTasks};
+use turbo_tasks_malloc::TurboMalloc;
+use turbo_tasks_memory::{GcPolicy, MemoryBackend};
+use turbo_tasks_testing::register;
+register!();
+
+/// Counts allocations, so garbage collection can react to memory pressure.
+#[global_allocator]
+static ALLOC: TurboMalloc = TurboMalloc;
+
+const MB: usize = 1024 * 1024;
+const MODULES: u32 = 100;
+const CONTENT_SIZE: usize = 64 * 1024;
+
+static VERSION: AtomicU32 = AtomicU32::new(0);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn updates_reach_readers_of_emptied_cells() {
+    lazy_static::initialize(&REGISTER);
+    // The app needs a lot more memory than the budget, so cells are emptied
+    let base = TurboMalloc::memory_usage();
+    let tt = TurboTasks::new(MemoryBackend::with_gc_policy(Some(GcPolicy {
+        soft_limit: base + MB,
+        hard_limit: base + 4 * MB,
+        ..GcPolicy::from_memory_limit(usize::MAX)
+    })));
+
+    for version in 0..5 {
+        VERSION.store(version, Ordering::SeqCst);
+        if let Some(invalidator) = INVALIDATOR.lock().unwrap().take() {
+            invalidator.invalidate();
+        }
+        let summary = tt
+            .run_once(async { Ok(*app().strongly_consistent().await?) })
+            .await
+            .unwrap();
+        assert_eq!(summary, expected_summary(version));
+    }
+    let metrics = tt.backend().gc_metrics().unwrap();
+    assert!(metrics.stats.empty_cells > 0, "{metrics:?}");
+}
+
+/// The summary [app] computes when the first module is at `version`.
+fn expected_summary(version: u32) -> u64 {
+    (0..MODULES)
+        .map(|i| {
+            let version = if i == 0 { version } else { 0 };
+            (i ^ version) as u8 as u64 * CONTENT_SIZE as u64
+        })
+        .sum()
+}
+
+#[turbo_tasks::value(transparent)]
+struct Version(u32);
+
+#[turbo_tasks::value(transparent)]
+struct Content(Vec<u8>);
+
+#[turbo_tasks::value(transparent)]
+struct Summary(u64);
+
+#[turbo_tasks::function]
+fn module_source(i: u32) -> VersionVc {
+    if i == 0 {
+        *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+        VersionVc::cell(VERSION.load(Ordering::SeqCst))
+    } else {
+        VersionVc::cell(0)
+    }
+}
+
+#[turbo_tasks::function]
+async fn module_content(i: u32) -> Result<ContentVc> {
+    let version = *module_source(i).await?;
+    Ok(ContentVc::cell(vec![(i ^ version) as u8; CONTENT_SIZE]))
+}
+
+#[turbo_tasks::function]
+async fn module_summary(i: u32) -> Result<SummaryVc> {
+    let content = module_content(i).await?;
+    Ok(SummaryVc::cell(
+        content.iter().map(|&byte| byte as u64).sum::<u64>(),
+    ))
+}
+
+#[turbo_tasks::function]
+async fn app() -> Result<SummaryVc> {
+    let summaries = (0..MODULES)
+        .map(|i| async move { Ok(*module_summary(i).await?) })
+        .try_join()
+        .await?;
+    Ok(SummaryVc::cell(summaries.into_iter().sum()))
+}
@@ -0,0 +1,140 @@
+#![feature(min_specialization)]
+
+use std::{
+    fmt::{Display, Formatter},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, InvalidationReason, Invalidator, NothingVc, TurboTasks};
+use turbo_tasks_memory::{inspector::InvalidationStep, MemoryBackend};
+use turbo_tasks_testing::register;
+register!();
+
+static VALUE: AtomicU32 = AtomicU32::new(1);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+static UNINSPECTED_INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[derive(PartialEq, Eq, Hash)]
+struct ValueChanged;
+
+impl InvalidationReason for ValueChanged {}
+
+impl Display for ValueChanged {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "value changed")
+    }
+}
+
+#[tokio::test]
+async fn explains_recomputations() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.backend().enable_inspector();
+    let root = tt.spawn_root_task(|| {
+        Box::pin(async {
+            inspected_double().await?;
+            Ok(NothingVc::new().into())
+        })
+    });
+    tt.wait_task_completion(root, true).await.unwrap();
+    tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+
+    VALUE.store(2, Ordering::SeqCst);
+    let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+    invalidator.invalidate_with_reason(ValueChanged);
+    tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+
+    let backend = tt.backend();
+    let [double] = &backend.inspect_search("INSPECTED_DOUBLE", 10)[..] else {
+        panic!("expected a single match");
+    };
+    let [source] = &backend.inspect_search("inspected_source", 10)[..] else {
+        panic!("expected a single match");
+    };
+
+    let details = backend.inspect_task(double.id).unwrap();
+    assert_eq!(details.dependencies.len(), 1);
+    assert_eq!(details.dependencies[0].id, source.id);
+    assert!(details.dependents.iter().any(|task| task.id == root));
+    match &details.invalidation[..] {
+        [InvalidationStep::Dependency { task, changed_by }, InvalidationStep::External {
+            task: source_task,
+            reason,
+        }] => {
+            assert_eq!(*task, double.id);
+            assert_eq!(changed_by.id, source.id);
+            assert_eq!(*source_task, source.id);
+            assert_eq!(reason.as_deref(), Some("value changed"));
+        }
+        steps => panic!("unexpected invalidation steps {steps:?}"),
+    }
+
+    let recomputed = backend
+        .inspect_slowest_recomputations(10)
+        .into_iter()
+        .map(|recomputation| recomputation.task.id)
+        .collect::<Vec<_>>();
+    assert!(recomputed.contains(&source.id));
+    assert!(recomputed.contains(&double.id));
+    assert!(recomputed.contains(&root));
+
+    let json = serde_json::to_value(&details).unwrap();
+    assert_eq!(json["invalidation"][1]["type"], "external");
+    assert_eq!(json["dependencies"][0]["id"], *source.id as u64);
+}
+
+#[tokio::test]
+async fn records_nothing_until_enabled() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let root = tt.spawn_root_task(|| {
+        Box::pin(async {
+            uninspected_double().await?;
+            Ok(NothingVc::new().into())
+        })
+    });
+    tt.wait_task_completion(root, true).await.unwrap();
+    tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+
+    let invalidator = UNINSPECTED_INVALIDATOR.lock().unwrap().take().unwrap();
+    invalidator.invalidate_with_reason(ValueChanged);
+    tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+
+    let backend = tt.backend();
+    let [double] = &backend.inspect_search("uninspected_double", 10)[..] else {
+        panic!("expected a single match");
+    };
+    let details = backend.inspect_task(double.id).unwrap();
+    assert!(details.invalidation.is_empty());
+    assert!(backend.inspect_slowest_recomputations(10).is_empty());
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+fn inspected_source() -> NumberVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    NumberVc::cell(VALUE.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn inspected_double() -> Result<NumberVc> {
+    Ok(NumberVc::cell(*inspected_source().await? * 2))
+}
+
+#[turbo_tasks::function]
+fn uninspected_source() -> NumberVc {
+    *UNINSPECTED_INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    NumberVc::cell(1)
+}
+
+#[turbo_tasks::function]
+async fn uninspected_double() -> Result<NumberVc> {
+    Ok(NumberVc::cell(*uninspected_source().await? * 2))
+}
@@ -0,0 +1,164 @@
+#![feature(min_specialization)]
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+use turbo_tasks::{get_invalidator, InvalidationReason, Invalidator, NothingVc, TurboTasks};
+use turbo_tasks_memory::{inspector::INVALIDATION_TRACE_TARGET, MemoryBackend};
+use turbo_tasks_testing::register;
+register!();
+
+static VALUE: AtomicU32 = AtomicU32::new(2);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+static PARITY_READS: AtomicU32 = AtomicU32::new(0);
+
+#[derive(PartialEq, Eq, Hash)]
+struct ValueChanged;
+
+impl InvalidationReason for ValueChanged {}
+
+impl Display for ValueChanged {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "value changed")
+    }
+}
+
+#[derive(Debug, Default)]
+struct TracedSpan {
+    kind: String,
+    parent: Option<u64>,
+    values: HashMap<String, String>,
+}
+
+/// Records the spans and events of the invalidation trace, keyed by span id.
+/// Events are stored with the id `0`.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<(u64, TracedSpan)>>>);
+
+struct Values<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for Values<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, _ctx: Context<'_, S>) {
+        if attrs.metadata().target() != INVALIDATION_TRACE_TARGET {
+            return;
+        }
+        let mut span = TracedSpan {
+            kind: attrs.metadata().name().to_string(),
+            parent: attrs.parent().map(|parent| parent.into_u64()),
+            ..Default::default()
+        };
+        attrs.record(&mut Values(&mut span.values));
+        self.0.lock().unwrap().push((id.into_u64(), span));
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, _ctx: Context<'_, S>) {
+        let mut spans = self.0.lock().unwrap();
+        if let Some((_, span)) = spans.iter_mut().find(|(i, _)| *i == id.into_u64()) {
+            values.record(&mut Values(&mut span.values));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != INVALIDATION_TRACE_TARGET {
+            return;
+        }
+        let mut span = TracedSpan {
+            kind: "event".to_string(),
+            parent: event.parent().map(|parent| parent.into_u64()),
+            ..Default::default()
+        };
+        event.record(&mut Values(&mut span.values));
+        self.0.lock().unwrap().push((0, span));
+    }
+}
+
+#[tokio::test]
+async fn traces_invalidation_propagation() {
+    lazy_static::initialize(&REGISTER);
+    let recorder = Recorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let root = tt.spawn_root_task(|| {
+        Box::pin(async {
+            traced_parity().await?;
+            PARITY_READS.fetch_add(1, Ordering::SeqCst);
+            Ok(NothingVc::new().into())
+        })
+    });
+    tt.wait_task_completion(root, true).await.unwrap();
+    tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+    assert!(recorder.0.lock().unwrap().is_empty());
+
+    // The parity stays the same, so the propagation stops there
+    VALUE.store(4, Ordering::SeqCst);
+    let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+    invalidator.invalidate_with_reason(ValueChanged);
+    tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+    assert_eq!(PARITY_READS.load(Ordering::SeqCst), 1);
+
+    let spans = recorder.0.lock().unwrap();
+    let find = |kind: &str, name: &str| {
+        spans
+            .iter()
+            .find(|(_, span)| span.kind == kind && span.values["name"].contains(name))
+            .unwrap_or_else(|| panic!("missing {kind} {name} in {spans:#?}"))
+    };
+    let (invalidation, _) = find("invalidation", "value changed");
+    let (_, dirty_source) = find("event", "traced_source");
+    assert_eq!(dirty_source.parent, Some(*invalidation));
+
+    let (source, recompute_source) = find("recompute", "traced_source");
+    assert_eq!(recompute_source.parent, Some(*invalidation));
+    assert_eq!(recompute_source.values["outcome"], "cascaded");
+    assert_eq!(recompute_source.values["invalidated"], "1");
+
+    let (_, dirty_parity) = find("event", "traced_parity");
+    assert_eq!(dirty_parity.parent, Some(*source));
+    let (_, recompute_parity) = find("recompute", "traced_parity");
+    assert_eq!(recompute_parity.parent, Some(*source));
+    assert_eq!(recompute_parity.values["outcome"], "unchanged");
+    assert_eq!(recompute_parity.values["invalidated"], "0");
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::value(transparent)]
+struct Bool(bool);
+
+#[turbo_tasks::function]
+fn traced_source() -> NumberVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    NumberVc::cell(VALUE.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn traced_parity() -> Result<BoolVc> {
+    Ok(BoolVc::cell(*traced_source().await? % 2 == 0))
+}
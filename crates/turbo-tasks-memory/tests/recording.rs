@@ -0,0 +1,70 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{bail, Result};
+use turbo_tasks::{
+    primitives::U32Vc,
+    recording::{record_or_replay, Recording, RecordingData, RecordingMode, RootTaskKind},
+    TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+register!();
+
+static INPUT: AtomicU32 = AtomicU32::new(1);
+
+#[tokio::test]
+async fn replays_recorded_inputs() {
+    lazy_static::initialize(&REGISTER);
+
+    let tt = TurboTasks::new_with_recording(MemoryBackend::default(), Recording::record());
+    let result = tt
+        .run_once(async { Ok(*double_input().await?) })
+        .await
+        .unwrap();
+    assert_eq!(result, 2);
+    let error = tt.run_once(async { failing_input().await }).await;
+    assert!(error.is_err());
+
+    let recording = tt.recording().unwrap();
+    assert_eq!(recording.mode(), RecordingMode::Record);
+    let data = recording.data();
+    assert_eq!(data.root_tasks.len(), 2);
+    assert_eq!(data.root_tasks[0].kind, RootTaskKind::Once);
+    assert_eq!(data.root_tasks[0].executions, 1);
+    assert_eq!(data.root_tasks[0].error, None);
+    assert!(data.root_tasks[1].error.is_some());
+    assert_eq!(data.values["test"].len(), 2);
+
+    // The recording survives serialization, and replays the recorded values
+    // even though the input changed.
+    let data: RecordingData = serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+    INPUT.store(10, Ordering::SeqCst);
+    let tt = TurboTasks::new_with_recording(MemoryBackend::default(), Recording::replay(data));
+    let result = tt
+        .run_once(async { Ok(*double_input().await?) })
+        .await
+        .unwrap();
+    assert_eq!(result, 2);
+    let error = tt.run_once(async { failing_input().await }).await;
+    assert!(format!("{:#}", error.unwrap_err()).contains("input is broken"));
+    let error = tt.run_once(async { unrecorded_input().await }).await;
+    assert!(format!("{:#}", error.unwrap_err()).contains("test unrecorded was not recorded"));
+    assert_eq!(tt.recording().unwrap().data().root_tasks.len(), 2);
+}
+
+#[turbo_tasks::function]
+async fn double_input() -> Result<U32Vc> {
+    let input =
+        record_or_replay("test", "input", async { Ok(INPUT.load(Ordering::SeqCst)) }).await?;
+    Ok(U32Vc::cell(input * 2))
+}
+
+async fn failing_input() -> Result<()> {
+    record_or_replay::<(), _>("test", "failing", async { bail!("input is broken") }).await
+}
+
+async fn unrecorded_input() -> Result<()> {
+    record_or_replay("test", "unrecorded", async { Ok(()) }).await
+}
@@ -0,0 +1,263 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{backend::TaskPriority, CancellationToken, SpawnOptions, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+register!();
+
+static SLOW: AtomicBool = AtomicBool::new(true);
+static WAIT_STARTED: AtomicUsize = AtomicUsize::new(0);
+static WAIT_FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+static RELEASED: AtomicBool = AtomicBool::new(false);
+static BLOCKING_STARTED: AtomicUsize = AtomicUsize::new(0);
+static BACKGROUND_STARTED: AtomicUsize = AtomicUsize::new(0);
+
+static OUTER_STARTED: AtomicBool = AtomicBool::new(false);
+static INTERACTIVE_WAITING: AtomicBool = AtomicBool::new(false);
+
+static HELD_RELEASED: AtomicBool = AtomicBool::new(false);
+static HELD_STARTED: AtomicBool = AtomicBool::new(false);
+static SHARED_SLOW: AtomicBool = AtomicBool::new(true);
+static SHARED_STARTED: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn cancellation_drops_execution() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let token = CancellationToken::new();
+    let options = SpawnOptions {
+        cancellation: Some(token.clone()),
+        ..Default::default()
+    };
+    let run = tokio::spawn({
+        let tt = tt.clone();
+        async move {
+            tt.run_once_with_options(options, async { Ok(*wait(1).await?) })
+                .await
+        }
+    });
+    while WAIT_STARTED.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    token.cancel();
+    let result = tokio::time::timeout(Duration::from_secs(5), run)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(format!("{:?}", result.unwrap_err()).contains("cancelled"));
+    assert_eq!(WAIT_FINISHED.load(Ordering::SeqCst), 0);
+
+    // The task is recomputed when it's needed again
+    SLOW.store(false, Ordering::SeqCst);
+    let value = tt.run_once(async { Ok(*wait(1).await?) }).await.unwrap();
+    assert_eq!(value, 1);
+    assert_eq!(WAIT_STARTED.load(Ordering::SeqCst), 2);
+    assert_eq!(WAIT_FINISHED.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn interactive_holds_back_background() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let interactive = tokio::spawn({
+        let tt = tt.clone();
+        let options = SpawnOptions {
+            priority: TaskPriority::Interactive,
+            ..Default::default()
+        };
+        async move {
+            tt.run_once_with_options(options, async { Ok(*blocking().await?) })
+                .await
+        }
+    });
+    while BLOCKING_STARTED.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let background = tokio::spawn({
+        let tt = tt.clone();
+        let options = SpawnOptions {
+            priority: TaskPriority::Background,
+            ..Default::default()
+        };
+        async move {
+            tt.run_once_with_options(options, async { Ok(*background_work().await?) })
+                .await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(BACKGROUND_STARTED.load(Ordering::SeqCst), 0);
+
+    RELEASED.store(true, Ordering::SeqCst);
+    assert_eq!(interactive.await.unwrap().unwrap(), 1);
+    assert_eq!(background.await.unwrap().unwrap(), 2);
+    assert_eq!(BACKGROUND_STARTED.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn awaited_tasks_are_not_held_back() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    // A normal priority task is admitted first...
+    let normal = tokio::spawn({
+        let tt = tt.clone();
+        async move { tt.run_once(async { Ok(*outer().await?) }).await }
+    });
+    while !OUTER_STARTED.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // ...then an interactive execution waits for it, while it waits for a new
+    // task that inherits its normal priority
+    let interactive = tokio::spawn({
+        let tt = tt.clone();
+        let options = SpawnOptions {
+            priority: TaskPriority::Interactive,
+            ..Default::default()
+        };
+        async move {
+            tt.run_once_with_options(options, async {
+                let outer = outer();
+                INTERACTIVE_WAITING.store(true, Ordering::SeqCst);
+                Ok(*outer.await?)
+            })
+            .await
+        }
+    });
+    let (normal, interactive) = tokio::time::timeout(Duration::from_secs(5), async {
+        (normal.await.unwrap(), interactive.await.unwrap())
+    })
+    .await
+    .expect("the tasks should not wait for each other forever");
+    assert_eq!(normal.unwrap(), 4);
+    assert_eq!(interactive.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn rescheduling_after_cancellation_keeps_priority() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let interactive = || SpawnOptions {
+        priority: TaskPriority::Interactive,
+        ..Default::default()
+    };
+    // Holds back every execution with a lower priority
+    let held = tokio::spawn({
+        let tt = tt.clone();
+        let options = interactive();
+        async move {
+            tt.run_once_with_options(options, async { Ok(*held().await?) })
+                .await
+        }
+    });
+    while !HELD_STARTED.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let token = CancellationToken::new();
+    let cancelled = tokio::spawn({
+        let tt = tt.clone();
+        let options = SpawnOptions {
+            cancellation: Some(token.clone()),
+            ..interactive()
+        };
+        async move {
+            tt.run_once_with_options(options, async { Ok(*shared().await?) })
+                .await
+        }
+    });
+    while SHARED_STARTED.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // Another interactive execution still needs the shared task
+    let needed = tokio::spawn({
+        let tt = tt.clone();
+        let options = interactive();
+        async move {
+            tt.run_once_with_options(options, async { Ok(*shared().await?) })
+                .await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    token.cancel();
+    assert!(cancelled.await.unwrap().is_err());
+    SHARED_SLOW.store(false, Ordering::SeqCst);
+
+    let value = tokio::time::timeout(Duration::from_secs(5), needed)
+        .await
+        .expect("the rescheduled task should not be held back")
+        .unwrap()
+        .unwrap();
+    assert_eq!(value, 5);
+    assert_eq!(SHARED_STARTED.load(Ordering::SeqCst), 2);
+
+    HELD_RELEASED.store(true, Ordering::SeqCst);
+    assert_eq!(held.await.unwrap().unwrap(), 6);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+async fn wait(value: u32) -> Result<NumberVc> {
+    WAIT_STARTED.fetch_add(1, Ordering::SeqCst);
+    if SLOW.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    }
+    WAIT_FINISHED.fetch_add(1, Ordering::SeqCst);
+    Ok(NumberVc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn blocking() -> Result<NumberVc> {
+    BLOCKING_STARTED.fetch_add(1, Ordering::SeqCst);
+    while !RELEASED.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    Ok(NumberVc::cell(1))
+}
+
+#[turbo_tasks::function]
+async fn background_work() -> Result<NumberVc> {
+    BACKGROUND_STARTED.fetch_add(1, Ordering::SeqCst);
+    Ok(NumberVc::cell(2))
+}
+
+#[turbo_tasks::function]
+async fn outer() -> Result<NumberVc> {
+    OUTER_STARTED.store(true, Ordering::SeqCst);
+    while !INTERACTIVE_WAITING.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // Give the interactive execution time to start waiting
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    Ok(NumberVc::cell(*inner().await? + 1))
+}
+
+#[turbo_tasks::function]
+async fn inner() -> Result<NumberVc> {
+    Ok(NumberVc::cell(3))
+}
+
+#[turbo_tasks::function]
+async fn held() -> Result<NumberVc> {
+    HELD_STARTED.store(true, Ordering::SeqCst);
+    while !HELD_RELEASED.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    Ok(NumberVc::cell(6))
+}
+
+#[turbo_tasks::function]
+async fn shared() -> Result<NumberVc> {
+    SHARED_STARTED.fetch_add(1, Ordering::SeqCst);
+    while SHARED_SLOW.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    Ok(NumberVc::cell(5))
+}
@@ -0,0 +1,108 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U32Vc, State, TurboTasks};
+use turbo_tasks_memory::{state_snapshot::StateSnapshot, MemoryBackend};
+use turbo_tasks_testing::register;
+register!();
+
+#[tokio::test]
+async fn restarted_engine_resumes_state() {
+    lazy_static::initialize(&REGISTER);
+
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.run_once(async {
+        counter("a".to_string()).await?.value.set(5);
+        counter("b".to_string()).await?.value.set(7);
+        selection()
+            .await?
+            .selected
+            .set(Some(counter("b".to_string())));
+        Ok(())
+    })
+    .await
+    .unwrap();
+    let before = tt
+        .run_once(async {
+            Ok((
+                *read_counter("a".to_string()).await?,
+                *read_selected().await?,
+            ))
+        })
+        .await
+        .unwrap();
+    assert_eq!(before, (5, 7));
+
+    let snapshot = tt.backend().snapshot_state().unwrap();
+    assert_eq!(snapshot.len(), 3);
+    let snapshot: StateSnapshot =
+        serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+    tt.stop_and_wait().await;
+
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.backend().restore_state(snapshot, &*tt).unwrap();
+    let after = tt
+        .run_once(async {
+            Ok((
+                *read_counter("a".to_string()).await?,
+                *read_selected().await?,
+                *read_counter("c".to_string()).await?,
+            ))
+        })
+        .await
+        .unwrap();
+    assert_eq!(after, (5, 7, 0));
+
+    // The restored state is still tracked
+    tt.run_once(async {
+        counter("a".to_string()).await?.value.set(6);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    let updated = tt
+        .run_once(async { Ok(*read_counter("a".to_string()).await?) })
+        .await
+        .unwrap();
+    assert_eq!(updated, 6);
+}
+
+#[turbo_tasks::value(cell = "new", eq = "manual")]
+struct Counter {
+    value: State<u32>,
+}
+
+#[turbo_tasks::function]
+fn counter(_name: String) -> CounterVc {
+    Counter {
+        value: State::new(0),
+    }
+    .cell()
+}
+
+#[turbo_tasks::function]
+async fn read_counter(name: String) -> Result<U32Vc> {
+    Ok(U32Vc::cell(*counter(name).await?.value.get()))
+}
+
+#[turbo_tasks::value(cell = "new", eq = "manual")]
+struct Selection {
+    selected: State<Option<CounterVc>>,
+}
+
+#[turbo_tasks::function]
+fn selection() -> SelectionVc {
+    Selection {
+        selected: State::new(None),
+    }
+    .cell()
+}
+
+#[turbo_tasks::function]
+async fn read_selected() -> Result<U32Vc> {
+    let selected = *selection().await?.selected.get();
+    Ok(U32Vc::cell(match selected {
+        Some(counter) => *counter.await?.value.get(),
+        None => 0,
+    }))
+}
@@ -0,0 +1,622 @@
+//! A [PersistedGraph] that stores the task graph in a [redb] database, so a
+//! restarted process can continue with the results of the previous one.
+//!
+//! The database stores the task types, the data of completed tasks, which
+//! tasks are dirty and which tasks depend on a task output or cell. Task ids
+//! are only valid for a single session, so the database identifies tasks by
+//! an id assigned to their serialized [PersistentTaskType] instead.
+//!
+//! Which tasks are active is only tracked for the current session. Nothing is
+//! active after opening the database. The consumer graph activates persisted
+//! tasks again when it connects them to its active tasks.
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use redb::{
+    Database, Durability, MultimapTableDefinition, ReadableMultimapTable, ReadableTable,
+    TableDefinition,
+};
+use turbo_tasks::{
+    backend::PersistentTaskType,
+    persisted_graph::{
+        ActivateResult, DeactivateResult, PersistResult, PersistTaskState, PersistedGraph,
+        PersistedGraphApi, ReadTaskState, TaskData,
+    },
+    with_task_id_mapping, IdMapping, RawVc, TaskId,
+};
+
+/// Serialized task type => persistent id
+const TASK_TYPES: TableDefinition<&[u8], u64> = TableDefinition::new("task_types");
+/// Persistent id => serialized [TaskData]
+const TASK_DATA: TableDefinition<u64, &[u8]> = TableDefinition::new("task_data");
+/// Persistent id => serialized dependencies, to update DEPENDENTS when the task
+/// is persisted again
+const TASK_DEPENDENCIES: TableDefinition<u64, &[u8]> = TableDefinition::new("task_dependencies");
+/// Persistent ids of tasks that need to be executed again
+const DIRTY_TASKS: TableDefinition<u64, ()> = TableDefinition::new("dirty_tasks");
+/// Serialized task output or cell => persistent ids of the tasks that read it
+const DEPENDENTS: MultimapTableDefinition<&[u8], u64> = MultimapTableDefinition::new("dependents");
+
+#[derive(Default)]
+struct Ids {
+    by_type: HashMap<Vec<u8>, u64>,
+    types: HashMap<u64, Vec<u8>>,
+    /// Types that were assigned an id, but were not written yet
+    new_types: Vec<(Vec<u8>, u64)>,
+    next_id: u64,
+    /// Persistent ids that have task data
+    persisted: HashSet<u64>,
+    task_to_id: HashMap<TaskId, u64>,
+    id_to_task: HashMap<u64, TaskId>,
+}
+
+#[derive(Default)]
+struct ActiveState {
+    /// Active persisted tasks that have this task as a child
+    internal_active_parents: u32,
+    /// Kept active by the consumer graph
+    externally_active: bool,
+    /// The children of this task were activated
+    activated: bool,
+}
+
+impl ActiveState {
+    fn is_active(&self) -> bool {
+        self.internal_active_parents > 0 || self.externally_active
+    }
+}
+
+#[derive(Default)]
+struct Session {
+    tasks: HashMap<TaskId, ActiveState>,
+    /// Children of the persisted tasks that were read or persisted in this
+    /// session
+    children: HashMap<TaskId, Vec<TaskId>>,
+}
+
+impl Session {
+    /// Returns true when the child became active
+    fn increment_active_parents(&mut self, child: TaskId) -> bool {
+        let state = self.tasks.entry(child).or_default();
+        state.internal_active_parents += 1;
+        state.internal_active_parents == 1
+    }
+
+    /// Returns true when the child is no longer kept active by persisted
+    /// tasks
+    fn decrement_active_parents(&mut self, child: TaskId) -> bool {
+        let state = self.tasks.entry(child).or_default();
+        let was_active = state.internal_active_parents > 0;
+        state.internal_active_parents = state.internal_active_parents.saturating_sub(1);
+        was_active && state.internal_active_parents == 0
+    }
+}
+
+pub struct RedbPersistedGraph {
+    db: Database,
+    ids: Mutex<Ids>,
+    session: Mutex<Session>,
+}
+
+impl RedbPersistedGraph {
+    /// Opens the database at `path`, creating it when it doesn't exist.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let db = Database::create(path)
+            .with_context(|| format!("unable to open task graph at {}", path.display()))?;
+        let txn = db.begin_write()?;
+        let mut ids = Ids::default();
+        {
+            let types = txn.open_table(TASK_TYPES)?;
+            for entry in types.iter()? {
+                let (ty, id) = entry?;
+                let (ty, id) = (ty.value().to_vec(), id.value());
+                ids.next_id = ids.next_id.max(id + 1);
+                ids.types.insert(id, ty.clone());
+                ids.by_type.insert(ty, id);
+            }
+            let data = txn.open_table(TASK_DATA)?;
+            for entry in data.iter()? {
+                let (id, _) = entry?;
+                ids.persisted.insert(id.value());
+            }
+            txn.open_table(TASK_DEPENDENCIES)?;
+            txn.open_table(DIRTY_TASKS)?;
+            txn.open_multimap_table(DEPENDENTS)?;
+        }
+        txn.commit()?;
+        Ok(Self {
+            db,
+            ids: Mutex::new(ids),
+            session: Mutex::new(Session::default()),
+        })
+    }
+
+    /// The persistent id of a task, when it already has one
+    fn existing_id(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Option<u64> {
+        let mapping = Mapping::new(self, api, false);
+        with_task_id_mapping(&mapping, || mapping.id_for_task(task))
+    }
+
+    fn is_dirty(&self, id: u64) -> Result<bool> {
+        let txn = self.db.begin_read()?;
+        let dirty = txn.open_table(DIRTY_TASKS)?;
+        let is_dirty = dirty.get(id)?.is_some();
+        Ok(is_dirty)
+    }
+
+    fn read_data(&self, id: u64, api: &dyn PersistedGraphApi) -> Result<Option<TaskData>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(TASK_DATA)?;
+        let Some(bytes) = table.get(id)? else {
+            return Ok(None);
+        };
+        let mapping = Mapping::new(self, api, false);
+        let data = with_task_id_mapping(&mapping, || {
+            bincode::deserialize::<TaskData>(bytes.value()).ok()
+        });
+        // Tasks that can't be restored, e.g. because their function no longer
+        // exists, are computed again
+        Ok(data.filter(|_| !mapping.failed.get()))
+    }
+
+    /// The children of a persisted task, reading them from the database when
+    /// they are not known in this session yet
+    fn children(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        if let Some(children) = self.session.lock().unwrap().children.get(&task) {
+            return Ok(children.clone());
+        }
+        let Some(id) = self.existing_id(task, api) else {
+            return Ok(Vec::new());
+        };
+        let children = self
+            .read_data(id, api)?
+            .map(|data| data.children)
+            .unwrap_or_default();
+        self.session
+            .lock()
+            .unwrap()
+            .children
+            .insert(task, children.clone());
+        Ok(children)
+    }
+
+    fn write_dirty(&self, ids: impl IntoIterator<Item = u64>) -> Result<()> {
+        let mut txn = self.db.begin_write()?;
+        txn.set_durability(Durability::Eventual);
+        {
+            let mut dirty = txn.open_table(DIRTY_TASKS)?;
+            for id in ids {
+                dirty.insert(id, ())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+/// Maps task ids of the current session to persistent ids while serializing
+/// and back while deserializing
+struct Mapping<'a> {
+    graph: &'a RedbPersistedGraph,
+    api: &'a dyn PersistedGraphApi,
+    /// Assign new ids to unknown task types
+    assign: bool,
+    /// A task couldn't be mapped and the serialized value must not be used
+    failed: Cell<bool>,
+}
+
+impl<'a> Mapping<'a> {
+    fn new(graph: &'a RedbPersistedGraph, api: &'a dyn PersistedGraphApi, assign: bool) -> Self {
+        Self {
+            graph,
+            api,
+            assign,
+            failed: Cell::new(false),
+        }
+    }
+
+    /// Needs to be called with this mapping installed, since task types can
+    /// contain other tasks.
+    fn id_for_task(&self, task: TaskId) -> Option<u64> {
+        if let Some(&id) = self.graph.ids.lock().unwrap().task_to_id.get(&task) {
+            return Some(id);
+        }
+        let ty = self.api.try_lookup_task_type(task)?;
+        let ty = bincode::serialize(ty).ok()?;
+        if self.failed.get() {
+            return None;
+        }
+        let mut ids = self.graph.ids.lock().unwrap();
+        let id = match ids.by_type.get(&ty) {
+            Some(&id) => id,
+            None if self.assign => {
+                let id = ids.next_id;
+                ids.next_id += 1;
+                ids.types.insert(id, ty.clone());
+                ids.by_type.insert(ty.clone(), id);
+                ids.new_types.push((ty, id));
+                id
+            }
+            None => return None,
+        };
+        ids.task_to_id.insert(task, id);
+        ids.id_to_task.insert(id, task);
+        Some(id)
+    }
+
+    /// Needs to be called with this mapping installed, since task types can
+    /// contain other tasks.
+    fn task_for_id(&self, id: u64) -> Option<TaskId> {
+        let ty = {
+            let ids = self.graph.ids.lock().unwrap();
+            if let Some(&task) = ids.id_to_task.get(&id) {
+                return Some(task);
+            }
+            ids.types.get(&id)?.clone()
+        };
+        let ty: PersistentTaskType = bincode::deserialize(&ty).ok()?;
+        if self.failed.get() {
+            return None;
+        }
+        let task = self.api.get_or_create_task_type(ty);
+        let mut ids = self.graph.ids.lock().unwrap();
+        ids.task_to_id.insert(task, id);
+        ids.id_to_task.insert(id, task);
+        Some(task)
+    }
+}
+
+impl<'a> IdMapping<TaskId> for Mapping<'a> {
+    fn forward(&self, task: TaskId) -> usize {
+        self.id_for_task(task).map_or_else(
+            || {
+                self.failed.set(true);
+                usize::MAX
+            },
+            |id| id as usize,
+        )
+    }
+
+    fn backward(&self, id: usize) -> TaskId {
+        self.task_for_id(id as u64).unwrap_or_else(|| {
+            self.failed.set(true);
+            TaskId::from(usize::MAX)
+        })
+    }
+}
+
+impl PersistedGraph for RedbPersistedGraph {
+    fn read(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<(TaskData, ReadTaskState)>> {
+        let Some(id) = self.existing_id(task, api) else {
+            return Ok(None);
+        };
+        let Some(data) = self.read_data(id, api)? else {
+            return Ok(None);
+        };
+        let mut session = self.session.lock().unwrap();
+        session.children.insert(task, data.children.clone());
+        let keeps_external_active = session
+            .tasks
+            .get(&task)
+            .map_or(false, |state| state.internal_active_parents > 0);
+        drop(session);
+        let state = ReadTaskState {
+            clean: !self.is_dirty(id)?,
+            keeps_external_active,
+        };
+        Ok(Some((data, state)))
+    }
+
+    fn lookup(
+        &self,
+        _partial_task_type: &PersistentTaskType,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<bool> {
+        // Task types are looked up one by one
+        Ok(false)
+    }
+
+    fn lookup_one(
+        &self,
+        task_type: &PersistentTaskType,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<TaskId>> {
+        let mapping = Mapping::new(self, api, false);
+        let ty = with_task_id_mapping(&mapping, || bincode::serialize(task_type));
+        let Ok(ty) = ty else {
+            return Ok(None);
+        };
+        if mapping.failed.get() {
+            return Ok(None);
+        }
+        let id = {
+            let ids = self.ids.lock().unwrap();
+            match ids.by_type.get(&ty) {
+                Some(id) if ids.persisted.contains(id) => *id,
+                _ => return Ok(None),
+            }
+        };
+        Ok(with_task_id_mapping(&mapping, || mapping.task_for_id(id)))
+    }
+
+    fn is_persisted(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        Ok(self
+            .existing_id(task, api)
+            .map_or(false, |id| self.ids.lock().unwrap().persisted.contains(&id)))
+    }
+
+    fn persist(
+        &self,
+        task: TaskId,
+        data: TaskData,
+        state: PersistTaskState,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<PersistResult>> {
+        let mapping = Mapping::new(self, api, true);
+        let serialized = with_task_id_mapping(&mapping, || {
+            let id = mapping.id_for_task(task)?;
+            let bytes = bincode::serialize(&data).ok()?;
+            let dependencies = data
+                .dependencies
+                .iter()
+                .map(|vc| bincode::serialize(vc).ok())
+                .collect::<Option<Vec<_>>>()?;
+            Some((id, bytes, dependencies))
+        });
+        // Tasks that reference values or tasks that can't be serialized are
+        // not persisted
+        let Some((id, bytes, dependencies)) = serialized.filter(|_| !mapping.failed.get()) else {
+            return Ok(None);
+        };
+
+        let mut txn = self.db.begin_write()?;
+        txn.set_durability(Durability::Eventual);
+        {
+            let new_types = std::mem::take(&mut self.ids.lock().unwrap().new_types);
+            let mut types = txn.open_table(TASK_TYPES)?;
+            for (ty, id) in new_types {
+                types.insert(ty.as_slice(), id)?;
+            }
+            txn.open_table(TASK_DATA)?.insert(id, bytes.as_slice())?;
+            txn.open_table(DIRTY_TASKS)?.remove(id)?;
+
+            let mut task_dependencies = txn.open_table(TASK_DEPENDENCIES)?;
+            let mut dependents = txn.open_multimap_table(DEPENDENTS)?;
+            if let Some(old) = task_dependencies.get(id)? {
+                let old: Vec<Vec<u8>> = bincode::deserialize(old.value())?;
+                for dependency in old {
+                    dependents.remove(dependency.as_slice(), id)?;
+                }
+            }
+            for dependency in dependencies.iter() {
+                dependents.insert(dependency.as_slice(), id)?;
+            }
+            task_dependencies.insert(id, bincode::serialize(&dependencies)?.as_slice())?;
+        }
+        txn.commit()?;
+        self.ids.lock().unwrap().persisted.insert(id);
+
+        let mut tasks_to_activate = Vec::new();
+        let mut tasks_to_deactivate = Vec::new();
+        let mut session = self.session.lock().unwrap();
+        let old_children = session.children.insert(task, data.children.clone());
+        let task_state = session.tasks.entry(task).or_default();
+        task_state.externally_active = state.externally_active;
+        let is_active = task_state.is_active();
+        if task_state.activated {
+            let old_children: HashSet<_> = old_children.unwrap_or_default().into_iter().collect();
+            let new_children: HashSet<_> = data.children.iter().copied().collect();
+            for &child in new_children.difference(&old_children) {
+                if session.increment_active_parents(child) {
+                    tasks_to_activate.push(child);
+                }
+            }
+            for &child in old_children.difference(&new_children) {
+                if session.decrement_active_parents(child) {
+                    tasks_to_deactivate.push(child);
+                }
+            }
+            if !is_active {
+                tasks_to_deactivate.push(task);
+            }
+        } else if is_active {
+            task_state.activated = true;
+            for &child in data.children.iter() {
+                if session.increment_active_parents(child) {
+                    tasks_to_activate.push(child);
+                }
+            }
+        }
+        Ok(Some(PersistResult {
+            tasks_to_activate,
+            tasks_to_deactivate,
+        }))
+    }
+
+    fn activate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<ActivateResult>> {
+        let persisted = self.is_persisted(task, api)?;
+        let children = if persisted {
+            self.children(task, api)?
+        } else {
+            Vec::new()
+        };
+        let dirty = match self.existing_id(task, api) {
+            Some(id) if persisted => self.is_dirty(id)?,
+            _ => false,
+        };
+        let mut session = self.session.lock().unwrap();
+        let state = session.tasks.entry(task).or_default();
+        if !state.is_active() {
+            return Ok(None);
+        }
+        let keeps_external_active = state.internal_active_parents > 0;
+        let mut more_tasks_to_activate = Vec::new();
+        if persisted && !state.activated {
+            state.activated = true;
+            for child in children {
+                if session.increment_active_parents(child) {
+                    more_tasks_to_activate.push(child);
+                }
+            }
+        }
+        Ok(Some(ActivateResult {
+            keeps_external_active,
+            external: !persisted,
+            dirty,
+            more_tasks_to_activate,
+        }))
+    }
+
+    fn deactivate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<DeactivateResult>> {
+        let children = self.children(task, api)?;
+        let mut session = self.session.lock().unwrap();
+        let Some(state) = session.tasks.get_mut(&task) else {
+            return Ok(None);
+        };
+        if state.is_active() {
+            return Ok(None);
+        }
+        let mut more_tasks_to_deactivate = Vec::new();
+        if state.activated {
+            state.activated = false;
+            for child in children {
+                if session.decrement_active_parents(child) {
+                    more_tasks_to_deactivate.push(child);
+                }
+            }
+        }
+        Ok(Some(DeactivateResult {
+            more_tasks_to_deactivate,
+        }))
+    }
+
+    fn set_externally_active(&self, task: TaskId, _api: &dyn PersistedGraphApi) -> Result<bool> {
+        let mut session = self.session.lock().unwrap();
+        let state = session.tasks.entry(task).or_default();
+        state.externally_active = true;
+        Ok(!state.activated)
+    }
+
+    fn unset_externally_active(&self, task: TaskId, _api: &dyn PersistedGraphApi) -> Result<bool> {
+        let mut session = self.session.lock().unwrap();
+        let state = session.tasks.entry(task).or_default();
+        state.externally_active = false;
+        Ok(state.activated && !state.is_active())
+    }
+
+    fn remove_outdated_externally_active(
+        &self,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<Vec<TaskId>> {
+        // Keep alives are not persisted, so they are all from this session
+        Ok(Vec::new())
+    }
+
+    fn make_dirty(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        let Some(id) = self.existing_id(task, api) else {
+            return Ok(false);
+        };
+        if !self.ids.lock().unwrap().persisted.contains(&id) {
+            return Ok(false);
+        }
+        self.write_dirty([id])?;
+        let session = self.session.lock().unwrap();
+        Ok(session
+            .tasks
+            .get(&task)
+            .map_or(false, |state| state.is_active()))
+    }
+
+    fn make_clean(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<()> {
+        let Some(id) = self.existing_id(task, api) else {
+            return Ok(());
+        };
+        let mut txn = self.db.begin_write()?;
+        txn.set_durability(Durability::Eventual);
+        txn.open_table(DIRTY_TASKS)?.remove(id)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn make_dependent_dirty(&self, vc: RawVc, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        let mapping = Mapping::new(self, api, false);
+        let key = with_task_id_mapping(&mapping, || bincode::serialize(&vc));
+        let Ok(key) = key else {
+            return Ok(Vec::new());
+        };
+        if mapping.failed.get() {
+            return Ok(Vec::new());
+        }
+        let dependents = {
+            let txn = self.db.begin_read()?;
+            let table = txn.open_multimap_table(DEPENDENTS)?;
+            let mut dependents = Vec::new();
+            for id in table.get(key.as_slice())? {
+                dependents.push(id?.value());
+            }
+            dependents
+        };
+        if dependents.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.write_dirty(dependents.iter().copied())?;
+        // Only tasks known in this session can be active
+        let ids = self.ids.lock().unwrap();
+        let session = self.session.lock().unwrap();
+        Ok(dependents
+            .into_iter()
+            .filter_map(|id| ids.id_to_task.get(&id).copied())
+            .filter(|task| {
+                session
+                    .tasks
+                    .get(task)
+                    .map_or(false, |state| state.is_active())
+            })
+            .collect())
+    }
+
+    fn get_active_external_tasks(&self, _api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        Ok(Vec::new())
+    }
+
+    fn get_dirty_active_tasks(&self, _api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        Ok(Vec::new())
+    }
+
+    fn get_pending_active_update(
+        &self,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<(Vec<TaskId>, Vec<TaskId>)> {
+        Ok((Vec::new(), Vec::new()))
+    }
+
+    fn stop(&self, _api: &dyn PersistedGraphApi) -> Result<()> {
+        // Flush all eventually durable commits to disk
+        let mut txn = self.db.begin_write()?;
+        txn.set_durability(Durability::Immediate);
+        txn.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+#![feature(min_specialization)]
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    emit, primitives::StringVc, CollectiblesSource, TurboTasks, ValueToString, ValueToStringVc,
+};
+use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
+use turbo_tasks_redb::RedbPersistedGraph;
+use turbo_tasks_testing::register;
+register!();
+
+static DOUBLE_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+static EMIT_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn open(path: &Path) -> Arc<TurboTasks<MemoryBackendWithPersistedGraph<RedbPersistedGraph>>> {
+    TurboTasks::new(MemoryBackendWithPersistedGraph::new(
+        RedbPersistedGraph::new(path.join("graph.redb")).unwrap(),
+    ))
+}
+
+#[tokio::test]
+async fn restores_task_results() {
+    lazy_static::initialize(&REGISTER);
+    let dir = tempfile::tempdir().unwrap();
+    for _ in 0..2 {
+        let tt = open(dir.path());
+        tt.run_once(async {
+            assert_eq!(double_plus_one(20).await?.0, 41);
+            Ok(())
+        })
+        .await
+        .unwrap();
+        tt.stop_and_wait().await;
+    }
+    // The second session read the results from the database
+    assert_eq!(DOUBLE_EXECUTIONS.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn restores_collectibles() {
+    lazy_static::initialize(&REGISTER);
+    let dir = tempfile::tempdir().unwrap();
+    for _ in 0..2 {
+        let tt = open(dir.path());
+        tt.run_once(async {
+            let result = emitting_parent();
+            let list = result
+                .peek_collectibles::<ValueToStringVc>()
+                .strongly_consistent()
+                .await?;
+            let mut values = Vec::new();
+            for collectible in list {
+                values.push(collectible.to_string().await?.clone_value());
+            }
+            values.sort();
+            assert_eq!(values, ["123", "42"]);
+            assert_eq!(result.await?.0, 0);
+            Ok(())
+        })
+        .await
+        .unwrap();
+        tt.stop_and_wait().await;
+    }
+    assert_eq!(EMIT_EXECUTIONS.load(Ordering::SeqCst), 1);
+}
+
+#[turbo_tasks::function]
+fn double_plus_one(value: u32) -> ThingVc {
+    let _ = double(value);
+    ThingVc::new(value * 2 + 1)
+}
+
+#[turbo_tasks::function]
+fn double(value: u32) -> ThingVc {
+    DOUBLE_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    ThingVc::new(value * 2)
+}
+
+#[turbo_tasks::function]
+fn emitting_parent() -> ThingVc {
+    let _ = emitting();
+    ThingVc::new(0)
+}
+
+#[turbo_tasks::function]
+fn emitting() -> ThingVc {
+    EMIT_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    emit(ThingVc::new(123).as_value_to_string());
+    emit(ThingVc::new(42).as_value_to_string());
+    ThingVc::new(1)
+}
+
+#[turbo_tasks::value(shared)]
+struct Thing(u32);
+
+impl ThingVc {
+    fn new(v: u32) -> Self {
+        Self::cell(Thing(v))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for Thing {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> StringVc {
+        StringVc::cell(self.0.to_string())
+    }
+}
@@ -0,0 +1,64 @@
+//! Helpers to assert on collectibles, like issues, emitted by tasks.
+
+use std::{any::type_name, future::Future};
+
+use anyhow::Result;
+use turbo_tasks::{CollectiblesSource, ValueTraitVc};
+
+/// Reads the collectibles of type `T` emitted by `source` and all tasks it
+/// spawned, sorted by the key `key` maps them to. Sorting by their values
+/// keeps the order stable, independent of the order the tasks finished in.
+pub async fn emitted<T, K, F, Fut>(source: impl CollectiblesSource, key: F) -> Result<Vec<T>>
+where
+    T: ValueTraitVc,
+    K: Ord,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<K>>,
+{
+    let mut collectibles = Vec::new();
+    for collectible in source.peek_collectibles::<T>().await? {
+        collectibles.push((key(collectible).await?, collectible));
+    }
+    collectibles.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(collectibles
+        .into_iter()
+        .map(|(_, collectible)| collectible)
+        .collect())
+}
+
+/// Asserts that `source` emitted collectibles of type `T` which `describe`
+/// maps exactly to `expected`, in any order.
+///
+/// ```ignore
+/// assert_emitted::<IssueVc, _, _>(
+///     my_task(),
+///     |issue| async move { Ok(issue.title().await?.clone_value()) },
+///     &["Module not found"],
+/// )
+/// .await?;
+/// ```
+pub async fn assert_emitted<T, F, Fut>(
+    source: impl CollectiblesSource,
+    describe: F,
+    expected: &[&str],
+) -> Result<()>
+where
+    T: ValueTraitVc,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let mut actual = Vec::new();
+    for collectible in source.peek_collectibles::<T>().await? {
+        actual.push(describe(collectible).await?);
+    }
+    actual.sort();
+    let mut expected = expected.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(
+        actual,
+        expected,
+        "unexpected collectibles of type {}",
+        type_name::<T>()
+    );
+    Ok(())
+}
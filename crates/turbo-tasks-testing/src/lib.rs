@@ -1,5 +1,6 @@
 //! Testing utilities and macros for turbo-tasks and applications based on it.
 
+pub mod collectibles;
 mod macros;
 pub mod retry;
 
@@ -12,15 +13,16 @@ use std::{
     sync::{Arc, Mutex, Weak},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use auto_hash_map::AutoSet;
 use futures::FutureExt;
 use turbo_tasks::{
     backend::CellContent,
     event::{Event, EventListener},
     primitives::RawVcSetVc,
+    recording::Recording,
     registry,
-    test_helpers::with_turbo_tasks_for_testing,
+    test_helpers::{current_task_for_testing, with_turbo_tasks_for_testing},
     util::{SharedError, StaticOrArc},
     CellId, InvalidationReason, RawVc, TaskId, TraitTypeId, TurboTasksApi, TurboTasksCallApi,
 };
@@ -30,11 +32,18 @@ enum Task {
     Finished(Result<RawVc, SharedError>),
 }
 
+type CollectibleCounts = HashMap<(TraitTypeId, RawVc), i32>;
+
 #[derive(Default)]
 pub struct VcStorage {
     this: Weak<Self>,
     cells: Mutex<HashMap<(TaskId, CellId), CellContent>>,
     tasks: Mutex<Vec<Task>>,
+    /// Tasks spawned during the execution of a task, in spawn order.
+    children: Mutex<HashMap<TaskId, Vec<TaskId>>>,
+    /// Collectibles emitted by a task with their count. Unemitting a
+    /// collectible emitted by another task results in a negative count.
+    collectibles: Mutex<HashMap<TaskId, CollectibleCounts>>,
 }
 
 impl TurboTasksCallApi for VcStorage {
@@ -43,43 +52,8 @@ impl TurboTasksCallApi for VcStorage {
         func: turbo_tasks::FunctionId,
         inputs: Vec<turbo_tasks::TaskInput>,
     ) -> RawVc {
-        let this = self.this.upgrade().unwrap();
         let func = registry::get_function(func).bind(&inputs);
-        let handle = tokio::runtime::Handle::current();
-        let future = func();
-        let i = {
-            let mut tasks = self.tasks.lock().unwrap();
-            let i = tasks.len();
-            tasks.push(Task::Spawned(Event::new(move || {
-                format!("Task({i})::event")
-            })));
-            i
-        };
-        handle.spawn(with_turbo_tasks_for_testing(
-            this.clone(),
-            TaskId::from(i),
-            async move {
-                let result = AssertUnwindSafe(future).catch_unwind().await;
-
-                // Convert the unwind panic to an anyhow error that can be cloned.
-                let result = result
-                    .map_err(|any| match any.downcast::<String>() {
-                        Ok(owned) => anyhow!(owned),
-                        Err(any) => match any.downcast::<&'static str>() {
-                            Ok(str) => anyhow!(str),
-                            Err(_) => anyhow!("unknown panic"),
-                        },
-                    })
-                    .and_then(|r| r)
-                    .map_err(SharedError::new);
-
-                let mut tasks = this.tasks.lock().unwrap();
-                if let Task::Spawned(event) = replace(&mut tasks[i], Task::Finished(result)) {
-                    event.notify(usize::MAX);
-                }
-            },
-        ));
-        RawVc::TaskOutput(i.into())
+        self.spawn(func())
     }
 
     fn native_call(
@@ -92,11 +66,29 @@ impl TurboTasksCallApi for VcStorage {
 
     fn trait_call(
         &self,
-        _trait_type: turbo_tasks::TraitTypeId,
-        _trait_fn_name: Cow<'static, str>,
-        _inputs: Vec<turbo_tasks::TaskInput>,
+        trait_type: turbo_tasks::TraitTypeId,
+        trait_fn_name: Cow<'static, str>,
+        mut inputs: Vec<turbo_tasks::TaskInput>,
     ) -> RawVc {
-        unreachable!()
+        let this = self.this.upgrade().unwrap();
+        self.spawn(async move {
+            let Some(first) = inputs.first_mut() else {
+                bail!("No arguments for trait call");
+            };
+            *first = first.clone().resolve().await?;
+            let value = first.clone().resolve_to_value().await?;
+            let native_fn = value
+                .get_trait_method(trait_type, trait_fn_name)
+                .map_err(|name| {
+                    anyhow!(
+                        "{} doesn't implement {}::{}",
+                        value,
+                        registry::get_trait(trait_type),
+                        name
+                    )
+                })?;
+            Ok(this.dynamic_call(native_fn, inputs))
+        })
     }
 
     fn run_once(
@@ -197,24 +189,31 @@ impl TurboTasksApi for VcStorage {
         self.read_own_task_cell(current_task, index)
     }
 
-    fn emit_collectible(&self, _trait_type: turbo_tasks::TraitTypeId, _collectible: RawVc) {
-        unimplemented!()
+    fn emit_collectible(&self, trait_type: turbo_tasks::TraitTypeId, collectible: RawVc) {
+        self.update_collectible(trait_type, collectible, 1);
     }
 
-    fn unemit_collectible(&self, _trait_type: turbo_tasks::TraitTypeId, _collectible: RawVc) {
-        unimplemented!()
+    fn unemit_collectible(&self, trait_type: turbo_tasks::TraitTypeId, collectible: RawVc) {
+        self.update_collectible(trait_type, collectible, -1);
     }
 
     fn unemit_collectibles(
         &self,
-        _trait_type: turbo_tasks::TraitTypeId,
-        _collectibles: &AutoSet<RawVc>,
+        trait_type: turbo_tasks::TraitTypeId,
+        collectibles: &AutoSet<RawVc>,
     ) {
-        unimplemented!()
+        for collectible in collectibles.iter() {
+            self.update_collectible(trait_type, *collectible, -1);
+        }
     }
 
-    fn read_task_collectibles(&self, _task: TaskId, _trait_id: TraitTypeId) -> RawVcSetVc {
-        unimplemented!()
+    fn read_task_collectibles(&self, task: TaskId, trait_id: TraitTypeId) -> RawVcSetVc {
+        let this = self.this.upgrade().unwrap();
+        self.spawn(async move {
+            let collectibles = this.read_collectibles(task, trait_id).await;
+            Ok(RawVcSetVc::cell(collectibles).into())
+        })
+        .into()
     }
 
     fn read_own_task_cell(&self, task: TaskId, index: CellId) -> Result<CellContent> {
@@ -246,9 +245,108 @@ impl TurboTasksApi for VcStorage {
     ) -> std::pin::Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>> {
         unimplemented!()
     }
+    fn recording(&self) -> Option<Arc<Recording>> {
+        None
+    }
 }
 
 impl VcStorage {
+    /// Spawns the future as a new child task of the current task.
+    fn spawn(&self, future: impl Future<Output = Result<RawVc>> + Send + 'static) -> RawVc {
+        let this = self.this.upgrade().unwrap();
+        let handle = tokio::runtime::Handle::current();
+        let i = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let i = tasks.len();
+            tasks.push(Task::Spawned(Event::new(move || {
+                format!("Task({i})::event")
+            })));
+            i
+        };
+        self.children
+            .lock()
+            .unwrap()
+            .entry(current_task_for_testing())
+            .or_default()
+            .push(TaskId::from(i));
+        handle.spawn(with_turbo_tasks_for_testing(
+            this.clone(),
+            TaskId::from(i),
+            async move {
+                let result = AssertUnwindSafe(future).catch_unwind().await;
+
+                // Convert the unwind panic to an anyhow error that can be cloned.
+                let result = result
+                    .map_err(|any| match any.downcast::<String>() {
+                        Ok(owned) => anyhow!(owned),
+                        Err(any) => match any.downcast::<&'static str>() {
+                            Ok(str) => anyhow!(str),
+                            Err(_) => anyhow!("unknown panic"),
+                        },
+                    })
+                    .and_then(|r| r)
+                    .map_err(SharedError::new);
+
+                let mut tasks = this.tasks.lock().unwrap();
+                if let Task::Spawned(event) = replace(&mut tasks[i], Task::Finished(result)) {
+                    event.notify(usize::MAX);
+                }
+            },
+        ));
+        RawVc::TaskOutput(i.into())
+    }
+
+    fn update_collectible(&self, trait_type: TraitTypeId, collectible: RawVc, by: i32) {
+        let task = current_task_for_testing();
+        let mut collectibles = self.collectibles.lock().unwrap();
+        let counts = collectibles.entry(task).or_default();
+        let count = counts.entry((trait_type, collectible)).or_default();
+        *count += by;
+        if *count == 0 {
+            counts.remove(&(trait_type, collectible));
+        }
+    }
+
+    /// Waits until the task has finished. Tasks not spawned by this storage,
+    /// like the root of [VcStorage::with], are considered finished.
+    async fn wait_for_task(&self, task: TaskId) {
+        loop {
+            let listener = {
+                let tasks = self.tasks.lock().unwrap();
+                match tasks.get(*task) {
+                    Some(Task::Spawned(event)) => event.listen(),
+                    _ => return,
+                }
+            };
+            listener.await;
+        }
+    }
+
+    /// Collects the collectibles emitted by the task and all tasks spawned by
+    /// it, after waiting for them to finish.
+    async fn read_collectibles(&self, task: TaskId, trait_id: TraitTypeId) -> AutoSet<RawVc> {
+        let mut counts: HashMap<RawVc, i32> = HashMap::new();
+        let mut queue = vec![task];
+        while let Some(task) = queue.pop() {
+            self.wait_for_task(task).await;
+            if let Some(children) = self.children.lock().unwrap().get(&task) {
+                queue.extend(children.iter().copied());
+            }
+            if let Some(collectibles) = self.collectibles.lock().unwrap().get(&task) {
+                for (&(trait_type, collectible), count) in collectibles.iter() {
+                    if trait_type == trait_id {
+                        *counts.entry(collectible).or_default() += count;
+                    }
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(collectible, _)| collectible)
+            .collect()
+    }
+
     pub fn with<T>(f: impl Future<Output = T>) -> impl Future<Output = T> {
         with_turbo_tasks_for_testing(
             Arc::new_cyclic(|weak| VcStorage {
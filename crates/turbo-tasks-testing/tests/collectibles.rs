@@ -0,0 +1,100 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{emit, primitives::StringVc, CollectiblesSource, ValueToString, ValueToStringVc};
+use turbo_tasks_testing::{
+    collectibles::{assert_emitted, emitted},
+    register, VcStorage,
+};
+register!();
+
+async fn describe(collectible: ValueToStringVc) -> Result<String> {
+    Ok(collectible.to_string().await?.clone_value())
+}
+
+#[tokio::test]
+async fn transitive_emitting() {
+    lazy_static::initialize(&REGISTER);
+    VcStorage::with(async {
+        let result = emitting_parent();
+        assert_emitted(result, describe, &["42", "123"]).await?;
+        assert_eq!(result.await?.0, 0);
+        anyhow::Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn stable_order() {
+    lazy_static::initialize(&REGISTER);
+    VcStorage::with(async {
+        // Sorted numerically, rather than by the descriptions
+        let value = |collectible: ValueToStringVc| async move {
+            Ok(describe(collectible).await?.parse::<u32>()?)
+        };
+        let list = emitted(emitting_parent(), value).await?;
+        let mut values = Vec::new();
+        for collectible in list {
+            values.push(describe(collectible).await?);
+        }
+        assert_eq!(values, ["42", "123"]);
+        anyhow::Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn taking_collectibles() {
+    lazy_static::initialize(&REGISTER);
+    VcStorage::with(async {
+        let result = collecting();
+        assert_eq!(*result.await?, "123, 42");
+        assert_emitted(result, describe, &[]).await?;
+        anyhow::Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+#[turbo_tasks::function]
+fn emitting_parent() -> ThingVc {
+    let _ = emitting(123);
+    let _ = emitting(42);
+    ThingVc::new(0)
+}
+
+#[turbo_tasks::function]
+fn emitting(value: u32) -> ThingVc {
+    emit(ThingVc::new(value).as_value_to_string());
+    ThingVc::new(value)
+}
+
+#[turbo_tasks::function]
+async fn collecting() -> Result<StringVc> {
+    let result = emitting_parent();
+    let mut values = Vec::new();
+    for collectible in result.take_collectibles::<ValueToStringVc>().await? {
+        values.push(describe(collectible).await?);
+    }
+    values.sort();
+    Ok(StringVc::cell(values.join(", ")))
+}
+
+#[turbo_tasks::value(shared)]
+struct Thing(u32);
+
+impl ThingVc {
+    fn new(v: u32) -> Self {
+        Self::cell(Thing(v))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for Thing {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> StringVc {
+        StringVc::cell(self.0.to_string())
+    }
+}
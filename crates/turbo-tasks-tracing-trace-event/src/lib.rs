@@ -0,0 +1,17 @@
+//! Cross-platform counterparts of `tracing-signpost`. The layers use the same
+//! span naming, so turbo-tasks functions show up under their function names.
+//!
+//! * [TraceEventLayer] writes the Chrome trace-event format, which can be
+//!   opened in https://ui.perfetto.dev/ or `chrome://tracing`.
+//! * [TraceMarkerLayer] (Linux only) writes ftrace markers, which are recorded
+//!   by `perf record -e ftrace:print` and shown as slices by Perfetto next to
+//!   the scheduling data of the kernel.
+
+mod trace_event;
+#[cfg(target_os = "linux")]
+mod trace_marker;
+mod visitor;
+
+pub use trace_event::{TraceEventGuard, TraceEventLayer};
+#[cfg(target_os = "linux")]
+pub use trace_marker::TraceMarkerLayer;
@@ -0,0 +1,266 @@
+use std::{
+    io::{BufWriter, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, ThreadId},
+    time::Instant,
+};
+
+use dashmap::DashMap;
+use serde_json::{json, Map, Value};
+use tracing::{span, subscriber::Subscriber, Event, Id};
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::visitor::FieldsVisitor;
+
+/// A [`tracing::Layer`] implementation that writes a trace event for each
+/// span and event in the Chrome trace-event format. Spans are written as
+/// duration events each time they are entered, events as instant events.
+///
+/// The output is a JSON array, which is closed when the [TraceEventGuard] is
+/// dropped.
+pub struct TraceEventLayer {
+    output: Arc<Mutex<Output>>,
+    spans: DashMap<Id, SpanDescriptor>,
+    threads: DashMap<ThreadId, u64>,
+    next_tid: AtomicU64,
+    start: Instant,
+    pid: u32,
+}
+
+/// Flushes the output of a [TraceEventLayer] when dropped. Nothing is written
+/// afterwards.
+pub struct TraceEventGuard {
+    output: Arc<Mutex<Output>>,
+}
+
+impl Drop for TraceEventGuard {
+    fn drop(&mut self) {
+        self.output.lock().unwrap().finish();
+    }
+}
+
+struct Output {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    first: bool,
+    finished: bool,
+}
+
+impl Output {
+    fn write(&mut self, event: &Value) {
+        if self.finished {
+            return;
+        }
+        let separator = if self.first { "[\n" } else { ",\n" };
+        self.first = false;
+        let _ = write!(self.writer, "{}{}", separator, event);
+    }
+
+    fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+        let end = if self.first { "[]\n" } else { "\n]\n" };
+        let _ = self.writer.write_all(end.as_bytes());
+        let _ = self.writer.flush();
+        self.finished = true;
+    }
+}
+
+struct SpanDescriptor {
+    name: &'static str,
+    category: &'static str,
+    args: Map<String, Value>,
+}
+
+impl TraceEventLayer {
+    /// Create a new `TraceEventLayer` writing to `writer`. Writes are
+    /// buffered, so the [TraceEventGuard] must be kept alive until the end of
+    /// the trace.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> (Self, TraceEventGuard) {
+        let output = Arc::new(Mutex::new(Output {
+            writer: BufWriter::new(Box::new(writer)),
+            first: true,
+            finished: false,
+        }));
+        (
+            TraceEventLayer {
+                output: output.clone(),
+                spans: Default::default(),
+                threads: Default::default(),
+                next_tid: AtomicU64::new(1),
+                start: Instant::now(),
+                pid: std::process::id(),
+            },
+            TraceEventGuard { output },
+        )
+    }
+
+    fn ts(&self) -> f64 {
+        self.start.elapsed().as_nanos() as f64 / 1000.0
+    }
+
+    /// Returns the trace id of the current thread. The first time a thread is
+    /// seen, its name is written as metadata.
+    fn tid(&self) -> u64 {
+        let thread = thread::current();
+        if let Some(tid) = self.threads.get(&thread.id()) {
+            return *tid;
+        }
+        let mut metadata = None;
+        let tid = *self.threads.entry(thread.id()).or_insert_with(|| {
+            let tid = self.next_tid.fetch_add(1, Ordering::Relaxed);
+            let name = thread
+                .name()
+                .map_or_else(|| format!("thread {}", tid), str::to_string);
+            metadata = Some(json!({
+                "ph": "M",
+                "name": "thread_name",
+                "pid": self.pid,
+                "tid": tid,
+                "args": { "name": name },
+            }));
+            tid
+        });
+        if let Some(metadata) = metadata {
+            self.output.lock().unwrap().write(&metadata);
+        }
+        tid
+    }
+}
+
+impl<S> Layer<S> for TraceEventLayer
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        let event = json!({
+            "ph": "i",
+            "s": "t",
+            "name": metadata.name(),
+            "cat": metadata.target(),
+            "ts": self.ts(),
+            "pid": self.pid,
+            "tid": self.tid(),
+            "args": visitor.fields,
+        });
+        self.output.lock().unwrap().write(&event);
+    }
+
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor::default();
+        attrs.record(&mut visitor);
+
+        self.spans.insert(
+            id.clone(),
+            SpanDescriptor {
+                name: attrs.metadata().name(),
+                category: attrs.metadata().target(),
+                args: visitor.fields,
+            },
+        );
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor::default();
+        values.record(&mut visitor);
+
+        if let Some(mut span) = self.spans.get_mut(id) {
+            span.args.extend(visitor.fields);
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        let Some(span) = self.spans.get(id) else {
+            return;
+        };
+        let event = json!({
+            "ph": "B",
+            "name": span.name,
+            "cat": span.category,
+            "ts": self.ts(),
+            "pid": self.pid,
+            "tid": self.tid(),
+            "args": span.args,
+        });
+        drop(span);
+        self.output.lock().unwrap().write(&event);
+    }
+
+    fn on_exit(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        let Some(span) = self.spans.get(id) else {
+            return;
+        };
+        let event = json!({
+            "ph": "E",
+            "name": span.name,
+            "ts": self.ts(),
+            "pid": self.pid,
+            "tid": self.tid(),
+        });
+        drop(span);
+        self.output.lock().unwrap().write(&event);
+    }
+
+    fn on_close(&self, id: span::Id, _ctx: Context<'_, S>) {
+        self.spans.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use serde_json::Value;
+    use tracing::{info_span, trace};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::TraceEventLayer;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_trace_events() {
+        let buffer = SharedBuffer::default();
+        let (layer, guard) = TraceEventLayer::new(buffer.clone());
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("compute", value = 42, path = "a.js");
+            let _guard = span.enter();
+            trace!(done = true, "inner");
+        });
+        drop(guard);
+
+        let events: Vec<Value> = serde_json::from_slice(&buffer.0.lock().unwrap()).unwrap();
+        let phases = events
+            .iter()
+            .map(|event| event["ph"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(phases, ["M", "B", "i", "E"]);
+        assert_eq!(events[1]["name"], "compute");
+        assert_eq!(events[1]["args"]["value"], 42);
+        assert_eq!(events[1]["args"]["path"], "a.js");
+        assert_eq!(events[2]["args"]["done"], true);
+        assert_eq!(events[1]["tid"], events[3]["tid"]);
+    }
+}
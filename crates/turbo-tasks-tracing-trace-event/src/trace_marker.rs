@@ -0,0 +1,133 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use dashmap::DashMap;
+use tracing::{span, subscriber::Subscriber, Event, Id};
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::visitor::FieldsVisitor;
+
+/// The locations of the ftrace marker file, depending on where tracefs is
+/// mounted.
+const TRACE_MARKER_PATHS: [&str; 2] = [
+    "/sys/kernel/tracing/trace_marker",
+    "/sys/kernel/debug/tracing/trace_marker",
+];
+
+/// The kernel truncates longer markers.
+const MAX_MARKER_LEN: usize = 1024;
+
+/// A [`tracing::Layer`] implementation that writes ftrace markers for each
+/// span and event. Markers use the atrace format (`B|pid|name` when a span is
+/// entered, `E|pid` when it is exited), which Perfetto shows as slices.
+/// Events are written as empty slices.
+///
+/// Record them with `perf record -e ftrace:print` or `trace-cmd record -e
+/// ftrace:print`. Writing markers requires write access to tracefs.
+pub struct TraceMarkerLayer {
+    file: File,
+    spans: DashMap<Id, SpanDescriptor>,
+    pid: u32,
+}
+
+struct SpanDescriptor {
+    name: &'static str,
+    description: String,
+}
+
+impl TraceMarkerLayer {
+    /// Create a new `TraceMarkerLayer` writing to the ftrace marker file of
+    /// the kernel.
+    pub fn new() -> io::Result<Self> {
+        let mut last_err = None;
+        for path in TRACE_MARKER_PATHS {
+            match Self::with_path(Path::new(path)) {
+                Ok(layer) => return Ok(layer),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    /// Create a new `TraceMarkerLayer` writing to the given marker file.
+    pub fn with_path(path: &Path) -> io::Result<Self> {
+        Ok(TraceMarkerLayer {
+            file: OpenOptions::new().write(true).open(path)?,
+            spans: Default::default(),
+            pid: std::process::id(),
+        })
+    }
+
+    fn begin(&self, name: &str, description: &str) {
+        let marker = if description.is_empty() {
+            format!("B|{}|{}", self.pid, name)
+        } else {
+            format!("B|{}|{} {}", self.pid, name, description)
+        };
+        self.write(marker);
+    }
+
+    fn end(&self) {
+        self.write(format!("E|{}", self.pid));
+    }
+
+    fn write(&self, mut marker: String) {
+        if marker.len() > MAX_MARKER_LEN {
+            let mut len = MAX_MARKER_LEN;
+            while !marker.is_char_boundary(len) {
+                len -= 1;
+            }
+            marker.truncate(len);
+        }
+        // Each marker must be written with a single write
+        let _ = (&self.file).write_all(marker.as_bytes());
+    }
+}
+
+impl<S> Layer<S> for TraceMarkerLayer
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor::default();
+        event.record(&mut visitor);
+
+        self.begin(
+            event.metadata().name(),
+            &FieldsVisitor::description(&visitor.fields),
+        );
+        self.end();
+    }
+
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor::default();
+        attrs.record(&mut visitor);
+
+        self.spans.insert(
+            id.clone(),
+            SpanDescriptor {
+                name: attrs.metadata().name(),
+                description: FieldsVisitor::description(&visitor.fields),
+            },
+        );
+    }
+
+    fn on_enter(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        if let Some(span) = self.spans.get(id) {
+            self.begin(span.name, &span.description);
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        if self.spans.contains_key(id) {
+            self.end();
+        }
+    }
+
+    fn on_close(&self, id: span::Id, _ctx: Context<'_, S>) {
+        self.spans.remove(&id);
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt::{self, Write};
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+
+/// Collects the fields of a span or event.
+#[derive(Default)]
+pub(crate) struct FieldsVisitor {
+    pub(crate) fields: Map<String, Value>,
+}
+
+impl FieldsVisitor {
+    /// Formats the fields the same way as `tracing-signpost` formats the
+    /// message of a signpost.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn description(fields: &Map<String, Value>) -> String {
+        let mut output = String::new();
+        for (name, value) in fields {
+            if !output.is_empty() {
+                output.push_str(", ");
+            }
+            match value {
+                Value::String(value) => write!(output, "{}: {}", name, value),
+                value => write!(output, "{}: {}", name, value),
+            }
+            .unwrap();
+        }
+        output
+    }
+}
+
+impl Visit for FieldsVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.fields
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+}
@@ -15,8 +15,8 @@ use serde::{Deserialize, Serialize};
 pub use crate::id::BackendJobId;
 use crate::{
     event::EventListener, manager::TurboTasksBackendApi, primitives::RawVcSetVc, raw_vc::CellId,
-    registry, task_input::SharedReference, FunctionId, RawVc, ReadRef, TaskId, TaskIdProvider,
-    TaskInput, TraitRef, TraitTypeId, ValueTraitVc,
+    registry, task_input::SharedReference, util::StaticOrArc, FunctionId, InvalidationReason,
+    RawVc, ReadRef, TaskId, TaskIdProvider, TaskInput, TraitRef, TraitTypeId, ValueTraitVc,
 };
 
 pub enum TaskType {
@@ -125,6 +125,26 @@ impl PersistentTaskType {
     }
 }
 
+/// The priority with which a task is scheduled. Tasks scheduled from within
+/// a task inherit its priority.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    /// Eager work nobody is waiting for yet, e.g. background compilation.
+    Background,
+    #[default]
+    Normal,
+    /// Work someone is waiting for, e.g. a dev server request.
+    Interactive,
+}
+
+impl TaskPriority {
+    pub const ALL: [TaskPriority; 3] = [
+        TaskPriority::Background,
+        TaskPriority::Normal,
+        TaskPriority::Interactive,
+    ];
+}
+
 pub struct TaskExecutionSpec {
     pub future: Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>,
 }
@@ -204,6 +224,30 @@ pub trait Backend: Sync + Send {
 
     fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
+    /// Invalidates a task because of an external event described by `reason`.
+    /// Backends can record the reason to explain why a task was recomputed.
+    #[allow(unused_variables)]
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        reason: StaticOrArc<dyn InvalidationReason>,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.invalidate_task(task, turbo_tasks);
+    }
+
+    /// Invalidates tasks because they depend on a value that the execution of
+    /// `cause` has changed.
+    #[allow(unused_variables)]
+    fn invalidate_tasks_caused_by(
+        &self,
+        tasks: Vec<TaskId>,
+        cause: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.invalidate_tasks(tasks, turbo_tasks);
+    }
+
     fn get_task_description(&self, task: TaskId) -> String;
 
     type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static>: Future<Output = Result<()>>
@@ -238,6 +282,42 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) -> bool;
 
+    /// Called instead of `task_execution_result` and
+    /// `task_execution_completed` when the execution was dropped because its
+    /// [crate::CancellationToken] has been cancelled. Returns true when the
+    /// task is still needed and should be scheduled again without the token.
+    fn task_execution_cancelled(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> bool;
+
+    /// Called when the [crate::CancellationToken] of a root or once task has
+    /// been cancelled. The task doesn't need to be kept up to date anymore.
+    fn root_task_cancelled(&self, _task: TaskId, _turbo_tasks: &dyn TurboTasksBackendApi<Self>) {}
+
+    /// Asks whether a task scheduled with `priority` may start executing now.
+    /// Returns the priority the task is accounted with until
+    /// `release_task_execution` is called, or a listener to wait for before
+    /// asking again.
+    fn try_admit_task_execution(
+        &self,
+        _task: TaskId,
+        priority: TaskPriority,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> Result<TaskPriority, EventListener> {
+        Ok(priority)
+    }
+
+    /// Called when an admitted task has no further executions scheduled.
+    fn release_task_execution(
+        &self,
+        _task: TaskId,
+        _priority: TaskPriority,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+    }
+
     fn run_backend_job<'a>(
         &'a self,
         id: BackendJobId,
@@ -0,0 +1,99 @@
+use std::{
+    fmt::{Debug, Formatter},
+    mem::take,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::event::Event;
+
+type OnCancel = Box<dyn FnOnce() + Send>;
+
+struct CancellationTokenInner {
+    cancelled: AtomicBool,
+    event: Event,
+    on_cancel: Mutex<Vec<OnCancel>>,
+}
+
+/// A token to cancel root tasks and all work scheduled on their behalf.
+///
+/// Pass it to [crate::TurboTasks::spawn_root_task_with_options] or
+/// [crate::TurboTasks::spawn_once_task_with_options]. Tasks scheduled from
+/// within these tasks inherit the token. Once the token is cancelled, running
+/// executions are dropped at their next await point.
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<CancellationTokenInner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(CancellationTokenInner {
+                cancelled: AtomicBool::new(false),
+                event: Event::new(|| "CancellationToken::event".to_string()),
+                on_cancel: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Cancels the token. Calling it multiple times has no additional effect.
+    pub fn cancel(&self) {
+        if self.inner.cancelled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let on_cancel = take(&mut *self.inner.on_cancel.lock().unwrap());
+        for callback in on_cancel {
+            callback();
+        }
+        self.inner.event.notify(usize::MAX);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Resolves when the token has been cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            if self.is_cancelled() {
+                return;
+            }
+            let listener = self.inner.event.listen();
+            if self.is_cancelled() {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    /// Registers a callback that runs synchronously on [Self::cancel], before
+    /// waiting executions are woken up. Runs immediately when the token is
+    /// already cancelled.
+    pub(crate) fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) {
+        {
+            let mut on_cancel = self.inner.on_cancel.lock().unwrap();
+            if !self.is_cancelled() {
+                on_cancel.push(Box::new(callback));
+                return;
+            }
+        }
+        callback();
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
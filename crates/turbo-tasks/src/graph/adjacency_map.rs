@@ -96,6 +96,110 @@ where
     }
 }
 
+impl<T> AdjacencyMap<T>
+where
+    T: Eq + std::hash::Hash + Clone,
+{
+    /// Returns the strongly connected components of the graph reachable from
+    /// the roots. Components are returned in reverse topological order, i.e.
+    /// a component comes before all components that reference it. The nodes
+    /// of a component are in the order they were reached from the roots.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<&T>> {
+        // Tarjan's algorithm, with an explicit stack to avoid overflows on
+        // deep graphs.
+        struct NodeState {
+            index: usize,
+            lowlink: usize,
+            on_stack: bool,
+        }
+
+        let mut states: HashMap<&T, NodeState> = HashMap::new();
+        let mut stack: Vec<&T> = Vec::new();
+        // Nodes that are being visited, with the index of their next child.
+        let mut call_stack: Vec<(&T, usize)> = Vec::new();
+        let mut components = Vec::new();
+
+        for root in &self.roots {
+            if states.contains_key(root) {
+                continue;
+            }
+            states.insert(
+                root,
+                NodeState {
+                    index: states.len(),
+                    lowlink: states.len(),
+                    on_stack: true,
+                },
+            );
+            stack.push(root);
+            call_stack.push((root, 0));
+
+            while let Some((node, next_child)) = call_stack.last_mut() {
+                let node = *node;
+                let children = self.adjacency_map.get(node).map_or(&[][..], |vec| vec);
+                if let Some(child) = children.get(*next_child) {
+                    *next_child += 1;
+                    match states.get(child) {
+                        None => {
+                            let index = states.len();
+                            states.insert(
+                                child,
+                                NodeState {
+                                    index,
+                                    lowlink: index,
+                                    on_stack: true,
+                                },
+                            );
+                            stack.push(child);
+                            call_stack.push((child, 0));
+                        }
+                        Some(child_state) if child_state.on_stack => {
+                            let child_index = child_state.index;
+                            let state = states.get_mut(node).unwrap();
+                            state.lowlink = state.lowlink.min(child_index);
+                        }
+                        Some(_) => {}
+                    }
+                    continue;
+                }
+
+                call_stack.pop();
+                let NodeState { index, lowlink, .. } = states[node];
+                if let Some((parent, _)) = call_stack.last() {
+                    let parent_state = states.get_mut(*parent).unwrap();
+                    parent_state.lowlink = parent_state.lowlink.min(lowlink);
+                }
+                if lowlink == index {
+                    let position = stack.iter().rposition(|n| *n == node).unwrap();
+                    let component = stack.split_off(position);
+                    for n in &component {
+                        states.get_mut(*n).unwrap().on_stack = false;
+                    }
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Returns the cycles of the graph, i.e. the strongly connected components
+    /// with more than one node, or a single node that references itself.
+    /// These are the nodes [`super::SkipDuplicates`] stopped at without
+    /// visiting them again.
+    pub fn cycles(&self) -> impl Iterator<Item = Vec<&T>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|component| match &component[..] {
+                [node] => self
+                    .adjacency_map
+                    .get(*node)
+                    .map_or(false, |children| children.contains(*node)),
+                _ => true,
+            })
+    }
+}
+
 #[derive(Debug)]
 enum ReverseTopologicalPass {
     Pre,
@@ -201,3 +305,71 @@ where
         Some(current)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use anyhow::Result;
+
+    use super::AdjacencyMap;
+    use crate::graph::{GraphTraversal, GraphTraversalResult};
+
+    fn graph() -> HashMap<u32, Vec<u32>> {
+        // 1 -> 2 -> 3 -> 2, 3 -> 4 -> 4, 1 -> 5
+        HashMap::from([(1, vec![2, 5]), (2, vec![3]), (3, vec![2, 4]), (4, vec![4])])
+    }
+
+    async fn traverse(max_in_flight: usize) -> (AdjacencyMap<u32>, usize) {
+        let graph = graph();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+        let result = AdjacencyMap::new()
+            .skip_duplicates()
+            .visit_bounded(
+                [1],
+                |&node: &u32| {
+                    let children = graph.get(&node).cloned().unwrap_or_default();
+                    let in_flight = in_flight.clone();
+                    let max_seen = max_seen.clone();
+                    async move {
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_seen.fetch_max(current, Ordering::SeqCst);
+                        tokio::task::yield_now().await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Result::<_>::Ok(children)
+                    }
+                },
+                max_in_flight,
+            )
+            .await;
+        let GraphTraversalResult::Completed(Ok(map)) = result else {
+            panic!("traversal failed");
+        };
+        (map.into_inner(), max_seen.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn visit_bounded_limits_in_flight_edges() {
+        let (map, max_seen) = traverse(1).await;
+        assert_eq!(max_seen, 1);
+        let mut nodes = map.into_reverse_topological().collect::<Vec<_>>();
+        nodes.sort();
+        assert_eq!(nodes, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn reports_strongly_connected_components() {
+        let (map, _) = traverse(usize::MAX).await;
+        let components = map.strongly_connected_components();
+        assert_eq!(components, vec![vec![&4], vec![&2, &3], vec![&5], vec![&1]]);
+        let cycles = map.cycles().collect::<Vec<_>>();
+        assert_eq!(cycles, vec![vec![&4], vec![&2, &3]]);
+    }
+}
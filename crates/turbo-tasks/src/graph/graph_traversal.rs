@@ -1,4 +1,4 @@
-use std::{future::Future, pin::Pin};
+use std::{collections::VecDeque, future::Future, pin::Pin};
 
 use anyhow::Result;
 use futures::{stream::FuturesUnordered, Stream};
@@ -23,6 +23,16 @@ pub trait GraphTraversal: GraphStore + Sized {
         VisitImpl: Visit<Self::Node, Abort, Impl>,
         RootEdgesIt: IntoIterator<Item = VisitImpl::Edge>;
 
+    fn visit_bounded<RootEdgesIt, VisitImpl, Abort, Impl>(
+        self,
+        root_edges: RootEdgesIt,
+        visit: VisitImpl,
+        max_in_flight: usize,
+    ) -> GraphTraversalFuture<Self, VisitImpl, Abort, Impl>
+    where
+        VisitImpl: Visit<Self::Node, Abort, Impl>,
+        RootEdgesIt: IntoIterator<Item = VisitImpl::Edge>;
+
     fn skip_duplicates(self) -> SkipDuplicates<Self>;
 }
 
@@ -33,15 +43,32 @@ where
     /// Visits the graph starting from the given `roots`, and returns a future
     /// that will resolve to the traversal result.
     fn visit<RootEdgesIt, VisitImpl, Abort, Impl>(
+        self,
+        root_edges: RootEdgesIt,
+        visit: VisitImpl,
+    ) -> GraphTraversalFuture<Self, VisitImpl, Abort, Impl>
+    where
+        VisitImpl: Visit<Self::Node, Abort, Impl>,
+        RootEdgesIt: IntoIterator<Item = VisitImpl::Edge>,
+    {
+        self.visit_bounded(root_edges, visit, usize::MAX)
+    }
+
+    /// Like [`GraphTraversal::visit`], but polls at most `max_in_flight`
+    /// [`Visit::edges`] futures at the same time. Further futures are queued
+    /// until one of them completes.
+    fn visit_bounded<RootEdgesIt, VisitImpl, Abort, Impl>(
         mut self,
         root_edges: RootEdgesIt,
         mut visit: VisitImpl,
+        max_in_flight: usize,
     ) -> GraphTraversalFuture<Self, VisitImpl, Abort, Impl>
     where
         VisitImpl: Visit<Self::Node, Abort, Impl>,
         RootEdgesIt: IntoIterator<Item = VisitImpl::Edge>,
     {
-        let futures = FuturesUnordered::new();
+        assert!(max_in_flight > 0, "max_in_flight must be greater than 0");
+        let mut futures = BoundedFutures::new(max_in_flight);
         for edge in root_edges {
             match visit.visit(edge) {
                 VisitControlFlow::Continue(node) => {
@@ -104,10 +131,52 @@ where
     VisitImpl: Visit<Store::Node, Abort, Impl>,
 {
     store: Store,
-    futures: FuturesUnordered<With<VisitImpl::EdgesFuture, Store::Handle>>,
+    futures: BoundedFutures<With<VisitImpl::EdgesFuture, Store::Handle>>,
     visit: VisitImpl,
 }
 
+/// Polls at most `max_in_flight` futures at the same time, and queues the
+/// others in insertion order.
+struct BoundedFutures<Fut> {
+    in_flight: FuturesUnordered<Fut>,
+    queued: VecDeque<Fut>,
+    max_in_flight: usize,
+}
+
+impl<Fut> BoundedFutures<Fut>
+where
+    Fut: Future,
+{
+    fn new(max_in_flight: usize) -> Self {
+        Self {
+            in_flight: FuturesUnordered::new(),
+            queued: VecDeque::with_capacity(0),
+            max_in_flight,
+        }
+    }
+
+    fn push(&mut self, future: Fut) {
+        if self.in_flight.len() < self.max_in_flight {
+            self.in_flight.push(future);
+        } else {
+            self.queued.push_back(future);
+        }
+    }
+
+    fn poll_next(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Fut::Output>> {
+        while self.in_flight.len() < self.max_in_flight {
+            let Some(future) = self.queued.pop_front() else {
+                break;
+            };
+            self.in_flight.push(future);
+        }
+        Pin::new(&mut self.in_flight).poll_next(cx)
+    }
+}
+
 pub enum GraphTraversalResult<Completed, Aborted> {
     Completed(Completed),
     Aborted(Aborted),
@@ -145,8 +214,7 @@ where
                 std::task::Poll::Ready(GraphTraversalResult::Aborted(abort)),
             ),
             GraphTraversalState::Running(mut running) => 'outer: loop {
-                let futures_pin = unsafe { Pin::new_unchecked(&mut running.futures) };
-                match futures_pin.poll_next(cx) {
+                match running.futures.poll_next(cx) {
                     std::task::Poll::Ready(Some((parent_handle, span, Ok(edges)))) => {
                         let _guard = span.enter();
                         for edge in edges {
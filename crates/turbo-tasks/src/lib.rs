@@ -34,6 +34,7 @@
 #![feature(never_type)]
 
 pub mod backend;
+mod cancellation;
 mod collectibles;
 mod completion;
 pub mod debug;
@@ -57,6 +58,7 @@ pub mod persisted_graph;
 pub mod primitives;
 mod raw_vc;
 mod read_ref;
+pub mod recording;
 pub mod registry;
 pub mod small_duration;
 mod state;
@@ -69,6 +71,7 @@ mod value;
 mod value_type;
 
 pub use anyhow::{Error, Result};
+pub use cancellation::CancellationToken;
 pub use collectibles::CollectiblesSource;
 pub use completion::{Completion, CompletionVc, CompletionsVc};
 pub use display::{ValueToString, ValueToStringVc};
@@ -81,10 +84,10 @@ pub use invalidation::{
 };
 pub use join_iter_ext::{JoinIterExt, TryJoinIterExt};
 pub use manager::{
-    dynamic_call, emit, get_invalidator, mark_finished, mark_stateful, run_once,
-    run_once_with_reason, spawn_blocking, spawn_thread, trait_call, turbo_tasks, Invalidator,
-    StatsType, TaskIdProvider, TurboTasks, TurboTasksApi, TurboTasksBackendApi, TurboTasksCallApi,
-    Unused, UpdateInfo,
+    current_task_priority, dynamic_call, emit, get_invalidator, mark_finished, mark_stateful,
+    run_once, run_once_with_reason, spawn_blocking, spawn_thread, trait_call, turbo_tasks,
+    Invalidator, SpawnOptions, StatsType, TaskIdProvider, TurboTasks, TurboTasksApi,
+    TurboTasksBackendApi, TurboTasksCallApi, Unused, UpdateInfo,
 };
 pub use native_function::{NativeFunction, NativeFunctionVc};
 pub use nothing::{Nothing, NothingVc};
@@ -5,7 +5,7 @@ use std::{
     future::Future,
     hash::Hash,
     mem::take,
-    panic::AssertUnwindSafe,
+    panic::{AssertUnwindSafe, Location},
     pin::Pin,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
@@ -24,13 +24,15 @@ use tokio::{runtime::Handle, select, task_local};
 use tracing::{instrument, trace_span, Instrument, Level};
 
 use crate::{
-    backend::{Backend, CellContent, PersistentTaskType, TransientTaskType},
+    backend::{Backend, CellContent, PersistentTaskType, TaskPriority, TransientTaskType},
+    cancellation::CancellationToken,
     event::{Event, EventListener},
     id::{BackendJobId, FunctionId, TraitTypeId},
     id_factory::IdFactory,
     invalidation::InvalidationReasonSet,
     primitives::RawVcSetVc,
     raw_vc::{CellId, RawVc},
+    recording::{Recording, RootTaskKind},
     registry,
     task_input::{SharedReference, TaskInput},
     timed_future::{self, TimedFuture},
@@ -125,6 +127,9 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
         &self,
         f: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
     ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>;
+
+    /// The recording of external inputs, when recording or replaying.
+    fn recording(&self) -> Option<Arc<Recording>>;
 }
 
 /// The type of stats reporting.
@@ -276,6 +281,17 @@ pub struct TurboTasks<B: Backend + 'static> {
     // locking overhead.
     enable_full_stats: AtomicBool,
     program_start: Instant,
+    recording: Option<Arc<Recording>>,
+}
+
+/// Options for spawning root and once tasks. Tasks scheduled from within a
+/// task inherit the options of that task.
+#[derive(Clone, Debug, Default)]
+pub struct SpawnOptions {
+    pub priority: TaskPriority,
+    /// Executions are dropped at their next await point once the token is
+    /// cancelled.
+    pub cancellation: Option<CancellationToken>,
 }
 
 #[derive(Default)]
@@ -299,6 +315,8 @@ task_local! {
     static CURRENT_TASK_ID: TaskId;
 
     static CURRENT_TASK_STATE: RefCell<CurrentTaskState>;
+
+    static CURRENT_SPAWN_OPTIONS: SpawnOptions;
 }
 
 impl<B: Backend + 'static> TurboTasks<B> {
@@ -307,7 +325,18 @@ impl<B: Backend + 'static> TurboTasks<B> {
     // that should be safe as long tasks can't outlife turbo task
     // so we probably want to make sure that all tasks are joined
     // when trying to drop turbo tasks
-    pub fn new(mut backend: B) -> Arc<Self> {
+    pub fn new(backend: B) -> Arc<Self> {
+        Self::new_internal(backend, None)
+    }
+
+    /// Creates a new instance that records all root tasks and external inputs
+    /// into `recording`, or replays the inputs from it. See
+    /// [crate::recording].
+    pub fn new_with_recording(backend: B, recording: Recording) -> Arc<Self> {
+        Self::new_internal(backend, Some(Arc::new(recording)))
+    }
+
+    fn new_internal(mut backend: B, recording: Option<Arc<Recording>>) -> Arc<Self> {
         let task_id_factory = IdFactory::new();
         backend.initialize(&task_id_factory);
         let this = Arc::new_cyclic(|this| Self {
@@ -327,6 +356,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
             event_background: Event::new(|| "TurboTasks::event_background".to_string()),
             enable_full_stats: AtomicBool::new(false),
             program_start: Instant::now(),
+            recording,
         });
         this.backend.startup(&*this);
         this
@@ -337,6 +367,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
     }
 
     /// Creates a new root task
+    #[track_caller]
     pub fn spawn_root_task(
         &self,
         functor: impl Fn() -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>
@@ -344,10 +375,35 @@ impl<B: Backend + 'static> TurboTasks<B> {
             + Send
             + 'static,
     ) -> TaskId {
-        let id = self
-            .backend
-            .create_transient_task(TransientTaskType::Root(Box::new(functor)), self);
-        self.schedule(id);
+        self.spawn_root_task_with_options(Default::default(), functor)
+    }
+
+    /// Creates a new root task with a priority and an optional cancellation
+    /// token.
+    #[track_caller]
+    pub fn spawn_root_task_with_options(
+        &self,
+        options: SpawnOptions,
+        functor: impl Fn() -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>
+            + Sync
+            + Send
+            + 'static,
+    ) -> TaskId {
+        let task_type = match self.record_root_task(RootTaskKind::Root) {
+            Some((recording, index)) => TransientTaskType::Root(Box::new(move || {
+                let recording = recording.clone();
+                let future = functor();
+                Box::pin(async move {
+                    let result = future.await;
+                    recording.finish_root_task(index, &result);
+                    result
+                })
+            })),
+            None => TransientTaskType::Root(Box::new(functor)),
+        };
+        let id = self.backend.create_transient_task(task_type, self);
+        self.register_root_cancellation(id, &options);
+        self.schedule_with_options(id, options);
         id
     }
 
@@ -359,19 +415,66 @@ impl<B: Backend + 'static> TurboTasks<B> {
         &self,
         future: impl Future<Output = Result<RawVc>> + Send + 'static,
     ) -> TaskId {
-        let id = self
-            .backend
-            .create_transient_task(TransientTaskType::Once(Box::pin(future)), self);
-        self.schedule(id);
+        self.spawn_once_task_with_options(Default::default(), future)
+    }
+
+    /// Creates a new root task, that is only executed once, with a priority
+    /// and an optional cancellation token. A cancelled once task fails with
+    /// an error.
+    #[track_caller]
+    pub fn spawn_once_task_with_options(
+        &self,
+        options: SpawnOptions,
+        future: impl Future<Output = Result<RawVc>> + Send + 'static,
+    ) -> TaskId {
+        let task_type = match self.record_root_task(RootTaskKind::Once) {
+            Some((recording, index)) => TransientTaskType::Once(Box::pin(async move {
+                let result = future.await;
+                recording.finish_root_task(index, &result);
+                result
+            })),
+            None => TransientTaskType::Once(Box::pin(future)),
+        };
+        let id = self.backend.create_transient_task(task_type, self);
+        self.register_root_cancellation(id, &options);
+        self.schedule_with_options(id, options);
         id
     }
 
+    #[track_caller]
+    fn record_root_task(&self, kind: RootTaskKind) -> Option<(Arc<Recording>, usize)> {
+        let recording = self.recording.as_ref()?;
+        let index = recording.add_root_task(kind, Location::caller())?;
+        Some((recording.clone(), index))
+    }
+
+    fn register_root_cancellation(&self, task_id: TaskId, options: &SpawnOptions) {
+        if let Some(token) = &options.cancellation {
+            let this = self.this.clone();
+            let handle = Handle::current();
+            token.on_cancel(move || {
+                if let Some(this) = this.upgrade() {
+                    let _guard = handle.enter();
+                    this.backend.root_task_cancelled(task_id, &*this);
+                }
+            });
+        }
+    }
+
     pub async fn run_once<T: TraceRawVcs + Send + 'static>(
         &self,
         future: impl Future<Output = Result<T>> + Send + 'static,
+    ) -> Result<T> {
+        self.run_once_with_options(Default::default(), future).await
+    }
+
+    pub async fn run_once_with_options<T: TraceRawVcs + Send + 'static>(
+        &self,
+        options: SpawnOptions,
+        future: impl Future<Output = Result<T>> + Send + 'static,
     ) -> Result<T> {
         let (tx, rx) = tokio::sync::oneshot::channel();
-        let task_id = self.spawn_once_task(async move {
+        let task_id = self.spawn_once_task_with_options(options, async move {
             let result = future.await?;
             tx.send(result)
                 .map_err(|_| anyhow!("unable to send result"))?;
@@ -440,6 +543,14 @@ impl<B: Backend + 'static> TurboTasks<B> {
 
     #[track_caller]
     pub(crate) fn schedule(&self, task_id: TaskId) {
+        let options = CURRENT_SPAWN_OPTIONS
+            .try_with(|options| options.clone())
+            .unwrap_or_default();
+        self.schedule_with_options(task_id, options)
+    }
+
+    #[track_caller]
+    fn schedule_with_options(&self, task_id: TaskId, options: SpawnOptions) {
         self.begin_primary_job();
         self.scheduled_tasks.fetch_add(1, Ordering::AcqRel);
 
@@ -447,7 +558,18 @@ impl<B: Backend + 'static> TurboTasks<B> {
         let description = self.backend.get_task_description(task_id);
 
         let this = self.pin();
+        let cancellation = options.cancellation.clone();
         let future = async move {
+            let priority = loop {
+                match this
+                    .backend
+                    .try_admit_task_execution(task_id, options.priority, &*this)
+                {
+                    Ok(priority) => break priority,
+                    Err(listener) => listener.await,
+                }
+            };
+            let mut reschedule = false;
             #[allow(clippy::blocks_in_if_conditions)]
             while CURRENT_TASK_STATE
                 .scope(Default::default(), async {
@@ -458,41 +580,68 @@ impl<B: Backend + 'static> TurboTasks<B> {
                     // Setup thread locals
                     let execution_future = CELL_COUNTERS.scope(Default::default(), async {
                         let execution = this.backend.try_start_task_execution(task_id, &*this)?;
-                        Some(
-                            TimedFuture::new(AssertUnwindSafe(execution.future).catch_unwind())
-                                .await,
-                        )
+                        let execution =
+                            TimedFuture::new(AssertUnwindSafe(execution.future).catch_unwind());
+                        Some(match &cancellation {
+                            Some(token) => select! {
+                                biased;
+                                _ = token.cancelled() => None,
+                                result = execution => Some(result),
+                            },
+                            None => Some(execution.await),
+                        })
                     });
-                    if let Some((result, duration, instant)) = execution_future.await {
-                        if cfg!(feature = "log_function_stats") && duration.as_millis() > 1000 {
-                            println!(
-                                "{} took {}",
-                                this.backend.get_task_description(task_id),
-                                FormatDuration(duration)
+                    match execution_future.await {
+                        Some(Some((result, duration, instant))) => {
+                            if cfg!(feature = "log_function_stats") && duration.as_millis() > 1000 {
+                                println!(
+                                    "{} took {}",
+                                    this.backend.get_task_description(task_id),
+                                    FormatDuration(duration)
+                                )
+                            }
+                            let result = result.map_err(|any| match any.downcast::<String>() {
+                                Ok(owned) => Some(Cow::Owned(*owned)),
+                                Err(any) => match any.downcast::<&'static str>() {
+                                    Ok(str) => Some(Cow::Borrowed(*str)),
+                                    Err(_) => None,
+                                },
+                            });
+                            this.backend.task_execution_result(task_id, result, &*this);
+                            let stateful = this.finish_current_task_state();
+                            this.backend.task_execution_completed(
+                                task_id, duration, instant, stateful, &*this,
                             )
                         }
-                        let result = result.map_err(|any| match any.downcast::<String>() {
-                            Ok(owned) => Some(Cow::Owned(*owned)),
-                            Err(any) => match any.downcast::<&'static str>() {
-                                Ok(str) => Some(Cow::Borrowed(*str)),
-                                Err(_) => None,
-                            },
-                        });
-                        this.backend.task_execution_result(task_id, result, &*this);
-                        let stateful = this.finish_current_task_state();
-                        let reexecute = this
-                            .backend
-                            .task_execution_completed(task_id, duration, instant, stateful, &*this);
-                        if !reexecute {
-                            return false;
+                        Some(None) => {
+                            // Changes made before the cancellation still need to be propagated
+                            this.finish_current_task_state();
+                            // Let scope changes caused by the cancellation of the root task
+                            // settle before the backend decides if the task is still needed
+                            while let Err(listener) = this.try_foreground_done() {
+                                listener.await;
+                            }
+                            reschedule = this.backend.task_execution_cancelled(task_id, &*this);
+                            false
                         }
-                    } else {
-                        return false;
+                        None => false,
                     }
-                    true
                 })
                 .await
             {}
+            this.backend
+                .release_task_execution(task_id, priority, &*this);
+            if reschedule {
+                // The task is still needed by others. It keeps its priority,
+                // but not the cancellation that has already happened.
+                this.schedule_with_options(
+                    task_id,
+                    SpawnOptions {
+                        priority: options.priority,
+                        cancellation: None,
+                    },
+                );
+            }
             this.finish_primary_job();
             anyhow::Ok(())
         };
@@ -500,7 +649,11 @@ impl<B: Backend + 'static> TurboTasks<B> {
         let future = TURBO_TASKS
             .scope(
                 self.pin(),
-                CURRENT_TASK_ID.scope(task_id, self.backend.execution_scope(task_id, future)),
+                CURRENT_TASK_ID.scope(
+                    task_id,
+                    CURRENT_SPAWN_OPTIONS
+                        .scope(options, self.backend.execution_scope(task_id, future)),
+                ),
             )
             .in_current_span();
 
@@ -799,15 +952,27 @@ impl<B: Backend + 'static> TurboTasks<B> {
             let tasks = take(tasks_to_notify);
             if !tasks.is_empty() {
                 let _guard = trace_span!("finish_current_task_state").entered();
-                self.backend.invalidate_tasks(tasks, self);
+                self.invalidate_tasks_caused_by_current_task(tasks);
             }
             *stateful
         })
     }
 
+    fn invalidate_tasks_caused_by_current_task(&self, tasks: Vec<TaskId>) {
+        match CURRENT_TASK_ID.try_with(|id| *id) {
+            Ok(cause) => self.backend.invalidate_tasks_caused_by(tasks, cause, self),
+            Err(_) => self.backend.invalidate_tasks(tasks, self),
+        }
+    }
+
     pub fn backend(&self) -> &B {
         &self.backend
     }
+
+    /// The recording passed to [TurboTasks::new_with_recording].
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_deref()
+    }
 }
 
 impl<B: Backend + 'static> TurboTasksCallApi for TurboTasks<B> {
@@ -878,9 +1043,9 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
     fn invalidate_with_reason(&self, task: TaskId, reason: StaticOrArc<dyn InvalidationReason>) {
         {
             let (_, reason_set) = &mut *self.aggregated_update.lock().unwrap();
-            reason_set.insert(reason);
+            reason_set.insert(reason.clone());
         }
-        self.backend.invalidate_task(task, self);
+        self.backend.invalidate_task_with_reason(task, reason, self);
     }
 
     fn notify_scheduled_tasks(&self) {
@@ -892,7 +1057,7 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
             if tasks.is_empty() {
                 return;
             }
-            self.backend.invalidate_tasks(tasks, self);
+            self.invalidate_tasks_caused_by_current_task(tasks);
         });
     }
 
@@ -1016,6 +1181,9 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
             ),
         ))
     }
+    fn recording(&self) -> Option<Arc<Recording>> {
+        self.recording.clone()
+    }
 }
 
 impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
@@ -1379,6 +1547,14 @@ pub fn mark_stateful() {
     })
 }
 
+/// Returns the priority of the current task execution, or `None` when called
+/// outside of a task.
+pub fn current_task_priority() -> Option<TaskPriority> {
+    CURRENT_SPAWN_OPTIONS
+        .try_with(|options| options.priority)
+        .ok()
+}
+
 /// Notifies scheduled tasks for execution.
 pub fn notify_scheduled_tasks() {
     with_turbo_tasks(|tt| tt.notify_scheduled_tasks())
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     backend::{CellContent, PersistentTaskType},
-    CellId, RawVc, TaskId,
+    CellId, RawVc, TaskId, TraitTypeId,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -24,6 +24,9 @@ pub struct TaskData {
     pub dependencies: Vec<RawVc>,
     pub cells: Vec<(CellId, TaskCell)>,
     pub output: RawVc,
+    /// Collectibles emitted by the task with their count. Unemitted
+    /// collectibles have a negative count.
+    pub collectibles: Vec<(TraitTypeId, RawVc, i32)>,
 }
 pub struct ReadTaskState {
     pub clean: bool,
@@ -200,6 +203,10 @@ pub trait PersistedGraphApi {
     fn get_or_create_task_type(&self, ty: PersistentTaskType) -> TaskId;
 
     fn lookup_task_type(&self, id: TaskId) -> &PersistentTaskType;
+
+    /// Like `lookup_task_type`, but returns None for tasks that don't have a
+    /// PersistentTaskType and can't be persisted.
+    fn try_lookup_task_type(&self, id: TaskId) -> Option<&PersistentTaskType>;
 }
 
 /*
@@ -0,0 +1,214 @@
+//! Recording and replay of the external inputs of an execution.
+//!
+//! In [RecordingMode::Record] every root task and every value read through
+//! [record_or_replay] (file system, env and fetch reads) is logged together
+//! with its result. The [Recording] can be saved to a file and loaded on
+//! another machine in [RecordingMode::Replay], where the recorded values are
+//! returned instead of reading them again. That reproduces an execution
+//! without the original files, env or network.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    fs,
+    future::Future,
+    panic::Location,
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{manager::with_turbo_tasks, RawVc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingMode {
+    /// Reads external inputs and records their values.
+    Record,
+    /// Returns the recorded values instead of reading external inputs.
+    Replay,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RootTaskKind {
+    Root,
+    Once,
+}
+
+impl Display for RootTaskKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RootTaskKind::Root => write!(f, "root"),
+            RootTaskKind::Once => write!(f, "once"),
+        }
+    }
+}
+
+/// A root task spawned during recording.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedRootTask {
+    pub kind: RootTaskKind,
+    /// The source location that spawned the task.
+    pub location: String,
+    /// How often the task was executed.
+    pub executions: usize,
+    /// The error of the last execution, if it failed.
+    pub error: Option<String>,
+}
+
+/// The result of reading an external input. Errors are recorded by their
+/// message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordedValue {
+    Ok(serde_json::Value),
+    Err(String),
+}
+
+/// The serialized form of a [Recording]. Values are sorted by kind and key,
+/// so recordings of two executions can be diffed to find nondeterminism.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingData {
+    pub root_tasks: Vec<RecordedRootTask>,
+    pub values: BTreeMap<String, BTreeMap<String, RecordedValue>>,
+}
+
+/// Records or replays the external inputs of a [crate::TurboTasks] instance.
+/// See [crate::TurboTasks::new_with_recording].
+pub struct Recording {
+    mode: RecordingMode,
+    data: Mutex<RecordingData>,
+}
+
+impl Recording {
+    /// Creates an empty recording that records everything read.
+    pub fn record() -> Self {
+        Self {
+            mode: RecordingMode::Record,
+            data: Mutex::new(RecordingData::default()),
+        }
+    }
+
+    /// Creates a recording that replays the given data.
+    pub fn replay(data: RecordingData) -> Self {
+        Self {
+            mode: RecordingMode::Replay,
+            data: Mutex::new(data),
+        }
+    }
+
+    /// Loads a recording saved with [Recording::save] for replay.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file =
+            fs::read(path).with_context(|| format!("reading recording {}", path.display()))?;
+        let data = serde_json::from_slice(&file)
+            .with_context(|| format!("parsing recording {}", path.display()))?;
+        Ok(Self::replay(data))
+    }
+
+    /// Saves the recording as JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(&*self.data.lock().unwrap())?;
+        fs::write(path, json).with_context(|| format!("writing recording {}", path.display()))
+    }
+
+    pub fn mode(&self) -> RecordingMode {
+        self.mode
+    }
+
+    /// Returns a copy of everything recorded so far.
+    pub fn data(&self) -> RecordingData {
+        self.data.lock().unwrap().clone()
+    }
+
+    /// Reads a value with `read` and records it under `kind` and `key`, or
+    /// returns the recorded value when replaying. When the same value is read
+    /// multiple times, the last read is recorded.
+    pub async fn record_or_replay<T, F>(&self, kind: &str, key: String, read: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T>>,
+    {
+        match self.mode {
+            RecordingMode::Record => {
+                let result = read.await;
+                let value = match &result {
+                    Ok(value) => RecordedValue::Ok(serde_json::to_value(value)?),
+                    Err(err) => RecordedValue::Err(format!("{:#}", err)),
+                };
+                self.data
+                    .lock()
+                    .unwrap()
+                    .values
+                    .entry(kind.to_string())
+                    .or_default()
+                    .insert(key, value);
+                result
+            }
+            RecordingMode::Replay => {
+                let value = self
+                    .data
+                    .lock()
+                    .unwrap()
+                    .values
+                    .get(kind)
+                    .and_then(|values| values.get(&key))
+                    .cloned();
+                match value {
+                    Some(RecordedValue::Ok(value)) => serde_json::from_value(value)
+                        .with_context(|| format!("replaying {kind} {key}")),
+                    Some(RecordedValue::Err(err)) => Err(anyhow!(err)),
+                    None => bail!("{kind} {key} was not recorded"),
+                }
+            }
+        }
+    }
+
+    /// Adds a root task to the recording. Returns its index, or [None] when
+    /// replaying.
+    pub(crate) fn add_root_task(
+        &self,
+        kind: RootTaskKind,
+        location: &Location<'_>,
+    ) -> Option<usize> {
+        if self.mode != RecordingMode::Record {
+            return None;
+        }
+        let mut data = self.data.lock().unwrap();
+        data.root_tasks.push(RecordedRootTask {
+            kind,
+            location: location.to_string(),
+            executions: 0,
+            error: None,
+        });
+        Some(data.root_tasks.len() - 1)
+    }
+
+    /// Records the result of an execution of the root task with the given
+    /// index.
+    pub(crate) fn finish_root_task(&self, index: usize, result: &Result<RawVc>) {
+        let mut data = self.data.lock().unwrap();
+        let task = &mut data.root_tasks[index];
+        task.executions += 1;
+        task.error = result.as_ref().err().map(|err| format!("{:#}", err));
+    }
+}
+
+/// Reads a value with `read`, recording or replaying it when the current
+/// [crate::TurboTasks] instance has a [Recording]. `kind` and `key` identify
+/// the value, e.g. `"fs"` and the path of a file. The value must serialize
+/// into all the information needed to replay it.
+pub async fn record_or_replay<T, F>(kind: &str, key: impl Into<String>, read: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T>>,
+{
+    match with_turbo_tasks(|tt| tt.recording()) {
+        Some(recording) => recording.record_or_replay(kind, key.into(), read).await,
+        None => read.await,
+    }
+}
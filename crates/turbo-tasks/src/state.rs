@@ -52,19 +52,25 @@ impl<T> PartialEq for State<T> {
 }
 impl<T> Eq for State<T> {}
 
-impl<T> Serialize for State<T> {
-    fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
-        // For this to work at all we need to do more. Changing the the state need to
-        // invalidate the serialization of the task that contains the state. So we
-        // probably need to store the state outside of the task to be able to serialize
-        // it independent from the creating task.
-        panic!("State serialization is not implemented yet");
+/// Only the value is serialized. Tasks that read the state are not, since
+/// they will read it again after it has been restored.
+impl<T: Serialize> Serialize for State<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.lock().value.serialize(serializer)
     }
 }
 
-impl<'de, T> Deserialize<'de> for State<T> {
-    fn deserialize<D: Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
-        panic!("State serialization is not implemented yet");
+/// Deserializing doesn't mark the current task as stateful. The task that
+/// owns the restored state has already been marked when it created the state
+/// that is replaced.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for State<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self {
+            inner: Mutex::new(StateInner {
+                value: T::deserialize(deserializer)?,
+                invalidators: AutoSet::new(),
+            }),
+        })
     }
 }
 
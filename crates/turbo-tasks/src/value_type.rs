@@ -214,7 +214,7 @@ impl ValueType {
         arc: &'a Arc<dyn MagicAny>,
     ) -> Option<&'a dyn erased_serde::Serialize> {
         if let Some(s) = self.magic_serialization {
-            let r: &dyn MagicAny = arc;
+            let r: &dyn MagicAny = &**arc;
             Some((s.0)(r))
         } else {
             None
@@ -108,4 +108,6 @@ pub mod features {
     pub use swc_ast_explorer;
     #[cfg(feature = "__feature_tracing_signpost")]
     pub use tracing_signpost;
+    #[cfg(feature = "__feature_tracing_trace_event")]
+    pub use tracing_trace_event;
 }
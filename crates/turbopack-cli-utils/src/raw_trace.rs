@@ -59,6 +59,20 @@ impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for RawTraceLayer<S> {
         });
     }
 
+    fn on_record(
+        &self,
+        id: &span::Id,
+        record: &span::Record<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let mut values = ValuesVisitor::new();
+        record.record(&mut values);
+        self.write(TraceRow::Record {
+            id: id.into_u64(),
+            values: values.values,
+        });
+    }
+
     fn on_close(&self, id: span::Id, _ctx: tracing_subscriber::layer::Context<'_, S>) {
         let ts = self.start.elapsed().as_micros() as u64;
         self.write(TraceRow::End {
@@ -47,6 +47,14 @@ pub enum TraceRow<'a> {
         /// Unique id for this span. Must be entered by a `Enter` event before.
         id: u64,
     },
+    /// Values have been recorded for a span after it has been started.
+    Record {
+        /// Unique id for this span. Must be created by a `Start` event before.
+        id: u64,
+        /// A list of key-value pairs for the recorded attributes of the span.
+        #[serde(borrow)]
+        values: Vec<(Cow<'a, str>, TraceValue<'a>)>,
+    },
     /// A event has happened for some span.
     Event {
         /// Timestamp
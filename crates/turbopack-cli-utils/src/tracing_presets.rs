@@ -42,3 +42,10 @@ pub static TRACING_TURBO_TASKS_TARGETS: Lazy<Vec<&str>> = Lazy::new(|| {
     ]
     .concat()
 });
+pub static TRACING_INVALIDATION_TARGETS: Lazy<Vec<&str>> = Lazy::new(|| {
+    [
+        &TRACING_OVERVIEW_TARGETS[..],
+        &["turbo_tasks_memory::invalidation=trace"],
+    ]
+    .concat()
+});
@@ -50,6 +50,12 @@ pub struct CommonArguments {
     #[clap(long)]
     pub full_stats: bool,
 
+    /// Whether to record why tasks are invalidated and how long their
+    /// recomputations take, for the task graph inspector at
+    /// /__turbo_tasks__/inspector.
+    #[clap(long)]
+    pub inspector: bool,
+
     /// Enable experimental garbage collection with the provided memory limit in
     /// MB.
     #[clap(long)]
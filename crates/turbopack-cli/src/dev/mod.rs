@@ -372,6 +372,9 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
         false => StatsType::Essential,
     };
     tt.set_stats_type(stats_type);
+    if args.common.inspector {
+        tt.backend().enable_inspector();
+    }
 
     let tt_clone = tt.clone();
 
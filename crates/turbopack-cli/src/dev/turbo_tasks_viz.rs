@@ -1,8 +1,8 @@
 use std::{sync::Arc, time::Duration};
 
-use anyhow::{bail, Result};
-use mime::TEXT_HTML_UTF_8;
-use turbo_tasks::{get_invalidator, TurboTasks, TurboTasksBackendApi, Value};
+use anyhow::{bail, Context, Result};
+use mime::{APPLICATION_JSON, TEXT_HTML_UTF_8};
+use turbo_tasks::{get_invalidator, TaskId, TurboTasks, TurboTasksBackendApi, Value};
 use turbo_tasks_fs::File;
 use turbo_tasks_memory::{
     stats::{ReferenceType, Stats},
@@ -10,6 +10,7 @@ use turbo_tasks_memory::{
 };
 use turbopack_core::asset::AssetContentVc;
 use turbopack_dev_server::source::{
+    query::{Query, QueryValue},
     route_tree::{BaseSegment, RouteTreeVc, RouteTreesVc, RouteType},
     ContentSource, ContentSourceContentVc, ContentSourceData, ContentSourceDataFilter,
     ContentSourceDataVary, ContentSourceDataVaryVc, ContentSourceVc, GetContentSourceContent,
@@ -55,6 +56,43 @@ impl ContentSource for TurboTasksSource {
                 RouteType::Exact,
                 self_vc.into(),
             ),
+            RouteTreeVc::new_route(
+                vec![BaseSegment::Static("inspector".to_string())],
+                RouteType::Exact,
+                self_vc.into(),
+            ),
+            RouteTreeVc::new_route(
+                vec![
+                    BaseSegment::Static("api".to_string()),
+                    BaseSegment::Static("tasks".to_string()),
+                ],
+                RouteType::Exact,
+                self_vc.into(),
+            ),
+            RouteTreeVc::new_route(
+                vec![
+                    BaseSegment::Static("api".to_string()),
+                    BaseSegment::Static("task".to_string()),
+                ],
+                RouteType::Exact,
+                self_vc.into(),
+            ),
+            RouteTreeVc::new_route(
+                vec![
+                    BaseSegment::Static("api".to_string()),
+                    BaseSegment::Static("recomputations".to_string()),
+                ],
+                RouteType::Exact,
+                self_vc.into(),
+            ),
+            RouteTreeVc::new_route(
+                vec![
+                    BaseSegment::Static("api".to_string()),
+                    BaseSegment::Static("gc".to_string()),
+                ],
+                RouteType::Exact,
+                self_vc.into(),
+            ),
         ])
         .merge()
     }
@@ -86,6 +124,12 @@ impl GetContentSourceContent for TurboTasksSource {
                 invalidator.invalidate();
             }
         });
+        if let Some(api_path) = path.strip_prefix("api/") {
+            let json = api(tt.backend(), api_path, data.query.as_ref())?;
+            return Ok(ContentSourceContentVc::static_content(
+                AssetContentVc::from(File::from(json).with_content_type(APPLICATION_JSON)).into(),
+            ));
+        }
         let html = match path {
             "graph" => {
                 let mut stats = Stats::new();
@@ -136,6 +180,7 @@ impl GetContentSourceContent for TurboTasksSource {
                 });
                 "Done".to_string()
             }
+            "inspector" => INSPECTOR_HTML.to_string(),
             _ => bail!("Unknown path: {}", path),
         };
         Ok(ContentSourceContentVc::static_content(
@@ -143,3 +188,46 @@ impl GetContentSourceContent for TurboTasksSource {
         ))
     }
 }
+
+const INSPECTOR_HTML: &str = include_str!("turbo_tasks_inspector.html");
+
+const DEFAULT_LIMIT: usize = 100;
+
+fn query_param<'a>(query: Option<&'a Query>, name: &str) -> Option<&'a str> {
+    match query?.get(name)? {
+        QueryValue::String(value) => Some(value),
+        _ => None,
+    }
+}
+
+fn query_limit(query: Option<&Query>) -> Result<usize> {
+    query_param(query, "limit").map_or(Ok(DEFAULT_LIMIT), |limit| {
+        limit.parse().context("Invalid limit")
+    })
+}
+
+/// Answers the JSON queries of the task graph inspector.
+fn api(backend: &MemoryBackend, path: &str, query: Option<&Query>) -> Result<String> {
+    let json = match path {
+        "tasks" => {
+            let search = query_param(query, "search").unwrap_or_default();
+            serde_json::to_string(&backend.inspect_search(search, query_limit(query)?))?
+        }
+        "task" => {
+            let Some(id) = query_param(query, "id") else {
+                bail!("Missing id");
+            };
+            let id = TaskId::from(id.parse::<usize>().context("Invalid id")?);
+            let Some(details) = backend.inspect_task(id) else {
+                bail!("Unknown task {}", id);
+            };
+            serde_json::to_string(&details)?
+        }
+        "recomputations" => {
+            serde_json::to_string(&backend.inspect_slowest_recomputations(query_limit(query)?))?
+        }
+        "gc" => serde_json::to_string(&backend.gc_metrics())?,
+        _ => bail!("Unknown path: api/{}", path),
+    };
+    Ok(json)
+}
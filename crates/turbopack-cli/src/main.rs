@@ -6,13 +6,15 @@ use std::path::Path;
 use anyhow::{Context, Result};
 use clap::Parser;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Registry};
+use tracing_trace_event::TraceEventLayer;
 use turbopack_cli::{arguments::Arguments, register};
 use turbopack_cli_utils::{
     exit::ExitGuard,
     raw_trace::RawTraceLayer,
     trace_writer::TraceWriter,
     tracing_presets::{
-        TRACING_OVERVIEW_TARGETS, TRACING_TURBOPACK_TARGETS, TRACING_TURBO_TASKS_TARGETS,
+        TRACING_INVALIDATION_TARGETS, TRACING_OVERVIEW_TARGETS, TRACING_TURBOPACK_TARGETS,
+        TRACING_TURBO_TASKS_TARGETS,
     },
 };
 
@@ -38,6 +40,9 @@ fn main() {
             "turbo-tasks" => {
                 trace = TRACING_TURBO_TASKS_TARGETS.join(",");
             }
+            "invalidations" => {
+                trace = TRACING_INVALIDATION_TARGETS.join(",");
+            }
             _ => {}
         }
 
@@ -52,12 +57,54 @@ fn main() {
         std::fs::create_dir_all(&internal_dir)
             .context("Unable to create .turbopack directory")
             .unwrap();
-        let trace_file = internal_dir.join("trace.log");
-        let trace_writer = std::fs::File::create(trace_file).unwrap();
-        let (trace_writer, guard) = TraceWriter::new(trace_writer);
-        let subscriber = subscriber.with(RawTraceLayer::new(trace_writer));
 
-        let guard = ExitGuard::new(guard).unwrap();
+        // Trace formats: "raw" can be converted with turbopack-convert-trace,
+        // "chrome" can be opened in Perfetto directly and "ftrace" (Linux only)
+        // emits markers for `perf record -e ftrace:print`
+        let mut raw_trace = None;
+        let mut trace_events = None;
+        #[cfg(target_os = "linux")]
+        let mut trace_markers = None;
+        let format = std::env::var("TURBOPACK_TRACE_FORMAT").ok();
+        let guards = match format.as_deref().unwrap_or("raw") {
+            "raw" => {
+                let trace_file = internal_dir.join("trace.log");
+                let trace_writer = std::fs::File::create(trace_file).unwrap();
+                let (trace_writer, guard) = TraceWriter::new(trace_writer);
+                raw_trace = Some(RawTraceLayer::new(trace_writer));
+                (Some(guard), None)
+            }
+            "chrome" => {
+                let trace_file = internal_dir.join("trace.json");
+                let trace_writer = std::fs::File::create(trace_file).unwrap();
+                let (layer, guard) = TraceEventLayer::new(trace_writer);
+                trace_events = Some(layer);
+                (None, Some(guard))
+            }
+            #[cfg(target_os = "linux")]
+            "ftrace" => {
+                trace_markers = Some(
+                    tracing_trace_event::TraceMarkerLayer::new()
+                        .context("Unable to open the ftrace marker file")
+                        .unwrap(),
+                );
+                (None, None)
+            }
+            format => {
+                let formats = if cfg!(target_os = "linux") {
+                    "raw, chrome or ftrace"
+                } else {
+                    "raw or chrome"
+                };
+                eprintln!("Unknown TURBOPACK_TRACE_FORMAT \"{format}\", expected {formats}");
+                std::process::exit(1);
+            }
+        };
+        let subscriber = subscriber.with(raw_trace).with(trace_events);
+        #[cfg(target_os = "linux")]
+        let subscriber = subscriber.with(trace_markers);
+
+        let guard = ExitGuard::new(guards).unwrap();
 
         subscriber.init();
 
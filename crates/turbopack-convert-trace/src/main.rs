@@ -18,6 +18,11 @@
 //! - `--idle`: Adds extra info spans when cpus are idle.
 //!
 //! Default is `--merged`.
+//!
+//! A trace recorded with `TURBOPACK_TRACING=invalidations` shows how
+//! invalidations propagate: each `invalidation` span contains the tasks it
+//! dirtied and their `recompute` spans, which are marked as `unchanged` when
+//! the propagation stopped there or `cascaded` otherwise.
 
 use std::{
     borrow::Cow,
@@ -182,6 +187,11 @@ fn main() {
                     }
                 }
             }
+            TraceRow::Record { id, values } => {
+                if let Some(&internal_id) = active_ids.get(&id) {
+                    spans[internal_id].values.extend(values);
+                }
+            }
             TraceRow::Event { ts, parent, values } => {
                 let mut values = values.into_iter().collect::<IndexMap<_, _>>();
                 let duration = values.get("duration").and_then(|v| v.as_u64()).unwrap_or(0);
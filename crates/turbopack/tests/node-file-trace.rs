@@ -279,19 +279,19 @@ fn node_file_trace_memory(#[case] input: CaseInput) {
 
 #[cfg(feature = "test_persistent_cache")]
 #[apply(test_cases)]
-fn node_file_trace_rocksdb(#[case] input: CaseInput) {
+fn node_file_trace_redb(#[case] input: CaseInput) {
     use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
-    use turbo_tasks_rocksdb::RocksDbPersistedGraph;
+    use turbo_tasks_redb::RedbPersistedGraph;
 
     node_file_trace(
         input,
-        "rockdb",
+        "redb",
         false,
         2,
         240,
         |directory_path| {
             TurboTasks::new(MemoryBackendWithPersistedGraph::new(
-                RocksDbPersistedGraph::new(directory_path.join(".db")).unwrap(),
+                RedbPersistedGraph::new(directory_path.join(".db")).unwrap(),
             ))
         },
         |_| {},
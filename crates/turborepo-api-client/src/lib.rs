@@ -4,7 +4,7 @@
 
 use std::env;
 
-use reqwest::RequestBuilder;
+use reqwest::{RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
 
 pub use crate::error::{Error, Result};
@@ -200,6 +200,41 @@ impl APIClient {
         Ok(response.json().await?)
     }
 
+    /// Checks whether an artifact exists in the remote cache without
+    /// downloading it. Returns the time in milliseconds the artifact saves,
+    /// or `None` if it isn't cached.
+    pub async fn artifact_exists(
+        &self,
+        hash: &str,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<Option<u64>> {
+        let request_builder = self
+            .client
+            .head(self.make_url(&format!("/v8/artifacts/{}", hash)))
+            .header("User-Agent", self.user_agent.clone())
+            .header("Authorization", format!("Bearer {}", token));
+
+        let request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        let response = retry::make_retryable_request(request_builder).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+
+        // A missing or malformed duration just means we can't report time saved
+        let duration = response
+            .headers()
+            .get("x-artifact-duration")
+            .and_then(|duration| duration.to_str().ok())
+            .and_then(|duration| duration.parse().ok())
+            .unwrap_or_default();
+
+        Ok(Some(duration))
+    }
+
     pub async fn get_spaces(&self, token: &str, team_id: Option<&str>) -> Result<SpacesResponse> {
         // create url with teamId if provided
         let endpoint = match team_id {
@@ -1,6 +1,10 @@
 #![allow(dead_code)]
 mod create;
+mod output_globs;
 mod restore;
 mod restore_directory;
 mod restore_regular;
 mod restore_symlink;
+
+pub use output_globs::OutputGlobs;
+pub use restore::CacheReader;
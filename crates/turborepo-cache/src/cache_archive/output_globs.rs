@@ -0,0 +1,112 @@
+use std::backtrace::Backtrace;
+
+use turbopath::AnchoredSystemPath;
+use wax::{Any, DirectoryMatcher, Glob, Pattern};
+
+use crate::CacheError;
+
+/// The declared outputs of a task, as globs anchored at the same directory
+/// that an artifact is restored to.
+///
+/// Used to check that an artifact only contains files that the task could
+/// have produced before any of them are written to disk.
+pub struct OutputGlobs {
+    inclusions: Any<'static>,
+    exclusions: Any<'static>,
+    // Used to allow the parent directories of included files
+    directories: Vec<DirectoryMatcher>,
+}
+
+impl OutputGlobs {
+    pub fn new(inclusions: &[String], exclusions: &[String]) -> Result<Self, CacheError> {
+        let inclusions = compile(inclusions)?;
+        let directories = inclusions
+            .iter()
+            .map(|glob| glob.directory_matcher())
+            .collect();
+        Ok(Self {
+            inclusions: any(inclusions)?,
+            exclusions: any(compile(exclusions)?)?,
+            directories,
+        })
+    }
+
+    /// Returns an error if `path` isn't one of the declared outputs.
+    /// Directories are also allowed if they could contain a declared output.
+    pub(crate) fn validate(
+        &self,
+        path: &AnchoredSystemPath,
+        is_dir: bool,
+    ) -> Result<(), CacheError> {
+        let path = path.as_path();
+        let is_declared = !self.exclusions.is_match(path)
+            && (self.inclusions.is_match(path)
+                || (is_dir
+                    && self
+                        .directories
+                        .iter()
+                        .any(|matcher| matcher.may_contain_matches(path))));
+        if is_declared {
+            Ok(())
+        } else {
+            Err(CacheError::UndeclaredOutput(
+                path.to_string_lossy().to_string(),
+                Backtrace::capture(),
+            ))
+        }
+    }
+}
+
+fn compile(globs: &[String]) -> Result<Vec<Glob<'static>>, CacheError> {
+    globs
+        .iter()
+        .map(|glob| {
+            Glob::new(glob).map(Glob::into_owned).map_err(|e| {
+                CacheError::InvalidGlob(glob.clone(), e.to_string(), Backtrace::capture())
+            })
+        })
+        .collect()
+}
+
+fn any(globs: Vec<Glob<'static>>) -> Result<Any<'static>, CacheError> {
+    let text = globs
+        .iter()
+        .map(|glob| glob.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    wax::any(globs).map_err(|e| CacheError::InvalidGlob(text, e.to_string(), Backtrace::capture()))
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+    use turbopath::AnchoredSystemPathBuf;
+
+    use super::OutputGlobs;
+
+    #[test_case("apps/web/dist/index.js", false, true ; "included file")]
+    #[test_case("apps/web/dist", true, true ; "included directory")]
+    #[test_case("apps/web", true, true ; "parent of included files")]
+    #[test_case("apps/web", false, false ; "file at parent directory path")]
+    #[test_case("apps/web/src/index.js", false, false ; "undeclared file")]
+    #[test_case("apps/web/src", true, false ; "undeclared directory")]
+    #[test_case("apps/web/dist/cache/index.js", false, false ; "excluded file")]
+    #[test_case("apps/web/.turbo/turbo-build.log", false, true ; "log file")]
+    fn test_validate(path: &str, is_dir: bool, is_valid: bool) {
+        let outputs = OutputGlobs::new(
+            &[
+                "apps/web/dist/**".to_string(),
+                "apps/web/.turbo/turbo-build.log".to_string(),
+            ],
+            &["apps/web/dist/cache/**".to_string()],
+        )
+        .unwrap();
+        let path = AnchoredSystemPathBuf::from_raw(path).unwrap();
+        assert_eq!(outputs.validate(&path, is_dir).is_ok(), is_valid);
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        assert!(OutputGlobs::new(&["dist/***".to_string()], &[]).is_err());
+    }
+}
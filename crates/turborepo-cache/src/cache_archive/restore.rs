@@ -1,4 +1,4 @@
-use std::{backtrace::Backtrace, collections::HashMap, io::Read};
+use std::{backtrace::Backtrace, collections::HashMap, fs, io::Read};
 
 use petgraph::graph::DiGraph;
 use ring::digest::{Context, SHA512};
@@ -7,6 +7,7 @@ use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf
 
 use crate::{
     cache_archive::{
+        output_globs::OutputGlobs,
         restore_directory::{restore_directory, CachedDirTree},
         restore_regular::restore_regular,
         restore_symlink::{
@@ -62,6 +63,48 @@ impl CacheReader {
     pub fn restore(
         &mut self,
         anchor: &AbsoluteSystemPath,
+    ) -> Result<Vec<AnchoredSystemPathBuf>, CacheError> {
+        self.restore_inner(anchor, None)
+    }
+
+    /// Checks that every entry of the artifact is one of the task's declared
+    /// outputs without writing anything. Returns the files and symlinks on
+    /// disk that differ from the artifact, and would be overwritten by
+    /// restoring it.
+    pub fn check_outputs(
+        &mut self,
+        anchor: &AbsoluteSystemPath,
+        outputs: &OutputGlobs,
+    ) -> Result<Vec<AnchoredSystemPathBuf>, CacheError> {
+        let mut conflicts = Vec::new();
+        let mut tr = tar::Archive::new(&mut self.reader);
+        for entry in tr.entries()? {
+            let mut entry = entry?;
+            let processed_name = AnchoredSystemPathBuf::from_system_path(&entry.header().path()?)?;
+            outputs.validate(&processed_name, entry.header().entry_type().is_dir())?;
+            if differs_from_disk(anchor, &processed_name, &mut entry)? {
+                conflicts.push(processed_name);
+            }
+        }
+        Ok(conflicts)
+    }
+
+    /// Restores the artifact, checking that every entry is one of the task's
+    /// declared outputs before writing it. Fails on the first entry that
+    /// isn't, so use [`CacheReader::check_outputs`] first to avoid writing
+    /// anything for an invalid artifact.
+    pub fn restore_outputs(
+        &mut self,
+        anchor: &AbsoluteSystemPath,
+        outputs: &OutputGlobs,
+    ) -> Result<Vec<AnchoredSystemPathBuf>, CacheError> {
+        self.restore_inner(anchor, Some(outputs))
+    }
+
+    fn restore_inner(
+        &mut self,
+        anchor: &AbsoluteSystemPath,
+        outputs: Option<&OutputGlobs>,
     ) -> Result<Vec<AnchoredSystemPathBuf>, CacheError> {
         let mut restored = Vec::new();
         anchor.create_dir_all()?;
@@ -83,7 +126,7 @@ impl CacheReader {
         let dir_cache = CachedDirTree::new(anchor.to_owned());
         let mut tr = tar::Archive::new(&mut self.reader);
 
-        Self::restore_entries(&mut tr, &mut restored, dir_cache, anchor)?;
+        Self::restore_entries(&mut tr, &mut restored, dir_cache, anchor, outputs)?;
         Ok(restored)
     }
 
@@ -92,6 +135,7 @@ impl CacheReader {
         restored: &mut Vec<AnchoredSystemPathBuf>,
         mut dir_cache: CachedDirTree,
         anchor: &AbsoluteSystemPath,
+        outputs: Option<&OutputGlobs>,
     ) -> Result<(), CacheError> {
         // On first attempt to restore it's possible that a link target doesn't exist.
         // Save them and topologically sort them.
@@ -99,6 +143,11 @@ impl CacheReader {
 
         for entry in tr.entries()? {
             let mut entry = entry?;
+            if let Some(outputs) = outputs {
+                let header = entry.header();
+                let processed_name = AnchoredSystemPathBuf::from_system_path(&header.path()?)?;
+                outputs.validate(&processed_name, header.entry_type().is_dir())?;
+            }
             match restore_entry(&mut dir_cache, anchor, &mut entry) {
                 Err(CacheError::LinkTargetDoesNotExist(_, _)) => {
                     symlinks.push(entry);
@@ -183,6 +232,39 @@ fn restore_entry<T: Read>(
     }
 }
 
+// Whether a file or symlink already on disk would be changed by restoring
+// `entry` over it
+fn differs_from_disk<T: Read>(
+    anchor: &AbsoluteSystemPath,
+    processed_name: &AnchoredSystemPathBuf,
+    entry: &mut Entry<T>,
+) -> Result<bool, CacheError> {
+    let path = anchor.resolve(processed_name);
+    let Ok(metadata) = path.symlink_metadata() else {
+        return Ok(false);
+    };
+    match entry.header().entry_type() {
+        tar::EntryType::Regular => {
+            if !metadata.is_file() || metadata.len() != entry.header().size()? {
+                return Ok(true);
+            }
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            Ok(fs::read(path.as_path())? != contents)
+        }
+        tar::EntryType::Symlink => {
+            let link_name = entry
+                .header()
+                .link_name()?
+                .ok_or_else(|| CacheError::MalformedTar(Backtrace::capture()))?;
+            Ok(path
+                .read_link()
+                .map_or(true, |existing| existing.as_std_path() != link_name))
+        }
+        _ => Ok(false),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs, fs::File, io::empty, path::Path};
@@ -194,7 +276,9 @@ mod tests {
     use tracing::debug;
     use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
 
-    use crate::cache_archive::{restore::CacheReader, restore_symlink::canonicalize_linkname};
+    use crate::cache_archive::{
+        output_globs::OutputGlobs, restore::CacheReader, restore_symlink::canonicalize_linkname,
+    };
 
     // Expected output of the cache
     #[derive(Debug)]
@@ -915,6 +999,107 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_restore_outputs_validates_globs() -> Result<()> {
+        let input_dir = tempdir()?;
+        let archive_path = generate_tar(
+            &input_dir,
+            &[
+                TarFile::Directory {
+                    path: AnchoredSystemPathBuf::from_raw("dist/").unwrap(),
+                },
+                TarFile::File {
+                    body: b"built".to_vec(),
+                    path: AnchoredSystemPathBuf::from_raw("dist/index.js").unwrap(),
+                },
+                TarFile::File {
+                    body: b"source".to_vec(),
+                    path: AnchoredSystemPathBuf::from_raw("src/index.js").unwrap(),
+                },
+            ],
+        )?;
+        let output_dir = tempdir()?;
+        let anchor = AbsoluteSystemPath::new(output_dir.path().to_str().unwrap())?;
+
+        let outputs = OutputGlobs::new(&["dist/**".to_string()], &[])?;
+        let mut cache_reader = CacheReader::open(&archive_path)?;
+        let result = cache_reader.check_outputs(anchor, &outputs);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "artifact contains src/index.js, which does not match the task's outputs"
+        );
+        // Nothing is written, not even the entries that precede the invalid one
+        assert!(!anchor.join_component("dist").exists());
+
+        let outputs = OutputGlobs::new(&["dist/**".to_string(), "src/**".to_string()], &[])?;
+        let mut cache_reader = CacheReader::open(&archive_path)?;
+        assert_eq!(cache_reader.check_outputs(anchor, &outputs)?, vec![]);
+        let mut cache_reader = CacheReader::open(&archive_path)?;
+        let restored = cache_reader.restore_outputs(anchor, &outputs)?;
+        assert_eq!(
+            restored,
+            into_anchored_system_path_vec(vec!["dist", "dist/index.js", "src/index.js"])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_outputs_reports_conflicts() -> Result<()> {
+        let input_dir = tempdir()?;
+        let archive_path = generate_tar(
+            &input_dir,
+            &[
+                TarFile::File {
+                    body: b"unchanged".to_vec(),
+                    path: AnchoredSystemPathBuf::from_raw("unchanged").unwrap(),
+                },
+                TarFile::File {
+                    body: b"cached".to_vec(),
+                    path: AnchoredSystemPathBuf::from_raw("same-size").unwrap(),
+                },
+                TarFile::File {
+                    body: b"cached".to_vec(),
+                    path: AnchoredSystemPathBuf::from_raw("different-size").unwrap(),
+                },
+                TarFile::File {
+                    body: b"new".to_vec(),
+                    path: AnchoredSystemPathBuf::from_raw("new").unwrap(),
+                },
+                TarFile::Symlink {
+                    link_path: AnchoredSystemPathBuf::from_raw("link").unwrap(),
+                    link_target: AnchoredSystemPathBuf::from_raw("unchanged").unwrap(),
+                },
+            ],
+        )?;
+        let output_dir = tempdir()?;
+        let anchor = AbsoluteSystemPath::new(output_dir.path().to_str().unwrap())?;
+        fs::write(anchor.join_component("unchanged"), "unchanged")?;
+        fs::write(anchor.join_component("same-size"), "edited")?;
+        fs::write(anchor.join_component("different-size"), "locally edited")?;
+        anchor.join_component("link").symlink_to_file("new")?;
+
+        let outputs = OutputGlobs::new(&["**".to_string()], &[])?;
+        let mut cache_reader = CacheReader::open(&archive_path)?;
+        let conflicts = cache_reader.check_outputs(anchor, &outputs)?;
+        assert_eq!(
+            conflicts,
+            into_anchored_system_path_vec(vec!["same-size", "different-size", "link"])
+        );
+        // Conflicts are found before anything is overwritten
+        assert_eq!(fs::read(anchor.join_component("same-size"))?, b"edited");
+
+        let mut cache_reader = CacheReader::open(&archive_path)?;
+        cache_reader.restore_outputs(anchor, &outputs)?;
+        assert_eq!(fs::read(anchor.join_component("same-size"))?, b"cached");
+        assert_eq!(
+            fs::read(anchor.join_component("different-size"))?,
+            b"cached"
+        );
+
+        Ok(())
+    }
+
     #[test_case(Path::new("source").try_into()?, Path::new("target"), "/Users/test/target", "C:\\Users\\test\\target" ; "hello world")]
     #[test_case(Path::new("child/source").try_into()?, Path::new("../sibling/target"), "/Users/test/sibling/target", "C:\\Users\\test\\sibling\\target" ; "Unix path subdirectory traversal")]
     #[test_case(Path::new("child/source").try_into()?, Path::new("..\\sibling\\target"), "/Users/test/child/..\\sibling\\target", "C:\\Users\\test\\sibling\\target" ; "Windows path subdirectory traversal")]
@@ -49,4 +49,8 @@ pub enum CacheError {
     WindowsUnsafeName(String, #[backtrace] Backtrace),
     #[error("tar attempts to write outside of directory: {0}")]
     LinkOutsideOfDirectory(String, #[backtrace] Backtrace),
+    #[error("invalid output glob {0}: {1}")]
+    InvalidGlob(String, String, #[backtrace] Backtrace),
+    #[error("artifact contains {0}, which does not match the task's outputs")]
+    UndeclaredOutput(String, #[backtrace] Backtrace),
 }
@@ -0,0 +1,160 @@
+//! Hashing of dotenv files.
+//!
+//! Files are hashed by their parsed key/value pairs rather than their raw
+//! bytes, so reordering variables or editing comments doesn't change the
+//! hash.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+};
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use turbopath::{AbsoluteSystemPath, PathError, RelativeUnixPathBuf};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid dotenv path {path}: {source}")]
+    Path {
+        path: String,
+        #[source]
+        source: PathError,
+    },
+    #[error("unable to read {path} for env vars: {source}")]
+    Read {
+        path: String,
+        #[source]
+        source: dotenv::Error,
+    },
+}
+
+/// Parses a dotenv file into its variables, sorted by name.
+///
+/// Mirrors how the file would be loaded: if a variable is defined more than
+/// once the first definition wins. References to other variables such as
+/// `$HOME` are left unexpanded, so the result only
+/// depends on the file and not on the environment it is parsed in.
+pub fn parse(mut contents: impl io::Read) -> Result<BTreeMap<String, String>, dotenv::Error> {
+    let mut buf = String::new();
+    contents.read_to_string(&mut buf)?;
+    // The parser always expands references, escaping every `\` and `$` stops
+    // it from expanding anything
+    let escaped = buf.replace('\\', "\\\\").replace('$', "\\$");
+    let dotenv = dotenv::from_read(escaped.as_bytes())?;
+    let mut vars = BTreeMap::new();
+    for (key, value) in dotenv.iter() {
+        vars.entry(key.to_string()).or_insert(value);
+    }
+    Ok(vars)
+}
+
+/// Hashes each of the dotenv files that exist under `base`, returning a map of
+/// file path to the hash of its parsed contents. Missing files are skipped.
+pub fn hash_files(
+    base: &AbsoluteSystemPath,
+    files: &[RelativeUnixPathBuf],
+) -> Result<BTreeMap<RelativeUnixPathBuf, String>, Error> {
+    let mut hashes = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for file in files {
+        if !seen.insert(file) {
+            continue;
+        }
+        let path = base.join_unix_path(file).map_err(|source| Error::Path {
+            path: file.as_str().to_string(),
+            source,
+        })?;
+        let contents = match path.open() {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(Error::Read {
+                    path: path.to_string(),
+                    source: e.into(),
+                })
+            }
+        };
+        let vars = parse(contents).map_err(|source| Error::Read {
+            path: path.to_string(),
+            source,
+        })?;
+        hashes.insert(file.clone(), hash_vars(&vars));
+    }
+    Ok(hashes)
+}
+
+fn hash_vars(vars: &BTreeMap<String, String>) -> String {
+    let mut hasher = Sha256::new();
+    for (key, value) in vars {
+        hasher.update(key.as_bytes());
+        hasher.update(b"=");
+        hasher.update(value.as_bytes());
+        // Terminate each pair so adjacent pairs can't run together
+        hasher.update(b"\0");
+    }
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+    use turbopath::{AbsoluteSystemPathBuf, RelativeUnixPathBuf};
+
+    use super::{hash_files, parse};
+
+    #[test]
+    fn test_parse() {
+        let vars = parse(
+            "# comment\nB=2\nA=1 # trailing comment\nA=overridden\nC=\"quoted\"\n".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            vars.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string()),
+                ("C".to_string(), "quoted".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_does_not_expand() {
+        std::env::set_var("TURBO_DOT_ENV_TEST_VAR", "from the environment");
+        let vars = parse("A=1\nA=2\nB=$A\nC=\"${TURBO_DOT_ENV_TEST_VAR}\\n\"".as_bytes()).unwrap();
+        assert_eq!(
+            vars.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "$A".to_string()),
+                ("C".to_string(), "${TURBO_DOT_ENV_TEST_VAR}\\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hash_files() {
+        let dir = tempdir().unwrap();
+        let base = AbsoluteSystemPathBuf::try_from(dir.path()).unwrap();
+        let files = [".env", ".env.local", ".env.missing"]
+            .iter()
+            .map(|file| RelativeUnixPathBuf::new(*file).unwrap())
+            .collect::<Vec<_>>();
+
+        fs::write(base.join_component(".env"), "A=1\nB=2\n").unwrap();
+        fs::write(base.join_component(".env.local"), "A=1\nB=3\n").unwrap();
+        let hashes = hash_files(&base, &files).unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert_ne!(hashes[&files[0]], hashes[&files[1]]);
+        let env_hash = hashes[&files[0]].clone();
+
+        // Comments and ordering don't change the hash, values do
+        fs::write(base.join_component(".env"), "# comment\nB=2\n\nA=1\n").unwrap();
+        assert_eq!(hash_files(&base, &files).unwrap()[&files[0]], env_hash);
+        fs::write(base.join_component(".env"), "A=1\nB=4\n").unwrap();
+        assert_ne!(hash_files(&base, &files).unwrap()[&files[0]], env_hash);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod dot_env;
+
 use std::{
     collections::HashMap,
     env,
@@ -7,6 +9,7 @@ use std::{
 
 use regex::Regex;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 const DEFAULT_ENV_VARS: [&str; 1] = ["VERCEL_ANALYTICS_ID"];
@@ -99,6 +102,42 @@ impl EnvironmentVariableMap {
         }
     }
 
+    // Returns a deterministically sorted list of the variable names, used when
+    // variables need to be reported without their values.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.0.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    // Returns a deterministically sorted list of KEY=value pairs. This is the
+    // form in which environment variables are used as a task hash input.
+    pub fn to_hashable(&self) -> Vec<String> {
+        self.map_to_pairs(|key, value| format!("{}={}", key, value))
+    }
+
+    // Returns a deterministically sorted list of KEY=sha256(value) pairs so that
+    // variables can be displayed without leaking their values.
+    pub fn to_secret_hashable(&self) -> Vec<String> {
+        self.map_to_pairs(|key, value| {
+            if value.is_empty() {
+                format!("{}=", key)
+            } else {
+                format!("{}={}", key, hex::encode(Sha256::digest(value)))
+            }
+        })
+    }
+
+    fn map_to_pairs(&self, transformer: impl Fn(&str, &str) -> String) -> Vec<String> {
+        let mut pairs = self
+            .0
+            .iter()
+            .map(|(key, value)| transformer(key, value))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        pairs
+    }
+
     // returns a WildcardMaps after processing wildcards against it.
     fn wildcard_map_from_wildcards(
         &self,
@@ -251,8 +290,12 @@ pub fn get_global_hashable_env_vars(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use test_case::test_case;
 
+    use crate::EnvironmentVariableMap;
+
     #[test_case("LITERAL_\\*", "LITERAL_\\*" ; "literal star")]
     #[test_case("\\*LEADING", "\\*LEADING" ; "leading literal star")]
     #[test_case("\\!LEADING", "\\\\!LEADING" ; "leading literal bang")]
@@ -262,4 +305,24 @@ mod tests {
         let actual = super::wildcard_to_regex_pattern(pattern);
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_to_secret_hashable() {
+        let map = EnvironmentVariableMap::from(HashMap::from([
+            ("NEXT_PUBLIC_API".to_string(), "value".to_string()),
+            ("EMPTY".to_string(), "".to_string()),
+        ]));
+        assert_eq!(
+            map.to_secret_hashable(),
+            vec![
+                "EMPTY=".to_string(),
+                "NEXT_PUBLIC_API=cd42404d52ad55ccfa9aca4adc828aa5800ad9d385a0671fbcbf724118320619"
+                    .to_string(),
+            ]
+        );
+        assert_eq!(
+            map.to_hashable(),
+            vec!["EMPTY=".to_string(), "NEXT_PUBLIC_API=value".to_string()]
+        );
+    }
 }
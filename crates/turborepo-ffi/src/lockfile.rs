@@ -5,7 +5,8 @@ use std::{
 
 use thiserror::Error;
 use turborepo_lockfiles::{
-    self, BerryLockfile, LockfileData, NpmLockfile, Package, PnpmLockfile, Yarn1Lockfile,
+    self, BerryLockfile, BerryManifest, LockfileData, NpmLockfile, Package, PnpmLockfile,
+    Yarn1Lockfile,
 };
 
 use super::{proto, Buffer};
@@ -82,10 +83,9 @@ fn berry_transitive_closure_inner(
         resolutions,
         ..
     } = request;
-    let resolutions =
-        resolutions.map(|r| turborepo_lockfiles::BerryManifest::with_resolutions(r.resolutions));
+    let manifest = berry_manifest(resolutions)?;
     let data = LockfileData::from_bytes(contents.as_slice())?;
-    let lockfile = BerryLockfile::new(&data, resolutions.as_ref())?;
+    let lockfile = BerryLockfile::new(&data, manifest.as_ref())?;
     let dependencies = turborepo_lockfiles::all_transitive_closures(
         &lockfile,
         workspaces.into_iter().map(|(k, v)| (k, v.into())).collect(),
@@ -93,6 +93,26 @@ fn berry_transitive_closure_inner(
     Ok(dependencies.into())
 }
 
+// Berry reads resolutions from package.json and packageExtensions from
+// .yarnrc.yml
+fn berry_manifest(
+    data: Option<proto::AdditionalBerryData>,
+) -> Result<Option<BerryManifest>, Error> {
+    let Some(proto::AdditionalBerryData {
+        resolutions,
+        yarnrc,
+    }) = data
+    else {
+        return Ok(None);
+    };
+    let manifest = BerryManifest::with_resolutions(resolutions);
+    if yarnrc.is_empty() {
+        Ok(Some(manifest))
+    } else {
+        Ok(Some(manifest.with_yarnrc(&yarnrc)?))
+    }
+}
+
 fn pnpm_transitive_closure_inner(
     request: proto::TransitiveDepsRequest,
 ) -> Result<proto::WorkspaceDependencies, Error> {
@@ -156,7 +176,7 @@ fn subgraph_inner(buf: Buffer) -> Result<Vec<u8>, Error> {
             &contents,
             &workspaces,
             &packages,
-            resolutions.map(|res| res.resolutions),
+            berry_manifest(resolutions)?.as_ref(),
         )?,
         proto::PackageManager::Pnpm => {
             turborepo_lockfiles::pnpm_subgraph(&contents, &workspaces, &packages)?
@@ -2,6 +2,7 @@
 #![feature(once_cell)]
 
 mod empty_glob;
+mod walk;
 
 use std::{
     borrow::Cow,
@@ -350,11 +351,41 @@ pub(crate) fn any_with_contextual_error(
     })
 }
 
+/// Settings that control how [`globwalk_with_settings`] traverses the file
+/// system
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Settings {
+    /// Skip files and directories that are ignored by a `.gitignore`
+    pub respect_gitignore: bool,
+}
+
+impl Settings {
+    pub fn respect_gitignore(mut self, respect_gitignore: bool) -> Self {
+        self.respect_gitignore = respect_gitignore;
+        self
+    }
+}
+
 pub fn globwalk(
     base_path: &AbsoluteSystemPath,
     include: &[String],
     exclude: &[String],
     walk_type: WalkType,
+) -> Result<HashSet<AbsoluteSystemPathBuf>, WalkError> {
+    globwalk_with_settings(base_path, include, exclude, walk_type, Settings::default())
+}
+
+/// Performs a glob walk with the given settings.
+///
+/// Unlike walking each include pattern on its own, all of the patterns are
+/// matched during a single parallel traversal, so directories shared between
+/// patterns are only read once.
+pub fn globwalk_with_settings(
+    base_path: &AbsoluteSystemPath,
+    include: &[String],
+    exclude: &[String],
+    walk_type: WalkType,
+    settings: Settings,
 ) -> Result<HashSet<AbsoluteSystemPathBuf>, WalkError> {
     let (base_path_new, include_paths, exclude_paths) =
         preprocess_paths_and_globs(base_path, include, exclude)?;
@@ -367,61 +398,42 @@ pub fn globwalk(
         .map(glob_with_contextual_error)
         .collect::<Result<Vec<_>, _>>()?;
 
-    let result = inc_patterns
-        .into_iter()
-        .flat_map(|glob| {
-            // Check if the glob specifies an exact filename with no meta characters.
-            if let Some(prefix) = glob.variance().path() {
-                // We expect all of our globs to be absolute paths (asserted above)
-                assert!(prefix.is_absolute(), "Found relative glob path {}", glob);
-                // We're either going to return this path or nothing. Check if it's a directory
-                // and if we want directories
-                match AbsoluteSystemPathBuf::try_from(prefix).and_then(|path| {
-                    let metadata = path.symlink_metadata()?;
-                    Ok((path, metadata))
-                }) {
-                    Err(e) if e.is_io_error(ErrorKind::NotFound) => {
-                        // If the file doesn't exist, it's not an error, there's just nothing to
-                        // glob
-                        vec![]
-                    }
-                    Err(e) => vec![Err(e.into())],
-                    Ok((_, md)) if walk_type == WalkType::Files && md.is_dir() => {
-                        vec![]
-                    }
-                    Ok((path, _)) => vec![Ok(path)],
-                }
-            } else {
-                glob.walk(&base_path_new)
-                    .not(ex_patterns.iter().cloned())
-                    // Per docs, only fails if exclusion list is too large, since we're using
-                    // pre-compiled globs
-                    .unwrap_or_else(|e| {
-                        panic!(
-                            "Failed to compile exclusion globs: {:?}: {}",
-                            ex_patterns, e,
-                        )
-                    })
-                    .filter_map(|entry| match entry {
-                        Ok(entry) if walk_type == WalkType::Files && entry.file_type().is_dir() => {
-                            None
-                        }
-                        Ok(entry) => Some(
-                            AbsoluteSystemPathBuf::try_from(entry.path()).map_err(|e| e.into()),
-                        ),
-                        Err(e) => {
-                            let io_err = std::io::Error::from(e);
-                            if io_err.kind() == std::io::ErrorKind::NotFound {
-                                None
-                            } else {
-                                Some(Err(io_err.into()))
-                            }
-                        }
-                    })
-                    .collect::<Vec<_>>()
+    let mut result = HashSet::new();
+    let mut walked = Vec::new();
+    for glob in inc_patterns {
+        // Check if the glob specifies an exact filename with no meta characters.
+        let Some(prefix) = glob.variance().path().map(|path| path.to_path_buf()) else {
+            walked.push(glob);
+            continue;
+        };
+        // We expect all of our globs to be absolute paths (asserted above)
+        assert!(prefix.is_absolute(), "Found relative glob path {}", glob);
+        // We're either going to return this path or nothing. Check if it's a directory
+        // and if we want directories
+        match AbsoluteSystemPathBuf::try_from(prefix.as_path()).and_then(|path| {
+            let metadata = path.symlink_metadata()?;
+            Ok((path, metadata))
+        }) {
+            Err(e) if e.is_io_error(ErrorKind::NotFound) => {
+                // If the file doesn't exist, it's not an error, there's just
+                // nothing to glob
+            }
+            Err(e) => return Err(e.into()),
+            Ok((_, md)) if walk_type == WalkType::Files && md.is_dir() => {}
+            Ok((path, _)) => {
+                result.insert(path);
             }
-        })
-        .collect::<Result<HashSet<_>, WalkError>>()?;
+        }
+    }
+
+    let exclusions = walk::Exclusions::new(ex_patterns);
+    result.extend(walk::walk_globs(
+        &base_path_new,
+        walked,
+        &exclusions,
+        walk_type,
+        settings,
+    )?);
     Ok(result)
 }
 
@@ -435,7 +447,7 @@ mod test {
 
     use crate::{
         collapse_path, empty_glob::InclusiveEmptyAny, glob_with_contextual_error, globwalk,
-        MatchType, WalkError, WalkType,
+        globwalk_with_settings, MatchType, Settings, WalkError, WalkType,
     };
 
     #[cfg(unix)]
@@ -1440,4 +1452,62 @@ mod test {
         );
         assert_eq!(paths, expected);
     }
+
+    #[test]
+    fn overlapping_includes() {
+        let files = &[
+            "dist/index.js",
+            "dist/index.js.map",
+            "dist/types/index.d.ts",
+            "dist/cache/entry",
+            "lib/index.js",
+        ];
+        let tmp = setup_files(files);
+        let root = AbsoluteSystemPathBuf::try_from(tmp.path()).unwrap();
+        let include = &[
+            "dist/**".to_string(),
+            "dist/types/*.d.ts".to_string(),
+            "**/*.js".to_string(),
+        ];
+        let exclude = &["dist/cache".to_string(), "**/*.map".to_string()];
+        let paths = globwalk(&root, include, exclude, WalkType::Files)
+            .unwrap()
+            .into_iter()
+            .map(|path| root.anchor(path).unwrap().to_unix().unwrap().to_string())
+            .collect::<HashSet<_>>();
+        let expected = ["dist/index.js", "dist/types/index.d.ts", "lib/index.js"]
+            .into_iter()
+            .map(String::from)
+            .collect::<HashSet<_>>();
+        assert_eq!(paths, expected);
+    }
+
+    #[test_case(false, &["dist/index.js", "dist/generated/types.d.ts", "dist/.gitignore"] ; "includes gitignored files by default")]
+    #[test_case(true, &["dist/index.js", "dist/.gitignore"] ; "skips gitignored files")]
+    fn respect_gitignore(respect_gitignore: bool, expected: &[&str]) {
+        let files = &[
+            "dist/index.js",
+            "dist/generated/types.d.ts",
+            "dist/.gitignore",
+        ];
+        let tmp = setup_files(files);
+        std::fs::write(tmp.path().join("dist/.gitignore"), "generated/\n").unwrap();
+        let root = AbsoluteSystemPathBuf::try_from(tmp.path()).unwrap();
+        let paths = globwalk_with_settings(
+            &root,
+            &["dist/**".to_string()],
+            &[],
+            WalkType::Files,
+            Settings::default().respect_gitignore(respect_gitignore),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|path| root.anchor(path).unwrap().to_unix().unwrap().to_string())
+        .collect::<HashSet<_>>();
+        let expected = expected
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<HashSet<_>>();
+        assert_eq!(paths, expected);
+    }
 }
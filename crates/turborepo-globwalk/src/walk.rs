@@ -0,0 +1,271 @@
+//! A single parallel traversal that matches many globs at once.
+//!
+//! Each include glob is rooted at its invariant prefix. Roots that are nested
+//! inside of another root are folded into the outermost one, so that shared
+//! subtrees are only read from disk once. Directories are only descended into
+//! if at least one glob could match something beneath them and no exhaustive
+//! exclusion matches them.
+
+use std::{
+    collections::HashSet,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use turbopath::AbsoluteSystemPathBuf;
+use wax::{Any, DirectoryMatcher, Glob, Pattern};
+
+use crate::{any_with_contextual_error, Settings, WalkError, WalkType};
+
+pub(crate) struct Exclusions {
+    // excluded directories matching an exhaustive glob, e.g. `dist/**`, are
+    // never read from disk
+    exhaustive: Any<'static>,
+    nonexhaustive: Any<'static>,
+}
+
+impl Exclusions {
+    pub fn new(patterns: Vec<Glob<'static>>) -> Self {
+        let (exhaustive, nonexhaustive): (Vec<_>, Vec<_>) =
+            patterns.into_iter().partition(|glob| glob.is_exhaustive());
+        // Per docs, only fails if exclusion list is too large, since we're using
+        // pre-compiled globs
+        let any = |patterns: Vec<Glob<'static>>| {
+            let text = patterns
+                .iter()
+                .map(|glob| glob.to_string())
+                .collect::<Vec<_>>();
+            wax::any(patterns)
+                .unwrap_or_else(|e| panic!("Failed to compile exclusion globs: {:?}: {}", text, e))
+        };
+        Self {
+            exhaustive: any(exhaustive),
+            nonexhaustive: any(nonexhaustive),
+        }
+    }
+}
+
+enum Exclusion {
+    None,
+    Entry,
+    Tree,
+}
+
+impl Exclusions {
+    fn check(&self, path: &Path) -> Exclusion {
+        if self.exhaustive.is_match(path) {
+            Exclusion::Tree
+        } else if self.nonexhaustive.is_match(path) {
+            Exclusion::Entry
+        } else {
+            Exclusion::None
+        }
+    }
+}
+
+/// Walks the given globs in a single traversal, returning every path that
+/// matches any of them.
+///
+/// Like `Glob::walk`, a glob whose invariant prefix passes through a symlink
+/// below `base_path` matches nothing, and symlinks are never followed unless
+/// they are the root of the traversal.
+pub(crate) fn walk_globs(
+    base_path: &Path,
+    globs: Vec<Glob<'static>>,
+    exclusions: &Exclusions,
+    walk_type: WalkType,
+    settings: Settings,
+) -> Result<HashSet<AbsoluteSystemPathBuf>, WalkError> {
+    let mut roots = Vec::new();
+    let mut walked_globs = Vec::new();
+    for glob in globs {
+        let (prefix, _) = glob.clone().partition();
+        // Absolute prefixes replace the base path when joined
+        let root = base_path.join(prefix);
+        if crosses_symlink(base_path, &root) {
+            continue;
+        }
+        roots.push(root);
+        walked_globs.push(glob);
+    }
+    if walked_globs.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let roots = outermost_roots(roots);
+    let matchers = walked_globs
+        .iter()
+        .map(|glob| glob.directory_matcher())
+        .collect::<Vec<_>>();
+    let text = walked_globs.iter().map(|glob| glob.to_string()).collect();
+    let include = any_with_contextual_error(walked_globs, text)?;
+    let walker = Walker {
+        include,
+        matchers,
+        exclusions,
+        walk_type,
+        results: Mutex::new(HashSet::new()),
+        error: Mutex::new(None),
+    };
+
+    let mut roots = roots.into_iter();
+    let Some(first) = roots.next() else {
+        return Ok(HashSet::new());
+    };
+    let mut builder = WalkBuilder::new(first);
+    for root in roots {
+        builder.add(root);
+    }
+    builder
+        .standard_filters(false)
+        .follow_links(false)
+        .git_ignore(settings.respect_gitignore)
+        .parents(settings.respect_gitignore)
+        .require_git(false)
+        .build_parallel()
+        .run(|| Box::new(|entry| walker.visit(entry)));
+
+    walker.finish()
+}
+
+struct Walker<'a> {
+    include: Any<'static>,
+    matchers: Vec<DirectoryMatcher>,
+    exclusions: &'a Exclusions,
+    walk_type: WalkType,
+    results: Mutex<HashSet<AbsoluteSystemPathBuf>>,
+    error: Mutex<Option<WalkError>>,
+}
+
+impl<'a> Walker<'a> {
+    fn visit(&self, entry: Result<DirEntry, ignore::Error>) -> WalkState {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // errors reading a .gitignore file shouldn't fail the walk
+            Err(e) if e.is_partial() => {
+                tracing::warn!("failed to read ignore files: {}", e);
+                return WalkState::Continue;
+            }
+            Err(e) => {
+                let io_err = match e.into_io_error() {
+                    Some(io_err) => io_err,
+                    None => return WalkState::Continue,
+                };
+                return if self.record_io_error(io_err) {
+                    WalkState::Quit
+                } else {
+                    WalkState::Continue
+                };
+            }
+        };
+
+        // Roots of the walk are always followed, but a symlink is still matched
+        // as a file, same as when it is encountered anywhere else
+        let is_symlink = match entry.depth() {
+            0 => entry.path().is_symlink(),
+            _ => entry.path_is_symlink(),
+        };
+        let is_dir = !is_symlink && entry.file_type().map_or(false, |ty| ty.is_dir());
+        match self.visit_path(entry.path(), is_dir) {
+            Ok(true) => WalkState::Continue,
+            Ok(false) => WalkState::Skip,
+            Err(e) => {
+                self.record_error(e);
+                WalkState::Quit
+            }
+        }
+    }
+
+    /// Records `path` if it matches, returning whether or not anything beneath
+    /// it could match
+    fn visit_path(&self, path: &Path, is_dir: bool) -> Result<bool, WalkError> {
+        let emit = match self.exclusions.check(path) {
+            Exclusion::Tree => return Ok(false),
+            Exclusion::Entry => false,
+            Exclusion::None => self.include.is_match(path),
+        };
+        if emit && !(self.walk_type == WalkType::Files && is_dir) {
+            let path = AbsoluteSystemPathBuf::try_from(path)?;
+            self.results.lock().expect("lock poisoned").insert(path);
+        }
+        Ok(self
+            .matchers
+            .iter()
+            .any(|matcher| matcher.may_contain_matches(path)))
+    }
+
+    /// Returns true if the error should stop the walk
+    fn record_io_error(&self, error: io::Error) -> bool {
+        // If the file doesn't exist, it's not an error, there's just nothing to
+        // glob
+        if error.kind() == ErrorKind::NotFound {
+            return false;
+        }
+        self.record_error(error.into());
+        true
+    }
+
+    fn record_error(&self, error: WalkError) {
+        self.error
+            .lock()
+            .expect("lock poisoned")
+            .get_or_insert(error);
+    }
+
+    fn finish(self) -> Result<HashSet<AbsoluteSystemPathBuf>, WalkError> {
+        match self.error.into_inner().expect("lock poisoned") {
+            Some(error) => Err(error),
+            None => Ok(self.results.into_inner().expect("lock poisoned")),
+        }
+    }
+}
+
+/// Returns true if any component of `root` below `base_path` is a symlink
+fn crosses_symlink(base_path: &Path, root: &Path) -> bool {
+    let Ok(tail) = root.strip_prefix(base_path) else {
+        return false;
+    };
+    let mut candidate = base_path.to_path_buf();
+    tail.components().any(|component| {
+        candidate.push(component);
+        candidate.is_symlink()
+    })
+}
+
+/// Removes any roots that are contained in another root
+fn outermost_roots(mut roots: Vec<PathBuf>) -> Vec<PathBuf> {
+    // sorting guarantees that a root is visited after all of its ancestors
+    roots.sort();
+    roots.dedup();
+    let mut outermost: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        if !outermost.iter().any(|ancestor| root.starts_with(ancestor)) {
+            outermost.push(root);
+        }
+    }
+    outermost
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::outermost_roots;
+
+    #[test]
+    fn test_outermost_roots() {
+        let roots = ["/a/b/c", "/a/bc", "/a/b", "/d", "/a/b", "/d/e/f"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(
+            outermost_roots(roots),
+            ["/a/b", "/a/bc", "/d"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+    }
+}
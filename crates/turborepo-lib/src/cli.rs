@@ -11,7 +11,7 @@ use turbopath::AbsoluteSystemPathBuf;
 #[cfg(feature = "run-stub")]
 use crate::commands::run;
 use crate::{
-    commands::{bin, daemon, generate, info, link, login, logout, unlink, CommandBase},
+    commands::{bin, daemon, generate, info, link, login, logout, ls, unlink, CommandBase},
     get_version,
     shim::{RepoMode, RepoState},
     tracing::TurboSubscriber,
@@ -67,6 +67,44 @@ pub enum DryRunMode {
     Json,
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, ValueEnum)]
+pub enum CacheRestoreMode {
+    /// Always restore outputs on a cache hit
+    #[default]
+    #[serde(rename = "always")]
+    Always,
+    /// Don't restore outputs that are unchanged since turbo last wrote them
+    #[serde(rename = "skip-if-unchanged")]
+    SkipIfUnchanged,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, ValueEnum)]
+pub enum OtlpProtocol {
+    #[default]
+    #[serde(rename = "grpc")]
+    Grpc,
+    #[serde(rename = "http")]
+    Http,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, ValueEnum)]
+pub enum UIMode {
+    #[default]
+    #[serde(rename = "stream")]
+    Stream,
+    #[serde(rename = "tui")]
+    Tui,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "json")]
+    Json,
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, ValueEnum)]
 pub enum EnvMode {
     #[default]
@@ -188,6 +226,10 @@ pub enum DaemonCommand {
         /// Pass --json to report status in JSON format
         #[clap(long)]
         json: bool,
+        /// Remove the daemon lock file if the process that created it is no
+        /// longer running
+        #[clap(long)]
+        force: bool,
     },
     /// Stops the turbo daemon
     Stop,
@@ -323,6 +365,22 @@ pub enum Command {
     },
     /// Logout to your Vercel account
     Logout {},
+    /// List the workspaces that match the given filters and why each of
+    /// them matched
+    Ls {
+        /// Use the given selector to specify package(s) to list. The
+        /// syntax is the same as `turbo run --filter`
+        #[clap(short = 'F', long, action = ArgAction::Append)]
+        filter: Vec<String>,
+        /// List the packages that changed since a mergebase, along with
+        /// their dependents
+        #[clap(long)]
+        since: Option<String>,
+        /// Set the output format. Use "json" for machine readable output.
+        /// (default text)
+        #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
     /// Prepare a subset of your monorepo.
     Prune {
         #[clap(long)]
@@ -415,6 +473,11 @@ pub struct RunArgs {
     /// Override the filesystem cache directory.
     #[clap(long)]
     pub cache_dir: Option<String>,
+    /// Set when outputs are restored on a cache hit. Use "skip-if-unchanged"
+    /// to leave outputs alone if the daemon has seen no changes to them
+    /// since they were last written. (default always)
+    #[clap(long, value_enum, default_value_t = CacheRestoreMode::Always)]
+    pub cache_restore: CacheRestoreMode,
     /// Set the number of concurrent cache operations (default 10)
     #[clap(long, default_value_t = 10)]
     pub cache_workers: u32,
@@ -481,6 +544,14 @@ pub struct RunArgs {
     /// output. (default full)
     #[clap(long, value_enum)]
     pub output_logs: Option<OutputLogsMode>,
+    /// Export a trace of the run to an OpenTelemetry collector at the given
+    /// OTLP endpoint, e.g. http://localhost:4317
+    #[clap(long, env = "TURBO_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+    /// Set the protocol used to export traces to the OTLP endpoint.
+    /// (default grpc)
+    #[clap(long, value_enum, default_value_t = OtlpProtocol::Grpc)]
+    pub otlp_protocol: OtlpProtocol,
 
     /// Set type of task output order. Use "stream" to show
     /// output as soon as it is available. Use "grouped" to
@@ -517,6 +588,11 @@ pub struct RunArgs {
     /// Generate a summary of the turbo run
     #[clap(long, env = "TURBO_RUN_SUMMARY", default_missing_value = "true")]
     pub summarize: Option<Option<bool>>,
+    /// Set how task progress is shown. Use "tui" for a full screen view of
+    /// each task's status and output, which falls back to "stream" when
+    /// not in an interactive terminal or in CI. (default stream)
+    #[clap(long, env = "TURBO_UI", value_enum, default_value_t = UIMode::Stream)]
+    pub ui: UIMode,
 
     /// Use "none" to remove prefixes from task logs. Use "task" to get task id
     /// prefixing. Use "auto" to let turbo decide how to prefix the logs
@@ -556,6 +632,34 @@ impl Default for LogPrefix {
     }
 }
 
+/// The directory turbo was invoked from relative to the repo root, which
+/// packages are inferred from. Overriding the cwd turns inference off.
+fn pkg_inference_root(cli_args: &Args, repo_state: Option<&RepoState>) -> Result<Option<String>> {
+    if cli_args.cwd.is_some() {
+        return Ok(None);
+    }
+    let Ok(invocation_dir) = env::var(INVOCATION_DIR_ENV_VAR) else {
+        debug!("{} not set", INVOCATION_DIR_ENV_VAR);
+        return Ok(None);
+    };
+    let invocation_path = Path::new(&invocation_dir);
+
+    // If repo state doesn't exist, we're either local turbo running at the root
+    // (cwd), or inference failed.
+    // If repo state does exist, we're global turbo, and want to calculate
+    // package inference based on the repo root
+    let this_dir = AbsoluteSystemPathBuf::cwd()?;
+    let repo_root = repo_state.map_or(&this_dir, |r| &r.root);
+    let Ok(relative_path) = invocation_path.strip_prefix(repo_root) else {
+        return Ok(None);
+    };
+    debug!("pkg_inference_root set to \"{}\"", relative_path.display());
+    let utf8_path = relative_path
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid utf8 path: {:?}", relative_path))?;
+    Ok(Some(utf8_path.to_owned()))
+}
+
 /// Runs the CLI by parsing arguments with clap, then either calling Rust code
 /// directly or returning a payload for the Go code to use.
 ///
@@ -596,6 +700,10 @@ pub async fn run(
         Command::Run(Box::new(run_args))
     };
 
+    // If we know the actual invocation path, packages can be inferred from it,
+    // as long as the user hasn't overridden the cwd
+    let pkg_inference_root = pkg_inference_root(&cli_args, repo_state.as_ref())?;
+
     // Set some run flags if we have the data and are executing a Run
     if let Command::Run(run_args) = &mut command {
         // Don't overwrite the flag if it's already been set for whatever reason
@@ -604,28 +712,8 @@ pub async fn run(
                 .as_ref()
                 .map(|repo_state| matches!(repo_state.mode, RepoMode::SinglePackage))
                 .unwrap_or(false);
-        // If this is a run command, and we know the actual invocation path, set the
-        // inference root, as long as the user hasn't overridden the cwd
-        if cli_args.cwd.is_none() {
-            if let Ok(invocation_dir) = env::var(INVOCATION_DIR_ENV_VAR) {
-                let invocation_path = Path::new(&invocation_dir);
-
-                // If repo state doesn't exist, we're either local turbo running at the root
-                // (cwd), or inference failed.
-                // If repo state does exist, we're global turbo, and want to calculate
-                // package inference based on the repo root
-                let this_dir = AbsoluteSystemPathBuf::cwd()?;
-                let repo_root = repo_state.as_ref().map_or(&this_dir, |r| &r.root);
-                if let Ok(relative_path) = invocation_path.strip_prefix(repo_root) {
-                    debug!("pkg_inference_root set to \"{}\"", relative_path.display());
-                    let utf8_path = relative_path
-                        .to_str()
-                        .ok_or_else(|| anyhow!("invalid utf8 path: {:?}", relative_path))?;
-                    run_args.pkg_inference_root = Some(utf8_path.to_owned());
-                }
-            } else {
-                debug!("{} not set", INVOCATION_DIR_ENV_VAR);
-            }
+        if pkg_inference_root.is_some() {
+            run_args.pkg_inference_root = pkg_inference_root.clone();
         }
     }
 
@@ -685,7 +773,7 @@ pub async fn run(
                 args: args.clone(),
             };
 
-            generate::run(tag, command, &args)?;
+            generate::run(tag, command, &args, &repo_root, ui)?;
             Ok(Payload::Rust(Ok(0)))
         }
         Command::Info { workspace } => {
@@ -720,6 +808,19 @@ pub async fn run(
 
             Ok(Payload::Rust(Ok(0)))
         }
+        Command::Ls {
+            filter,
+            since,
+            output,
+        } => {
+            let filter = filter.clone();
+            let since = since.clone();
+            let output = *output;
+            let base = CommandBase::new(cli_args, repo_root, version, ui)?;
+            ls::run(&base, filter, since, pkg_inference_root.as_deref(), output)?;
+
+            Ok(Payload::Rust(Ok(0)))
+        }
         Command::Login { sso_team } => {
             if cli_args.test_run {
                 println!("Login test run successful");
@@ -837,7 +938,8 @@ mod test {
     use anyhow::Result;
 
     use crate::cli::{
-        Args, Command, DryRunMode, EnvMode, LogOrder, LogPrefix, OutputLogsMode, RunArgs, Verbosity,
+        Args, CacheRestoreMode, Command, DryRunMode, EnvMode, LogOrder, LogPrefix, OtlpProtocol,
+        OutputFormat, OutputLogsMode, RunArgs, UIMode, Verbosity,
     };
 
     #[test]
@@ -995,6 +1097,59 @@ mod test {
             }
         );
 
+        assert_eq!(
+            Args::try_parse_from([
+                "turbo",
+                "run",
+                "build",
+                "--cache-restore",
+                "skip-if-unchanged"
+            ])
+            .unwrap(),
+            Args {
+                command: Some(Command::Run(Box::new(RunArgs {
+                    tasks: vec!["build".to_string()],
+                    cache_restore: CacheRestoreMode::SkipIfUnchanged,
+                    ..get_default_run_args()
+                }))),
+                ..Args::default()
+            }
+        );
+
+        assert_eq!(
+            Args::try_parse_from([
+                "turbo",
+                "run",
+                "build",
+                "--otlp-endpoint",
+                "http://localhost:4318",
+                "--otlp-protocol",
+                "http"
+            ])
+            .unwrap(),
+            Args {
+                command: Some(Command::Run(Box::new(RunArgs {
+                    tasks: vec!["build".to_string()],
+                    otlp_endpoint: Some("http://localhost:4318".to_string()),
+                    otlp_protocol: OtlpProtocol::Http,
+                    ..get_default_run_args()
+                }))),
+                ..Args::default()
+            }
+        );
+
+        assert_eq!(
+            Args::try_parse_from(["turbo", "run", "build", "--ui", "tui"]).unwrap(),
+            Args {
+                command: Some(Command::Run(Box::new(RunArgs {
+                    tasks: vec!["build".to_string()],
+                    ui: UIMode::Tui,
+                    ..get_default_run_args()
+                }))),
+                ..Args::default()
+            }
+        );
+
         assert_eq!(
             Args::try_parse_from(["turbo", "run", "build", "--cache-workers", "100"]).unwrap(),
             Args {
@@ -1586,6 +1741,40 @@ mod test {
         .test();
     }
 
+    #[test]
+    fn test_parse_ls() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "ls"]).unwrap(),
+            Args {
+                command: Some(Command::Ls {
+                    filter: Vec::new(),
+                    since: None,
+                    output: OutputFormat::Text,
+                }),
+                ..Args::default()
+            }
+        );
+
+        CommandTestCase {
+            command: "ls",
+            command_args: vec![
+                vec!["--filter", "web...", "-F", "docs"],
+                vec!["--since", "main"],
+                vec!["--output", "json"],
+            ],
+            global_args: vec![],
+            expected_output: Args {
+                command: Some(Command::Ls {
+                    filter: vec!["web...".to_string(), "docs".to_string()],
+                    since: Some("main".to_string()),
+                    output: OutputFormat::Json,
+                }),
+                ..Args::default()
+            },
+        }
+        .test();
+    }
+
     #[test]
     fn test_parse_unlink() {
         assert_eq!(
@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use camino::Utf8PathBuf;
-use pidlock::PidlockError::AlreadyOwned;
+use pidlock::{LockFileState, LockOwner, Pidlock, PidlockError::AlreadyOwned};
 use time::{format_description, OffsetDateTime};
 use tracing::{trace, warn};
 use turbopath::AbsoluteSystemPathBuf;
@@ -9,7 +9,9 @@ use turbopath::AbsoluteSystemPathBuf;
 use super::CommandBase;
 use crate::{
     cli::DaemonCommand,
-    daemon::{endpoint::SocketOpenError, CloseReason, DaemonConnector, DaemonError},
+    daemon::{
+        endpoint::SocketOpenError, CloseReason, DaemonConnector, DaemonConnectorError, DaemonError,
+    },
     tracing::TurboSubscriber,
 };
 
@@ -44,8 +46,38 @@ pub async fn daemon_client(command: &DaemonCommand, base: &CommandBase) -> Resul
             let client = connector.connect().await?;
             client.stop().await?;
         }
-        DaemonCommand::Status { json } => {
-            let mut client = connector.connect().await?;
+        DaemonCommand::Status { json, force } => {
+            let lock = Pidlock::new(pid_file.as_std_path().to_owned());
+            let lock_status = if *force {
+                let mut status = DaemonLockStatus::from(lock.clear_stale()?);
+                if status.is_removable() {
+                    // without a live owner the socket file is stale as well
+                    std::fs::remove_file(&sock_file).ok();
+                    status.removed = true;
+                }
+                status
+            } else {
+                DaemonLockStatus::from(lock.file_state())
+            };
+
+            let mut client = match connector.connect().await {
+                Ok(client) => client,
+                Err(DaemonConnectorError::NotRunning) => {
+                    if *json {
+                        let status = DaemonNotRunningStatus {
+                            running: false,
+                            pid_file,
+                            sock_file,
+                            lock: lock_status,
+                        };
+                        println!("{}", serde_json::to_string_pretty(&status)?);
+                    } else {
+                        print_lock_status(&lock_status, &pid_file);
+                    }
+                    return Err(DaemonConnectorError::NotRunning.into());
+                }
+                Err(e) => return Err(e.into()),
+            };
             let status = client.status().await?;
             let log_file = log_filename(&status.log_file)?;
             let status = DaemonStatus {
@@ -53,6 +85,7 @@ pub async fn daemon_client(command: &DaemonCommand, base: &CommandBase) -> Resul
                 log_file: log_file.into(),
                 pid_file: client.pid_file().to_owned(),
                 sock_file: client.sock_file().to_owned(),
+                lock: lock_status,
             };
             if *json {
                 println!("{}", serde_json::to_string_pretty(&status)?);
@@ -64,6 +97,9 @@ pub async fn daemon_client(command: &DaemonCommand, base: &CommandBase) -> Resul
                 );
                 println!("Daemon pid file: {}", status.pid_file);
                 println!("Daemon socket file: {}", status.sock_file);
+                if let Some(owner) = &status.lock.owner {
+                    print_lock_owner(owner);
+                }
             }
         }
         DaemonCommand::Clean => {
@@ -118,6 +154,45 @@ pub async fn daemon_client(command: &DaemonCommand, base: &CommandBase) -> Resul
     Ok(())
 }
 
+fn print_lock_owner(owner: &DaemonLockOwner) {
+    println!("Daemon pid: {}", owner.pid);
+    if let Some(version) = &owner.version {
+        println!("Daemon version: {}", version);
+    }
+    if let Some(started) = owner.start_time.and_then(format_start_time) {
+        println!("Daemon started: {}", started);
+    }
+}
+
+fn print_lock_status(lock: &DaemonLockStatus, pid_file: &AbsoluteSystemPathBuf) {
+    println!("Daemon is not running");
+    match lock.state {
+        DaemonLockState::Unlocked | DaemonLockState::Owned => {}
+        DaemonLockState::Stale | DaemonLockState::Corrupted => {
+            match lock.state {
+                DaemonLockState::Stale => println!("Stale daemon pid file: {}", pid_file),
+                _ => println!("Invalid daemon pid file: {}", pid_file),
+            }
+            if let Some(owner) = &lock.owner {
+                print_lock_owner(owner);
+            }
+            if lock.removed {
+                println!("Removed daemon pid file");
+            } else {
+                println!("Run `turbo daemon status --force` to remove it");
+            }
+        }
+    }
+}
+
+fn format_start_time(start_time: u64) -> Option<String> {
+    let start_time =
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(start_time) * 1_000_000).ok()?;
+    let format =
+        format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second] UTC").ok()?;
+    start_time.format(&format).ok()
+}
+
 // log_filename matches the algorithm used by tracing_appender::Rotation::DAILY
 // to generate the log filename. This is kind of a hack, but there didn't appear
 // to be a simple way to grab the generated filename.
@@ -191,4 +266,78 @@ pub struct DaemonStatus {
     pub log_file: Utf8PathBuf,
     pub pid_file: turbopath::AbsoluteSystemPathBuf,
     pub sock_file: turbopath::AbsoluteSystemPathBuf,
+    pub lock: DaemonLockStatus,
+}
+
+#[derive(serde::Serialize)]
+pub struct DaemonNotRunningStatus {
+    pub running: bool,
+    pub pid_file: turbopath::AbsoluteSystemPathBuf,
+    pub sock_file: turbopath::AbsoluteSystemPathBuf,
+    pub lock: DaemonLockStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DaemonLockState {
+    Unlocked,
+    Owned,
+    Stale,
+    Corrupted,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DaemonLockStatus {
+    pub state: DaemonLockState,
+    pub owner: Option<DaemonLockOwner>,
+    /// Whether the lock file was removed by `--force`
+    pub removed: bool,
+}
+
+impl DaemonLockStatus {
+    // Only locks that aren't held by a running process are safe to remove
+    fn is_removable(&self) -> bool {
+        matches!(
+            self.state,
+            DaemonLockState::Stale | DaemonLockState::Corrupted
+        )
+    }
+}
+
+impl From<LockFileState> for DaemonLockStatus {
+    fn from(state: LockFileState) -> Self {
+        let (state, owner) = match state {
+            LockFileState::Unlocked => (DaemonLockState::Unlocked, None),
+            LockFileState::Owned(owner) => (DaemonLockState::Owned, Some(owner.into())),
+            LockFileState::Stale(owner) => (DaemonLockState::Stale, Some(owner.into())),
+            LockFileState::Corrupted => (DaemonLockState::Corrupted, None),
+        };
+        Self {
+            state,
+            owner,
+            removed: false,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DaemonLockOwner {
+    pub pid: u32,
+    /// Milliseconds since the Unix epoch
+    pub start_time: Option<u64>,
+    pub version: Option<String>,
+    pub socket: Option<Utf8PathBuf>,
+}
+
+impl From<LockOwner> for DaemonLockOwner {
+    fn from(owner: LockOwner) -> Self {
+        Self {
+            pid: owner.pid,
+            start_time: owner.start_time,
+            version: owner.version,
+            socket: owner
+                .socket
+                .and_then(|socket| Utf8PathBuf::from_path_buf(socket).ok()),
+        }
+    }
 }
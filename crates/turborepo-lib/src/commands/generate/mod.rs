@@ -2,13 +2,17 @@ use std::process::{Command, Stdio};
 
 use anyhow::{Context, Result};
 use tracing::debug;
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf};
 use which::which;
 
 use crate::{
     child::spawn_child,
     cli::{GenerateCommand, GeneratorCustomArgs},
+    ui::UI,
 };
 
+mod workspace;
+
 fn call_turbo_gen(command: &str, tag: &String, raw_args: &str) -> Result<i32> {
     debug!(
         "Running @turbo/gen@{} with command `{}` and args {:?}",
@@ -33,11 +37,24 @@ pub fn run(
     tag: &String,
     command: &Option<Box<GenerateCommand>>,
     args: &GeneratorCustomArgs,
+    repo_root: &AbsoluteSystemPath,
+    ui: UI,
 ) -> Result<()> {
     // check if a subcommand was passed
     if let Some(box GenerateCommand::Workspace(workspace_args)) = command {
-        let raw_args = serde_json::to_string(&workspace_args)?;
-        call_turbo_gen("workspace", tag, &raw_args)?;
+        // Local workspaces can be generated without Node, anything that requires
+        // prompting or fetching a remote example is still handled by @turbo/gen
+        if workspace::can_generate(workspace_args) {
+            let root = workspace_args
+                .root
+                .as_deref()
+                .map(AbsoluteSystemPathBuf::from_cwd)
+                .transpose()?;
+            workspace::generate(root.as_deref().unwrap_or(repo_root), workspace_args, ui)?;
+        } else {
+            let raw_args = serde_json::to_string(&workspace_args)?;
+            call_turbo_gen("workspace", tag, &raw_args)?;
+        }
     } else {
         // if no subcommand was passed, run the generate command as default
         let raw_args = serde_json::to_string(&args)?;
@@ -0,0 +1,794 @@
+use std::{fs, io};
+
+use anyhow::{anyhow, bail, Context, Result};
+use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect};
+use indexmap::IndexMap;
+use serde_json::Value;
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+
+use crate::{
+    cli::GenerateWorkspaceArgs,
+    package_json::PackageJson,
+    package_manager::{PackageManager, WorkspaceGlobs},
+    ui::{BOLD, UI},
+};
+
+const DEPENDENCY_GROUPS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WorkspaceType {
+    App,
+    Package,
+}
+
+impl WorkspaceType {
+    fn parse(raw: Option<&str>) -> Option<Self> {
+        match raw? {
+            "app" => Some(Self::App),
+            "package" => Some(Self::Package),
+            _ => None,
+        }
+    }
+}
+
+// A workspace that already exists in the repository
+#[derive(Debug)]
+struct Workspace {
+    name: String,
+    path: AnchoredSystemPathBuf,
+}
+
+impl Workspace {
+    // The first directory of a workspace path e.g. `apps` for `apps/web`
+    fn group(&self) -> &str {
+        self.path
+            .as_str()
+            .split(std::path::MAIN_SEPARATOR)
+            .next()
+            .unwrap_or_default()
+    }
+}
+
+/// Returns true if the workspace can be generated without prompting for
+/// anything but dependencies, or fetching a remote example.
+pub fn can_generate(args: &GenerateWorkspaceArgs) -> bool {
+    let has_local_source = match args.copy.as_deref() {
+        None => true,
+        // `--copy` without a source requires prompting for one
+        Some("") => false,
+        Some(source) => !is_remote_source(source),
+    };
+    args.name.is_some()
+        && WorkspaceType::parse(args.r#type.as_deref()).is_some()
+        && has_local_source
+        // Example paths only apply to remote examples
+        && args.example_path.is_none()
+}
+
+fn is_remote_source(source: &str) -> bool {
+    ["https://", "http://", "github.com/"]
+        .iter()
+        .any(|prefix| source.starts_with(prefix))
+}
+
+pub fn generate(
+    repo_root: &AbsoluteSystemPath,
+    args: &GenerateWorkspaceArgs,
+    ui: UI,
+) -> Result<()> {
+    let name = args
+        .name
+        .as_deref()
+        .ok_or_else(|| anyhow!("a name is required to generate a workspace"))?;
+    let workspace_type = WorkspaceType::parse(args.r#type.as_deref())
+        .ok_or_else(|| anyhow!("workspace type must be either \"app\" or \"package\""))?;
+    validate_name(name)?;
+
+    let root_package_json = PackageJson::load(&repo_root.join_component("package.json"))?;
+    let package_manager = PackageManager::get_package_manager(repo_root, Some(&root_package_json))?;
+    let globs = package_manager.get_workspace_globs(repo_root)?;
+    let workspaces = find_workspaces(repo_root, &package_manager)?;
+
+    if workspaces.iter().any(|workspace| workspace.name == name) {
+        bail!("a workspace named \"{name}\" already exists");
+    }
+
+    let location = match &args.destination {
+        Some(destination) => AbsoluteSystemPathBuf::from_unknown(repo_root, destination.as_str()),
+        None => default_location(repo_root, &globs, &workspaces, workspace_type, name)?,
+    };
+    if location.exists() && fs::read_dir(&location)?.next().is_some() {
+        bail!("{location} already exists and is not empty");
+    }
+
+    match args.copy.as_deref() {
+        Some(source) => {
+            let source = workspaces
+                .iter()
+                .find(|workspace| workspace.name == source)
+                .ok_or_else(|| anyhow!("workspace \"{source}\" not found"))?;
+            copy_workspace(&repo_root.resolve(&source.path), &location)?;
+        }
+        None => create_empty_workspace(&location, name)?,
+    }
+
+    let package_json_path = location.join_component("package.json");
+    // Keeps the order of the top level fields of the source package.json
+    let mut package_json: IndexMap<String, Value> = serde_json::from_str(
+        &fs::read_to_string(&package_json_path)
+            .with_context(|| format!("new workspace is missing {package_json_path}"))?,
+    )
+    .context("package.json must contain an object")?;
+    update_package_json(
+        &mut package_json,
+        name,
+        &workspaces,
+        &package_manager,
+        args.show_all_dependencies,
+    );
+    // Like @turbo/gen, ask which workspaces to add as dependencies
+    if ui.is_interactive() {
+        let candidates = dependency_candidates(&workspaces, args.show_all_dependencies);
+        let selection = prompt_dependencies(name, &candidates, &package_json)?;
+        add_dependencies(&mut package_json, selection, &package_manager);
+    }
+    fs::write(
+        &package_json_path,
+        serde_json::to_string_pretty(&package_json)? + "\n",
+    )?;
+
+    if !globs.target_is_workspace(repo_root, &location)? {
+        add_workspace_glob(repo_root, &package_manager, &repo_root.anchor(&location)?)?;
+    }
+
+    println!(
+        "\n{} Created {name} at \"{}\"",
+        ui.apply(BOLD.apply_to(">>> Success!")),
+        repo_root.anchor(&location)?
+    );
+
+    Ok(())
+}
+
+// Mirrors the subset of npm's package name rules that apply to new packages
+fn validate_name(name: &str) -> Result<()> {
+    let unscoped = match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, rest)) if !scope.is_empty() => rest,
+            _ => bail!("invalid workspace name \"{name}\""),
+        },
+        None => name,
+    };
+    let is_valid = !unscoped.is_empty()
+        && name.len() <= 214
+        && !unscoped.starts_with(['.', '_'])
+        && !unscoped.contains('/')
+        && name == name.to_lowercase()
+        && name.trim() == name
+        && !name.chars().any(|c| {
+            c.is_whitespace() || matches!(c, '~' | '\'' | '!' | '(' | ')' | '*' | '"' | '\\')
+        });
+    if !is_valid {
+        bail!("invalid workspace name \"{name}\"");
+    }
+    Ok(())
+}
+
+fn find_workspaces(
+    repo_root: &AbsoluteSystemPath,
+    package_manager: &PackageManager,
+) -> Result<Vec<Workspace>> {
+    let mut workspaces = Vec::new();
+    for package_json_path in package_manager.get_package_jsons(repo_root)? {
+        let package_json = PackageJson::load(&package_json_path)?;
+        let (Some(name), Some(path)) = (package_json.name, package_json_path.parent()) else {
+            continue;
+        };
+        workspaces.push(Workspace {
+            name,
+            path: repo_root.anchor(path)?,
+        });
+    }
+    workspaces.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(workspaces)
+}
+
+// This follows the same naive approach as @turbo/gen: the first segment of
+// each workspace glob is considered a possible location for new workspaces.
+fn workspace_roots<'a>(globs: &'a WorkspaceGlobs, workspaces: &[Workspace]) -> Vec<&'a str> {
+    let mut roots = Vec::new();
+    for inclusion in globs.raw_inclusions() {
+        let is_workspace_path = workspaces.iter().any(|workspace| {
+            workspace
+                .path
+                .to_unix()
+                .map_or(false, |path| path.as_str() == inclusion)
+        });
+        if is_workspace_path {
+            continue;
+        }
+        let root = inclusion.split('/').next().unwrap_or(inclusion);
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+fn default_location(
+    repo_root: &AbsoluteSystemPath,
+    globs: &WorkspaceGlobs,
+    workspaces: &[Workspace],
+    workspace_type: WorkspaceType,
+    name: &str,
+) -> Result<AbsoluteSystemPathBuf> {
+    // Scoped packages only use the package name for their directory
+    let name_as_path = name.split_once('/').map_or(name, |(_, name)| name);
+    let roots = workspace_roots(globs, workspaces);
+    let root = match workspace_type {
+        WorkspaceType::App => "apps",
+        WorkspaceType::Package => "packages",
+    };
+    if !roots.contains(&root) {
+        bail!("unable to determine where to create \"{name}\", please provide a --destination");
+    }
+    Ok(repo_root.join_components(&[root, name_as_path]))
+}
+
+fn copy_workspace(source: &AbsoluteSystemPath, destination: &AbsoluteSystemPath) -> Result<()> {
+    fn copy_dir(source: &std::path::Path, destination: &std::path::Path) -> io::Result<()> {
+        fs::create_dir_all(destination)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            if entry.file_name() == "node_modules" {
+                continue;
+            }
+            let target = destination.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                copy_dir(&entry.path(), &target)?;
+            } else if file_type.is_symlink() {
+                copy_symlink(&entry.path(), &target)?;
+            } else {
+                fs::copy(entry.path(), target)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn copy_symlink(source: &std::path::Path, destination: &std::path::Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(fs::read_link(source)?, destination)
+    }
+
+    #[cfg(windows)]
+    fn copy_symlink(source: &std::path::Path, destination: &std::path::Path) -> io::Result<()> {
+        fs::copy(source, destination).map(|_| ())
+    }
+
+    copy_dir(source.as_std_path(), destination.as_std_path())
+        .with_context(|| format!("unable to copy {source} to {destination}"))
+}
+
+fn create_empty_workspace(location: &AbsoluteSystemPath, name: &str) -> Result<()> {
+    location.create_dir_all()?;
+    let package_json = serde_json::json!({
+        "name": name,
+        "version": "0.0.0",
+        "private": true,
+        "scripts": {
+            "build": "turbo build"
+        }
+    });
+    fs::write(
+        location.join_component("package.json"),
+        serde_json::to_string_pretty(&package_json)?,
+    )?;
+    fs::write(location.join_component("README.md"), format!("# `{name}`"))?;
+    Ok(())
+}
+
+fn internal_version(package_manager: &PackageManager) -> &'static str {
+    match package_manager {
+        PackageManager::Pnpm | PackageManager::Pnpm6 => "workspace:*",
+        PackageManager::Berry | PackageManager::Npm | PackageManager::Yarn => "*",
+    }
+}
+
+fn update_package_json(
+    package_json: &mut IndexMap<String, Value>,
+    name: &str,
+    workspaces: &[Workspace],
+    package_manager: &PackageManager,
+    show_all_dependencies: bool,
+) {
+    package_json.insert("name".to_string(), Value::String(name.to_string()));
+
+    let internal_version = internal_version(package_manager);
+
+    for group in DEPENDENCY_GROUPS {
+        let Some(Value::Object(dependencies)) = package_json.get_mut(group) else {
+            continue;
+        };
+        dependencies.retain(|dependency, version| {
+            let Some(workspace) = workspaces
+                .iter()
+                .find(|workspace| &workspace.name == dependency)
+            else {
+                return true;
+            };
+            // Apps can never be dependencies of other workspaces
+            if workspace.group() == "apps" && !show_all_dependencies {
+                return false;
+            }
+            *version = Value::String(internal_version.to_string());
+            true
+        });
+        if dependencies.is_empty() {
+            package_json.shift_remove(group);
+        }
+    }
+}
+
+// Apps can never be dependencies of other workspaces
+fn dependency_candidates(workspaces: &[Workspace], show_all_dependencies: bool) -> Vec<&str> {
+    workspaces
+        .iter()
+        .filter(|workspace| show_all_dependencies || workspace.group() != "apps")
+        .map(|workspace| workspace.name.as_str())
+        .collect()
+}
+
+// Mirrors the dependency prompts of @turbo/gen. Dependencies the new
+// workspace already has are selected by default.
+fn prompt_dependencies(
+    name: &str,
+    candidates: &[&str],
+    package_json: &IndexMap<String, Value>,
+) -> Result<Vec<(&'static str, Vec<String>)>> {
+    let theme = ColorfulTheme::default();
+    let add_dependencies = Confirm::with_theme(&theme)
+        .with_prompt(format!("Add workspace dependencies to \"{name}\"?"))
+        .interact()?;
+    if !add_dependencies {
+        return Ok(Vec::new());
+    }
+
+    let groups = MultiSelect::with_theme(&theme)
+        .with_prompt(format!(
+            "Select all dependencies types to modify for \"{name}\""
+        ))
+        .items(&DEPENDENCY_GROUPS)
+        .interact()?;
+    let mut selection = Vec::new();
+    for group in groups.into_iter().map(|index| DEPENDENCY_GROUPS[index]) {
+        let existing = package_json.get(group).and_then(Value::as_object);
+        let defaults = candidates
+            .iter()
+            .map(|candidate| existing.map_or(false, |existing| existing.contains_key(*candidate)))
+            .collect::<Vec<_>>();
+        let selected = MultiSelect::with_theme(&theme)
+            .with_prompt(format!(
+                "Which packages should be added as {group} to \"{name}\"?"
+            ))
+            .items(candidates)
+            .defaults(&defaults)
+            .interact()?;
+        selection.push((
+            group,
+            selected
+                .into_iter()
+                .map(|index| candidates[index].to_string())
+                .collect(),
+        ));
+    }
+    Ok(selection)
+}
+
+fn add_dependencies(
+    package_json: &mut IndexMap<String, Value>,
+    selection: Vec<(&str, Vec<String>)>,
+    package_manager: &PackageManager,
+) {
+    for (group, dependencies) in selection {
+        if dependencies.is_empty() {
+            continue;
+        }
+        let Value::Object(existing) = package_json
+            .entry(group.to_string())
+            .or_insert_with(|| Value::Object(Default::default()))
+        else {
+            continue;
+        };
+        for dependency in dependencies {
+            existing
+                .entry(dependency)
+                .or_insert_with(|| Value::String(internal_version(package_manager).to_string()));
+        }
+    }
+}
+
+fn add_workspace_glob(
+    repo_root: &AbsoluteSystemPath,
+    package_manager: &PackageManager,
+    location: &AnchoredSystemPathBuf,
+) -> Result<()> {
+    let location = location.to_unix()?;
+    let glob = match location.as_str().rsplit_once('/') {
+        Some((parent, _)) => format!("{parent}/*"),
+        None => location.as_str().to_string(),
+    };
+
+    match package_manager {
+        PackageManager::Pnpm | PackageManager::Pnpm6 => {
+            let path = repo_root.join_component("pnpm-workspace.yaml");
+            let mut workspace: serde_yaml::Value =
+                serde_yaml::from_str(&fs::read_to_string(&path)?)?;
+            let packages = workspace
+                .get_mut("packages")
+                .and_then(|packages| packages.as_sequence_mut())
+                .ok_or_else(|| anyhow!("pnpm-workspace.yaml is missing packages"))?;
+            packages.push(serde_yaml::Value::String(glob));
+            fs::write(&path, serde_yaml::to_string(&workspace)?)?;
+        }
+        PackageManager::Berry | PackageManager::Npm | PackageManager::Yarn => {
+            let path = repo_root.join_component("package.json");
+            let mut package_json: IndexMap<String, Value> =
+                serde_json::from_str(&fs::read_to_string(&path)?)?;
+            let workspaces = match package_json.get_mut("workspaces") {
+                Some(Value::Object(nested)) => nested.get_mut("packages"),
+                workspaces => workspaces,
+            }
+            .and_then(|workspaces| workspaces.as_array_mut())
+            .ok_or_else(|| anyhow!("package.json is missing workspaces"))?;
+            workspaces.push(Value::String(glob));
+            fs::write(&path, serde_json::to_string_pretty(&package_json)? + "\n")?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::*;
+
+    fn setup_repo(package_manager: &str) -> (TempDir, AbsoluteSystemPathBuf) {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::try_from(tmp.path()).unwrap();
+        let write_json = |path: &[&str], value: Value| {
+            let path = repo_root.join_components(path);
+            path.parent().unwrap().create_dir_all().unwrap();
+            fs::write(&path, serde_json::to_string_pretty(&value).unwrap()).unwrap();
+        };
+        write_json(
+            &["package.json"],
+            json!({
+                "name": "monorepo",
+                "packageManager": package_manager,
+                "workspaces": ["apps/*", "packages/*"]
+            }),
+        );
+        // written verbatim so the source field order isn't sorted by `Value`
+        let web_package_json = repo_root.join_components(&["apps", "web", "package.json"]);
+        web_package_json.parent().unwrap().create_dir_all().unwrap();
+        fs::write(
+            &web_package_json,
+            r#"{
+  "name": "web",
+  "version": "1.0.0",
+  "scripts": { "dev": "next dev" },
+  "dependencies": { "ui": "^0.1.0", "docs": "*", "react": "^18.2.0" },
+  "devDependencies": { "tsconfig": "workspace:^" }
+}"#,
+        )
+        .unwrap();
+        write_json(&["apps", "docs", "package.json"], json!({ "name": "docs" }));
+        write_json(&["packages", "ui", "package.json"], json!({ "name": "ui" }));
+        write_json(
+            &["packages", "tsconfig", "package.json"],
+            json!({ "name": "tsconfig" }),
+        );
+        let web = repo_root.join_components(&["apps", "web"]);
+        fs::write(web.join_component("index.js"), "console.log('web');").unwrap();
+        web.join_components(&["node_modules", "react"])
+            .create_dir_all()
+            .unwrap();
+        fs::write(
+            pnpm_workspace_path(&repo_root),
+            "packages:\n  - apps/*\n  - packages/*\n",
+        )
+        .unwrap();
+        (tmp, repo_root)
+    }
+
+    fn pnpm_workspace_path(repo_root: &AbsoluteSystemPath) -> AbsoluteSystemPathBuf {
+        repo_root.join_component("pnpm-workspace.yaml")
+    }
+
+    fn args(name: &str, r#type: &str) -> GenerateWorkspaceArgs {
+        GenerateWorkspaceArgs {
+            name: Some(name.to_string()),
+            r#type: Some(r#type.to_string()),
+            empty: true,
+            ..Default::default()
+        }
+    }
+
+    fn read_json(path: &AbsoluteSystemPath) -> Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_can_generate() {
+        assert!(can_generate(&args("foo", "app")));
+        assert!(!can_generate(&GenerateWorkspaceArgs::default()));
+        assert!(!can_generate(&args("foo", "library")));
+        let with_copy = |copy: &str| GenerateWorkspaceArgs {
+            copy: Some(copy.to_string()),
+            ..args("foo", "app")
+        };
+        assert!(can_generate(&with_copy("web")));
+        assert!(!can_generate(&with_copy("")));
+        assert!(!can_generate(&with_copy(
+            "https://github.com/vercel/turbo/tree/main/examples/basic"
+        )));
+        assert!(!can_generate(&GenerateWorkspaceArgs {
+            example_path: Some("examples/basic".to_string()),
+            ..args("foo", "app")
+        }));
+    }
+
+    #[test]
+    fn test_add_dependencies() {
+        let workspaces = [("web", "apps/web"), ("ui", "packages/ui")]
+            .into_iter()
+            .map(|(name, path)| Workspace {
+                name: name.to_string(),
+                path: AnchoredSystemPathBuf::from_raw(path).unwrap(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(dependency_candidates(&workspaces, false), ["ui"]);
+        assert_eq!(dependency_candidates(&workspaces, true), ["web", "ui"]);
+
+        let mut package_json: IndexMap<String, Value> = serde_json::from_value(json!({
+            "name": "admin",
+            "dependencies": { "ui": "workspace:^", "react": "^18.2.0" }
+        }))
+        .unwrap();
+        add_dependencies(
+            &mut package_json,
+            vec![
+                ("dependencies", vec!["ui".to_string(), "web".to_string()]),
+                ("devDependencies", vec!["ui".to_string()]),
+                ("peerDependencies", vec![]),
+            ],
+            &PackageManager::Pnpm,
+        );
+        assert_eq!(
+            serde_json::to_value(&package_json).unwrap(),
+            json!({
+                "name": "admin",
+                "dependencies": { "ui": "workspace:^", "react": "^18.2.0", "web": "workspace:*" },
+                "devDependencies": { "ui": "workspace:*" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_name() {
+        for valid in ["foo", "@scope/foo", "foo-bar.baz"] {
+            assert!(validate_name(valid).is_ok(), "{valid} should be valid");
+        }
+        for invalid in [
+            "", "Foo", "_foo", ".foo", "foo bar", "@scope", "@/foo", "a/b",
+        ] {
+            assert!(
+                validate_name(invalid).is_err(),
+                "{invalid} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_empty() {
+        let (_tmp, repo_root) = setup_repo("npm@8.19.0");
+        generate(&repo_root, &args("@acme/utils", "package"), UI::new(true)).unwrap();
+
+        let package_json =
+            read_json(&repo_root.join_components(&["packages", "utils", "package.json"]));
+        assert_eq!(
+            package_json,
+            json!({
+                "name": "@acme/utils",
+                "version": "0.0.0",
+                "private": true,
+                "scripts": { "build": "turbo build" }
+            })
+        );
+        assert!(repo_root
+            .join_components(&["packages", "utils", "README.md"])
+            .exists());
+    }
+
+    #[test]
+    fn test_generate_copy() {
+        let (_tmp, repo_root) = setup_repo("pnpm@8.6.0");
+        let args = GenerateWorkspaceArgs {
+            copy: Some("web".to_string()),
+            ..args("admin", "app")
+        };
+        generate(&repo_root, &args, UI::new(true)).unwrap();
+
+        let admin = repo_root.join_components(&["apps", "admin"]);
+        assert!(admin.join_component("index.js").exists());
+        assert!(!admin.join_component("node_modules").exists());
+        let package_json = read_json(&admin.join_component("package.json"));
+        assert_eq!(
+            package_json,
+            json!({
+                "name": "admin",
+                "version": "1.0.0",
+                "scripts": { "dev": "next dev" },
+                "dependencies": { "ui": "workspace:*", "react": "^18.2.0" },
+                "devDependencies": { "tsconfig": "workspace:*" }
+            })
+        );
+        // the order of the top level fields of the source package.json is preserved
+        let ordered: IndexMap<String, Value> = serde_json::from_str(
+            &fs::read_to_string(admin.join_component("package.json")).unwrap(),
+        )
+        .unwrap();
+        let keys: Vec<_> = ordered.keys().cloned().collect();
+        assert_eq!(
+            keys,
+            [
+                "name",
+                "version",
+                "scripts",
+                "dependencies",
+                "devDependencies"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_copy_show_all_dependencies() {
+        let (_tmp, repo_root) = setup_repo("yarn@1.22.19");
+        let args = GenerateWorkspaceArgs {
+            copy: Some("web".to_string()),
+            show_all_dependencies: true,
+            ..args("admin", "app")
+        };
+        generate(&repo_root, &args, UI::new(true)).unwrap();
+
+        let package_json =
+            read_json(&repo_root.join_components(&["apps", "admin", "package.json"]));
+        assert_eq!(
+            package_json["dependencies"],
+            json!({ "ui": "*", "docs": "*", "react": "^18.2.0" })
+        );
+    }
+
+    #[test]
+    fn test_generate_preserves_nested_order() {
+        let (_tmp, repo_root) = setup_repo("npm@8.19.0");
+        let root_package_json = repo_root.join_component("package.json");
+        fs::write(
+            &root_package_json,
+            r#"{
+  "name": "monorepo",
+  "packageManager": "npm@8.19.0",
+  "scripts": { "lint": "turbo lint", "build": "turbo build" },
+  "workspaces": ["apps/*", "packages/*"]
+}"#,
+        )
+        .unwrap();
+        let args = GenerateWorkspaceArgs {
+            copy: Some("web".to_string()),
+            destination: Some("tooling/admin".to_string()),
+            show_all_dependencies: true,
+            ..args("admin", "app")
+        };
+        generate(&repo_root, &args, UI::new(true)).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&root_package_json).unwrap(),
+            r#"{
+  "name": "monorepo",
+  "packageManager": "npm@8.19.0",
+  "scripts": {
+    "lint": "turbo lint",
+    "build": "turbo build"
+  },
+  "workspaces": [
+    "apps/*",
+    "packages/*",
+    "tooling/*"
+  ]
+}
+"#
+        );
+        assert_eq!(
+            fs::read_to_string(repo_root.join_components(&["tooling", "admin", "package.json"]))
+                .unwrap(),
+            r#"{
+  "name": "admin",
+  "version": "1.0.0",
+  "scripts": {
+    "dev": "next dev"
+  },
+  "dependencies": {
+    "ui": "*",
+    "docs": "*",
+    "react": "^18.2.0"
+  },
+  "devDependencies": {
+    "tsconfig": "*"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_updates_globs() {
+        let (_tmp, repo_root) = setup_repo("npm@8.19.0");
+        let args = GenerateWorkspaceArgs {
+            destination: Some("tooling/lint".to_string()),
+            ..args("lint", "package")
+        };
+        generate(&repo_root, &args, UI::new(true)).unwrap();
+
+        let root_package_json = read_json(&repo_root.join_component("package.json"));
+        assert_eq!(
+            root_package_json["workspaces"],
+            json!(["apps/*", "packages/*", "tooling/*"])
+        );
+    }
+
+    #[test]
+    fn test_generate_updates_pnpm_globs() {
+        let (_tmp, repo_root) = setup_repo("pnpm@8.6.0");
+        let args = GenerateWorkspaceArgs {
+            destination: Some("tooling/lint".to_string()),
+            ..args("lint", "package")
+        };
+        generate(&repo_root, &args, UI::new(true)).unwrap();
+
+        let workspace: serde_yaml::Value =
+            serde_yaml::from_str(&fs::read_to_string(pnpm_workspace_path(&repo_root)).unwrap())
+                .unwrap();
+        assert_eq!(
+            workspace["packages"],
+            serde_yaml::from_str::<serde_yaml::Value>("[apps/*, packages/*, tooling/*]").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        let (_tmp, repo_root) = setup_repo("npm@8.19.0");
+        assert!(generate(&repo_root, &args("ui", "package"), UI::new(true)).is_err());
+        let missing_source = GenerateWorkspaceArgs {
+            copy: Some("missing".to_string()),
+            ..args("new", "app")
+        };
+        assert!(generate(&repo_root, &missing_source, UI::new(true)).is_err());
+        let existing_destination = GenerateWorkspaceArgs {
+            destination: Some("apps/web".to_string()),
+            ..args("new", "app")
+        };
+        assert!(generate(&repo_root, &existing_destination, UI::new(true)).is_err());
+    }
+}
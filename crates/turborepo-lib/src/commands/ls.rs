@@ -0,0 +1,111 @@
+use anyhow::Result;
+use serde::Serialize;
+use tracing::warn;
+use turborepo_scm::SCM;
+
+use crate::{
+    cli::OutputFormat,
+    commands::CommandBase,
+    opts::{LegacyFilter, ScopeOpts},
+    package_graph::PackageGraph,
+    package_json::PackageJson,
+    package_manager::PackageManager,
+    run::scope::{self, workspace_name, PackageInclusionReason},
+    ui::GREY,
+};
+
+#[derive(Debug, Serialize)]
+struct LsOutput {
+    packages: Vec<PackageMatch>,
+}
+
+#[derive(Debug, Serialize)]
+struct PackageMatch {
+    name: String,
+    // The workspace's directory relative to the repo root
+    path: String,
+    reason: PackageInclusionReason,
+}
+
+pub fn run(
+    base: &CommandBase,
+    filter: Vec<String>,
+    since: Option<String>,
+    pkg_inference_root: Option<&str>,
+    output: OutputFormat,
+) -> Result<()> {
+    let root_package_json = PackageJson::load(&base.repo_root.join_component("package.json"))?;
+
+    let package_manager =
+        PackageManager::get_package_manager(&base.repo_root, Some(&root_package_json))?;
+
+    let package_graph = PackageGraph::builder(&base.repo_root, root_package_json)
+        .with_package_manger(Some(package_manager))
+        .build()?;
+
+    let scm = SCM::new(&base.repo_root);
+    let opts = ScopeOpts {
+        pkg_inference_root: ScopeOpts::parse_pkg_inference_root(pkg_inference_root)?,
+        filter_patterns: filter,
+        legacy_filter: LegacyFilter {
+            since,
+            ..LegacyFilter::default()
+        },
+        ..ScopeOpts::default()
+    };
+    let selected = scope::select_packages(&opts, &base.repo_root, &package_graph, &scm)?;
+    for filter in &selected.unused_filters {
+        warn!("filter {} did not match any packages", filter);
+    }
+
+    let mut packages: Vec<_> = selected
+        .packages
+        .into_iter()
+        .map(|(workspace, reason)| PackageMatch {
+            path: package_graph
+                .package_json_path(&workspace)
+                .and_then(|path| path.parent())
+                .map(|dir| dir.to_string().replace('\\', "/"))
+                .unwrap_or_default(),
+            name: workspace_name(&workspace),
+            reason,
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    match output {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&LsOutput { packages })?);
+        }
+        OutputFormat::Text => {
+            println!("{} packages matched\n", packages.len());
+            for package in &packages {
+                println!(
+                    "- {} {} ({})",
+                    package.name,
+                    GREY.apply_to(&package.path),
+                    describe(&package.reason)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn describe(reason: &PackageInclusionReason) -> String {
+    match reason {
+        PackageInclusionReason::All => "no filter specified".to_string(),
+        PackageInclusionReason::Selected { filter } => format!("matched {}", filter),
+        PackageInclusionReason::Changed { filter } => format!("changed, matched {}", filter),
+        PackageInclusionReason::DependencyChanged { filter, dependency } => {
+            format!("dependency {} changed, matched {}", dependency, filter)
+        }
+        PackageInclusionReason::Dependency { filter, dependent } => {
+            format!("dependency of {}, matched {}", dependent, filter)
+        }
+        PackageInclusionReason::Dependent { filter, dependency } => {
+            format!("depends on {}, matched {}", dependency, filter)
+        }
+    }
+}
@@ -22,6 +22,7 @@ pub(crate) mod info;
 pub(crate) mod link;
 pub(crate) mod login;
 pub(crate) mod logout;
+pub(crate) mod ls;
 pub(crate) mod run;
 pub(crate) mod unlink;
 
@@ -142,7 +143,7 @@ impl CommandBase {
         &self.args
     }
 
-    pub fn api_client(&mut self) -> Result<APIClient> {
+    pub fn api_client(&self) -> Result<APIClient> {
         let repo_config = self.repo_config()?;
         let client_config = self.client_config()?;
 
@@ -1,7 +1,14 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
+use turbopath::{AbsoluteSystemPath, RelativeUnixPathBuf};
 
 use crate::{opts::RemoteCacheOpts, task_graph::Pipeline};
 
+const ENV_PIPELINE_DELIMITER: &str = "$";
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct SpacesJson {
@@ -17,8 +24,121 @@ pub struct TurboJson {
     other: serde_json::Value,
     pub(crate) remote_cache_opts: Option<RemoteCacheOpts>,
     pub space_id: Option<String>,
-    #[allow(dead_code)]
+    #[serde(default)]
     pub pipeline: Pipeline,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_env: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global_pass_through_env: Option<Vec<String>>,
+    #[serde(default)]
+    pub global_dot_env: Vec<RelativeUnixPathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub experimental_spaces: Option<SpacesJson>,
 }
+
+impl TurboJson {
+    pub fn load(path: &AbsoluteSystemPath) -> Result<TurboJson> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("unable to read {}", path))?;
+        serde_json::from_str(&contents).with_context(|| format!("unable to parse {}", path))
+    }
+
+    /// The file globs from `globalDependencies`, sorted and without any
+    /// deprecated `$` environment variable entries
+    pub fn global_deps(&self) -> Vec<String> {
+        self.global_dependencies
+            .iter()
+            .filter(|dependency| !dependency.starts_with(ENV_PIPELINE_DELIMITER))
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// The sorted `globalEnv` variables, including any declared with the
+    /// deprecated `$` syntax in `globalDependencies`
+    pub fn global_env(&self) -> Result<Vec<String>> {
+        let mut global_env = gather_env_vars(&self.global_env, "globalEnv")?;
+        for dependency in &self.global_dependencies {
+            if let Some(env_var) = dependency.strip_prefix(ENV_PIPELINE_DELIMITER) {
+                warn!(
+                    "[DEPRECATED] Declaring an environment variable in \"globalDependencies\" is \
+                     deprecated, found {}. Use the \"globalEnv\" key or use `npx @turbo/codemod \
+                     migrate-env-var-dependencies`.",
+                    dependency
+                );
+                global_env.insert(env_var.to_string());
+            }
+        }
+        Ok(global_env.into_iter().collect())
+    }
+
+    /// The sorted `globalPassThroughEnv` variables. `None` if it wasn't
+    /// configured, which is distinct from it being configured as empty.
+    pub fn global_pass_through_env(&self) -> Result<Option<Vec<String>>> {
+        self.global_pass_through_env
+            .as_ref()
+            .map(|vars| {
+                gather_env_vars(vars, "globalPassThroughEnv")
+                    .map(|vars| vars.into_iter().collect::<Vec<_>>())
+            })
+            .transpose()
+    }
+}
+
+fn gather_env_vars(vars: &[String], key: &str) -> Result<BTreeSet<String>> {
+    vars.iter()
+        .map(|value| {
+            if value.starts_with(ENV_PIPELINE_DELIMITER) {
+                // Hard error to help people specify this correctly during migration.
+                Err(anyhow!(
+                    "You specified \"{}\" in the \"{}\" key. You should not prefix your \
+                     environment variables with \"{}\"",
+                    value,
+                    key,
+                    ENV_PIPELINE_DELIMITER
+                ))
+            } else {
+                Ok(value.clone())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::TurboJson;
+
+    #[test]
+    fn test_global_config() {
+        let turbo_json: TurboJson = serde_json::from_value(json!({
+            "globalDependencies": ["tsconfig.json", "$LEGACY_VAR", ".env"],
+            "globalEnv": ["NODE_ENV"],
+            "pipeline": {
+                "build": { "dependsOn": ["^build"] },
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(turbo_json.global_deps(), vec![".env", "tsconfig.json"]);
+        assert_eq!(
+            turbo_json.global_env().unwrap(),
+            vec!["LEGACY_VAR", "NODE_ENV"]
+        );
+        assert_eq!(turbo_json.global_pass_through_env().unwrap(), None);
+        assert!(turbo_json.pipeline.contains_key("build"));
+    }
+
+    #[test]
+    fn test_global_env_prefix_is_an_error() {
+        let turbo_json: TurboJson = serde_json::from_value(json!({
+            "globalPassThroughEnv": ["$AWS_SECRET"],
+        }))
+        .unwrap();
+        assert!(turbo_json.global_pass_through_env().is_err());
+    }
+}
@@ -161,6 +161,9 @@ pub enum DaemonError {
 
     #[error("unable to complete daemon clean")]
     CleanFailed,
+
+    #[error("unable to read daemon lock: {0}")]
+    Lock(#[from] pidlock::PidlockError),
 }
 
 impl From<Status> for DaemonError {
@@ -41,11 +41,13 @@ pub async fn listen_socket(
 > {
     let pid_path = path.join_component("turbod.pid");
     let sock_path = path.join_component("turbod.sock");
-    let mut lock = pidlock::Pidlock::new(pid_path.as_std_path().to_owned());
+    let mut lock = pidlock::Pidlock::new(pid_path.as_std_path().to_owned())
+        .with_version(crate::get_version())
+        .with_socket(sock_path.as_std_path());
 
     trace!("acquiring pidlock");
-    // this will fail if the pid is already owned
-    // todo: make sure we fall back and handle this
+    // this will fail if the pid is already owned, a lock left behind by a
+    // process that is no longer running is taken over
     lock.acquire()?;
     std::fs::remove_file(&sock_path).ok();
 
@@ -196,23 +198,31 @@ mod test {
     #[tokio::test]
     async fn test_stale_pid() {
         let tmp_dir = tempfile::tempdir().unwrap();
-        let tmp_path = tmp_dir.path().to_owned();
-        let pid_path = pid_path(&tmp_path);
-        // A pid that will never be running and is guaranteed not to be us
-        pid_path.create_with_contents("100000").unwrap();
+        let tmp_path = AbsoluteSystemPathBuf::try_from(tmp_dir.path()).unwrap();
+        let pid_path = pid_path(tmp_dir.path());
+        // A process that has already exited
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        child.wait().unwrap();
+        pid_path
+            .create_with_contents(format!("{}", child.id()).as_ref())
+            .unwrap();
 
         let running = Arc::new(AtomicBool::new(true));
-        let result = listen_socket(pid_path, running).await;
-
-        // Note: PidLock doesn't implement Debug, so we can't unwrap_err()
-
-        // todo: update this test to gracefully connect if the lock file exists but has
-        // no process
-        if let Err(err) = result {
-            assert_matches!(err, SocketOpenError::LockError(PidlockError::LockExists(_)));
-        } else {
-            panic!("expected an error")
-        }
+        let result = listen_socket(tmp_path, running).await;
+
+        // Note: PidLock doesn't implement Debug, so we can't unwrap()
+        let Ok((lock, _)) = result else {
+            panic!("expected stale lock to be taken over")
+        };
+        assert_eq!(lock.get_owner(), Some(std::process::id()));
+        let Ok(contents) = std::fs::read_to_string(&pid_path) else {
+            panic!("expected pid file to exist")
+        };
+        assert!(contents.contains(&format!("version={}", crate::get_version())));
     }
 
     #[tokio::test]
@@ -0,0 +1,399 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+
+use super::{Engine, TaskNode};
+use crate::{
+    package_graph::{PackageGraph, WorkspaceName, WorkspaceNode},
+    run::{
+        scope::workspace_name,
+        task_id::{self, ROOT_PKG_NAME},
+    },
+    task_graph::{Pipeline, TaskDefinition},
+};
+
+/// Builds the task graph for running `tasks` in `workspaces` by following
+/// each task's `dependsOn` configuration from the pipeline.
+pub struct EngineBuilder<'a> {
+    package_graph: &'a PackageGraph,
+    pipeline: &'a Pipeline,
+    is_single_package: bool,
+    workspaces: Vec<String>,
+    tasks: Vec<String>,
+    tasks_only: bool,
+}
+
+impl<'a> EngineBuilder<'a> {
+    pub fn new(package_graph: &'a PackageGraph, pipeline: &'a Pipeline) -> Self {
+        Self {
+            package_graph,
+            pipeline,
+            is_single_package: false,
+            workspaces: Vec::new(),
+            tasks: Vec::new(),
+            tasks_only: false,
+        }
+    }
+
+    pub fn with_single_package_mode(mut self, is_single_package: bool) -> Self {
+        self.is_single_package = is_single_package;
+        self
+    }
+
+    /// The names of the workspaces to run tasks in, `//` being the root
+    pub fn with_workspaces(mut self, workspaces: impl IntoIterator<Item = String>) -> Self {
+        self.workspaces = workspaces.into_iter().collect();
+        self.workspaces.sort();
+        self
+    }
+
+    pub fn with_tasks(mut self, tasks: impl IntoIterator<Item = String>) -> Self {
+        self.tasks = tasks.into_iter().collect();
+        self
+    }
+
+    /// Only include dependencies that are one of the requested tasks
+    pub fn with_tasks_only(mut self, tasks_only: bool) -> Self {
+        self.tasks_only = tasks_only;
+        self
+    }
+
+    pub fn build(self) -> Result<Engine> {
+        let mut engine = Engine {
+            task_graph: petgraph::Graph::new(),
+            task_lookup: HashMap::new(),
+            task_definitions: HashMap::new(),
+        };
+        // If there are no affected workspaces, there's nothing to run
+        if self.workspaces.is_empty() {
+            return Ok(engine);
+        }
+
+        // Tasks in the root workspace only run if they're configured as `//#task`
+        let root_enabled_tasks = self
+            .pipeline
+            .keys()
+            .filter_map(|key| {
+                key.strip_prefix(ROOT_PKG_NAME)?
+                    .strip_prefix(task_id::TASK_DELIMITER)
+            })
+            .collect::<HashSet<_>>();
+
+        // Non-package tasks don't need to exist in every workspace, but they
+        // do need to exist somewhere
+        let mut missing = self.tasks.iter().collect::<BTreeSet<_>>();
+        let mut traversal_queue = VecDeque::with_capacity(self.workspaces.len() * self.tasks.len());
+        for workspace in &self.workspaces {
+            for task in &self.tasks {
+                let task_id = task_id::get_task_id(workspace, task);
+                if self.task_definition(&task_id, task).is_none() {
+                    continue;
+                }
+                missing.remove(task);
+
+                if workspace != ROOT_PKG_NAME || root_enabled_tasks.contains(task.as_str()) {
+                    traversal_queue.push_back(task_id);
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "Could not find the following tasks in project: {}",
+                missing.into_iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        let mut visited = HashSet::new();
+        while let Some(task_id) = traversal_queue.pop_front() {
+            let (workspace, task) = task_id::get_package_task_from_id(&task_id);
+
+            if workspace == ROOT_PKG_NAME && !root_enabled_tasks.contains(task.as_str()) {
+                return Err(anyhow!(
+                    "{} needs an entry in turbo.json before it can be depended on because it is a \
+                     task run from the root package",
+                    task_id
+                ));
+            }
+
+            let workspace_node = WorkspaceNode::Workspace(to_workspace_name(&workspace));
+            if self
+                .package_graph
+                .package_json(&to_workspace_name(&workspace))
+                .is_none()
+            {
+                return Err(anyhow!(
+                    "Could not find workspace \"{}\" from task \"{}\" in project",
+                    workspace,
+                    task_id
+                ));
+            }
+
+            let task_definition = self.task_definition(&task_id, &task).ok_or_else(|| {
+                if self.is_single_package {
+                    anyhow!("Could not find \"{}\" in root turbo.json", task_id)
+                } else {
+                    anyhow!(
+                        "Could not find \"{}\" in root turbo.json or \"{}\" workspace",
+                        task_id,
+                        workspace
+                    )
+                }
+            })?;
+
+            if !visited.insert(task_id.clone()) {
+                continue;
+            }
+
+            let is_requested = |dependency: &&String| self.tasks.contains(*dependency);
+            let mut topological_dependencies = task_definition
+                .topological_dependencies()
+                .iter()
+                .filter(|dependency| !self.tasks_only || is_requested(dependency))
+                .peekable();
+            let mut task_dependencies = task_definition
+                .task_dependencies()
+                .iter()
+                .filter(|dependency| !self.tasks_only || is_requested(dependency))
+                .peekable();
+
+            let to_task = engine.add_task(TaskNode::Task(task_id.clone()));
+            let dependency_workspaces = self
+                .package_graph
+                .immediate_dependencies(&workspace_node)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|node| match node {
+                    WorkspaceNode::Workspace(workspace) => Some(workspace),
+                    WorkspaceNode::Root => None,
+                })
+                .collect::<BTreeSet<_>>();
+
+            let has_topological_dependencies =
+                topological_dependencies.peek().is_some() && !dependency_workspaces.is_empty();
+            let has_task_dependencies = task_dependencies.peek().is_some();
+
+            if has_topological_dependencies {
+                for dependency in topological_dependencies {
+                    for dependency_workspace in &dependency_workspaces {
+                        let from_task_id =
+                            task_id::get_task_id(workspace_name(dependency_workspace), dependency);
+                        let from_task = engine.add_task(TaskNode::Task(from_task_id.clone()));
+                        engine.task_graph.update_edge(to_task, from_task, ());
+                        traversal_queue.push_back(from_task_id);
+                    }
+                }
+            }
+
+            if has_task_dependencies {
+                for dependency in task_dependencies {
+                    let from_task_id = task_id::get_task_id(&workspace, dependency);
+                    let from_task = engine.add_task(TaskNode::Task(from_task_id.clone()));
+                    engine.task_graph.update_edge(to_task, from_task, ());
+                    traversal_queue.push_back(from_task_id);
+                }
+            }
+
+            if !has_topological_dependencies && !has_task_dependencies {
+                let root = engine.add_task(TaskNode::Root);
+                engine.task_graph.update_edge(to_task, root, ());
+            }
+
+            engine.task_definitions.insert(task_id, task_definition);
+        }
+
+        Ok(engine)
+    }
+
+    // Package tasks (`workspace#task`) take precedence over the task's
+    // definition for all workspaces
+    fn task_definition(&self, task_id: &str, task: &str) -> Option<TaskDefinition> {
+        self.pipeline
+            .get(task_id)
+            .or_else(|| self.pipeline.get(task))
+            .map(TaskDefinition::from)
+    }
+}
+
+impl Engine {
+    fn add_task(&mut self, task: TaskNode) -> petgraph::graph::NodeIndex {
+        if let Some(idx) = self.task_lookup.get(&task) {
+            return *idx;
+        }
+        let idx = self.task_graph.add_node(task.clone());
+        self.task_lookup.insert(task, idx);
+        idx
+    }
+}
+
+fn to_workspace_name(workspace: &str) -> WorkspaceName {
+    match workspace {
+        ROOT_PKG_NAME => WorkspaceName::Root,
+        workspace => WorkspaceName::from(workspace),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeSet, HashMap};
+
+    use serde_json::json;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::EngineBuilder;
+    use crate::{
+        package_graph::PackageGraph, package_json::PackageJson, package_manager::PackageManager,
+        task_graph::Pipeline,
+    };
+
+    fn package_graph() -> PackageGraph {
+        let root =
+            AbsoluteSystemPathBuf::new(if cfg!(windows) { r"C:\repo" } else { "/repo" }).unwrap();
+        let package_jsons = [
+            ("web", json!({"name": "web", "dependencies": {"ui": "*"}})),
+            ("ui", json!({"name": "ui", "dependencies": {"config": "*"}})),
+            ("config", json!({"name": "config"})),
+        ]
+        .into_iter()
+        .map(|(dir, package_json)| {
+            (
+                root.join_component(dir),
+                PackageJson::from_value(package_json).unwrap(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+        PackageGraph::builder(
+            &root,
+            PackageJson::from_value(json!({"name": "root"})).unwrap(),
+        )
+        .with_package_manger(Some(PackageManager::Npm))
+        .with_package_jsons(Some(package_jsons))
+        .build()
+        .unwrap()
+    }
+
+    fn parse_pipeline(value: serde_json::Value) -> Pipeline {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn workspaces(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_topological_dependencies() {
+        let package_graph = package_graph();
+        let pipeline = parse_pipeline(json!({
+            "build": { "dependsOn": ["^build", "codegen"] },
+            "codegen": {},
+        }));
+        let engine = EngineBuilder::new(&package_graph, &pipeline)
+            .with_workspaces(workspaces(&["web"]))
+            .with_tasks(vec!["build".to_string()])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            engine.tasks().collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                "config#build",
+                "config#codegen",
+                "ui#build",
+                "ui#codegen",
+                "web#build",
+                "web#codegen",
+            ])
+        );
+        assert_eq!(
+            engine.immediate_dependencies("web#build").unwrap(),
+            BTreeSet::from(["ui#build", "web#codegen"])
+        );
+        assert_eq!(
+            engine.dependents("config#codegen").unwrap(),
+            BTreeSet::from(["config#build", "ui#build", "web#build"])
+        );
+
+        let order = engine.topological_order().unwrap();
+        let position = |task_id: &str| order.iter().position(|task| *task == task_id).unwrap();
+        assert!(position("config#build") < position("ui#build"));
+        assert!(position("ui#build") < position("web#build"));
+        assert!(position("web#codegen") < position("web#build"));
+    }
+
+    #[test]
+    fn test_package_task_definition_takes_precedence() {
+        let package_graph = package_graph();
+        let pipeline = parse_pipeline(json!({
+            "build": { "dependsOn": ["^build"] },
+            "web#build": { "dependsOn": ["ui#lint"] },
+            "lint": {},
+        }));
+        let engine = EngineBuilder::new(&package_graph, &pipeline)
+            .with_workspaces(workspaces(&["web"]))
+            .with_tasks(vec!["build".to_string()])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            engine.tasks().collect::<BTreeSet<_>>(),
+            BTreeSet::from(["ui#lint", "web#build"])
+        );
+    }
+
+    #[test]
+    fn test_root_tasks_require_an_entry() {
+        let package_graph = package_graph();
+        let pipeline = parse_pipeline(json!({
+            "build": {},
+            "//#lint": {},
+        }));
+        let engine = EngineBuilder::new(&package_graph, &pipeline)
+            .with_workspaces(workspaces(&["//", "web"]))
+            .with_tasks(vec!["build".to_string(), "lint".to_string()])
+            .build()
+            .unwrap();
+        assert_eq!(
+            engine.tasks().collect::<BTreeSet<_>>(),
+            BTreeSet::from(["//#lint", "web#build"])
+        );
+
+        let pipeline = parse_pipeline(json!({
+            "build": { "dependsOn": ["//#codegen"] },
+            "codegen": {},
+        }));
+        let result = EngineBuilder::new(&package_graph, &pipeline)
+            .with_workspaces(workspaces(&["web"]))
+            .with_tasks(vec!["build".to_string()])
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_missing_tasks() {
+        let package_graph = package_graph();
+        let pipeline = parse_pipeline(json!({ "build": {} }));
+        let result = EngineBuilder::new(&package_graph, &pipeline)
+            .with_workspaces(workspaces(&["web"]))
+            .with_tasks(vec!["test".to_string(), "build".to_string()])
+            .build();
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Could not find the following tasks in project: test"
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let package_graph = package_graph();
+        let pipeline = parse_pipeline(json!({
+            "build": { "dependsOn": ["test"] },
+            "test": { "dependsOn": ["build"] },
+        }));
+        let engine = EngineBuilder::new(&package_graph, &pipeline)
+            .with_workspaces(workspaces(&["config"]))
+            .with_tasks(vec!["build".to_string()])
+            .build()
+            .unwrap();
+        assert!(engine.topological_order().is_err());
+    }
+}
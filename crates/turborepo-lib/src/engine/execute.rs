@@ -0,0 +1,393 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    future::Future,
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use futures::{stream::FuturesUnordered, StreamExt};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+use super::Engine;
+
+pub struct ExecutionOptions {
+    // The number of cpus that running tasks may occupy at once
+    pub concurrency: u32,
+    pub continue_on_error: bool,
+}
+
+/// How a task's final attempt ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome {
+    Succeeded,
+    Failed(String),
+    TimedOut(Duration),
+    /// The task never ran because a dependency didn't succeed or the run
+    /// was stopped by an earlier failure
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskExecution {
+    // Includes the first attempt, so a task that was retried twice has 3
+    pub attempts: u32,
+    pub outcome: TaskOutcome,
+    /// From when the first attempt started to when the final attempt ended.
+    /// Unset for tasks that never started.
+    pub time: Option<Range<SystemTime>>,
+}
+
+impl Engine {
+    /// Runs `visitor` for every task once all of its dependencies have
+    /// succeeded.
+    ///
+    /// Each task occupies as many units of `concurrency` as its definition
+    /// has `cpus`, and waits to be admitted until that many are free. Tasks
+    /// that fail or exceed their `timeout` are retried up to `maxRetries`
+    /// times. The timed out attempt's future is dropped, so visitors should
+    /// kill anything they spawned when dropped.
+    pub async fn execute<F, Fut>(
+        &self,
+        opts: ExecutionOptions,
+        visitor: F,
+    ) -> Result<HashMap<String, TaskExecution>>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        // A task that needs more cpus than we have could never be admitted
+        for task_id in self.tasks() {
+            let cpus = self.cpus(task_id);
+            if cpus > opts.concurrency {
+                return Err(anyhow!(
+                    "{} requires {} cpus but --concurrency is {}. Increase --concurrency or lower \
+                     the task's \"cpus\"",
+                    task_id,
+                    cpus,
+                    opts.concurrency
+                ));
+            }
+        }
+
+        let mut remaining_dependencies = HashMap::new();
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut ready = BTreeSet::new();
+        for task_id in self.tasks() {
+            let dependencies = self.immediate_dependencies(task_id).unwrap_or_default();
+            if dependencies.is_empty() {
+                ready.insert(task_id);
+            }
+            for dependency in &dependencies {
+                dependents.entry(dependency).or_default().push(task_id);
+            }
+            remaining_dependencies.insert(task_id, dependencies.len());
+        }
+
+        let semaphore = Semaphore::new(opts.concurrency as usize);
+        let stopped = AtomicBool::new(false);
+        let mut executions = HashMap::new();
+        let mut running = FuturesUnordered::new();
+        // Ready tasks are started in a stable order, although they may still
+        // be admitted in any order as cpus free up
+        for task_id in ready {
+            running.push(self.execute_task(task_id, &semaphore, &stopped, &visitor));
+        }
+
+        while let Some((task_id, execution)) = running.next().await {
+            let succeeded = execution.outcome == TaskOutcome::Succeeded;
+            executions.insert(task_id.to_string(), execution);
+            if !succeeded {
+                if !opts.continue_on_error {
+                    stopped.store(true, Ordering::SeqCst);
+                }
+                // Dependents of a failed task can never become ready
+                continue;
+            }
+            let mut newly_ready = BTreeSet::new();
+            for dependent in dependents.get(task_id).into_iter().flatten() {
+                let remaining = remaining_dependencies
+                    .get_mut(dependent)
+                    .expect("every task has a dependency count");
+                *remaining -= 1;
+                if *remaining == 0 {
+                    newly_ready.insert(*dependent);
+                }
+            }
+            for dependent in newly_ready {
+                running.push(self.execute_task(dependent, &semaphore, &stopped, &visitor));
+            }
+        }
+
+        for task_id in self.tasks() {
+            executions
+                .entry(task_id.to_string())
+                .or_insert(TaskExecution {
+                    attempts: 0,
+                    outcome: TaskOutcome::Skipped,
+                    time: None,
+                });
+        }
+
+        Ok(executions)
+    }
+
+    async fn execute_task<'a, F, Fut>(
+        &self,
+        task_id: &'a str,
+        semaphore: &Semaphore,
+        stopped: &AtomicBool,
+        visitor: &F,
+    ) -> (&'a str, TaskExecution)
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let _permit = semaphore
+            .acquire_many(self.cpus(task_id))
+            .await
+            .expect("semaphore is never closed");
+        // Tasks that were waiting to be admitted when the run stopped don't start
+        if stopped.load(Ordering::SeqCst) {
+            return (
+                task_id,
+                TaskExecution {
+                    attempts: 0,
+                    outcome: TaskOutcome::Skipped,
+                    time: None,
+                },
+            );
+        }
+
+        let task_definition = self.task_definition(task_id);
+        let timeout = task_definition.and_then(|definition| definition.timeout());
+        let max_attempts = task_definition
+            .map(|definition| definition.max_retries())
+            .unwrap_or_default()
+            + 1;
+
+        let start = SystemTime::now();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let outcome = match timeout {
+                Some(timeout) => {
+                    match tokio::time::timeout(timeout, visitor(task_id.to_string())).await {
+                        Ok(result) => Self::outcome(result),
+                        Err(_) => TaskOutcome::TimedOut(timeout),
+                    }
+                }
+                None => Self::outcome(visitor(task_id.to_string()).await),
+            };
+            match &outcome {
+                TaskOutcome::Failed(error) if attempts < max_attempts => warn!(
+                    "{} failed (attempt {} of {}), retrying: {}",
+                    task_id, attempts, max_attempts, error
+                ),
+                TaskOutcome::TimedOut(timeout) if attempts < max_attempts => warn!(
+                    "{} timed out after {} (attempt {} of {}), retrying",
+                    task_id,
+                    humantime::format_duration(*timeout),
+                    attempts,
+                    max_attempts
+                ),
+                _ => {
+                    return (
+                        task_id,
+                        TaskExecution {
+                            attempts,
+                            outcome,
+                            time: Some(start..SystemTime::now()),
+                        },
+                    )
+                }
+            }
+        }
+    }
+
+    fn outcome(result: Result<()>) -> TaskOutcome {
+        match result {
+            Ok(()) => TaskOutcome::Succeeded,
+            Err(e) => TaskOutcome::Failed(e.to_string()),
+        }
+    }
+
+    fn cpus(&self, task_id: &str) -> u32 {
+        self.task_definition(task_id)
+            .map(|definition| definition.cpus())
+            .unwrap_or(1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Mutex,
+        },
+        time::Duration,
+    };
+
+    use anyhow::anyhow;
+    use petgraph::Graph;
+
+    use super::{ExecutionOptions, TaskExecution, TaskOutcome};
+    use crate::{
+        engine::{Engine, TaskNode},
+        task_graph::{BookkeepingTaskDefinition, TaskDefinition},
+    };
+
+    // Builds an engine from (task, definition, dependencies), where tasks
+    // without dependencies depend on the root node
+    fn engine(tasks: &[(&str, serde_json::Value, &[&str])]) -> Engine {
+        let mut task_graph = Graph::new();
+        let mut task_lookup = HashMap::new();
+        let root = task_graph.add_node(TaskNode::Root);
+        task_lookup.insert(TaskNode::Root, root);
+        let mut task_definitions = HashMap::new();
+        for (task_id, definition, _) in tasks {
+            let node = TaskNode::Task(task_id.to_string());
+            task_lookup.insert(node.clone(), task_graph.add_node(node));
+            let definition: BookkeepingTaskDefinition =
+                serde_json::from_value(definition.clone()).unwrap();
+            task_definitions.insert(task_id.to_string(), TaskDefinition::from(&definition));
+        }
+        for (task_id, _, dependencies) in tasks {
+            let from = task_lookup[&TaskNode::Task(task_id.to_string())];
+            if dependencies.is_empty() {
+                task_graph.add_edge(from, root, ());
+            }
+            for dependency in *dependencies {
+                let to = task_lookup[&TaskNode::Task(dependency.to_string())];
+                task_graph.add_edge(from, to, ());
+            }
+        }
+        Engine {
+            task_graph,
+            task_lookup,
+            task_definitions,
+        }
+    }
+
+    fn opts(concurrency: u32) -> ExecutionOptions {
+        ExecutionOptions {
+            concurrency,
+            continue_on_error: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_run_first() {
+        let engine = engine(&[
+            ("ui#build", serde_json::json!({}), &[]),
+            ("web#build", serde_json::json!({}), &["ui#build"]),
+        ]);
+        let order = Mutex::new(Vec::new());
+        let executions = engine
+            .execute(opts(2), |task_id| {
+                order.lock().unwrap().push(task_id);
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(*order.lock().unwrap(), vec!["ui#build", "web#build"]);
+        assert!(executions
+            .values()
+            .all(|execution| execution.outcome == TaskOutcome::Succeeded));
+    }
+
+    #[tokio::test]
+    async fn test_cpus_limit_admission() {
+        let engine = engine(&[
+            ("a#test", serde_json::json!({ "cpus": 2 }), &[]),
+            ("b#test", serde_json::json!({ "cpus": 2 }), &[]),
+            ("c#test", serde_json::json!({}), &[]),
+        ]);
+        let running = AtomicU32::new(0);
+        let max_running = AtomicU32::new(0);
+        let cpus = |task_id: &str| if task_id == "c#test" { 1 } else { 2 };
+        engine
+            .execute(opts(3), |task_id| {
+                let cpus = cpus(&task_id);
+                let running = &running;
+                let max_running = &max_running;
+                async move {
+                    let now = running.fetch_add(cpus, Ordering::SeqCst) + cpus;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(cpus, Ordering::SeqCst);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+        // The two 2-cpu tasks can never run together with a concurrency of 3
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+
+        let result = engine.execute(opts(1), |_| async { Ok(()) }).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retries_and_timeouts() {
+        let engine = engine(&[
+            ("flaky#test", serde_json::json!({ "maxRetries": 2 }), &[]),
+            (
+                "hangs#test",
+                serde_json::json!({ "timeout": "10ms", "maxRetries": 1 }),
+                &[],
+            ),
+            ("after#test", serde_json::json!({}), &["hangs#test"]),
+        ]);
+        let flaky_attempts = AtomicU32::new(0);
+        let executions = engine
+            .execute(
+                ExecutionOptions {
+                    concurrency: 2,
+                    continue_on_error: true,
+                },
+                |task_id| {
+                    let flaky_attempts = &flaky_attempts;
+                    async move {
+                        if task_id == "hangs#test" {
+                            tokio::time::sleep(Duration::from_secs(60)).await;
+                        } else if task_id == "flaky#test"
+                            && flaky_attempts.fetch_add(1, Ordering::SeqCst) == 0
+                        {
+                            return Err(anyhow!("flaked"));
+                        }
+                        Ok(())
+                    }
+                },
+            )
+            .await
+            .unwrap();
+
+        let flaky = &executions["flaky#test"];
+        assert_eq!(flaky.attempts, 2);
+        assert_eq!(flaky.outcome, TaskOutcome::Succeeded);
+        let hangs = &executions["hangs#test"];
+        assert_eq!(hangs.attempts, 2);
+        assert_eq!(
+            hangs.outcome,
+            TaskOutcome::TimedOut(Duration::from_millis(10))
+        );
+        // Both attempts are included in the task's time
+        let time = hangs.time.clone().unwrap();
+        assert!(time.end.duration_since(time.start).unwrap() >= Duration::from_millis(20));
+        assert_eq!(
+            executions["after#test"],
+            TaskExecution {
+                attempts: 0,
+                outcome: TaskOutcome::Skipped,
+                time: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,104 @@
+mod builder;
+mod execute;
+
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, Result};
+pub use builder::EngineBuilder;
+pub use execute::{ExecutionOptions, TaskExecution, TaskOutcome};
+use petgraph::{graph::NodeIndex, visit::Reversed};
+
+use crate::task_graph::TaskDefinition;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TaskNode {
+    // Placeholder that tasks without any dependencies depend on
+    Root,
+    Task(String),
+}
+
+/// The graph of tasks to run along with the definition each task was resolved
+/// to. Edges point from a task to the tasks it depends on.
+pub struct Engine {
+    task_graph: petgraph::Graph<TaskNode, ()>,
+    task_lookup: HashMap<TaskNode, NodeIndex>,
+    task_definitions: HashMap<String, TaskDefinition>,
+}
+
+impl Engine {
+    /// The ids of all of the tasks in the graph
+    pub fn tasks(&self) -> impl Iterator<Item = &str> {
+        self.task_graph
+            .node_weights()
+            .filter_map(|node| match node {
+                TaskNode::Task(task_id) => Some(task_id.as_str()),
+                TaskNode::Root => None,
+            })
+    }
+
+    pub fn task_definition(&self, task_id: &str) -> Option<&TaskDefinition> {
+        self.task_definitions.get(task_id)
+    }
+
+    /// Returns the tasks that `task_id` directly depends on
+    pub fn immediate_dependencies(&self, task_id: &str) -> Option<BTreeSet<&str>> {
+        let idx = self.task_lookup.get(&TaskNode::Task(task_id.to_string()))?;
+        Some(
+            self.task_graph
+                .neighbors_directed(*idx, petgraph::Outgoing)
+                .filter_map(|n| self.task_id(n))
+                .collect(),
+        )
+    }
+
+    /// Returns the tasks that `task_id` depends on, directly or transitively
+    pub fn dependencies(&self, task_id: &str) -> Option<BTreeSet<&str>> {
+        let idx = self.task_lookup.get(&TaskNode::Task(task_id.to_string()))?;
+        Some(self.reachable(&self.task_graph, *idx))
+    }
+
+    /// Returns the tasks that depend on `task_id`, directly or transitively
+    pub fn dependents(&self, task_id: &str) -> Option<BTreeSet<&str>> {
+        let idx = self.task_lookup.get(&TaskNode::Task(task_id.to_string()))?;
+        Some(self.reachable(Reversed(&self.task_graph), *idx))
+    }
+
+    /// Returns the tasks ordered so that every task comes after all of its
+    /// dependencies
+    pub fn topological_order(&self) -> Result<Vec<&str>> {
+        let sorted = petgraph::algo::toposort(&self.task_graph, None).map_err(|cycle| {
+            anyhow!(
+                "Invalid task dependency graph: cyclic dependency detected involving {}",
+                self.task_id(cycle.node_id()).unwrap_or("the root task")
+            )
+        })?;
+        // Edges point at dependencies, so dependencies sort last
+        Ok(sorted
+            .into_iter()
+            .rev()
+            .filter_map(|n| self.task_id(n))
+            .collect())
+    }
+
+    fn reachable<G>(&self, graph: G, start: NodeIndex) -> BTreeSet<&str>
+    where
+        G: petgraph::visit::IntoNeighbors<NodeId = NodeIndex> + petgraph::visit::Visitable,
+    {
+        let mut reachable = BTreeSet::new();
+        petgraph::visit::depth_first_search(graph, Some(start), |event| {
+            if let petgraph::visit::DfsEvent::Discover(n, _) = event {
+                if n != start {
+                    reachable.extend(self.task_id(n));
+                }
+            }
+        });
+        reachable
+    }
+
+    fn task_id(&self, idx: NodeIndex) -> Option<&str> {
+        match self.task_graph.node_weight(idx)? {
+            TaskNode::Task(task_id) => Some(task_id),
+            TaskNode::Root => None,
+        }
+    }
+}
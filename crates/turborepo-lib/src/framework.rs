@@ -0,0 +1,235 @@
+use crate::package_json::PackageJson;
+
+#[derive(Debug, PartialEq)]
+enum Strategy {
+    All,
+    Some,
+}
+
+#[derive(Debug, PartialEq)]
+struct Matcher {
+    strategy: Strategy,
+    dependencies: &'static [&'static str],
+}
+
+/// A framework that we can infer from a workspace's dependencies along with
+/// the environment variables it inlines into its build output
+#[derive(Debug, PartialEq)]
+pub struct Framework {
+    slug: &'static str,
+    env_wildcards: &'static [&'static str],
+    dependency_match: Matcher,
+}
+
+impl Framework {
+    pub fn slug(&self) -> &'static str {
+        self.slug
+    }
+
+    pub fn env_wildcards(&self) -> &'static [&'static str] {
+        self.env_wildcards
+    }
+}
+
+// Order matters, the first framework that matches is used. Frameworks built on
+// top of other frameworks (e.g. blitz on next) need to come first.
+static FRAMEWORKS: &[Framework] = &[
+    Framework {
+        slug: "blitzjs",
+        env_wildcards: &["NEXT_PUBLIC_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["blitz"],
+        },
+    },
+    Framework {
+        slug: "nextjs",
+        env_wildcards: &["NEXT_PUBLIC_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["next"],
+        },
+    },
+    Framework {
+        slug: "gatsby",
+        env_wildcards: &["GATSBY_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["gatsby"],
+        },
+    },
+    Framework {
+        slug: "astro",
+        env_wildcards: &["PUBLIC_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["astro"],
+        },
+    },
+    Framework {
+        slug: "solidstart",
+        env_wildcards: &["VITE_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["solid-js", "solid-start"],
+        },
+    },
+    Framework {
+        slug: "vue",
+        env_wildcards: &["VUE_APP_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["@vue/cli-service"],
+        },
+    },
+    Framework {
+        slug: "sveltekit",
+        env_wildcards: &["VITE_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["@sveltejs/kit"],
+        },
+    },
+    Framework {
+        slug: "create-react-app",
+        env_wildcards: &["REACT_APP_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::Some,
+            dependencies: &["react-scripts", "react-dev-utils"],
+        },
+    },
+    Framework {
+        slug: "nuxtjs",
+        env_wildcards: &["NUXT_ENV_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::Some,
+            dependencies: &["nuxt", "nuxt-edge", "nuxt3", "nuxt3-edge"],
+        },
+    },
+    Framework {
+        slug: "redwoodjs",
+        env_wildcards: &["REDWOOD_ENV_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["@redwoodjs/core"],
+        },
+    },
+    // Remix only inlines environment variables when it is built with Vite
+    Framework {
+        slug: "remix",
+        env_wildcards: &["VITE_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["@remix-run/dev", "vite"],
+        },
+    },
+    Framework {
+        slug: "vite",
+        env_wildcards: &["VITE_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["vite"],
+        },
+    },
+    Framework {
+        slug: "sanity",
+        env_wildcards: &["SANITY_STUDIO_*"],
+        dependency_match: Matcher {
+            strategy: Strategy::All,
+            dependencies: &["@sanity/cli"],
+        },
+    },
+];
+
+impl Matcher {
+    fn test(&self, package_json: &PackageJson, is_monorepo: bool) -> bool {
+        // In a monorepo every kind of external dependency is considered, for a
+        // single package only production dependencies are
+        let has_dependency = |name: &str| {
+            if is_monorepo {
+                package_json
+                    .all_dependencies()
+                    .any(|(dependency, _)| dependency == name)
+            } else {
+                package_json
+                    .dependencies
+                    .as_ref()
+                    .map_or(false, |dependencies| dependencies.contains_key(name))
+            }
+        };
+
+        match self.strategy {
+            Strategy::All => self.dependencies.iter().all(|dep| has_dependency(dep)),
+            Strategy::Some => self.dependencies.iter().any(|dep| has_dependency(dep)),
+        }
+    }
+}
+
+/// Returns the framework used by a workspace, if we can tell what it is from
+/// its dependencies
+pub fn infer_framework(
+    package_json: &PackageJson,
+    is_monorepo: bool,
+) -> Option<&'static Framework> {
+    FRAMEWORKS
+        .iter()
+        .find(|framework| framework.dependency_match.test(package_json, is_monorepo))
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::{infer_framework, Framework, FRAMEWORKS};
+    use crate::package_json::PackageJson;
+
+    fn get_framework_by_slug(slug: &str) -> &'static Framework {
+        FRAMEWORKS
+            .iter()
+            .find(|framework| framework.slug == slug)
+            .expect("framework not found")
+    }
+
+    #[test_case(serde_json::json!({}), None ; "no dependencies")]
+    #[test_case(serde_json::json!({"dependencies": {"blitz": "*"}}), Some("blitzjs") ; "blitz")]
+    #[test_case(
+        serde_json::json!({"dependencies": {"blitz": "*", "next": "*"}}),
+        Some("blitzjs")
+        ; "order is preserved"
+    )]
+    #[test_case(serde_json::json!({"dependencies": {"next": "*"}}), Some("nextjs") ; "next")]
+    #[test_case(
+        serde_json::json!({"dependencies": {"solid-js": "*", "solid-start": "*"}}),
+        Some("solidstart")
+        ; "match strategy of all"
+    )]
+    #[test_case(serde_json::json!({"dependencies": {"solid-js": "*"}}), None ; "match strategy of all requires every dependency")]
+    #[test_case(serde_json::json!({"dependencies": {"nuxt3": "*"}}), Some("nuxtjs") ; "match strategy of some")]
+    #[test_case(serde_json::json!({"devDependencies": {"react-scripts": "*"}}), Some("create-react-app") ; "dev dependencies")]
+    #[test_case(
+        serde_json::json!({"devDependencies": {"@remix-run/dev": "*", "vite": "*"}}),
+        Some("remix")
+        ; "remix with vite"
+    )]
+    #[test_case(serde_json::json!({"devDependencies": {"vite": "*"}}), Some("vite") ; "vite")]
+    fn test_infer_framework_monorepo(package_json: serde_json::Value, expected: Option<&str>) {
+        let package_json = PackageJson::from_value(package_json).unwrap();
+        assert_eq!(
+            infer_framework(&package_json, true),
+            expected.map(get_framework_by_slug)
+        );
+    }
+
+    #[test_case(serde_json::json!({"dependencies": {"next": "*"}}), Some("nextjs") ; "dependencies")]
+    #[test_case(serde_json::json!({"devDependencies": {"next": "*"}}), None ; "dev dependencies are ignored")]
+    fn test_infer_framework_single_package(
+        package_json: serde_json::Value,
+        expected: Option<&str>,
+    ) {
+        let package_json = PackageJson::from_value(package_json).unwrap();
+        assert_eq!(
+            infer_framework(&package_json, false),
+            expected.map(get_framework_by_slug)
+        );
+    }
+}
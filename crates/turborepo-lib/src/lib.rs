@@ -8,7 +8,9 @@ mod cli;
 mod commands;
 mod config;
 mod daemon;
+mod engine;
 mod execution_state;
+mod framework;
 pub(crate) mod globwatcher;
 mod manager;
 mod opts;
@@ -1,10 +1,13 @@
 #![allow(dead_code)]
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
 
 use crate::{
-    cli::{Command, DryRunMode, EnvMode, LogPrefix, RunArgs},
-    daemon::{DaemonClient, DaemonConnector},
+    cli::{
+        CacheRestoreMode, Command, DryRunMode, EnvMode, LogPrefix, OtlpProtocol, RunArgs, UIMode,
+    },
+    run::cache::OutputWatcher,
     Args,
 };
 
@@ -36,6 +39,25 @@ impl<'a> From<&'a RunArgs> for CacheOpts<'a> {
     }
 }
 
+impl<'a> CacheOpts<'a> {
+    /// The directory local cache artifacts are stored in. Relative overrides
+    /// are resolved against the repo root.
+    pub fn cache_dir(&self, repo_root: &AbsoluteSystemPath) -> AbsoluteSystemPathBuf {
+        match self.override_dir {
+            Some(dir) => AbsoluteSystemPathBuf::from_unknown(repo_root, dir),
+            None => repo_root.join_components(&["node_modules", ".cache", "turbo"]),
+        }
+    }
+
+    pub fn skip_filesystem(&self) -> bool {
+        self.skip_filesystem
+    }
+
+    pub fn skip_remote(&self) -> bool {
+        self.skip_remote
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RemoteCacheOpts {
     team_id: String,
@@ -51,34 +73,49 @@ impl<'a> TryFrom<&'a Args> for Opts<'a> {
         };
         let run_opts = RunOpts::try_from(run_args.as_ref())?;
         let cache_opts = CacheOpts::from(run_args.as_ref());
+        let runcache_opts = RunCacheOpts::from(run_args.as_ref());
+        let scope_opts = ScopeOpts::try_from(run_args.as_ref())?;
 
         Ok(Self {
             run_opts,
             cache_opts,
-            scope_opts: ScopeOpts::default(),
-            runcache_opts: RunCacheOpts::default(),
+            scope_opts,
+            runcache_opts,
         })
     }
 }
 
 #[derive(Debug, Default)]
 pub struct RunCacheOpts {
-    pub(crate) output_watcher: Option<DaemonClient<DaemonConnector>>,
+    pub(crate) output_watcher: Option<Box<dyn OutputWatcher>>,
+    pub(crate) cache_restore: CacheRestoreMode,
+}
+
+impl<'a> From<&'a RunArgs> for RunCacheOpts {
+    fn from(run_args: &'a RunArgs) -> Self {
+        RunCacheOpts {
+            cache_restore: run_args.cache_restore,
+            ..RunCacheOpts::default()
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct RunOpts<'a> {
-    tasks: &'a [String],
-    concurrency: u32,
+    pub(crate) tasks: &'a [String],
+    pub(crate) concurrency: u32,
     parallel: bool,
     pub(crate) env_mode: EnvMode,
     // Whether or not to infer the framework for each workspace.
     pub(crate) framework_inference: bool,
     profile: Option<&'a str>,
-    continue_on_error: bool,
-    passthrough_args: &'a [String],
-    only: bool,
-    dry_run: bool,
+    pub(crate) otlp_endpoint: Option<&'a str>,
+    pub(crate) otlp_protocol: OtlpProtocol,
+    pub(crate) ui_mode: UIMode,
+    pub(crate) continue_on_error: bool,
+    pub(crate) passthrough_args: &'a [String],
+    pub(crate) only: bool,
+    pub(crate) dry_run: bool,
     pub(crate) dry_run_json: bool,
     pub graph_dot: bool,
     graph_file: Option<&'a str>,
@@ -118,6 +155,9 @@ impl<'a> TryFrom<&'a RunArgs> for RunOpts<'a> {
             concurrency,
             parallel: args.parallel,
             profile: args.profile.as_deref(),
+            otlp_endpoint: args.otlp_endpoint.as_deref(),
+            otlp_protocol: args.otlp_protocol,
+            ui_mode: args.ui,
             continue_on_error: args.continue_execution,
             passthrough_args: args.pass_through_args.as_ref(),
             only: args.only,
@@ -155,4 +195,132 @@ fn parse_concurrency(concurrency_raw: &str) -> Result<u32> {
 }
 
 #[derive(Debug, Default)]
-pub struct ScopeOpts {}
+pub struct ScopeOpts {
+    pub pkg_inference_root: Option<AnchoredSystemPathBuf>,
+    pub legacy_filter: LegacyFilter,
+    pub global_deps: Vec<String>,
+    pub filter_patterns: Vec<String>,
+    pub ignore_patterns: Vec<String>,
+}
+
+impl ScopeOpts {
+    /// All of the filter patterns, including those from the legacy options
+    pub fn filter_patterns(&self) -> Vec<String> {
+        let mut filter_patterns = self.filter_patterns.clone();
+        filter_patterns.extend(self.legacy_filter.as_filter_patterns());
+        filter_patterns
+    }
+
+    /// Parses the directory turbo was invoked from. Invoking turbo from the
+    /// repo root doesn't narrow the scope.
+    pub fn parse_pkg_inference_root(root: Option<&str>) -> Result<Option<AnchoredSystemPathBuf>> {
+        root.filter(|root| !root.is_empty() && *root != ".")
+            .map(AnchoredSystemPathBuf::from_raw)
+            .transpose()
+            .map_err(|e| anyhow!("invalid package inference root: {}", e))
+    }
+
+    /// Whether every workspace is selected since nothing narrows the scope
+    pub fn is_all_packages(&self) -> bool {
+        self.filter_patterns().is_empty() && self.pkg_inference_root.is_none()
+    }
+}
+
+impl<'a> TryFrom<&'a RunArgs> for ScopeOpts {
+    type Error = anyhow::Error;
+
+    fn try_from(args: &'a RunArgs) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            pkg_inference_root: ScopeOpts::parse_pkg_inference_root(
+                args.pkg_inference_root.as_deref(),
+            )?,
+            legacy_filter: LegacyFilter {
+                include_dependencies: args.include_dependencies,
+                skip_dependents: args.no_deps,
+                entrypoints: args.scope.clone(),
+                since: args.since.clone(),
+            },
+            global_deps: args.global_deps.clone(),
+            filter_patterns: args.filter.clone(),
+            ignore_patterns: args.ignore.clone(),
+        })
+    }
+}
+
+/// The options used to select packages before `--filter` existed
+#[derive(Debug, Default)]
+pub struct LegacyFilter {
+    pub include_dependencies: bool,
+    pub skip_dependents: bool,
+    pub entrypoints: Vec<String>,
+    pub since: Option<String>,
+}
+
+impl LegacyFilter {
+    /// Converts the legacy options to the equivalent `--filter` patterns
+    pub fn as_filter_patterns(&self) -> Vec<String> {
+        let prefix = if self.skip_dependents { "" } else { "..." };
+        let suffix = if self.include_dependencies { "..." } else { "" };
+        let since = self
+            .since
+            .as_ref()
+            .map(|since| format!("[{}]", since))
+            .unwrap_or_default();
+
+        if !self.entrypoints.is_empty() {
+            // --scope implies our tweaked syntax to see if any dependency matches
+            let since = if since.is_empty() {
+                since
+            } else {
+                format!("...{}", since)
+            };
+            self.entrypoints
+                .iter()
+                .map(|pattern| {
+                    if pattern.starts_with('!') {
+                        pattern.clone()
+                    } else {
+                        format!("{prefix}{pattern}{since}{suffix}")
+                    }
+                })
+                .collect()
+        } else if !since.is_empty() {
+            // no scopes specified, but --since was provided
+            vec![format!("{prefix}{since}{suffix}")]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::LegacyFilter;
+
+    #[test_case(LegacyFilter::default(), &[] ; "no filter")]
+    #[test_case(
+        LegacyFilter { since: Some("main".into()), ..Default::default() },
+        &["...[main]"]
+        ; "since"
+    )]
+    #[test_case(
+        LegacyFilter { since: Some("main".into()), skip_dependents: true, include_dependencies: true, ..Default::default() },
+        &["[main]..."]
+        ; "since with dependencies and without dependents"
+    )]
+    #[test_case(
+        LegacyFilter { entrypoints: vec!["web".into(), "!docs".into()], ..Default::default() },
+        &["...web", "!docs"]
+        ; "scope"
+    )]
+    #[test_case(
+        LegacyFilter { entrypoints: vec!["web".into()], since: Some("main".into()), ..Default::default() },
+        &["...web...[main]"]
+        ; "scope and since"
+    )]
+    fn test_legacy_filter_patterns(legacy_filter: LegacyFilter, expected: &[&str]) {
+        assert_eq!(legacy_filter.as_filter_patterns(), expected);
+    }
+}
@@ -87,6 +87,11 @@ impl PackageGraph {
         Some(&entry.package_json)
     }
 
+    pub fn package_json_path(&self, workspace: &WorkspaceName) -> Option<&AnchoredSystemPathBuf> {
+        let entry = self.workspaces.get(workspace)?;
+        Some(&entry.package_json_path)
+    }
+
     pub fn workspaces(&self) -> impl Iterator<Item = (&WorkspaceName, &Entry)> {
         self.workspaces.iter()
     }
@@ -111,6 +116,63 @@ impl PackageGraph {
         Some(visited)
     }
 
+    /// Returns the workspaces that `node` depends on, directly or
+    /// transitively, not including `node` itself
+    pub fn dependencies(&self, node: &WorkspaceNode) -> Option<HashSet<&WorkspaceNode>> {
+        let mut dependencies = self.transitive_closure(node)?;
+        dependencies.remove(node);
+        Some(dependencies)
+    }
+
+    /// Returns the workspaces that depend on `node`, directly or
+    /// transitively, not including `node` itself
+    pub fn dependents(&self, node: &WorkspaceNode) -> Option<HashSet<&WorkspaceNode>> {
+        let idx = self.node_lookup.get(node)?;
+        let mut visited = HashSet::new();
+        petgraph::visit::depth_first_search(
+            petgraph::visit::Reversed(&self.workspace_graph),
+            Some(*idx),
+            |event| {
+                if let petgraph::visit::DfsEvent::Discover(n, _) = event {
+                    if n != *idx {
+                        visited.insert(
+                            self.workspace_graph
+                                .node_weight(n)
+                                .expect("node index found during dfs doesn't exist"),
+                        );
+                    }
+                }
+            },
+        );
+        Some(visited)
+    }
+
+    /// Returns the workspaces that `node` directly depends on
+    pub fn immediate_dependencies(&self, node: &WorkspaceNode) -> Option<HashSet<&WorkspaceNode>> {
+        let idx = self.node_lookup.get(node)?;
+        Some(
+            self.workspace_graph
+                .neighbors_directed(*idx, petgraph::Outgoing)
+                .map(|n| {
+                    self.workspace_graph
+                        .node_weight(n)
+                        .expect("node index from neighbors should be present")
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the external packages that `workspace` depends on, directly or
+    /// transitively, as resolved by the lockfile. `None` if there's no
+    /// lockfile information available.
+    pub fn transitive_external_dependencies(
+        &self,
+        workspace: &WorkspaceName,
+    ) -> Option<&HashSet<turborepo_lockfiles::Package>> {
+        let entry = self.workspaces.get(workspace)?;
+        entry.transitive_dependencies.as_ref()
+    }
+
     #[allow(dead_code)]
     fn external_dependencies(&self, workspace: &WorkspaceName) -> Option<&HashSet<Package>> {
         let entry = self.workspaces.get(workspace)?;
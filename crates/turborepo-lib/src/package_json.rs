@@ -14,6 +14,8 @@ pub struct PackageJson {
     pub dev_dependencies: Option<BTreeMap<String, String>>,
     pub optional_dependencies: Option<BTreeMap<String, String>>,
     pub peer_dependencies: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -157,6 +157,20 @@ impl WorkspaceGlobs {
         })
     }
 
+    /// The configured workspace exclusion globs
+    pub fn raw_exclusions(&self) -> impl Iterator<Item = &str> {
+        self.raw_exclusions
+            .iter()
+            .map(|exclusion| exclusion.as_str())
+    }
+
+    /// The configured workspace globs, without the package.json suffix
+    pub fn raw_inclusions(&self) -> impl Iterator<Item = &str> {
+        self.package_json_inclusions
+            .iter()
+            .map(|inclusion| inclusion.strip_suffix("/package.json").unwrap_or(inclusion))
+    }
+
     pub fn target_is_workspace(
         &self,
         root: &AbsoluteSystemPath,
@@ -277,6 +291,29 @@ static PACKAGE_MANAGER_PATTERN: Lazy<Regex> =
     lazy_regex!(r"(?P<manager>npm|pnpm|yarn)@(?P<version>\d+\.\d+\.\d+(-.+)?)");
 
 impl PackageManager {
+    /// Returns the name of the package manager's lockfile, relative to the
+    /// repo root. npm uses npm-shrinkwrap.json instead of package-lock.json
+    /// when it exists.
+    pub fn lockfile_name(&self, repo_root: &AbsoluteSystemPath) -> &'static str {
+        match self {
+            PackageManager::Npm if repo_root.join_component(npm::SHRINKWRAP_LOCKFILE).exists() => {
+                npm::SHRINKWRAP_LOCKFILE
+            }
+            PackageManager::Npm => npm::LOCKFILE,
+            PackageManager::Pnpm | PackageManager::Pnpm6 => pnpm::LOCKFILE,
+            PackageManager::Yarn | PackageManager::Berry => yarn::LOCKFILE,
+        }
+    }
+
+    /// Returns the executable used to run package.json scripts
+    pub fn command(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Pnpm | PackageManager::Pnpm6 => "pnpm",
+            PackageManager::Yarn | PackageManager::Berry => "yarn",
+        }
+    }
+
     /// Returns the set of globs for the workspace.
     pub fn get_workspace_globs(
         &self,
@@ -667,6 +704,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_lockfile_name() -> Result<(), Error> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPathBuf::try_from(repo_root.path())?;
+
+        assert_eq!(
+            PackageManager::Npm.lockfile_name(&repo_root_path),
+            npm::LOCKFILE
+        );
+        File::create(repo_root.path().join(npm::SHRINKWRAP_LOCKFILE))?;
+        assert_eq!(
+            PackageManager::Npm.lockfile_name(&repo_root_path),
+            npm::SHRINKWRAP_LOCKFILE
+        );
+        assert_eq!(
+            PackageManager::Pnpm.lockfile_name(&repo_root_path),
+            pnpm::LOCKFILE
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_globs_test() {
         struct TestCase {
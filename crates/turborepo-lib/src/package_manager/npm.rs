@@ -3,6 +3,7 @@ use turbopath::AbsoluteSystemPath;
 use crate::package_manager::{Error, PackageManager};
 
 pub const LOCKFILE: &str = "package-lock.json";
+pub const SHRINKWRAP_LOCKFILE: &str = "npm-shrinkwrap.json";
 
 pub struct NpmDetector<'a> {
     repo_root: &'a AbsoluteSystemPath,
@@ -27,9 +28,11 @@ impl<'a> Iterator for NpmDetector<'a> {
         }
 
         self.found = true;
-        let package_json = self.repo_root.join_component(LOCKFILE);
+        let has_lockfile = [LOCKFILE, SHRINKWRAP_LOCKFILE]
+            .into_iter()
+            .any(|lockfile| self.repo_root.join_component(lockfile).exists());
 
-        if package_json.exists() {
+        if has_lockfile {
             Some(Ok(PackageManager::Npm))
         } else {
             None
@@ -45,7 +48,7 @@ mod tests {
     use tempfile::tempdir;
     use turbopath::AbsoluteSystemPathBuf;
 
-    use super::LOCKFILE;
+    use super::{LOCKFILE, SHRINKWRAP_LOCKFILE};
     use crate::package_manager::PackageManager;
 
     #[test]
@@ -60,4 +63,17 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_detect_npm_shrinkwrap() -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPathBuf::try_from(repo_root.path())?;
+
+        let lockfile_path = repo_root.path().join(SHRINKWRAP_LOCKFILE);
+        File::create(lockfile_path)?;
+        let package_manager = PackageManager::detect_package_manager(&repo_root_path)?;
+        assert_eq!(package_manager, PackageManager::Npm);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,464 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use anyhow::Result;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+use turborepo_api_client::APIClient;
+use turborepo_cache::cache_archive::{CacheReader, OutputGlobs};
+
+use crate::{
+    cli::CacheRestoreMode,
+    daemon::{DaemonClient, DaemonConnector},
+    opts::RunCacheOpts,
+};
+
+/// Tracks whether a task's outputs have changed since they were last written,
+/// so that restoring them can be skipped. The daemon does this by watching the
+/// output globs.
+#[tonic::async_trait]
+pub trait OutputWatcher: Debug + Send {
+    async fn get_changed_outputs(
+        &mut self,
+        hash: String,
+        output_globs: Vec<String>,
+    ) -> Result<Vec<String>>;
+
+    async fn notify_outputs_written(
+        &mut self,
+        hash: String,
+        output_globs: Vec<String>,
+        output_exclusion_globs: Vec<String>,
+        time_saved: u64,
+    ) -> Result<()>;
+}
+
+#[tonic::async_trait]
+impl OutputWatcher for DaemonClient<DaemonConnector> {
+    async fn get_changed_outputs(
+        &mut self,
+        hash: String,
+        output_globs: Vec<String>,
+    ) -> Result<Vec<String>> {
+        Ok(DaemonClient::get_changed_outputs(self, hash, output_globs).await?)
+    }
+
+    async fn notify_outputs_written(
+        &mut self,
+        hash: String,
+        output_globs: Vec<String>,
+        output_exclusion_globs: Vec<String>,
+        time_saved: u64,
+    ) -> Result<()> {
+        Ok(DaemonClient::notify_outputs_written(
+            self,
+            hash,
+            output_globs,
+            output_exclusion_globs,
+            time_saved,
+        )
+        .await?)
+    }
+}
+
+pub struct RunCache {
+    cache_restore: CacheRestoreMode,
+    // Only locked while talking to the watcher, so restores can run in parallel
+    output_watcher: Option<Mutex<Box<dyn OutputWatcher>>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RestoreOutcome {
+    /// The outputs were restored from the artifact
+    Restored {
+        files: Vec<AnchoredSystemPathBuf>,
+        // Files that had been changed locally and were overwritten
+        conflicts: Vec<AnchoredSystemPathBuf>,
+    },
+    /// The daemon reported no changes to the outputs since they were last
+    /// written, so nothing was restored
+    Unchanged,
+}
+
+impl RunCache {
+    pub fn new(opts: RunCacheOpts) -> Self {
+        Self {
+            cache_restore: opts.cache_restore,
+            output_watcher: opts.output_watcher.map(Mutex::new),
+        }
+    }
+
+    /// Restores a task's outputs from a cache artifact. Globs are relative to
+    /// the repo root and the artifact may only contain files matching them.
+    pub async fn restore_outputs(
+        &self,
+        repo_root: &AbsoluteSystemPath,
+        artifact: &AbsoluteSystemPathBuf,
+        hash: &str,
+        inclusions: &[String],
+        exclusions: &[String],
+    ) -> Result<RestoreOutcome> {
+        if self.cache_restore == CacheRestoreMode::SkipIfUnchanged {
+            if let Some(output_watcher) = &self.output_watcher {
+                match output_watcher
+                    .lock()
+                    .await
+                    .get_changed_outputs(hash.to_string(), inclusions.to_vec())
+                    .await
+                {
+                    Ok(changed_output_globs) if changed_output_globs.is_empty() => {
+                        debug!("outputs for {} are unchanged, skipping restore", hash);
+                        return Ok(RestoreOutcome::Unchanged);
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(
+                        "failed to check if we can skip restoring outputs for {}: {}. Proceeding \
+                         to check cache",
+                        hash, e
+                    ),
+                }
+            }
+        }
+
+        let outputs = OutputGlobs::new(inclusions, exclusions)?;
+        let repo_root = repo_root.to_owned();
+        let artifact = artifact.clone();
+        let (files, conflicts) = tokio::task::spawn_blocking(move || -> Result<_> {
+            // The whole artifact is checked before anything is written, so an
+            // invalid artifact doesn't leave partial outputs behind
+            let conflicts = CacheReader::open(&artifact)?.check_outputs(&repo_root, &outputs)?;
+            for conflict in &conflicts {
+                warn!(
+                    "overwriting {} which has been modified since it was cached",
+                    conflict
+                );
+            }
+            let files = CacheReader::open(&artifact)?.restore_outputs(&repo_root, &outputs)?;
+            Ok((files, conflicts))
+        })
+        .await??;
+
+        if let Some(output_watcher) = &self.output_watcher {
+            // Don't fail the restore just because we failed to watch the outputs
+            if let Err(e) = output_watcher
+                .lock()
+                .await
+                .notify_outputs_written(
+                    hash.to_string(),
+                    inclusions.to_vec(),
+                    exclusions.to_vec(),
+                    0,
+                )
+                .await
+            {
+                warn!("failed to mark outputs as cached for {}: {}", hash, e);
+            }
+        }
+
+        Ok(RestoreOutcome::Restored { files, conflicts })
+    }
+}
+
+/// Where a cache artifact was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CacheSource {
+    #[serde(rename = "LOCAL")]
+    Local,
+    #[serde(rename = "REMOTE")]
+    Remote,
+}
+
+/// Whether an artifact exists in each of the caches, without it having been
+/// fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStatus {
+    pub local: bool,
+    pub remote: bool,
+    // Milliseconds the original task took to run
+    pub time_saved: u64,
+}
+
+impl CacheStatus {
+    pub fn is_hit(&self) -> bool {
+        self.local || self.remote
+    }
+
+    pub fn source(&self) -> Option<CacheSource> {
+        if self.local {
+            Some(CacheSource::Local)
+        } else if self.remote {
+            Some(CacheSource::Remote)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CacheMetadata {
+    duration: u64,
+}
+
+pub struct RemoteCacheClient {
+    pub client: APIClient,
+    pub token: String,
+    pub team_id: String,
+    pub team_slug: Option<String>,
+}
+
+/// Checks which caches contain artifacts for given hashes
+pub struct CacheStatusChecker {
+    // `None` if the filesystem cache is disabled
+    cache_dir: Option<AbsoluteSystemPathBuf>,
+    remote: Option<RemoteCacheClient>,
+}
+
+impl CacheStatusChecker {
+    // Matches the number of concurrent cache requests in the Go implementation
+    const MAX_CONCURRENT_CHECKS: usize = 8;
+
+    pub fn new(
+        cache_dir: Option<AbsoluteSystemPathBuf>,
+        remote: Option<RemoteCacheClient>,
+    ) -> Self {
+        Self { cache_dir, remote }
+    }
+
+    pub async fn statuses(&self, hashes: &[String]) -> HashMap<String, CacheStatus> {
+        futures::stream::iter(hashes)
+            .map(|hash| async move { (hash.clone(), self.status(hash).await) })
+            .buffer_unordered(Self::MAX_CONCURRENT_CHECKS)
+            .collect()
+            .await
+    }
+
+    pub async fn status(&self, hash: &str) -> CacheStatus {
+        let mut status = CacheStatus::default();
+        if let Some(cache_dir) = &self.cache_dir {
+            if let Some(time_saved) = Self::local_status(cache_dir, hash) {
+                status.local = true;
+                status.time_saved = time_saved;
+            }
+        }
+        if let Some(remote) = &self.remote {
+            match remote
+                .client
+                .artifact_exists(
+                    hash,
+                    &remote.token,
+                    &remote.team_id,
+                    remote.team_slug.as_deref(),
+                )
+                .await
+            {
+                Ok(Some(time_saved)) => {
+                    status.remote = true;
+                    if !status.local {
+                        status.time_saved = time_saved;
+                    }
+                }
+                Ok(None) => {}
+                // The remote cache being unavailable shouldn't fail the run
+                Err(e) => warn!("failed to check remote cache for {}: {}", hash, e),
+            }
+        }
+        status
+    }
+
+    fn local_status(cache_dir: &AbsoluteSystemPath, hash: &str) -> Option<u64> {
+        local_artifact(cache_dir, hash)?;
+        // A missing or malformed metadata file just means we can't report
+        // time saved
+        let duration =
+            std::fs::read_to_string(cache_dir.join_component(&format!("{}-meta.json", hash)))
+                .ok()
+                .and_then(|contents| serde_json::from_str::<CacheMetadata>(&contents).ok())
+                .map(|metadata| metadata.duration)
+                .unwrap_or_default();
+        Some(duration)
+    }
+}
+
+/// The artifact for `hash` in the filesystem cache, if there is one
+pub fn local_artifact(cache_dir: &AbsoluteSystemPath, hash: &str) -> Option<AbsoluteSystemPathBuf> {
+    [format!("{}.tar.zst", hash), format!("{}.tar", hash)]
+        .iter()
+        .map(|artifact| cache_dir.join_component(artifact))
+        .find(|artifact| artifact.exists())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, fs::File};
+
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+
+    use super::{CacheSource, CacheStatusChecker, OutputWatcher, RestoreOutcome, RunCache};
+    use crate::{cli::CacheRestoreMode, opts::RunCacheOpts};
+
+    // Reports every output as unchanged, like the daemon does for outputs it
+    // has seen written and that haven't been touched since
+    #[derive(Debug)]
+    struct UnchangedOutputs;
+
+    #[tonic::async_trait]
+    impl OutputWatcher for UnchangedOutputs {
+        async fn get_changed_outputs(
+            &mut self,
+            _hash: String,
+            _output_globs: Vec<String>,
+        ) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn notify_outputs_written(
+            &mut self,
+            _hash: String,
+            _output_globs: Vec<String>,
+            _output_exclusion_globs: Vec<String>,
+            _time_saved: u64,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn write_artifact(path: &AbsoluteSystemPathBuf, files: &[(&str, &str)]) -> Result<()> {
+        let mut builder = tar::Builder::new(File::create(path)?);
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, contents.as_bytes())?;
+        }
+        builder.into_inner()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_outputs() -> Result<()> {
+        let dir = tempdir()?;
+        let repo_root = AbsoluteSystemPathBuf::try_from(dir.path())?;
+        let artifact = repo_root.join_component("artifact.tar");
+        write_artifact(&artifact, &[("dist/index.js", "built")])?;
+        fs::create_dir(repo_root.join_component("dist"))?;
+        fs::write(repo_root.join_components(&["dist", "index.js"]), "edited")?;
+
+        // Without a daemon there's nothing to tell us the outputs are unchanged
+        let run_cache = RunCache::new(RunCacheOpts {
+            cache_restore: CacheRestoreMode::SkipIfUnchanged,
+            ..Default::default()
+        });
+        let outcome = run_cache
+            .restore_outputs(
+                &repo_root,
+                &artifact,
+                "abc123",
+                &["dist/**".to_string()],
+                &[],
+            )
+            .await?;
+
+        let index = AnchoredSystemPathBuf::from_raw("dist/index.js")?;
+        assert_eq!(
+            outcome,
+            RestoreOutcome::Restored {
+                files: vec![index.clone()],
+                conflicts: vec![index],
+            }
+        );
+        assert_eq!(
+            fs::read_to_string(repo_root.join_components(&["dist", "index.js"]))?,
+            "built"
+        );
+
+        let result = run_cache
+            .restore_outputs(
+                &repo_root,
+                &artifact,
+                "abc123",
+                &["lib/**".to_string()],
+                &[],
+            )
+            .await;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_skip_unchanged_outputs() -> Result<()> {
+        let dir = tempdir()?;
+        let repo_root = AbsoluteSystemPathBuf::try_from(dir.path())?;
+        let artifact = repo_root.join_component("artifact.tar");
+        write_artifact(&artifact, &[("dist/index.js", "built")])?;
+        fs::create_dir(repo_root.join_component("dist"))?;
+        fs::write(repo_root.join_components(&["dist", "index.js"]), "edited")?;
+        let outputs = ["dist/**".to_string()];
+
+        let run_cache = RunCache::new(RunCacheOpts {
+            cache_restore: CacheRestoreMode::SkipIfUnchanged,
+            output_watcher: Some(Box::new(UnchangedOutputs)),
+        });
+        let outcome = run_cache
+            .restore_outputs(&repo_root, &artifact, "abc123", &outputs, &[])
+            .await?;
+        assert_eq!(outcome, RestoreOutcome::Unchanged);
+        assert_eq!(
+            fs::read_to_string(repo_root.join_components(&["dist", "index.js"]))?,
+            "edited"
+        );
+
+        // Outputs are always restored unless asked otherwise
+        let run_cache = RunCache::new(RunCacheOpts {
+            cache_restore: CacheRestoreMode::Always,
+            output_watcher: Some(Box::new(UnchangedOutputs)),
+        });
+        let outcome = run_cache
+            .restore_outputs(&repo_root, &artifact, "abc123", &outputs, &[])
+            .await?;
+        assert!(matches!(outcome, RestoreOutcome::Restored { .. }));
+        assert_eq!(
+            fs::read_to_string(repo_root.join_components(&["dist", "index.js"]))?,
+            "built"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_cache_status() -> Result<()> {
+        let dir = tempdir()?;
+        let cache_dir = AbsoluteSystemPathBuf::try_from(dir.path())?;
+        fs::write(cache_dir.join_component("abc123.tar.zst"), "")?;
+        fs::write(
+            cache_dir.join_component("abc123-meta.json"),
+            r#"{"hash":"abc123","duration":1500}"#,
+        )?;
+        fs::write(cache_dir.join_component("def456.tar"), "")?;
+
+        let checker = CacheStatusChecker::new(Some(cache_dir), None);
+        let statuses = checker
+            .statuses(&[
+                "abc123".to_string(),
+                "def456".to_string(),
+                "missing".to_string(),
+            ])
+            .await;
+
+        let hit = statuses["abc123"];
+        assert_eq!(hit.source(), Some(CacheSource::Local));
+        assert_eq!(hit.time_saved, 1500);
+        // Artifacts without metadata are still hits
+        assert!(statuses["def456"].is_hit());
+        assert_eq!(statuses["def456"].time_saved, 0);
+        assert!(!statuses["missing"].is_hit());
+        assert_eq!(statuses["missing"].source(), None);
+
+        Ok(())
+    }
+}
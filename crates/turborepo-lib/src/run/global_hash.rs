@@ -1,14 +1,26 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use anyhow::Result;
-use turbopath::{AbsoluteSystemPath, RelativeUnixPathBuf};
-use turborepo_env::{BySource, DetailedMap, EnvironmentVariableMap};
+use tracing::debug;
+use turbopath::{AbsoluteSystemPath, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+use turborepo_env::{dot_env, BySource, DetailedMap, EnvironmentVariableMap};
 use turborepo_lockfiles::Lockfile;
+use turborepo_scm::SCM;
 
-use crate::{cli::EnvMode, package_json::PackageJson, package_manager::PackageManager, ui::UI};
+use crate::{
+    cli::EnvMode,
+    package_manager::PackageManager,
+    run::task_hash::{go_struct, hash_external_dependencies, hash_object, GoFormat},
+    ui::UI,
+};
 
 static DEFAULT_ENV_VARS: [&str; 1] = ["VERCEL_ANALYTICS_ID"];
 
+// Bumping this invalidates every existing cache artifact
+const GLOBAL_CACHE_KEY: &str = "You don't understand! I coulda had class. I coulda been a \
+                                contender. I could've been somebody, instead of a bum, which is \
+                                what I am.";
+
 #[derive(Default)]
 pub struct GlobalHashableInputs {
     global_cache_key: &'static str,
@@ -17,26 +29,130 @@ pub struct GlobalHashableInputs {
     env: Vec<String>,
     // Only Option to allow #[derive(Default)]
     resolved_env_vars: Option<DetailedMap>,
-    pass_through_env: Vec<String>,
+    pass_through_env: Option<Vec<String>>,
     env_mode: EnvMode,
     framework_inference: bool,
     dot_env: Vec<RelativeUnixPathBuf>,
 }
 
+struct GlobalHashable<'a> {
+    global_cache_key: &'static str,
+    global_file_hash_map: BTreeMap<&'a RelativeUnixPathBuf, &'a String>,
+    root_external_deps_hash: &'a str,
+    env: &'a [String],
+    resolved_env_vars: Vec<String>,
+    pass_through_env: Option<&'a [String]>,
+    env_mode: EnvMode,
+    framework_inference: bool,
+    // This is _explicitly_ ordered and should not be sorted
+    dot_env: &'a [RelativeUnixPathBuf],
+}
+
+impl<'a> GoFormat for GlobalHashable<'a> {
+    fn go_format(&self, out: &mut String) {
+        go_struct(
+            out,
+            &[
+                &self.global_cache_key,
+                &self.global_file_hash_map,
+                &self.root_external_deps_hash,
+                &self.env,
+                &self.resolved_env_vars,
+                &self.pass_through_env.unwrap_or_default(),
+                &self.env_mode,
+                &self.framework_inference,
+                &self.dot_env,
+            ],
+        );
+    }
+}
+
+impl GlobalHashableInputs {
+    /// The hashes of the parsed contents of each global dotenv file that exists
+    pub fn dot_env_hashes(&self) -> BTreeMap<RelativeUnixPathBuf, String> {
+        self.dot_env
+            .iter()
+            .filter_map(|file| {
+                let hash = self.global_file_hash_map.get(file)?;
+                Some((file.clone(), hash.clone()))
+            })
+            .collect()
+    }
+
+    pub fn calculate_global_hash(&self) -> Result<String> {
+        let mut env_mode = self.env_mode;
+        let mut pass_through_env = self.pass_through_env.as_deref();
+        match env_mode {
+            // In infer mode, any passThroughEnv config (even an empty one)
+            // means the whole config is hashed so we can detect changes to it
+            EnvMode::Infer if pass_through_env.is_some() => env_mode = EnvMode::Strict,
+            EnvMode::Infer => {}
+            // Pass through variables aren't considered in loose mode
+            EnvMode::Loose => pass_through_env = None,
+            // Collapse unset and empty in strict mode
+            EnvMode::Strict => pass_through_env = pass_through_env.or(Some(&[])),
+        }
+
+        Ok(hash_object(&GlobalHashable {
+            global_cache_key: self.global_cache_key,
+            global_file_hash_map: self.global_file_hash_map.iter().collect(),
+            root_external_deps_hash: &self.root_external_deps_hash,
+            env: &self.env,
+            resolved_env_vars: self
+                .resolved_env_vars
+                .as_ref()
+                .map(|env_vars| env_vars.all.to_hashable())
+                .unwrap_or_default(),
+            pass_through_env,
+            env_mode,
+            framework_inference: self.framework_inference,
+            dot_env: &self.dot_env,
+        }))
+    }
+
+    pub fn global_cache_key(&self) -> &'static str {
+        self.global_cache_key
+    }
+
+    pub fn file_hashes(&self) -> BTreeMap<RelativeUnixPathBuf, String> {
+        self.global_file_hash_map
+            .iter()
+            .map(|(file, hash)| (file.clone(), hash.clone()))
+            .collect()
+    }
+
+    pub fn root_external_deps_hash(&self) -> &str {
+        &self.root_external_deps_hash
+    }
+
+    pub fn env(&self) -> &[String] {
+        &self.env
+    }
+
+    pub fn pass_through_env(&self) -> Option<&[String]> {
+        self.pass_through_env.as_deref()
+    }
+
+    pub fn resolved_env_vars(&self) -> Option<&DetailedMap> {
+        self.resolved_env_vars.as_ref()
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn get_global_hash_inputs<L: ?Sized + Lockfile>(
     _ui: &UI,
-    _root_path: &AbsoluteSystemPath,
-    _root_package_json: &PackageJson,
-    _package_manager: &PackageManager,
-    _lockfile: Option<&L>,
-    _global_file_dependencies: Vec<String>,
+    root_path: &AbsoluteSystemPath,
+    scm: &SCM,
+    root_external_dependencies: Option<&HashSet<turborepo_lockfiles::Package>>,
+    package_manager: &PackageManager,
+    lockfile: Option<&L>,
+    global_file_dependencies: Vec<String>,
     env_at_execution_start: &EnvironmentVariableMap,
     global_env: Vec<String>,
-    _global_pass_through_env: Vec<String>,
-    _env_mode: EnvMode,
-    _framework_inference: bool,
-    _dot_env: Vec<RelativeUnixPathBuf>,
+    global_pass_through_env: Option<Vec<String>>,
+    env_mode: EnvMode,
+    framework_inference: bool,
+    dot_env: Vec<RelativeUnixPathBuf>,
 ) -> Result<GlobalHashableInputs> {
     let default_env_var_map = env_at_execution_start.from_wildcards(&DEFAULT_ENV_VARS[..])?;
 
@@ -63,9 +179,89 @@ pub fn get_global_hash_inputs<L: ?Sized + Lockfile>(
             matching: matching_env_var_map,
         },
     };
+    debug!(
+        "global hash env vars: {:?}",
+        global_hashable_env_vars.all.names()
+    );
+
+    let mut global_deps = HashSet::new();
+    if !global_file_dependencies.is_empty() {
+        let exclusions = match package_manager.get_workspace_globs(root_path) {
+            Ok(globs) => globs
+                .raw_exclusions()
+                .map(|glob| glob.to_string())
+                .collect(),
+            // Single package repos don't have workspace globs
+            Err(_) => Vec::new(),
+        };
+        let files = globwalk::globwalk(
+            root_path,
+            &global_file_dependencies,
+            &exclusions,
+            globwalk::WalkType::Files,
+        )?;
+        global_deps.extend(files);
+    }
+
+    if lockfile.is_none() {
+        // Without lockfile information the whole package.json and lockfile
+        // are inputs instead
+        global_deps.insert(root_path.join_component("package.json"));
+        let lockfile_path = root_path.join_component(package_manager.lockfile_name(root_path));
+        if lockfile_path.exists() {
+            global_deps.insert(lockfile_path);
+        }
+    }
+
+    let global_deps = global_deps
+        .iter()
+        .map(|path| AnchoredSystemPathBuf::relative_path_between(root_path, path))
+        .collect::<Vec<_>>();
+    let mut global_file_hash_map = scm.hash_files(root_path, global_deps.into_iter())?;
+    // Dotenv files are hashed by their contents rather than their bytes
+    if !dot_env.is_empty() {
+        global_file_hash_map.extend(dot_env::hash_files(root_path, &dot_env)?);
+    }
 
     Ok(GlobalHashableInputs {
+        global_cache_key: GLOBAL_CACHE_KEY,
+        global_file_hash_map,
+        root_external_deps_hash: hash_external_dependencies(root_external_dependencies)?,
+        env: global_env,
         resolved_env_vars: Some(global_hashable_env_vars),
-        ..GlobalHashableInputs::default()
+        pass_through_env: global_pass_through_env,
+        env_mode,
+        framework_inference,
+        dot_env,
     })
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use turbopath::RelativeUnixPathBuf;
+
+    use super::{GlobalHashableInputs, GLOBAL_CACHE_KEY};
+    use crate::cli::EnvMode;
+
+    #[test]
+    fn test_global_hash_matches_go() {
+        let inputs = GlobalHashableInputs {
+            global_cache_key: GLOBAL_CACHE_KEY,
+            global_file_hash_map: HashMap::from([(
+                RelativeUnixPathBuf::new("package.json").unwrap(),
+                "abc".to_string(),
+            )]),
+            root_external_deps_hash: "ext".to_string(),
+            env: vec!["VERCEL_ANALYTICS_ID".to_string()],
+            env_mode: EnvMode::Infer,
+            framework_inference: true,
+            dot_env: vec![RelativeUnixPathBuf::new(".env").unwrap()],
+            ..Default::default()
+        };
+        // The Go implementation hashes `{<global cache key> map[package.json:abc]
+        // ext [VERCEL_ANALYTICS_ID] [] [] infer true [.env]}` to this
+        assert_eq!(inputs.calculate_global_hash().unwrap(), "0f2f22b9a73c6297");
+    }
+}
@@ -1,14 +1,20 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
-use anyhow::Result;
-use turbopath::AbsoluteSystemPath;
+use anyhow::{anyhow, Result};
+use tracing::debug;
+use turbopath::{AbsoluteSystemPath, RelativeUnixPathBuf};
+use turborepo_env::{BySource, DetailedMap, EnvironmentVariableMap};
 
 use crate::{
     config::TurboJson,
+    framework::Framework,
     package_graph::PackageGraph,
-    task_graph::{Pipeline, TaskDefinition},
+    run::task_id::{self, ROOT_PKG_NAME},
+    task_graph::{BookkeepingTaskDefinition, Pipeline, TaskDefinition},
 };
 
+const CONFIG_FILE: &str = "turbo.json";
+
 pub struct CompleteGraph<'run> {
     // Expresses the dependencies between packages
     package_graph: &'run PackageGraph,
@@ -35,15 +41,265 @@ impl<'run> CompleteGraph<'run> {
         }
     }
 
+    /// Loads the turbo.json for a workspace. In single package mode the
+    /// pipeline is prefixed with the root package name and any root
+    /// package.json scripts without a definition are added as uncached tasks.
+    // TODO: only the root turbo.json is supported, workspace configs that
+    // extend it still need to be ported
     pub fn get_turbo_config_from_workspace(
         &self,
-        _workspace_name: &str,
-        _is_single_package: bool,
+        workspace_name: &str,
+        is_single_package: bool,
     ) -> Result<TurboJson> {
-        // TODO
-        Ok(TurboJson::default())
+        if workspace_name != ROOT_PKG_NAME {
+            return Err(anyhow!(
+                "turbo.json in workspace {} is not supported yet",
+                workspace_name
+            ));
+        }
+
+        let turbo_json_path = self.repo_root.join_component(CONFIG_FILE);
+        let turbo_json_exists = turbo_json_path.exists();
+        if !is_single_package {
+            if !turbo_json_exists {
+                return Err(anyhow!(
+                    "Could not find {}. Follow directions at https://turbo.build/repo/docs to \
+                     create one",
+                    CONFIG_FILE
+                ));
+            }
+            return TurboJson::load(&turbo_json_path);
+        }
+
+        let mut turbo_json = if turbo_json_exists {
+            TurboJson::load(&turbo_json_path)?
+        } else {
+            TurboJson::default()
+        };
+        let mut pipeline = Pipeline::new();
+        for (task_name, task_definition) in std::mem::take(&mut turbo_json.pipeline) {
+            if task_id::is_package_task(&task_name) {
+                return Err(anyhow!(
+                    "Package tasks (<package>#<task>) are not allowed in single-package \
+                     repositories: found {}",
+                    task_name
+                ));
+            }
+            pipeline.insert(task_id::root_task_id(&task_name), task_definition);
+        }
+        for script in self.package_graph.root_package_json().scripts.keys() {
+            pipeline
+                .entry(task_id::root_task_id(script))
+                .or_insert_with(BookkeepingTaskDefinition::uncached);
+        }
+        turbo_json.pipeline = pipeline;
+
+        Ok(turbo_json)
     }
 }
 
 #[derive(Default)]
-pub struct TaskHashTracker {}
+pub struct TaskHashTracker {
+    package_task_env_vars: HashMap<String, DetailedMap>,
+    package_task_framework: HashMap<String, &'static Framework>,
+    package_task_hashes: HashMap<String, String>,
+    package_task_file_hashes: HashMap<String, BTreeMap<RelativeUnixPathBuf, String>>,
+    package_task_external_deps_hashes: HashMap<String, String>,
+}
+
+impl TaskHashTracker {
+    /// Resolves the environment variables that are inputs to a task's hash.
+    ///
+    /// If a framework was inferred for the task's workspace, the variables it
+    /// inlines into build output are included as well. Exclusions in the
+    /// task's `env` take precedence over inferred variables.
+    pub fn calculate_env_vars(
+        &mut self,
+        task_id: &str,
+        task_env: &[String],
+        framework: Option<&'static Framework>,
+        env_at_execution_start: &EnvironmentVariableMap,
+    ) -> Result<&DetailedMap> {
+        let mut all_env_var_map = EnvironmentVariableMap::default();
+        let mut explicit_env_var_map = EnvironmentVariableMap::default();
+        let mut matching_env_var_map = EnvironmentVariableMap::default();
+
+        if let Some(framework) = framework {
+            debug!(
+                "auto detected framework for {}: {} with env prefixes {:?}",
+                task_id,
+                framework.slug(),
+                framework.env_wildcards()
+            );
+            let mut computed_wildcards = framework
+                .env_wildcards()
+                .iter()
+                .map(|wildcard| wildcard.to_string())
+                .collect::<Vec<_>>();
+
+            // Vendor excludes are only applied against inferred includes.
+            if let Some(exclude_prefix) = env_at_execution_start
+                .get("TURBO_CI_VENDOR_ENV_KEY")
+                .filter(|prefix| !prefix.is_empty())
+            {
+                let computed_exclude = format!("!{}*", exclude_prefix);
+                debug!(
+                    "excluding environment variables matching wildcard {}",
+                    computed_exclude
+                );
+                computed_wildcards.push(computed_exclude);
+            }
+
+            let inference_env_var_map =
+                env_at_execution_start.from_wildcards(&computed_wildcards)?;
+            let user_env_var_set =
+                env_at_execution_start.wildcard_map_from_wildcards_unresolved(task_env)?;
+
+            all_env_var_map.union(&user_env_var_set.inclusions);
+            all_env_var_map.union(&inference_env_var_map);
+            all_env_var_map.difference(&user_env_var_set.exclusions);
+
+            explicit_env_var_map.union(&user_env_var_set.inclusions);
+            explicit_env_var_map.difference(&user_env_var_set.exclusions);
+
+            matching_env_var_map.union(&inference_env_var_map);
+            matching_env_var_map.difference(&user_env_var_set.exclusions);
+
+            self.package_task_framework
+                .insert(task_id.to_string(), framework);
+        } else {
+            all_env_var_map = env_at_execution_start.from_wildcards(task_env)?;
+            explicit_env_var_map.union(&all_env_var_map);
+        }
+
+        let env_vars = DetailedMap {
+            all: all_env_var_map,
+            by_source: BySource {
+                explicit: explicit_env_var_map,
+                matching: matching_env_var_map,
+            },
+        };
+        debug!(
+            "task hash env vars for {}: {:?}",
+            task_id,
+            env_vars.all.to_hashable()
+        );
+        self.package_task_env_vars
+            .insert(task_id.to_string(), env_vars);
+        Ok(&self.package_task_env_vars[task_id])
+    }
+
+    pub fn env_vars(&self, task_id: &str) -> Option<&DetailedMap> {
+        self.package_task_env_vars.get(task_id)
+    }
+
+    pub fn framework(&self, task_id: &str) -> Option<&'static Framework> {
+        self.package_task_framework.get(task_id).copied()
+    }
+
+    /// Records a task's hash along with the hashes of the inputs that went
+    /// into it
+    pub fn insert_hash(
+        &mut self,
+        task_id: &str,
+        hash: String,
+        file_hashes: BTreeMap<RelativeUnixPathBuf, String>,
+        external_deps_hash: String,
+    ) {
+        self.package_task_hashes.insert(task_id.to_string(), hash);
+        self.package_task_file_hashes
+            .insert(task_id.to_string(), file_hashes);
+        self.package_task_external_deps_hashes
+            .insert(task_id.to_string(), external_deps_hash);
+    }
+
+    pub fn hash(&self, task_id: &str) -> Option<&str> {
+        self.package_task_hashes
+            .get(task_id)
+            .map(|hash| hash.as_str())
+    }
+
+    pub fn file_hashes(&self, task_id: &str) -> Option<&BTreeMap<RelativeUnixPathBuf, String>> {
+        self.package_task_file_hashes.get(task_id)
+    }
+
+    pub fn external_deps_hash(&self, task_id: &str) -> Option<&str> {
+        self.package_task_external_deps_hashes
+            .get(task_id)
+            .map(|hash| hash.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use turborepo_env::EnvironmentVariableMap;
+
+    use super::TaskHashTracker;
+    use crate::{framework::infer_framework, package_json::PackageJson};
+
+    fn env(vars: &[(&str, &str)]) -> EnvironmentVariableMap {
+        EnvironmentVariableMap::from(
+            vars.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    fn keys(map: &EnvironmentVariableMap) -> Vec<&str> {
+        let mut keys = map.keys().map(|key| key.as_str()).collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_framework_env_vars_are_inferred() {
+        let package_json =
+            PackageJson::from_value(serde_json::json!({"dependencies": {"next": "*"}})).unwrap();
+        let framework = infer_framework(&package_json, true);
+        let env_at_execution_start = env(&[
+            ("NEXT_PUBLIC_API", "api"),
+            ("NEXT_PUBLIC_SECRET", "secret"),
+            ("NEXT_PUBLIC_VERCEL_URL", "url"),
+            ("API_KEY", "key"),
+            ("OTHER", "other"),
+            ("TURBO_CI_VENDOR_ENV_KEY", "NEXT_PUBLIC_VERCEL_"),
+        ]);
+        let mut tracker = TaskHashTracker::default();
+
+        let env_vars = tracker
+            .calculate_env_vars(
+                "web#build",
+                &["API_KEY".to_string(), "!NEXT_PUBLIC_SECRET".to_string()],
+                framework,
+                &env_at_execution_start,
+            )
+            .unwrap();
+
+        assert_eq!(keys(&env_vars.all), vec!["API_KEY", "NEXT_PUBLIC_API"]);
+        assert_eq!(keys(&env_vars.by_source.explicit), vec!["API_KEY"]);
+        assert_eq!(keys(&env_vars.by_source.matching), vec!["NEXT_PUBLIC_API"]);
+        assert_eq!(tracker.framework("web#build").unwrap().slug(), "nextjs");
+    }
+
+    #[test]
+    fn test_no_framework() {
+        let env_at_execution_start = env(&[("NEXT_PUBLIC_API", "api"), ("API_KEY", "key")]);
+        let mut tracker = TaskHashTracker::default();
+
+        let env_vars = tracker
+            .calculate_env_vars(
+                "web#build",
+                &["API_KEY".to_string()],
+                None,
+                &env_at_execution_start,
+            )
+            .unwrap();
+
+        assert_eq!(keys(&env_vars.all), vec!["API_KEY"]);
+        assert_eq!(keys(&env_vars.by_source.explicit), vec!["API_KEY"]);
+        assert!(env_vars.by_source.matching.is_empty());
+        assert!(tracker.framework("web#build").is_none());
+    }
+}
@@ -1,26 +1,60 @@
 #![allow(dead_code)]
 
+pub(crate) mod cache;
 mod global_hash;
 pub mod graph;
-mod scope;
-mod task_id;
+mod otlp;
+pub(crate) mod scope;
+mod summary;
+mod task_hash;
+pub(crate) mod task_id;
 
-use anyhow::{Context as ErrorContext, Result};
-use graph::CompleteGraph;
-use tracing::{debug, info};
-use turborepo_env::EnvironmentVariableMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+    mem,
+    process::{ExitStatus, Stdio},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context as ErrorContext, Result};
+use command_group::{AsyncCommandGroup, AsyncGroupChild};
+use graph::{CompleteGraph, TaskHashTracker};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tracing::{debug, error, info, warn};
+use turbopath::{AbsoluteSystemPath, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+use turborepo_env::{dot_env, EnvironmentVariableMap};
 use turborepo_scm::SCM;
 
 use crate::{
+    cli::UIMode,
     commands::CommandBase,
     daemon::DaemonConnector,
+    engine::{Engine, EngineBuilder, ExecutionOptions, TaskExecution},
+    framework::infer_framework,
+    get_version,
     manager::Manager,
-    opts::Opts,
-    package_graph::PackageGraph,
+    opts::{CacheOpts, Opts},
+    package_graph::{PackageGraph, WorkspaceName},
     package_json::PackageJson,
-    run::{global_hash::get_global_hash_inputs, task_id::ROOT_PKG_NAME},
+    run::{
+        cache::{CacheStatusChecker, RemoteCacheClient, RestoreOutcome, RunCache},
+        global_hash::get_global_hash_inputs,
+        otlp::{RunTracer, TaskSpan},
+        summary::{
+            framework_name, EnvConfiguration, EnvVarSummary, GlobalHashSummary, RunSummary,
+            TaskExecutionSummary, TaskSummary,
+        },
+        task_hash::{hash_external_dependencies, task_env_mode, TaskHashInputs},
+        task_id::ROOT_PKG_NAME,
+    },
+    task_graph::TaskDefinition,
+    ui::{TaskReporter, TaskStatus, Tui},
 };
 
+// The command reported for tasks without a script in package.json
+const MISSING_TASK_COMMAND: &str = "<NONEXISTENT>";
+
 #[derive(Debug)]
 pub struct Run {
     base: CommandBase,
@@ -42,7 +76,13 @@ impl Run {
     }
 
     pub async fn run(&mut self) -> Result<()> {
+        self.run_with_output(&mut io::stdout()).await
+    }
+
+    /// Runs with the `--dry` summary written to `out` rather than stdout
+    async fn run_with_output(&mut self, out: &mut (impl Write + Send)) -> Result<()> {
         let _start_at = std::time::Instant::now();
+        let start_time = SystemTime::now();
         let package_json_path = self.base.repo_root.join_component("package.json");
         let root_package_json =
             PackageJson::load(&package_json_path).context("failed to read package.json")?;
@@ -69,7 +109,7 @@ impl Run {
 
             let client = connector.connect().await?;
             debug!("running in daemon mode");
-            opts.runcache_opts.output_watcher = Some(client);
+            opts.runcache_opts.output_watcher = Some(Box::new(client));
         }
 
         pkg_dep_graph
@@ -94,7 +134,7 @@ impl Run {
         let mut filtered_pkgs =
             scope::resolve_packages(&opts.scope_opts, &self.base, &pkg_dep_graph, &scm)?;
 
-        if filtered_pkgs.len() == pkg_dep_graph.len() {
+        if opts.scope_opts.is_all_packages() {
             for target in targets {
                 let key = task_id::root_task_id(target);
                 if pipeline.contains_key(&key) {
@@ -106,36 +146,699 @@ impl Run {
 
         let env_at_execution_start = EnvironmentVariableMap::infer();
 
-        let _global_hash_inputs = get_global_hash_inputs(
+        let global_hash_inputs = get_global_hash_inputs(
             &self.base.ui,
             &self.base.repo_root,
-            pkg_dep_graph.root_package_json(),
+            &scm,
+            pkg_dep_graph.transitive_external_dependencies(&WorkspaceName::Root),
             pkg_dep_graph.package_manager(),
             pkg_dep_graph.lockfile(),
-            // TODO: Fill in these vec![] once turbo.json is ported
-            vec![],
+            turbo_json.global_deps(),
             &env_at_execution_start,
-            vec![],
-            vec![],
+            turbo_json.global_env()?,
+            turbo_json.global_pass_through_env()?,
             opts.run_opts.env_mode,
             opts.run_opts.framework_inference,
-            vec![],
+            turbo_json.global_dot_env.clone(),
         )?;
+        let global_hash = global_hash_inputs.calculate_global_hash()?;
+        debug!("global hash: {}", global_hash);
+
+        let engine = EngineBuilder::new(&pkg_dep_graph, pipeline)
+            .with_single_package_mode(is_single_package)
+            .with_workspaces(filtered_pkgs.iter().cloned())
+            .with_tasks(targets.iter().cloned())
+            .with_tasks_only(opts.run_opts.only)
+            .build()?;
+
+        let mut task_hash_tracker = TaskHashTracker::default();
+        for task_id in engine.topological_order()? {
+            let task_definition = engine
+                .task_definition(task_id)
+                .ok_or_else(|| anyhow!("missing definition for task {}", task_id))?;
+            let (pkg, task) = task_id::get_package_task_from_id(task_id);
+            let workspace = workspace_from_name(&pkg);
+            let package_dir = package_dir(&pkg_dep_graph, &workspace)?;
+            let package_dir_path = self.base.repo_root.resolve(&package_dir);
+
+            let mut file_hashes: BTreeMap<_, _> = scm
+                .get_package_file_hashes(
+                    &self.base.repo_root,
+                    &package_dir,
+                    task_definition.inputs(),
+                )?
+                .into_iter()
+                .collect();
+            // Dotenv files are hashed by their contents rather than their bytes
+            if !task_definition.dot_env().is_empty() {
+                file_hashes.extend(dot_env::hash_files(
+                    &package_dir_path,
+                    task_definition.dot_env(),
+                )?);
+            }
+
+            let framework = match pkg_dep_graph.package_json(&workspace) {
+                Some(package_json) if opts.run_opts.framework_inference => {
+                    infer_framework(package_json, !is_single_package)
+                }
+                _ => None,
+            };
+            task_hash_tracker.calculate_env_vars(
+                task_id,
+                task_definition.env(),
+                framework,
+                &env_at_execution_start,
+            )?;
+            let env_vars = task_hash_tracker
+                .env_vars(task_id)
+                .expect("env vars were just calculated");
+
+            let dependency_hashes = engine
+                .immediate_dependencies(task_id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|dependency| {
+                    task_hash_tracker
+                        .hash(dependency)
+                        .ok_or_else(|| anyhow!("missing hash for dependency {}", dependency))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Pass through args only apply to the tasks that were asked for
+            let pass_through_args = if targets.contains(&task) {
+                opts.run_opts.passthrough_args
+            } else {
+                &[]
+            };
+            let external_deps_hash = hash_external_dependencies(
+                pkg_dep_graph.transitive_external_dependencies(&workspace),
+            )?;
+
+            let hash = TaskHashInputs {
+                global_hash: &global_hash,
+                dependency_hashes,
+                package_dir: package_dir.to_unix()?,
+                file_hashes: &file_hashes,
+                external_deps_hash: &external_deps_hash,
+                task: &task,
+                task_definition,
+                pass_through_args,
+                env_vars,
+                env_mode: task_env_mode(opts.run_opts.env_mode, task_definition),
+            }
+            .calculate_task_hash()?;
+            debug!("task hash for {}: {}", task_id, hash);
+            task_hash_tracker.insert_hash(task_id, hash, file_hashes, external_deps_hash);
+        }
+
+        // Checking the cache is only done for dry runs, a real run checks each
+        // task's cache as it executes
+        let cache_statuses = if opts.run_opts.dry_run {
+            let hashes = engine
+                .tasks()
+                .filter_map(|task_id| task_hash_tracker.hash(task_id))
+                .map(|hash| hash.to_string())
+                .collect::<Vec<_>>();
+            self.cache_status_checker(&opts.cache_opts)?
+                .statuses(&hashes)
+                .await
+        } else {
+            HashMap::new()
+        };
+
+        let (executions, task_cache_statuses) = if opts.run_opts.dry_run {
+            (HashMap::new(), HashMap::new())
+        } else {
+            let run_cache = RunCache::new(mem::take(&mut opts.runcache_opts));
+            self.execute_tasks(
+                &engine,
+                &pkg_dep_graph,
+                &opts,
+                &task_hash_tracker,
+                run_cache,
+            )
+            .await?
+        };
+
+        let global_env_vars = global_hash_inputs
+            .resolved_env_vars()
+            .expect("global env vars are always resolved");
+        let global_pass_through_env_vars = env_at_execution_start
+            .from_wildcards(global_hash_inputs.pass_through_env().unwrap_or_default())?
+            .names();
+        let global_cache_inputs = GlobalHashSummary {
+            root_key: global_hash_inputs.global_cache_key(),
+            files: global_hash_inputs.file_hashes(),
+            hash_of_external_dependencies: global_hash_inputs.root_external_deps_hash().to_string(),
+            environment_variables: EnvVarSummary::new(
+                EnvConfiguration {
+                    env: global_hash_inputs.env().to_vec(),
+                    pass_through_env: global_hash_inputs
+                        .pass_through_env()
+                        .map(|env| env.to_vec()),
+                },
+                global_env_vars,
+                global_pass_through_env_vars,
+            ),
+        };
+        let package_dirs = filtered_pkgs
+            .iter()
+            .map(|pkg| {
+                let package_dir = package_dir(&pkg_dep_graph, &workspace_from_name(pkg))?;
+                Ok((pkg.clone(), package_dir.to_unix()?))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        let mut run_summary = RunSummary::new(
+            get_version(),
+            is_single_package,
+            global_cache_inputs,
+            package_dirs,
+            opts.run_opts.env_mode,
+            opts.run_opts.framework_inference,
+            global_hash_inputs.dot_env_hashes(),
+        );
+        for task_id in engine.tasks() {
+            let task_definition = engine
+                .task_definition(task_id)
+                .ok_or_else(|| anyhow!("missing definition for task {}", task_id))?;
+            let (pkg, task) = task_id::get_package_task_from_id(task_id);
+            let workspace = workspace_from_name(&pkg);
+            let package_dir = package_dir(&pkg_dep_graph, &workspace)?.to_unix()?;
+            let hash = task_hash_tracker
+                .hash(task_id)
+                .ok_or_else(|| anyhow!("missing hash for task {}", task_id))?;
+            let env_vars = task_hash_tracker
+                .env_vars(task_id)
+                .ok_or_else(|| anyhow!("missing env vars for task {}", task_id))?;
+            let pass_through_env_vars = env_at_execution_start
+                .from_wildcards(task_definition.pass_through_env().unwrap_or_default())?
+                .names();
+            let command = pkg_dep_graph
+                .package_json(&workspace)
+                .and_then(|package_json| package_json.scripts.get(&task))
+                .cloned()
+                .unwrap_or_else(|| MISSING_TASK_COMMAND.to_string());
+            let log_file = if package_dir.as_str().is_empty() {
+                task_hash::log_file(&task)
+            } else {
+                RelativeUnixPathBuf::new(format!("{}/{}", package_dir, task_hash::log_file(&task)))?
+            };
+            // Single package repos don't have packages, so task ids are just
+            // the task name
+            let display_id = |task_id: &str| {
+                if is_single_package {
+                    task_id::root_task_task_name(task_id)
+                } else {
+                    task_id.to_string()
+                }
+            };
+            let (package, directory) = if is_single_package {
+                (None, None)
+            } else {
+                (Some(pkg.clone()), Some(package_dir))
+            };
+
+            run_summary.add_task(TaskSummary {
+                task_id: display_id(task_id),
+                task: task.clone(),
+                package,
+                hash: hash.to_string(),
+                inputs: task_hash_tracker
+                    .file_hashes(task_id)
+                    .cloned()
+                    .unwrap_or_default(),
+                hash_of_external_dependencies: task_hash_tracker
+                    .external_deps_hash(task_id)
+                    .unwrap_or_default()
+                    .to_string(),
+                cache: cache_statuses.get(hash).copied().unwrap_or_default().into(),
+                command,
+                cli_arguments: if targets.contains(&task) {
+                    opts.run_opts.passthrough_args.to_vec()
+                } else {
+                    Vec::new()
+                },
+                outputs: task_definition.outputs().inclusions.clone(),
+                excluded_outputs: task_definition.outputs().exclusions.clone(),
+                log_file,
+                directory,
+                dependencies: engine
+                    .dependencies(task_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(display_id)
+                    .collect(),
+                dependents: engine
+                    .dependents(task_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(display_id)
+                    .collect(),
+                resolved_task_definition: task_definition.clone(),
+                framework: framework_name(
+                    opts.run_opts.framework_inference,
+                    task_hash_tracker.framework(task_id),
+                ),
+                env_mode: task_env_mode(opts.run_opts.env_mode, task_definition),
+                env_vars: EnvVarSummary::new(
+                    EnvConfiguration {
+                        env: task_definition.env().to_vec(),
+                        pass_through_env: task_definition
+                            .pass_through_env()
+                            .map(|env| env.to_vec()),
+                    },
+                    env_vars,
+                    pass_through_env_vars,
+                ),
+                dot_env: task_definition
+                    .dot_env()
+                    .iter()
+                    .filter_map(|file| {
+                        let hash = task_hash_tracker.file_hashes(task_id)?.get(file)?;
+                        Some((file.clone(), hash.clone()))
+                    })
+                    .collect(),
+                execution: executions.get(task_id).map(TaskExecutionSummary::new),
+            });
+        }
+
+        if opts.run_opts.dry_run {
+            if opts.run_opts.dry_run_json {
+                writeln!(out, "{}", run_summary.format_json()?)?;
+            } else {
+                write!(out, "{}", run_summary.format_text(&self.base.ui))?;
+            }
+            return Ok(());
+        }
+
+        debug!("run summary: {:?}", run_summary);
+
+        if let Some(endpoint) = opts.run_opts.otlp_endpoint {
+            let tracer = RunTracer::new(endpoint, opts.run_opts.otlp_protocol)?;
+            let run_span = tracer.start_run(start_time, filtered_pkgs.len());
+            for task in run_summary.tasks() {
+                // Tasks that never started have no time to record
+                let Some(time) = executions
+                    .get(&task.task_id)
+                    .and_then(|execution| execution.time.clone())
+                else {
+                    continue;
+                };
+                run_span.record_task(TaskSpan {
+                    task_id: &task.task_id,
+                    package: task.package.as_deref().unwrap_or(ROOT_PKG_NAME),
+                    hash: Some(&task.hash),
+                    cache_status: task_cache_statuses
+                        .get(&task.task_id)
+                        .map(TaskCacheStatus::as_str),
+                    start: time.start,
+                    end: time.end,
+                });
+            }
+            run_span.end(SystemTime::now());
+            // Flushing blocks until the collector responds
+            if let Err(e) = tokio::task::spawn_blocking(move || tracer.flush()).await? {
+                warn!("{}", e);
+            }
+        }
+
+        let failures = run_summary
+            .tasks()
+            .iter()
+            .filter_map(|task| Some((&task.task_id, task.execution.as_ref()?.error.as_ref()?)))
+            .collect::<Vec<_>>();
+        for (task_id, error) in &failures {
+            error!("{}: {}", task_id, error);
+        }
+        if !failures.is_empty() {
+            return Err(anyhow!(
+                "run failed: {} task(s) did not succeed",
+                failures.len()
+            ));
+        }
 
         Ok(())
     }
+
+    /// Runs each task's package.json script, admitting tasks by their `cpus`
+    /// against `--concurrency`. Tasks whose outputs are in the filesystem
+    /// cache are restored instead of run. Returns how each task executed and
+    /// the cache status of each task with cacheable outputs.
+    async fn execute_tasks(
+        &self,
+        engine: &Engine,
+        pkg_dep_graph: &PackageGraph,
+        opts: &Opts<'_>,
+        task_hash_tracker: &TaskHashTracker,
+        run_cache: RunCache,
+    ) -> Result<(
+        HashMap<String, TaskExecution>,
+        HashMap<String, TaskCacheStatus>,
+    )> {
+        let package_manager = pkg_dep_graph.package_manager().command();
+        let cache_dir = (!opts.cache_opts.skip_filesystem())
+            .then(|| opts.cache_opts.cache_dir(&self.base.repo_root));
+        // The directory and arguments to run each task with. Tasks without a
+        // script have nothing to run.
+        let mut commands = HashMap::new();
+        let mut cached_outputs = HashMap::new();
+        let task_cache_statuses = std::sync::Mutex::new(HashMap::new());
+        for task_id in engine.tasks() {
+            let (pkg, task) = task_id::get_package_task_from_id(task_id);
+            let workspace = workspace_from_name(&pkg);
+            let has_script = pkg_dep_graph
+                .package_json(&workspace)
+                .map_or(false, |package_json| {
+                    package_json.scripts.contains_key(&task)
+                });
+            if !has_script {
+                continue;
+            }
+            let mut args = vec!["run".to_string(), task.clone()];
+            if opts.run_opts.tasks.contains(&task) && !opts.run_opts.passthrough_args.is_empty() {
+                args.push("--".to_string());
+                args.extend(opts.run_opts.passthrough_args.iter().cloned());
+            }
+            let package_dir = package_dir(pkg_dep_graph, &workspace)?;
+            let task_definition = engine
+                .task_definition(task_id)
+                .ok_or_else(|| anyhow!("missing definition for task {}", task_id))?;
+            if task_definition.should_cache() {
+                let hash = task_hash_tracker
+                    .hash(task_id)
+                    .ok_or_else(|| anyhow!("missing hash for task {}", task_id))?;
+                cached_outputs.insert(
+                    task_id.to_string(),
+                    CachedOutputs::new(hash, &package_dir.to_unix()?, &task, task_definition),
+                );
+            }
+            let dir = self.base.repo_root.resolve(&package_dir);
+            commands.insert(task_id.to_string(), (dir, args));
+        }
+
+        let execution_opts = ExecutionOptions {
+            concurrency: opts.run_opts.concurrency,
+            continue_on_error: opts.run_opts.continue_on_error,
+        };
+        let tui = (opts.run_opts.ui_mode == UIMode::Tui && self.base.ui.supports_tui())
+            .then(|| Tui::start(engine.tasks().map(|task_id| task_id.to_string()).collect()))
+            .transpose()?;
+        let sender = tui.as_ref().map(Tui::sender);
+        let execution = engine.execute(execution_opts, |task_id| {
+            let command = commands.get(&task_id);
+            let outputs = cached_outputs.get(&task_id).zip(cache_dir.as_deref());
+            let reporter = sender.as_ref().map(|sender| sender.start_task(&task_id));
+            let run_cache = &run_cache;
+            let task_cache_statuses = &task_cache_statuses;
+            async move {
+                let Some((dir, args)) = command else {
+                    if let Some(reporter) = reporter {
+                        reporter.finish(TaskStatus::Succeeded);
+                    }
+                    return Ok(());
+                };
+                let set_cache_status = |status| {
+                    task_cache_statuses
+                        .lock()
+                        .expect("lock poisoned")
+                        .insert(task_id.clone(), status);
+                };
+                if let Some((outputs, cache_dir)) = outputs {
+                    let restored = outputs
+                        .restore(run_cache, &self.base.repo_root, cache_dir)
+                        .await;
+                    if let Some((status, message)) = restored {
+                        set_cache_status(status);
+                        match reporter {
+                            Some(reporter) => {
+                                reporter.output(message);
+                                reporter.finish(TaskStatus::Cached);
+                            }
+                            None => println!("{}: {}", task_id, message),
+                        }
+                        return Ok(());
+                    }
+                    set_cache_status(TaskCacheStatus::Miss);
+                }
+                let mut command = tokio::process::Command::new(package_manager);
+                command.args(args).current_dir(dir);
+                // Dropping the future on timeout kills the script's process group
+                let status = match &reporter {
+                    Some(reporter) => wait_with_output(command, reporter).await?,
+                    None => ScriptProcess::spawn(&mut command)?.wait().await?,
+                };
+                if let Some(reporter) = reporter {
+                    reporter.finish(if status.success() {
+                        TaskStatus::Succeeded
+                    } else {
+                        TaskStatus::Failed
+                    });
+                }
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(anyhow!(
+                        "command ({}) {} {} exited ({})",
+                        dir,
+                        package_manager,
+                        args.join(" "),
+                        status
+                            .code()
+                            .map_or_else(|| "signal".to_string(), |code| code.to_string())
+                    ))
+                }
+            }
+        });
+
+        let executions = match tui {
+            Some(tui) => {
+                // Raw mode swallows Ctrl-C, so the UI tells us when to stop.
+                // Dropping the execution kills any running scripts.
+                let executions = tokio::select! {
+                    executions = execution => executions,
+                    _ = tui.interrupted() => Err(anyhow!("run interrupted")),
+                };
+                drop(sender);
+                tui.finish()?;
+                executions?
+            }
+            None => execution.await?,
+        };
+        Ok((
+            executions,
+            task_cache_statuses.into_inner().expect("lock poisoned"),
+        ))
+    }
+
+    fn cache_status_checker(&self, cache_opts: &CacheOpts) -> Result<CacheStatusChecker> {
+        let cache_dir =
+            (!cache_opts.skip_filesystem()).then(|| cache_opts.cache_dir(&self.base.repo_root));
+
+        let mut remote = None;
+        if !cache_opts.skip_remote() {
+            let token = self.base.user_config()?.token();
+            let repo_config = self.base.repo_config()?;
+            // Without credentials the remote cache is disabled
+            if let (Some(token), Some(team_id)) = (token, repo_config.team_id()) {
+                remote = Some(RemoteCacheClient {
+                    client: self.base.api_client()?,
+                    token: token.to_string(),
+                    team_id: team_id.to_string(),
+                    team_slug: repo_config.team_slug().map(|slug| slug.to_string()),
+                });
+            }
+        }
+
+        Ok(CacheStatusChecker::new(cache_dir, remote))
+    }
+}
+
+/// How a task with cacheable outputs used the cache when it executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskCacheStatus {
+    /// The outputs were restored from a cache artifact
+    Restored,
+    /// There was a cache artifact, but the outputs were already in place
+    Hit,
+    /// The task's script was run
+    Miss,
+}
+
+impl TaskCacheStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TaskCacheStatus::Restored => "restored",
+            TaskCacheStatus::Hit => "hit",
+            TaskCacheStatus::Miss => "miss",
+        }
+    }
+}
+
+/// The outputs a task would restore from the cache, as globs relative to the
+/// repo root. Like the Go implementation, the task's log file is one of them.
+struct CachedOutputs {
+    hash: String,
+    inclusions: Vec<String>,
+    exclusions: Vec<String>,
+}
+
+impl CachedOutputs {
+    fn new(
+        hash: &str,
+        package_dir: &RelativeUnixPathBuf,
+        task: &str,
+        task_definition: &TaskDefinition,
+    ) -> Self {
+        let repo_relative = |glob: &str| {
+            if package_dir.as_str().is_empty() {
+                glob.to_string()
+            } else {
+                format!("{}/{}", package_dir, glob)
+            }
+        };
+        let outputs = task_definition.outputs();
+        let log_file = task_hash::log_file(task);
+        Self {
+            hash: hash.to_string(),
+            inclusions: std::iter::once(log_file.as_str())
+                .chain(outputs.inclusions.iter().map(String::as_str))
+                .map(repo_relative)
+                .collect(),
+            exclusions: outputs
+                .exclusions
+                .iter()
+                .map(|glob| repo_relative(glob))
+                .collect(),
+        }
+    }
+
+    /// Restores the outputs if there's an artifact for them in `cache_dir`,
+    /// returning the cache status and a message describing the cache hit.
+    /// Failing to restore is treated as a cache miss.
+    async fn restore(
+        &self,
+        run_cache: &RunCache,
+        repo_root: &AbsoluteSystemPath,
+        cache_dir: &AbsoluteSystemPath,
+    ) -> Option<(TaskCacheStatus, String)> {
+        let artifact = cache::local_artifact(cache_dir, &self.hash)?;
+        match run_cache
+            .restore_outputs(
+                repo_root,
+                &artifact,
+                &self.hash,
+                &self.inclusions,
+                &self.exclusions,
+            )
+            .await
+        {
+            Ok(RestoreOutcome::Restored { .. }) => Some((
+                TaskCacheStatus::Restored,
+                format!("cache hit, restored outputs {}", self.hash),
+            )),
+            Ok(RestoreOutcome::Unchanged) => Some((
+                TaskCacheStatus::Hit,
+                format!("cache hit, outputs already in place {}", self.hash),
+            )),
+            Err(e) => {
+                warn!("failed to restore outputs for {}: {}", self.hash, e);
+                None
+            }
+        }
+    }
+}
+
+fn workspace_from_name(name: &str) -> WorkspaceName {
+    if name == ROOT_PKG_NAME {
+        WorkspaceName::Root
+    } else {
+        WorkspaceName::from(name)
+    }
+}
+
+// The directory of a workspace, relative to the repo root
+fn package_dir(
+    pkg_dep_graph: &PackageGraph,
+    workspace: &WorkspaceName,
+) -> Result<AnchoredSystemPathBuf> {
+    let mut package_dir = pkg_dep_graph
+        .package_json_path(workspace)
+        .ok_or_else(|| anyhow!("could not find workspace {}", workspace))?
+        .clone();
+    package_dir.pop();
+    Ok(package_dir)
+}
+
+/// A script running in its own process group. The package manager doesn't
+/// pass signals on to the script's children, so the whole group is killed if
+/// this is dropped before the script exits.
+struct ScriptProcess(AsyncGroupChild);
+
+impl ScriptProcess {
+    fn spawn(command: &mut tokio::process::Command) -> io::Result<Self> {
+        command.group_spawn().map(Self)
+    }
+
+    async fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.0.wait().await
+    }
+}
+
+impl Drop for ScriptProcess {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            if let Err(e) = self.0.kill() {
+                warn!("failed to kill script: {}", e);
+            }
+        }
+    }
+}
+
+/// Waits for `command` to exit while sending each line it writes to
+/// `reporter`
+async fn wait_with_output(
+    mut command: tokio::process::Command,
+    reporter: &TaskReporter,
+) -> io::Result<ExitStatus> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = ScriptProcess::spawn(&mut command)?;
+    let stdout = child.0.inner().stdout.take().expect("stdout is piped");
+    let stderr = child.0.inner().stderr.take().expect("stderr is piped");
+    let (status, stdout, stderr) = tokio::join!(
+        child.wait(),
+        forward_lines(stdout, reporter),
+        forward_lines(stderr, reporter)
+    );
+    stdout?;
+    stderr?;
+    status
+}
+
+async fn forward_lines<R: AsyncRead + Unpin>(reader: R, reporter: &TaskReporter) -> io::Result<()> {
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        reporter.output(line);
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use std::fs;
+    use std::{fs, process::Stdio, time::Duration};
 
     use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
     use tempfile::tempdir;
+    use tokio::io::{AsyncBufReadExt, BufReader};
     use turbopath::AbsoluteSystemPathBuf;
 
+    use super::ScriptProcess;
     use crate::{
-        cli::{Command, RunArgs},
+        cli::{Command, DryRunMode, RunArgs},
         commands::CommandBase,
         get_version,
         run::Run,
@@ -165,4 +868,194 @@ mod test {
         let mut run = Run::new(base);
         run.run().await
     }
+
+    #[tokio::test]
+    async fn test_dry_run() -> Result<()> {
+        let dir = tempdir()?;
+        let repo_root = AbsoluteSystemPathBuf::try_from(dir.path())?;
+        fs::write(
+            repo_root.join_component("package.json"),
+            r#"{"name": "root", "packageManager": "npm@8.19.2", "workspaces": ["packages/*"]}"#,
+        )?;
+        fs::write(
+            repo_root.join_component("turbo.json"),
+            r#"{"pipeline": {"build": {"dependsOn": ["^build"], "outputs": ["dist/**"]}}}"#,
+        )?;
+        for (name, package_json) in [
+            (
+                "web",
+                r#"{"name": "web", "scripts": {"build": "next build"}, "dependencies": {"ui": "*"}}"#,
+            ),
+            ("ui", r#"{"name": "ui", "scripts": {"build": "tsc"}}"#),
+        ] {
+            let package_dir = repo_root.join_components(&["packages", name]);
+            package_dir.create_dir_all()?;
+            fs::write(package_dir.join_component("package.json"), package_json)?;
+        }
+
+        let mut args = Args::default();
+        let run_args = RunArgs {
+            tasks: vec!["build".to_string()],
+            no_daemon: true,
+            dry_run: Some(DryRunMode::Json),
+            ..Default::default()
+        };
+        args.command = Some(Command::Run(Box::new(run_args)));
+
+        let base = CommandBase::new(args, repo_root, get_version(), UI::new(true))?;
+        let mut run = Run::new(base);
+        let mut out = Vec::new();
+        run.run_with_output(&mut out).await?;
+
+        let summary: Value = serde_json::from_slice(&out)?;
+        assert_eq!(
+            summary,
+            json!({
+                "version": "2",
+                "turboVersion": get_version(),
+                "monorepo": true,
+                "globalCacheInputs": {
+                    "rootKey": "You don't understand! I coulda had class. I coulda been a contender. I could've been somebody, instead of a bum, which is what I am.",
+                    "files": { "package.json": "e4a5edff6b433f2b0c6c35e247f4caabdc1864f1" },
+                    "hashOfExternalDependencies": "ccab0b28617f1f56",
+                    "environmentVariables": {
+                        "specified": { "env": [], "passThroughEnv": null },
+                        "configured": [],
+                        "inferred": [],
+                        "passthrough": []
+                    }
+                },
+                "packages": ["ui", "web"],
+                "envMode": "infer",
+                "frameworkInference": false,
+                "globalDotEnv": {},
+                "tasks": [
+                {
+                    "taskId": "ui#build",
+                    "task": "build",
+                    "package": "ui",
+                    "hash": "18a2f2a8a8f6928b",
+                    "inputs": { "package.json": "040157e6e32be0780525b55007cfb75d65a5a8a5" },
+                    "hashOfExternalDependencies": "ccab0b28617f1f56",
+                    "cache": {
+                        "local": false,
+                        "remote": false,
+                        "status": "MISS",
+                        "timeSaved": 0
+                    },
+                    "command": "tsc",
+                    "cliArguments": [],
+                    "outputs": ["dist/**"],
+                    "excludedOutputs": [],
+                    "logFile": "packages/ui/.turbo/turbo-build.log",
+                    "directory": "packages/ui",
+                    "dependencies": [],
+                    "dependents": ["web#build"],
+                    "resolvedTaskDefinition": {
+                        "outputs": ["dist/**"],
+                        "cache": true,
+                        "dependsOn": ["^build"],
+                        "inputs": [],
+                        "outputMode": "full",
+                        "persistent": false,
+                        "env": [],
+                        "passThroughEnv": null,
+                        "dotEnv": [],
+                        "timeout": null,
+                        "maxRetries": 0,
+                        "cpus": 1
+                    },
+                    "framework": "<FRAMEWORK DETECTION SKIPPED>",
+                    "envMode": "loose",
+                    "environmentVariables": {
+                        "specified": { "env": [], "passThroughEnv": null },
+                        "configured": [],
+                        "inferred": [],
+                        "passthrough": []
+                    },
+                    "dotEnv": {}
+                },
+                {
+                    "taskId": "web#build",
+                    "task": "build",
+                    "package": "web",
+                    "hash": "056fe9501d4ee43f",
+                    "inputs": { "package.json": "c15343c121505aa0666e7b34a653cf21710edec1" },
+                    "hashOfExternalDependencies": "ccab0b28617f1f56",
+                    "cache": {
+                        "local": false,
+                        "remote": false,
+                        "status": "MISS",
+                        "timeSaved": 0
+                    },
+                    "command": "next build",
+                    "cliArguments": [],
+                    "outputs": ["dist/**"],
+                    "excludedOutputs": [],
+                    "logFile": "packages/web/.turbo/turbo-build.log",
+                    "directory": "packages/web",
+                    "dependencies": ["ui#build"],
+                    "dependents": [],
+                    "resolvedTaskDefinition": {
+                        "outputs": ["dist/**"],
+                        "cache": true,
+                        "dependsOn": ["^build"],
+                        "inputs": [],
+                        "outputMode": "full",
+                        "persistent": false,
+                        "env": [],
+                        "passThroughEnv": null,
+                        "dotEnv": [],
+                        "timeout": null,
+                        "maxRetries": 0,
+                        "cpus": 1
+                    },
+                    "framework": "<FRAMEWORK DETECTION SKIPPED>",
+                    "envMode": "loose",
+                    "environmentVariables": {
+                        "specified": { "env": [], "passThroughEnv": null },
+                        "configured": [],
+                        "inferred": [],
+                        "passthrough": []
+                    },
+                    "dotEnv": {}
+                }
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    // Whether the process exists and hasn't exited
+    #[cfg(target_os = "linux")]
+    fn is_running(pid: &str) -> bool {
+        fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map_or(false, |stat| !stat.contains(") Z "))
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_dropping_script_kills_its_children() -> Result<()> {
+        let mut command = tokio::process::Command::new("sh");
+        command
+            .args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(Stdio::piped());
+        let mut script = ScriptProcess::spawn(&mut command)?;
+        let stdout = script.0.inner().stdout.take().expect("stdout is piped");
+        let sleep_pid = BufReader::new(stdout)
+            .lines()
+            .next_line()
+            .await?
+            .expect("script prints the pid");
+        assert!(is_running(&sleep_pid));
+
+        drop(script);
+        for _ in 0..50 {
+            if !is_running(&sleep_pid) {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("child process {} outlived its script", sleep_pid);
+    }
 }
@@ -0,0 +1,226 @@
+//! Exports a trace of a run to an OpenTelemetry collector over OTLP.
+//!
+//! A run is exported as a single span with a child span for each task.
+
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+use opentelemetry::{
+    runtime,
+    sdk::{
+        trace::{self as sdktrace, TracerProvider},
+        Resource,
+    },
+    trace::{Span, TraceContextExt, Tracer, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+
+use crate::{cli::OtlpProtocol, get_version};
+
+const HTTP_TRACES_PATH: &str = "/v1/traces";
+
+/// A task to record as part of the run's trace
+pub struct TaskSpan<'a> {
+    pub task_id: &'a str,
+    pub package: &'a str,
+    pub hash: Option<&'a str>,
+    pub cache_status: Option<&'a str>,
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+pub struct RunTracer {
+    provider: TracerProvider,
+    tracer: sdktrace::Tracer,
+}
+
+impl RunTracer {
+    /// Creates a tracer that batches spans and sends them to the collector
+    /// at `endpoint`. Must be called from within a tokio runtime.
+    pub fn new(endpoint: &str, protocol: OtlpProtocol) -> Result<Self> {
+        let exporter: SpanExporterBuilder = match protocol {
+            OtlpProtocol::Grpc => opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint)
+                .into(),
+            OtlpProtocol::Http => {
+                // Unlike the gRPC exporter, the HTTP exporter posts to the endpoint
+                // as is, so we add the signal specific path ourselves
+                let endpoint = endpoint.trim_end_matches('/');
+                let endpoint = if endpoint.ends_with(HTTP_TRACES_PATH) {
+                    endpoint.to_string()
+                } else {
+                    format!("{endpoint}{HTTP_TRACES_PATH}")
+                };
+                opentelemetry_otlp::new_exporter()
+                    .http()
+                    .with_endpoint(endpoint)
+                    .into()
+            }
+        };
+        let exporter = exporter.build_span_exporter()?;
+
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_config(sdktrace::config().with_resource(Resource::new(vec![
+                KeyValue::new("service.name", "turbo"),
+                KeyValue::new("service.version", get_version()),
+            ])))
+            .build();
+        let tracer = provider.tracer("turbo");
+
+        Ok(Self { provider, tracer })
+    }
+
+    /// Starts the span that every task in the run is nested under
+    pub fn start_run(&self, start: SystemTime, packages: usize) -> RunSpan<'_> {
+        let span = self
+            .tracer
+            .span_builder("turbo run")
+            .with_start_time(start)
+            .with_attributes(vec![KeyValue::new("turbo.run.packages", packages as i64)])
+            .start(&self.tracer);
+
+        RunSpan {
+            tracer: &self.tracer,
+            context: Context::current_with_span(span),
+        }
+    }
+
+    /// Sends any spans that haven't been exported yet. This blocks the current
+    /// thread, so it can't be used from a single threaded runtime.
+    pub fn flush(self) -> Result<()> {
+        for result in self.provider.force_flush() {
+            result.map_err(|e| anyhow!("failed to export trace: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct RunSpan<'a> {
+    tracer: &'a sdktrace::Tracer,
+    context: Context,
+}
+
+impl<'a> RunSpan<'a> {
+    pub fn record_task(&self, task: TaskSpan) {
+        let mut attributes = vec![
+            KeyValue::new("turbo.task.id", task.task_id.to_string()),
+            KeyValue::new("turbo.task.package", task.package.to_string()),
+        ];
+        if let Some(hash) = task.hash {
+            attributes.push(KeyValue::new("turbo.task.hash", hash.to_string()));
+        }
+        if let Some(cache_status) = task.cache_status {
+            attributes.push(KeyValue::new(
+                "turbo.task.cache_status",
+                cache_status.to_string(),
+            ));
+        }
+
+        let mut span = self
+            .tracer
+            .span_builder(task.task_id.to_string())
+            .with_start_time(task.start)
+            .with_attributes(attributes)
+            .start_with_context(self.tracer, &self.context);
+        span.end_with_timestamp(task.end);
+    }
+
+    pub fn end(self, end: SystemTime) {
+        self.context.span().end_with_timestamp(end);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::SocketAddr, sync::Arc, time::SystemTime};
+
+    use anyhow::Result;
+    use axum::{body::Bytes, extract::State, routing::post, Router};
+    use opentelemetry_proto::tonic::{
+        collector::trace::v1::ExportTraceServiceRequest, common::v1::any_value,
+    };
+    use prost::Message;
+    use tokio::sync::Mutex;
+
+    use super::{RunTracer, TaskSpan};
+    use crate::cli::OtlpProtocol;
+
+    type Received = Arc<Mutex<Vec<ExportTraceServiceRequest>>>;
+
+    async fn export_traces(State(received): State<Received>, body: Bytes) {
+        let request = ExportTraceServiceRequest::decode(body).unwrap();
+        received.lock().await.push(request);
+    }
+
+    fn string_attribute(
+        attributes: &[opentelemetry_proto::tonic::common::v1::KeyValue],
+        key: &str,
+    ) -> Option<String> {
+        attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .and_then(|attribute| attribute.value.as_ref()?.value.as_ref())
+            .and_then(|value| match value {
+                any_value::Value::StringValue(value) => Some(value.clone()),
+                _ => None,
+            })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_exports_run_to_collector() -> Result<()> {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/v1/traces", post(export_traces))
+            .with_state(received.clone());
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(app.into_make_service());
+        let endpoint = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let tracer = RunTracer::new(&endpoint, OtlpProtocol::Http)?;
+        let start = SystemTime::now();
+        let run = tracer.start_run(start, 1);
+        run.record_task(TaskSpan {
+            task_id: "web#build",
+            package: "web",
+            hash: Some("abc123"),
+            cache_status: Some("MISS"),
+            start,
+            end: SystemTime::now(),
+        });
+        run.end(SystemTime::now());
+        // Flushing blocks, so keep it off of the runtime's worker threads
+        tokio::task::spawn_blocking(move || tracer.flush()).await??;
+
+        let received = received.lock().await;
+        let spans = received
+            .iter()
+            .flat_map(|request| &request.resource_spans)
+            .flat_map(|resource_spans| &resource_spans.scope_spans)
+            .flat_map(|scope_spans| &scope_spans.spans)
+            .collect::<Vec<_>>();
+        assert_eq!(spans.len(), 2);
+
+        let run_span = spans.iter().find(|span| span.name == "turbo run").unwrap();
+        let task_span = spans.iter().find(|span| span.name == "web#build").unwrap();
+        assert_eq!(task_span.trace_id, run_span.trace_id);
+        assert_eq!(task_span.parent_span_id, run_span.span_id);
+        assert_eq!(
+            string_attribute(&task_span.attributes, "turbo.task.package").as_deref(),
+            Some("web")
+        );
+        assert_eq!(
+            string_attribute(&task_span.attributes, "turbo.task.hash").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(
+            string_attribute(&task_span.attributes, "turbo.task.cache_status").as_deref(),
+            Some("MISS")
+        );
+
+        Ok(())
+    }
+}
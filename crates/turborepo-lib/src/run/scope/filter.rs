@@ -0,0 +1,661 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::Serialize;
+use wax::Pattern;
+
+use super::target_selector::TargetSelector;
+use crate::package_graph::{PackageGraph, WorkspaceName, WorkspaceNode};
+
+/// Finds the workspaces that have changed between two git refs
+pub trait PackageChangeDetector {
+    fn changed_packages(&self, from_ref: &str, to_ref: &str) -> Result<HashSet<WorkspaceName>>;
+}
+
+/// Why a workspace was included by a set of filters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PackageInclusionReason {
+    /// No filters were given, so every workspace is included
+    All,
+    /// Matched a filter by name or directory
+    Selected { filter: String },
+    /// Contains files that changed in the filter's commit range
+    Changed { filter: String },
+    /// Depends on a workspace that changed in the filter's commit range
+    DependencyChanged { filter: String, dependency: String },
+    /// A dependency of a workspace that the filter included
+    Dependency { filter: String, dependent: String },
+    /// Depends on a workspace that the filter included
+    Dependent { filter: String, dependency: String },
+}
+
+impl PackageInclusionReason {
+    // Reasons that come from the filter itself rather than from walking the
+    // graph from another workspace take precedence
+    fn is_direct(&self) -> bool {
+        !matches!(self, Self::Dependency { .. } | Self::Dependent { .. })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SelectedPackages {
+    pub packages: HashMap<WorkspaceName, PackageInclusionReason>,
+    pub unused_filters: Vec<String>,
+}
+
+impl SelectedPackages {
+    fn add(&mut self, workspace: WorkspaceName, reason: PackageInclusionReason) {
+        match self.packages.get(&workspace) {
+            Some(existing) if existing.is_direct() || !reason.is_direct() => {}
+            _ => {
+                self.packages.insert(workspace, reason);
+            }
+        }
+    }
+}
+
+/// Information inferred from the directory turbo was invoked in about which
+/// workspaces are of interest
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PackageInference {
+    // Filters without a name pattern get this name
+    pub package_name: Option<String>,
+    // Used as a prefix for filters with a directory, or as a directory of
+    // interest for filters without one
+    pub directory_root: String,
+}
+
+impl PackageInference {
+    fn apply(&self, selector: &mut TargetSelector) {
+        if selector.name_pattern.is_some() {
+            // The selector references a workspace name, don't apply inference
+            return;
+        }
+        if let Some(package_name) = &self.package_name {
+            selector.name_pattern = Some(package_name.clone());
+        }
+        if let Some(parent_dir) = &selector.parent_dir {
+            selector.parent_dir = Some(join(&self.directory_root, parent_dir));
+        } else if self.package_name.is_none() {
+            // We didn't find a single workspace, so select everything under the
+            // inferred directory
+            selector.parent_dir = Some(join(&self.directory_root, "**"));
+        }
+    }
+}
+
+pub struct FilterResolver<'a, T> {
+    pkg_graph: &'a PackageGraph,
+    inference: Option<PackageInference>,
+    change_detector: T,
+}
+
+impl<'a, T: PackageChangeDetector> FilterResolver<'a, T> {
+    pub fn new(
+        pkg_graph: &'a PackageGraph,
+        inference: Option<PackageInference>,
+        change_detector: T,
+    ) -> Self {
+        Self {
+            pkg_graph,
+            inference,
+            change_detector,
+        }
+    }
+
+    /// Compiles the filter patterns and applies them to the package graph
+    pub fn get_packages_from_patterns(&self, patterns: &[String]) -> Result<SelectedPackages> {
+        let mut selectors = patterns
+            .iter()
+            .map(|pattern| TargetSelector::parse(pattern))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(inference) = &self.inference {
+            // If there are no patterns, synthesize one that inference can fill in
+            if selectors.is_empty() {
+                selectors.push(TargetSelector::default());
+            }
+            for selector in &mut selectors {
+                inference.apply(selector);
+            }
+        }
+
+        if selectors.is_empty() {
+            return Ok(SelectedPackages::default());
+        }
+
+        self.filter_graph(&selectors)
+    }
+
+    fn filter_graph(&self, selectors: &[TargetSelector]) -> Result<SelectedPackages> {
+        let (exclude_selectors, include_selectors): (Vec<_>, Vec<_>) =
+            selectors.iter().partition(|selector| selector.exclude);
+
+        let mut selected = if include_selectors.is_empty() {
+            let mut all = SelectedPackages::default();
+            for (workspace, _) in self.pkg_graph.workspaces() {
+                all.add(workspace.clone(), PackageInclusionReason::All);
+            }
+            all
+        } else {
+            self.filter_graph_with_selectors(&include_selectors)?
+        };
+
+        let excluded = self.filter_graph_with_selectors(&exclude_selectors)?;
+        for workspace in excluded.packages.keys() {
+            selected.packages.remove(workspace);
+        }
+        selected.unused_filters.extend(excluded.unused_filters);
+
+        Ok(selected)
+    }
+
+    fn filter_graph_with_selectors(
+        &self,
+        selectors: &[&TargetSelector],
+    ) -> Result<SelectedPackages> {
+        let mut selected = SelectedPackages::default();
+
+        for selector in selectors {
+            let entry_packages = self.filter_graph_with_selector(selector)?;
+            if entry_packages.is_empty() {
+                selected.unused_filters.push(selector.raw.clone());
+            }
+
+            for (workspace, reason) in entry_packages {
+                let node = WorkspaceNode::Workspace(workspace.clone());
+                let name = workspace_name(&workspace);
+
+                if selector.include_dependencies {
+                    for dependency in self.dependencies(&node)? {
+                        selected.add(
+                            dependency,
+                            PackageInclusionReason::Dependency {
+                                filter: selector.raw.clone(),
+                                dependent: name.clone(),
+                            },
+                        );
+                    }
+                }
+                if selector.include_dependents {
+                    let dependents = self
+                        .pkg_graph
+                        .dependents(&node)
+                        .ok_or_else(|| anyhow!("failed to get dependents of package {}", name))?;
+                    for dependent in dependents {
+                        let WorkspaceNode::Workspace(dependent) = dependent else {
+                            continue;
+                        };
+                        if selector.include_dependencies {
+                            let dependent_node = WorkspaceNode::Workspace(dependent.clone());
+                            for dependency in self.dependencies(&dependent_node)? {
+                                selected.add(
+                                    dependency,
+                                    PackageInclusionReason::Dependency {
+                                        filter: selector.raw.clone(),
+                                        dependent: workspace_name(dependent),
+                                    },
+                                );
+                            }
+                        }
+                        selected.add(
+                            dependent.clone(),
+                            PackageInclusionReason::Dependent {
+                                filter: selector.raw.clone(),
+                                dependency: name.clone(),
+                            },
+                        );
+                    }
+                }
+
+                let walked = selector.include_dependencies || selector.include_dependents;
+                if !walked || !selector.exclude_self {
+                    selected.add(workspace, reason);
+                }
+            }
+        }
+
+        Ok(selected)
+    }
+
+    fn filter_graph_with_selector(
+        &self,
+        selector: &TargetSelector,
+    ) -> Result<HashMap<WorkspaceName, PackageInclusionReason>> {
+        if selector.match_dependencies {
+            self.filter_subtrees_with_selector(selector)
+        } else {
+            self.filter_nodes_with_selector(selector)
+        }
+    }
+
+    /// Returns the workspaces that match a selector
+    fn filter_nodes_with_selector(
+        &self,
+        selector: &TargetSelector,
+    ) -> Result<HashMap<WorkspaceName, PackageInclusionReason>> {
+        let mut entry_packages = HashSet::new();
+        let mut selector_was_used = false;
+        let reason = if let Some(from_ref) = &selector.from_ref {
+            selector_was_used = true;
+            let changed_packages = self
+                .change_detector
+                .changed_packages(from_ref, selector.to_ref())?;
+            match &selector.parent_dir {
+                Some(parent_dir) => {
+                    let in_parent_dir = self.workspaces_in_directory(parent_dir)?;
+                    entry_packages.extend(
+                        changed_packages
+                            .into_iter()
+                            .filter(|workspace| in_parent_dir.contains(workspace)),
+                    );
+                }
+                None => entry_packages.extend(changed_packages),
+            }
+            PackageInclusionReason::Changed {
+                filter: selector.raw.clone(),
+            }
+        } else {
+            if let Some(parent_dir) = &selector.parent_dir {
+                selector_was_used = true;
+                entry_packages.extend(self.workspaces_in_directory(parent_dir)?);
+            }
+            PackageInclusionReason::Selected {
+                filter: selector.raw.clone(),
+            }
+        };
+
+        if let Some(name_pattern) = &selector.name_pattern {
+            if !selector_was_used {
+                selector_was_used = true;
+                entry_packages = self
+                    .pkg_graph
+                    .workspaces()
+                    .map(|(workspace, _)| workspace.clone())
+                    .collect();
+            }
+            entry_packages = match_package_names(name_pattern, entry_packages)?;
+        }
+
+        if !selector_was_used {
+            return Err(anyhow!("invalid selector: {}", selector.raw));
+        }
+
+        Ok(entry_packages
+            .into_iter()
+            .map(|workspace| (workspace, reason.clone()))
+            .collect())
+    }
+
+    /// Returns the workspaces that match a selector where the workspace or
+    /// any of its dependencies changed
+    fn filter_subtrees_with_selector(
+        &self,
+        selector: &TargetSelector,
+    ) -> Result<HashMap<WorkspaceName, PackageInclusionReason>> {
+        let from_ref = selector.from_ref.as_deref().unwrap_or_default();
+        let changed_packages = self
+            .change_detector
+            .changed_packages(from_ref, selector.to_ref())?;
+
+        let mut entry_packages = match &selector.parent_dir {
+            Some(parent_dir) => self.workspaces_in_directory(parent_dir)?,
+            None => self
+                .pkg_graph
+                .workspaces()
+                .map(|(workspace, _)| workspace.clone())
+                .collect(),
+        };
+        if let Some(name_pattern) = &selector.name_pattern {
+            entry_packages = match_package_names(name_pattern, entry_packages)?;
+        }
+
+        let mut roots = HashMap::new();
+        for workspace in entry_packages {
+            if !selector.exclude_self && changed_packages.contains(&workspace) {
+                roots.insert(
+                    workspace,
+                    PackageInclusionReason::Changed {
+                        filter: selector.raw.clone(),
+                    },
+                );
+                continue;
+            }
+            let node = WorkspaceNode::Workspace(workspace.clone());
+            let mut changed_dependencies = self
+                .dependencies(&node)?
+                .into_iter()
+                .filter(|dependency| changed_packages.contains(dependency))
+                .collect::<Vec<_>>();
+            changed_dependencies.sort();
+            if let Some(dependency) = changed_dependencies.first() {
+                roots.insert(
+                    workspace,
+                    PackageInclusionReason::DependencyChanged {
+                        filter: selector.raw.clone(),
+                        dependency: workspace_name(dependency),
+                    },
+                );
+            }
+        }
+
+        Ok(roots)
+    }
+
+    fn dependencies(&self, node: &WorkspaceNode) -> Result<Vec<WorkspaceName>> {
+        let dependencies = self.pkg_graph.dependencies(node).ok_or_else(|| {
+            anyhow!(
+                "failed to get dependencies of package {}",
+                match node {
+                    WorkspaceNode::Workspace(workspace) => workspace_name(workspace),
+                    WorkspaceNode::Root => workspace_name(&WorkspaceName::Root),
+                }
+            )
+        })?;
+        Ok(dependencies
+            .into_iter()
+            .filter_map(|dependency| match dependency {
+                WorkspaceNode::Workspace(workspace) => Some(workspace.clone()),
+                WorkspaceNode::Root => None,
+            })
+            .collect())
+    }
+
+    /// Returns the workspaces whose directory matches `parent_dir`, a glob
+    /// relative to the repo root
+    fn workspaces_in_directory(&self, parent_dir: &str) -> Result<HashSet<WorkspaceName>> {
+        let Some(parent_dir) = clean(parent_dir) else {
+            // The directory is outside of the repo, so it can't contain any workspaces
+            return Ok(HashSet::new());
+        };
+        if parent_dir == "." {
+            return Ok([WorkspaceName::Root].into_iter().collect());
+        }
+        let glob = wax::Glob::new(&parent_dir)
+            .map_err(|e| anyhow!("invalid directory filter {}: {}", parent_dir, e))?;
+
+        let mut workspaces = HashSet::new();
+        for (workspace, entry) in self.pkg_graph.workspaces() {
+            if matches!(workspace, WorkspaceName::Root) {
+                continue;
+            }
+            let Some(dir) = entry.package_json_path().parent() else {
+                continue;
+            };
+            // The glob uses forward slashes, so it has to match a unix path
+            let dir = dir.to_owned().to_unix()?;
+            if glob.is_match(dir.as_str()) {
+                workspaces.insert(workspace.clone());
+            }
+        }
+        Ok(workspaces)
+    }
+}
+
+/// The name a workspace is referred to by in filters
+pub fn workspace_name(workspace: &WorkspaceName) -> String {
+    match workspace {
+        WorkspaceName::Root => crate::run::task_id::ROOT_PKG_NAME.to_string(),
+        WorkspaceName::Other(name) => name.clone(),
+    }
+}
+
+fn match_package_names(
+    pattern: &str,
+    workspaces: HashSet<WorkspaceName>,
+) -> Result<HashSet<WorkspaceName>> {
+    let matcher = matcher_from_pattern(pattern)?;
+    let matched = workspaces
+        .iter()
+        .filter(|workspace| matcher(&workspace_name(workspace)))
+        .cloned()
+        .collect::<HashSet<_>>();
+
+    if matched.is_empty() && !pattern.starts_with('@') && !pattern.contains('/') {
+        // We got no matches and the pattern isn't a scoped package. Check if
+        // there is exactly one scoped package that does match
+        let scoped_matcher = matcher_from_pattern(&format!("@*/{}", pattern))?;
+        let scoped = workspaces
+            .into_iter()
+            .filter(|workspace| scoped_matcher(&workspace_name(workspace)))
+            .collect::<Vec<_>>();
+        if scoped.len() == 1 {
+            return Ok(scoped.into_iter().collect());
+        }
+    }
+
+    Ok(matched)
+}
+
+type Matcher = Box<dyn Fn(&str) -> bool>;
+
+fn matcher_from_pattern(pattern: &str) -> Result<Matcher> {
+    if pattern == "*" {
+        return Ok(Box::new(|_| true));
+    }
+    if !pattern.contains('*') {
+        let pattern = pattern.to_string();
+        return Ok(Box::new(move |name| name == pattern));
+    }
+    let regex = Regex::new(&format!(
+        "^{}$",
+        regex::escape(pattern).replace("\\*", ".*")
+    ))
+    .map_err(|e| {
+        anyhow!(
+            "failed to compile filter pattern to regex {}: {}",
+            pattern,
+            e
+        )
+    })?;
+    Ok(Box::new(move |name| regex.is_match(name)))
+}
+
+fn join(base: &str, path: &str) -> String {
+    if base.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}/{}", base, path)
+    }
+}
+
+/// Removes `.` and `..` components from a relative unix path. Returns `None`
+/// if the path escapes the directory it is relative to.
+fn clean(path: &str) -> Option<String> {
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            component => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        Some(".".to_string())
+    } else {
+        Some(components.join("/"))
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use std::collections::{HashMap, HashSet};
+
+    use anyhow::Result;
+    use serde_json::json;
+    use test_case::test_case;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::{FilterResolver, PackageChangeDetector, PackageInclusionReason, PackageInference};
+    use crate::{
+        package_graph::{PackageGraph, WorkspaceName},
+        package_json::PackageJson,
+        package_manager::PackageManager,
+    };
+
+    struct StubChangeDetector(&'static [&'static str]);
+
+    impl PackageChangeDetector for StubChangeDetector {
+        fn changed_packages(
+            &self,
+            _from_ref: &str,
+            _to_ref: &str,
+        ) -> Result<HashSet<WorkspaceName>> {
+            Ok(self
+                .0
+                .iter()
+                .map(|name| WorkspaceName::from(*name))
+                .collect())
+        }
+    }
+
+    // web -> ui -> utils, docs -> ui, @scope/config has no dependencies
+    pub(in crate::run::scope) fn package_graph() -> PackageGraph {
+        let root =
+            AbsoluteSystemPathBuf::new(if cfg!(windows) { r"C:\repo" } else { "/repo" }).unwrap();
+        let workspaces = [
+            (
+                "apps/web",
+                json!({"name": "web", "dependencies": {"ui": "*"}}),
+            ),
+            (
+                "apps/docs",
+                json!({"name": "docs", "dependencies": {"ui": "*"}}),
+            ),
+            (
+                "packages/ui",
+                json!({"name": "ui", "dependencies": {"utils": "*"}}),
+            ),
+            ("packages/utils", json!({"name": "utils"})),
+            ("packages/config", json!({"name": "@scope/config"})),
+        ];
+        let package_jsons = workspaces
+            .into_iter()
+            .map(|(dir, package_json)| {
+                let dir = dir.split('/').collect::<Vec<_>>();
+                (
+                    root.join_components(&dir).join_component("package.json"),
+                    PackageJson::from_value(package_json).unwrap(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        PackageGraph::builder(
+            &root,
+            PackageJson::from_value(json!({"name": "root"})).unwrap(),
+        )
+        .with_package_manger(Some(PackageManager::Npm))
+        .with_package_jsons(Some(package_jsons))
+        .build()
+        .unwrap()
+    }
+
+    fn names(packages: &HashMap<WorkspaceName, PackageInclusionReason>) -> Vec<String> {
+        let mut names = packages
+            .keys()
+            .map(super::workspace_name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test_case(&["web"], &["web"] ; "name")]
+    #[test_case(&["config"], &["@scope/config"] ; "unique scoped name")]
+    #[test_case(&["*"], &["//", "@scope/config", "docs", "ui", "utils", "web"] ; "wildcard")]
+    #[test_case(&["web..."], &["ui", "utils", "web"] ; "dependencies")]
+    #[test_case(&["web^..."], &["ui", "utils"] ; "dependencies excluding self")]
+    #[test_case(&["...ui"], &["docs", "ui", "web"] ; "dependents")]
+    #[test_case(&["...utils..."], &["docs", "ui", "utils", "web"] ; "dependents and their dependencies")]
+    #[test_case(&["{apps/*}"], &["docs", "web"] ; "directory")]
+    #[test_case(&["./packages/ui"], &["ui"] ; "location")]
+    #[test_case(&["."], &["//"] ; "root location")]
+    #[test_case(&["//"], &["//"] ; "root name")]
+    #[test_case(&["!web"], &["//", "@scope/config", "docs", "ui", "utils"] ; "exclude only")]
+    #[test_case(&["...ui", "!docs"], &["ui", "web"] ; "exclude")]
+    #[test_case(&["[HEAD^]"], &["utils"] ; "changed")]
+    #[test_case(&["...[HEAD^]"], &["docs", "ui", "utils", "web"] ; "dependents of changed")]
+    #[test_case(&["{apps/*}[HEAD^]"], &[] ; "changed in directory")]
+    #[test_case(&["web...[HEAD^]"], &["web"] ; "dependency changed")]
+    fn test_filter(patterns: &[&str], expected: &[&str]) {
+        let pkg_graph = package_graph();
+        let resolver = FilterResolver::new(&pkg_graph, None, StubChangeDetector(&["utils"]));
+        let patterns = patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let selected = resolver.get_packages_from_patterns(&patterns).unwrap();
+        assert_eq!(names(&selected.packages), expected);
+    }
+
+    #[test]
+    fn test_inclusion_reasons() {
+        let pkg_graph = package_graph();
+        let resolver = FilterResolver::new(&pkg_graph, None, StubChangeDetector(&["ui"]));
+        let selected = resolver
+            .get_packages_from_patterns(&["...[main]...".to_string(), "docs".to_string()])
+            .unwrap();
+
+        let filter = "...[main]...".to_string();
+        let reason = |name: &str| selected.packages.get(&WorkspaceName::from(name)).cloned();
+        assert_eq!(
+            reason("ui"),
+            Some(PackageInclusionReason::Changed {
+                filter: filter.clone()
+            })
+        );
+        assert_eq!(
+            reason("utils"),
+            Some(PackageInclusionReason::Dependency {
+                filter: filter.clone(),
+                dependent: "ui".to_string()
+            })
+        );
+        assert_eq!(
+            reason("web"),
+            Some(PackageInclusionReason::Dependent {
+                filter,
+                dependency: "ui".to_string()
+            })
+        );
+        // Matching a filter directly takes precedence over being a dependent
+        assert_eq!(
+            reason("docs"),
+            Some(PackageInclusionReason::Selected {
+                filter: "docs".to_string()
+            })
+        );
+        assert!(selected.unused_filters.is_empty());
+    }
+
+    #[test]
+    fn test_unused_filters() {
+        let pkg_graph = package_graph();
+        let resolver = FilterResolver::new(&pkg_graph, None, StubChangeDetector(&[]));
+        let selected = resolver
+            .get_packages_from_patterns(&["missing".to_string()])
+            .unwrap();
+        assert!(selected.packages.is_empty());
+        assert_eq!(selected.unused_filters, vec!["missing".to_string()]);
+    }
+
+    #[test_case(Some("web"), "apps/web", &[], &["web"] ; "inferred package")]
+    #[test_case(None, "apps", &[], &["docs", "web"] ; "inferred directory")]
+    #[test_case(None, "apps", &["{web}"], &["web"] ; "directory filter is relative to inferred directory")]
+    #[test_case(Some("web"), "apps/web", &["ui"], &["ui"] ; "name filter ignores inference")]
+    fn test_inference(
+        package_name: Option<&str>,
+        directory_root: &str,
+        patterns: &[&str],
+        expected: &[&str],
+    ) {
+        let pkg_graph = package_graph();
+        let inference = PackageInference {
+            package_name: package_name.map(|name| name.to_string()),
+            directory_root: directory_root.to_string(),
+        };
+        let resolver = FilterResolver::new(&pkg_graph, Some(inference), StubChangeDetector(&[]));
+        let patterns = patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let selected = resolver.get_packages_from_patterns(&patterns).unwrap();
+        assert_eq!(names(&selected.packages), expected);
+    }
+}
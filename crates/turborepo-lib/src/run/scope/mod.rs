@@ -0,0 +1,263 @@
+mod filter;
+mod target_selector;
+
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+pub use filter::{workspace_name, PackageInclusionReason, SelectedPackages};
+use filter::{FilterResolver, PackageChangeDetector, PackageInference};
+use tracing::debug;
+use turbopath::{AbsoluteSystemPath, AnchoredSystemPathBuf};
+use turborepo_scm::SCM;
+use wax::Pattern;
+
+use crate::{
+    commands::CommandBase,
+    opts::ScopeOpts,
+    package_graph::{self, WorkspaceName},
+};
+
+// turbo.json and the root package.json are always global dependencies
+const DEFAULT_GLOBAL_DEPS: &[&str] = &["turbo.json", "package.json"];
+
+/// Resolves the filters in `opts` to the names of the selected workspaces
+pub fn resolve_packages(
+    opts: &ScopeOpts,
+    base: &CommandBase,
+    pkg_graph: &package_graph::PackageGraph,
+    scm: &SCM,
+) -> Result<HashSet<String>> {
+    let selected = select_packages(opts, &base.repo_root, pkg_graph, scm)?;
+    Ok(selected.packages.keys().map(workspace_name).collect())
+}
+
+/// Resolves the filters in `opts` to the selected workspaces along with why
+/// each one was selected. The root workspace is only selected when a filter
+/// matches it.
+pub fn select_packages(
+    opts: &ScopeOpts,
+    repo_root: &AbsoluteSystemPath,
+    pkg_graph: &package_graph::PackageGraph,
+    scm: &SCM,
+) -> Result<SelectedPackages> {
+    let inference = calculate_inference(opts.pkg_inference_root.as_ref(), pkg_graph);
+    let change_detector = ScmChangeDetector::new(
+        repo_root,
+        scm,
+        pkg_graph,
+        &opts.global_deps,
+        &opts.ignore_patterns,
+    )?;
+    let resolver = FilterResolver::new(pkg_graph, inference, change_detector);
+
+    let mut selected = resolver.get_packages_from_patterns(&opts.filter_patterns())?;
+    if opts.is_all_packages() {
+        // no filters specified, select every workspace except the root
+        for (workspace, _) in pkg_graph.workspaces() {
+            if matches!(workspace, WorkspaceName::Root) {
+                continue;
+            }
+            selected
+                .packages
+                .insert(workspace.clone(), PackageInclusionReason::All);
+        }
+    }
+
+    Ok(selected)
+}
+
+fn calculate_inference(
+    pkg_inference_root: Option<&AnchoredSystemPathBuf>,
+    pkg_graph: &package_graph::PackageGraph,
+) -> Option<PackageInference> {
+    // No inference specified, no need to calculate anything
+    let pkg_inference_root = pkg_inference_root?;
+    debug!(
+        "Using {} as a basis for selecting packages",
+        pkg_inference_root
+    );
+    let directory_root = to_unix_string(pkg_inference_root);
+
+    for (workspace, entry) in pkg_graph.workspaces() {
+        // We skip over the root workspace as the inferred path will always be
+        // below it
+        let WorkspaceName::Other(name) = workspace else {
+            continue;
+        };
+        let Some(dir) = entry.package_json_path().parent() else {
+            continue;
+        };
+        if is_within(&directory_root, &to_unix_string(dir)) {
+            // Set both. The user might have set a directory filter, in which
+            // case we *should* fail to find any packages, but we should do so
+            // in a consistent manner
+            return Some(PackageInference {
+                package_name: Some(name.clone()),
+                directory_root,
+            });
+        }
+    }
+
+    Some(PackageInference {
+        package_name: None,
+        directory_root,
+    })
+}
+
+/// Finds changed workspaces by mapping the files git reports as changed to
+/// the workspaces that contain them
+struct ScmChangeDetector<'a> {
+    turbo_root: &'a AbsoluteSystemPath,
+    scm: &'a SCM,
+    pkg_graph: &'a package_graph::PackageGraph,
+    global_deps: Vec<wax::Glob<'static>>,
+    ignore_patterns: Vec<wax::Glob<'static>>,
+}
+
+impl<'a> ScmChangeDetector<'a> {
+    fn new(
+        turbo_root: &'a AbsoluteSystemPath,
+        scm: &'a SCM,
+        pkg_graph: &'a package_graph::PackageGraph,
+        global_deps: &[String],
+        ignore_patterns: &[String],
+    ) -> Result<Self> {
+        let global_deps = DEFAULT_GLOBAL_DEPS
+            .iter()
+            .copied()
+            .chain(global_deps.iter().map(|glob| glob.as_str()))
+            .map(|glob| compile_glob(glob, "global deps"))
+            .collect::<Result<_>>()?;
+        let ignore_patterns = ignore_patterns
+            .iter()
+            .map(|glob| compile_glob(glob, "ignore"))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            turbo_root,
+            scm,
+            pkg_graph,
+            global_deps,
+            ignore_patterns,
+        })
+    }
+
+    fn all_packages(&self) -> HashSet<WorkspaceName> {
+        self.pkg_graph
+            .workspaces()
+            .map(|(workspace, _)| workspace.clone())
+            .collect()
+    }
+}
+
+impl<'a> PackageChangeDetector for ScmChangeDetector<'a> {
+    fn changed_packages(&self, from_ref: &str, to_ref: &str) -> Result<HashSet<WorkspaceName>> {
+        // We could filter changed files at the git level, since it's possible
+        // that the changes we're interested in are scoped, but we need to handle
+        // global dependencies changing as well.
+        let mut changed_files = self
+            .scm
+            .changed_files(self.turbo_root, Some(from_ref), to_ref)?
+            .iter()
+            .map(to_unix_string)
+            .collect::<Vec<_>>();
+        changed_files.sort();
+
+        let is_match =
+            |globs: &[wax::Glob], file: &str| globs.iter().any(|glob| glob.is_match(file));
+        if changed_files
+            .iter()
+            .any(|file| is_match(&self.global_deps, file))
+        {
+            return Ok(self.all_packages());
+        }
+
+        // We can't tell yet which workspaces a lockfile change affects, so
+        // assume that all of them changed
+        let lockfile = self
+            .pkg_graph
+            .package_manager()
+            .lockfile_name(self.turbo_root);
+        if changed_files.iter().any(|file| file == lockfile) {
+            return Ok(self.all_packages());
+        }
+
+        let workspace_dirs = self
+            .pkg_graph
+            .workspaces()
+            .filter(|(workspace, _)| !matches!(workspace, WorkspaceName::Root))
+            .filter_map(|(workspace, entry)| {
+                let dir = entry.package_json_path().parent()?;
+                Some((workspace, to_unix_string(dir)))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(changed_files
+            .iter()
+            .filter(|file| !is_match(&self.ignore_patterns, file))
+            .map(|file| {
+                workspace_dirs
+                    .iter()
+                    .find(|(_, dir)| is_within(file, dir))
+                    .map_or(WorkspaceName::Root, |(workspace, _)| (*workspace).clone())
+            })
+            .collect())
+    }
+}
+
+fn compile_glob(glob: &str, kind: &str) -> Result<wax::Glob<'static>> {
+    wax::Glob::new(glob)
+        .map(wax::Glob::into_owned)
+        .map_err(|e| anyhow!("invalid {} glob {}: {}", kind, glob, e))
+}
+
+fn to_unix_string(path: impl AsRef<std::path::Path>) -> String {
+    path.as_ref().to_string_lossy().replace('\\', "/")
+}
+
+// Whether `path` is `dir` or is inside of it, both relative unix paths
+fn is_within(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod test {
+    use turbopath::AbsoluteSystemPathBuf;
+    use turborepo_scm::SCM;
+
+    use super::{filter::test::package_graph, select_packages, workspace_name};
+    use crate::opts::ScopeOpts;
+
+    fn selected_names(filter_patterns: &[&str]) -> Vec<String> {
+        let pkg_graph = package_graph();
+        let repo_root =
+            AbsoluteSystemPathBuf::new(if cfg!(windows) { r"C:\repo" } else { "/repo" }).unwrap();
+        let scm = SCM::new(&repo_root);
+        let opts = ScopeOpts {
+            filter_patterns: filter_patterns.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        };
+        let selected = select_packages(&opts, &repo_root, &pkg_graph, &scm).unwrap();
+        let mut names = selected
+            .packages
+            .keys()
+            .map(workspace_name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_no_filter_excludes_root() {
+        assert_eq!(
+            selected_names(&[]),
+            &["@scope/config", "docs", "ui", "utils", "web"]
+        );
+    }
+
+    #[test]
+    fn test_root_filter_selects_root() {
+        assert_eq!(selected_names(&["//"]), &["//"]);
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{anyhow, Result};
+use lazy_regex::{lazy_regex, Lazy};
+use regex::Regex;
+
+static TARGET_SELECTOR_PATTERN: Lazy<Regex> = lazy_regex!(
+    r"^(?P<name>[^.](?:[^{}\[\]]*[^{}\[\].])?)?(?P<directory>\{[^}]*\})?(?P<commits>(?:\.{3})?\[[^\]]+\])?$"
+);
+
+/// A single `--filter` selector. The syntax mirrors pnpm's.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TargetSelector {
+    pub include_dependencies: bool,
+    pub match_dependencies: bool,
+    pub include_dependents: bool,
+    pub exclude: bool,
+    pub exclude_self: bool,
+    // Relative to the repo root, using unix separators
+    pub parent_dir: Option<String>,
+    pub name_pattern: Option<String>,
+    pub from_ref: Option<String>,
+    pub to_ref_override: Option<String>,
+    pub raw: String,
+}
+
+impl TargetSelector {
+    /// The git ref to use as the upper bound when finding changed packages
+    pub fn to_ref(&self) -> &str {
+        self.to_ref_override.as_deref().unwrap_or("HEAD")
+    }
+
+    pub fn parse(raw_selector: &str) -> Result<Self> {
+        let (selector, exclude) = match raw_selector.strip_prefix('!') {
+            Some(selector) => (selector, true),
+            None => (raw_selector, false),
+        };
+
+        let mut exclude_self = false;
+        let (selector, include_dependencies) = match selector.strip_suffix("...") {
+            Some(selector) => match selector.strip_suffix('^') {
+                Some(selector) => {
+                    exclude_self = true;
+                    (selector, true)
+                }
+                None => (selector, true),
+            },
+            None => (selector, false),
+        };
+        let (selector, include_dependents) = match selector.strip_prefix("...") {
+            Some(selector) => match selector.strip_prefix('^') {
+                Some(selector) => {
+                    exclude_self = true;
+                    (selector, true)
+                }
+                None => (selector, true),
+            },
+            None => (selector, false),
+        };
+
+        let Some(captures) = TARGET_SELECTOR_PATTERN.captures(selector) else {
+            if is_selector_by_location(selector) {
+                return Ok(Self {
+                    exclude,
+                    include_dependencies,
+                    include_dependents,
+                    parent_dir: Some(selector.replace('\\', "/")),
+                    raw: raw_selector.to_string(),
+                    ..Default::default()
+                });
+            }
+            return Ok(Self {
+                exclude,
+                exclude_self,
+                include_dependencies,
+                include_dependents,
+                name_pattern: Some(selector.to_string()).filter(|name| !name.is_empty()),
+                raw: raw_selector.to_string(),
+                ..Default::default()
+            });
+        };
+
+        let name_pattern = captures
+            .name("name")
+            .map(|name| name.as_str().to_string())
+            .filter(|name| !name.is_empty());
+
+        let mut parent_dir = None;
+        if let Some(directory) = captures.name("directory") {
+            // trim {}
+            let directory = &directory.as_str()[1..directory.as_str().len() - 1];
+            if directory.is_empty() {
+                return Err(anyhow!("empty path specification"));
+            }
+            let directory = directory.replace('\\', "/");
+            if directory.starts_with('/') || is_windows_absolute(&directory) {
+                return Err(anyhow!("invalid path specification: {}", directory));
+            }
+            parent_dir = Some(directory);
+        }
+
+        let mut from_ref = None;
+        let mut to_ref_override = None;
+        let mut match_dependencies = false;
+        if let Some(commits) = captures.name("commits") {
+            let mut commits = commits.as_str();
+            if let Some(rest) = commits.strip_prefix("...") {
+                if parent_dir.is_none() && name_pattern.is_none() {
+                    return Err(anyhow!(
+                        "cannot use match dependencies without specifying either a directory or \
+                         package"
+                    ));
+                }
+                match_dependencies = true;
+                commits = rest;
+            }
+            // strip []
+            let commits = &commits[1..commits.len() - 1];
+            match commits.split_once("...") {
+                Some((from, to)) if !to.contains("...") => {
+                    from_ref = Some(from.to_string());
+                    to_ref_override = Some(to.to_string());
+                }
+                _ => from_ref = Some(commits.to_string()),
+            }
+        }
+
+        Ok(Self {
+            from_ref,
+            to_ref_override,
+            exclude,
+            exclude_self,
+            include_dependencies,
+            match_dependencies,
+            include_dependents,
+            name_pattern,
+            parent_dir,
+            raw: raw_selector.to_string(),
+        })
+    }
+}
+
+/// Returns true if the selector is a filesystem location, i.e. `.`, `..` or
+/// a path starting with either of them
+fn is_selector_by_location(selector: &str) -> bool {
+    let Some(rest) = selector.strip_prefix('.') else {
+        return false;
+    };
+    let rest = rest.strip_prefix('.').unwrap_or(rest);
+    rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\')
+}
+
+fn is_windows_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::TargetSelector;
+
+    #[test_case("foo", TargetSelector { name_pattern: Some("foo".into()), ..Default::default() } ; "name")]
+    #[test_case("foo...", TargetSelector { name_pattern: Some("foo".into()), include_dependencies: true, ..Default::default() } ; "dependencies")]
+    #[test_case("...foo", TargetSelector { name_pattern: Some("foo".into()), include_dependents: true, ..Default::default() } ; "dependents")]
+    #[test_case("...foo...", TargetSelector { name_pattern: Some("foo".into()), include_dependents: true, include_dependencies: true, ..Default::default() } ; "dependents and dependencies")]
+    #[test_case("foo^...", TargetSelector { name_pattern: Some("foo".into()), include_dependencies: true, exclude_self: true, ..Default::default() } ; "dependencies excluding self")]
+    #[test_case("!foo", TargetSelector { name_pattern: Some("foo".into()), exclude: true, ..Default::default() } ; "exclude")]
+    #[test_case("{packages/*}", TargetSelector { parent_dir: Some("packages/*".into()), ..Default::default() } ; "directory")]
+    #[test_case("./packages/foo", TargetSelector { parent_dir: Some("./packages/foo".into()), ..Default::default() } ; "location")]
+    #[test_case("..", TargetSelector { parent_dir: Some("..".into()), ..Default::default() } ; "parent location")]
+    #[test_case("[main]", TargetSelector { from_ref: Some("main".into()), ..Default::default() } ; "commits")]
+    #[test_case("[main...feature]", TargetSelector { from_ref: Some("main".into()), to_ref_override: Some("feature".into()), ..Default::default() } ; "commit range")]
+    #[test_case("...[HEAD^]", TargetSelector { from_ref: Some("HEAD^".into()), include_dependents: true, ..Default::default() } ; "dependents of changed")]
+    #[test_case("foo...[main]", TargetSelector { name_pattern: Some("foo".into()), from_ref: Some("main".into()), match_dependencies: true, ..Default::default() } ; "match dependencies")]
+    #[test_case("@scope/foo{packages/*}[main]", TargetSelector { name_pattern: Some("@scope/foo".into()), parent_dir: Some("packages/*".into()), from_ref: Some("main".into()), ..Default::default() } ; "all parts")]
+    fn test_parse(raw: &str, expected: TargetSelector) {
+        let expected = TargetSelector {
+            raw: raw.to_string(),
+            ..expected
+        };
+        assert_eq!(TargetSelector::parse(raw).unwrap(), expected);
+    }
+
+    #[test_case("{}" ; "empty directory")]
+    #[test_case("......[main]" ; "match dependencies without name or directory")]
+    fn test_parse_invalid(raw: &str) {
+        assert!(TargetSelector::parse(raw).is_err());
+    }
+}
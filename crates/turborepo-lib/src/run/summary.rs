@@ -0,0 +1,377 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+use serde::{Serialize, Serializer};
+use turbopath::RelativeUnixPathBuf;
+use turborepo_env::DetailedMap;
+
+use crate::{
+    cli::EnvMode,
+    engine::{TaskExecution, TaskOutcome},
+    framework::Framework,
+    run::cache::{CacheSource, CacheStatus},
+    task_graph::TaskDefinition,
+    ui::{BOLD, GREY, UI},
+};
+
+// NOTE: When changing this, please ensure that the server side is updated to
+// handle the new version on vercel.com this is required to ensure safe handling
+// of env vars (unknown run summary versions will be ignored on the server)
+const RUN_SUMMARY_SCHEMA_VERSION: &str = "2";
+
+// Used to identify when a workspace doesn't detect a framework
+pub const NO_FRAMEWORK_DETECTED: &str = "<NO FRAMEWORK DETECTED>";
+// Used to identify when framework detection was skipped
+pub const FRAMEWORK_DETECTION_SKIPPED: &str = "<FRAMEWORK DETECTION SKIPPED>";
+
+// RunSummary contains a summary of what happens in the `turbo run` command and
+// why.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    version: &'static str,
+    turbo_version: &'static str,
+    monorepo: bool,
+    global_cache_inputs: GlobalHashSummary,
+    packages: Vec<String>,
+    // Only used for text output, the JSON output has package names only
+    #[serde(skip)]
+    package_dirs: BTreeMap<String, RelativeUnixPathBuf>,
+    #[serde(serialize_with = "serialize_env_mode")]
+    env_mode: EnvMode,
+    framework_inference: bool,
+    // The hash of each global dotenv file that was included in the global hash
+    global_dot_env: BTreeMap<RelativeUnixPathBuf, String>,
+    tasks: Vec<TaskSummary>,
+}
+
+impl RunSummary {
+    pub fn new(
+        turbo_version: &'static str,
+        single_package: bool,
+        global_cache_inputs: GlobalHashSummary,
+        package_dirs: BTreeMap<String, RelativeUnixPathBuf>,
+        env_mode: EnvMode,
+        framework_inference: bool,
+        global_dot_env: BTreeMap<RelativeUnixPathBuf, String>,
+    ) -> Self {
+        Self {
+            version: RUN_SUMMARY_SCHEMA_VERSION,
+            turbo_version,
+            monorepo: !single_package,
+            global_cache_inputs,
+            packages: package_dirs.keys().cloned().collect(),
+            package_dirs,
+            env_mode,
+            framework_inference,
+            global_dot_env,
+            tasks: Vec::new(),
+        }
+    }
+
+    pub fn add_task(&mut self, task: TaskSummary) {
+        // Keep tasks sorted so that output is stable between runs
+        let idx = self
+            .tasks
+            .partition_point(|existing| existing.task_id < task.task_id);
+        self.tasks.insert(idx, task);
+    }
+
+    pub fn tasks(&self) -> &[TaskSummary] {
+        &self.tasks
+    }
+
+    /// The `--dry=json` output. This is a stable schema that can be diffed
+    /// between runs, so nothing that varies between identical runs is
+    /// included.
+    pub fn format_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// The `--dry` text output
+    pub fn format_text(&self, ui: &UI) -> String {
+        let mut out = String::new();
+        // Writing to a String can't fail
+        self.write_text(ui, &mut out)
+            .expect("writing to a string should not fail");
+        out
+    }
+
+    fn write_text(&self, ui: &UI, out: &mut String) -> fmt::Result {
+        if self.monorepo {
+            writeln!(out, "\n{}", ui.apply(BOLD.apply_to("Packages in Scope")))?;
+            let width = self
+                .package_dirs
+                .keys()
+                .map(|name| name.len())
+                .chain(Some("Name".len()))
+                .max()
+                .unwrap_or_default();
+            writeln!(out, "{:<width$}  Path", "Name")?;
+            for (name, dir) in &self.package_dirs {
+                writeln!(out, "{:<width$}  {}", name, dir)?;
+            }
+        }
+
+        writeln!(out, "\n{}", ui.apply(BOLD.apply_to("Global Hash Inputs")))?;
+        let global = &self.global_cache_inputs;
+        let env_vars = &global.environment_variables;
+        write_fields(
+            ui,
+            out,
+            &[
+                ("Global Files", global.files.len().to_string()),
+                (
+                    "External Dependencies Hash",
+                    global.hash_of_external_dependencies.clone(),
+                ),
+                ("Global Cache Key", global.root_key.to_string()),
+                (
+                    "Global .env Files Considered",
+                    self.global_dot_env.len().to_string(),
+                ),
+                ("Global Env Vars", env_vars.specified.env.join(", ")),
+                ("Global Env Vars Matched", env_vars.configured.join(", ")),
+                ("Inferred Global Env Vars", env_vars.inferred.join(", ")),
+                (
+                    "Global Passed Through Env Vars",
+                    env_vars
+                        .specified
+                        .pass_through_env
+                        .as_deref()
+                        .unwrap_or_default()
+                        .join(", "),
+                ),
+                (
+                    "Global Passed Through Env Vars Matched",
+                    env_vars.passthrough.join(", "),
+                ),
+            ],
+        )?;
+
+        writeln!(out, "\n{}", ui.apply(BOLD.apply_to("Tasks to Run")))?;
+        for task in &self.tasks {
+            writeln!(out, "{}", ui.apply(BOLD.apply_to(&task.task_id)))?;
+            let mut fields = vec![("Task", task.task.clone())];
+            if let Some(package) = &task.package {
+                fields.push(("Package", package.clone()));
+            }
+            fields.extend([
+                ("Hash", task.hash.clone()),
+                ("Cached (Local)", task.cache.local.to_string()),
+                ("Cached (Remote)", task.cache.remote.to_string()),
+            ]);
+            if let Some(directory) = &task.directory {
+                fields.push(("Directory", directory.to_string()));
+            }
+            let env_vars = &task.env_vars;
+            let resolved_task_definition =
+                serde_json::to_string(&task.resolved_task_definition).map_err(|_| fmt::Error)?;
+            fields.extend([
+                ("Command", task.command.clone()),
+                ("Outputs", task.outputs.join(", ")),
+                ("Log File", task.log_file.to_string()),
+                ("Dependencies", task.dependencies.join(", ")),
+                ("Dependents", task.dependents.join(", ")),
+                ("Inputs Files Considered", task.inputs.len().to_string()),
+                (".env Files Considered", task.dot_env.len().to_string()),
+                ("Env Vars", env_vars.specified.env.join(", ")),
+                ("Env Vars Matched", env_vars.configured.join(", ")),
+                ("Inferred Env Vars", env_vars.inferred.join(", ")),
+                (
+                    "Passed Through Env Vars",
+                    env_vars
+                        .specified
+                        .pass_through_env
+                        .as_deref()
+                        .unwrap_or_default()
+                        .join(", "),
+                ),
+                (
+                    "Passed Through Env Vars Matched",
+                    env_vars.passthrough.join(", "),
+                ),
+                ("Resolved Task Definition", resolved_task_definition),
+                ("Framework", task.framework.clone()),
+            ]);
+            write_fields(ui, out, &fields)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Writes `name = value` lines with the values aligned
+fn write_fields(ui: &UI, out: &mut String, fields: &[(&str, String)]) -> fmt::Result {
+    let width = fields
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or_default();
+    for (name, value) in fields {
+        let name = format!("{:<width$}", name);
+        writeln!(out, "  {} = {}", ui.apply(GREY.apply_to(name)), value)?;
+    }
+    Ok(())
+}
+
+// GlobalHashSummary contains the inputs to the global hash
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalHashSummary {
+    pub root_key: &'static str,
+    pub files: BTreeMap<RelativeUnixPathBuf, String>,
+    pub hash_of_external_dependencies: String,
+    pub environment_variables: EnvVarSummary,
+}
+
+// TaskSummary contains information about the task that was about to run
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSummary {
+    pub task_id: String,
+    pub task: String,
+    // Omitted in single package mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    pub hash: String,
+    pub inputs: BTreeMap<RelativeUnixPathBuf, String>,
+    pub hash_of_external_dependencies: String,
+    pub cache: TaskCacheSummary,
+    pub command: String,
+    pub cli_arguments: Vec<String>,
+    pub outputs: Vec<String>,
+    pub excluded_outputs: Vec<String>,
+    pub log_file: RelativeUnixPathBuf,
+    // Omitted in single package mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<RelativeUnixPathBuf>,
+    pub dependencies: Vec<String>,
+    pub dependents: Vec<String>,
+    pub resolved_task_definition: TaskDefinition,
+    pub framework: String,
+    #[serde(serialize_with = "serialize_env_mode")]
+    pub env_mode: EnvMode,
+    #[serde(rename = "environmentVariables")]
+    pub env_vars: EnvVarSummary,
+    // The hash of each of the task's dotenv files that exist
+    pub dot_env: BTreeMap<RelativeUnixPathBuf, String>,
+    // Omitted for dry runs, where nothing is executed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution: Option<TaskExecutionSummary>,
+}
+
+// TaskExecutionSummary is how a task's execution ended, including retries
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskExecutionSummary {
+    pub attempts: u32,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TaskExecutionSummary {
+    pub fn new(execution: &TaskExecution) -> Self {
+        let (status, error) = match &execution.outcome {
+            TaskOutcome::Succeeded => ("succeeded", None),
+            TaskOutcome::Failed(error) => ("failed", Some(error.clone())),
+            TaskOutcome::TimedOut(timeout) => (
+                "timedOut",
+                Some(format!(
+                    "killed after exceeding its timeout of {}",
+                    humantime::format_duration(*timeout)
+                )),
+            ),
+            TaskOutcome::Skipped => ("skipped", None),
+        };
+        Self {
+            attempts: execution.attempts,
+            status,
+            error,
+        }
+    }
+}
+
+/// The name reported for a workspace's framework
+pub fn framework_name(framework_inference: bool, framework: Option<&Framework>) -> String {
+    match framework {
+        Some(framework) => framework.slug().to_string(),
+        None if framework_inference => NO_FRAMEWORK_DETECTED.to_string(),
+        None => FRAMEWORK_DETECTION_SKIPPED.to_string(),
+    }
+}
+
+// TaskCacheSummary is whether a task's artifact was found in the cache
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskCacheSummary {
+    pub local: bool,
+    pub remote: bool,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<CacheSource>,
+    pub time_saved: u64,
+}
+
+impl From<CacheStatus> for TaskCacheSummary {
+    fn from(status: CacheStatus) -> Self {
+        Self {
+            local: status.local,
+            remote: status.remote,
+            status: if status.is_hit() { "HIT" } else { "MISS" },
+            source: status.source(),
+            time_saved: status.time_saved,
+        }
+    }
+}
+
+// EnvConfiguration contains the environment variable inputs that were
+// configured in turbo.json
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvConfiguration {
+    pub env: Vec<String>,
+    pub pass_through_env: Option<Vec<String>>,
+}
+
+// EnvVarSummary contains the names of the environment variables that impacted
+// a hash. Values are never included.
+#[derive(Debug, Default, Serialize)]
+pub struct EnvVarSummary {
+    pub specified: EnvConfiguration,
+    // Variables that matched the `env` configuration
+    pub configured: Vec<String>,
+    // Variables that were included because of an inferred framework
+    pub inferred: Vec<String>,
+    // Variables that matched the `passThroughEnv` configuration
+    pub passthrough: Vec<String>,
+}
+
+impl EnvVarSummary {
+    pub fn new(
+        specified: EnvConfiguration,
+        env_vars: &DetailedMap,
+        pass_through_env_vars: Vec<String>,
+    ) -> Self {
+        Self {
+            specified,
+            configured: env_vars.by_source.explicit.names(),
+            inferred: env_vars.by_source.matching.names(),
+            passthrough: pass_through_env_vars,
+        }
+    }
+}
+
+pub(crate) fn serialize_env_mode<S: Serializer>(
+    env_mode: &EnvMode,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(match env_mode {
+        EnvMode::Infer => "infer",
+        EnvMode::Loose => "loose",
+        EnvMode::Strict => "strict",
+    })
+}
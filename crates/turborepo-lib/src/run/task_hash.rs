@@ -0,0 +1,365 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    hash::Hasher,
+};
+
+use anyhow::Result;
+use turbopath::RelativeUnixPathBuf;
+use turborepo_env::DetailedMap;
+use twox_hash::XxHash64;
+
+use crate::{
+    cli::EnvMode,
+    task_graph::{TaskDefinition, TaskOutputs},
+};
+
+/// Writes a value the way Go's `fmt.Sprintf("%v", value)` does. Hashes are
+/// computed over this representation so that they match the Go
+/// implementation's, and runs of either can share a cache.
+pub trait GoFormat {
+    fn go_format(&self, out: &mut String);
+}
+
+impl GoFormat for str {
+    fn go_format(&self, out: &mut String) {
+        out.push_str(self);
+    }
+}
+
+impl GoFormat for String {
+    fn go_format(&self, out: &mut String) {
+        out.push_str(self);
+    }
+}
+
+impl GoFormat for RelativeUnixPathBuf {
+    fn go_format(&self, out: &mut String) {
+        out.push_str(self.as_str());
+    }
+}
+
+impl GoFormat for bool {
+    fn go_format(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+impl GoFormat for EnvMode {
+    fn go_format(&self, out: &mut String) {
+        out.push_str(match self {
+            EnvMode::Infer => "infer",
+            EnvMode::Loose => "loose",
+            EnvMode::Strict => "strict",
+        });
+    }
+}
+
+impl<T: GoFormat + ?Sized> GoFormat for &T {
+    fn go_format(&self, out: &mut String) {
+        (**self).go_format(out);
+    }
+}
+
+// Go formats nil and empty slices the same way
+impl<T: GoFormat> GoFormat for [T] {
+    fn go_format(&self, out: &mut String) {
+        out.push('[');
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            item.go_format(out);
+        }
+        out.push(']');
+    }
+}
+
+impl<T: GoFormat> GoFormat for Vec<T> {
+    fn go_format(&self, out: &mut String) {
+        self.as_slice().go_format(out);
+    }
+}
+
+// Go sorts map keys when formatting them
+impl<K: GoFormat, V: GoFormat> GoFormat for BTreeMap<K, V> {
+    fn go_format(&self, out: &mut String) {
+        out.push_str("map[");
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            key.go_format(out);
+            out.push(':');
+            value.go_format(out);
+        }
+        out.push(']');
+    }
+}
+
+impl GoFormat for TaskOutputs {
+    fn go_format(&self, out: &mut String) {
+        go_struct(out, &[&self.inclusions, &self.exclusions]);
+    }
+}
+
+impl GoFormat for turborepo_lockfiles::Package {
+    fn go_format(&self, out: &mut String) {
+        // Go also records whether the package was found, which it always is
+        go_struct(out, &[&self.key, &self.version, &true]);
+    }
+}
+
+/// Formats a struct's fields in order, as Go does for a struct value
+pub fn go_struct(out: &mut String, fields: &[&dyn GoFormat]) {
+    out.push('{');
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        field.go_format(out);
+    }
+    out.push('}');
+}
+
+/// Hashes the Go formatting of `value` with xxHash64, returning the digest as
+/// hex.
+pub fn hash_object<T: GoFormat + ?Sized>(value: &T) -> String {
+    let mut formatted = String::new();
+    value.go_format(&mut formatted);
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(formatted.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Hashes a workspace's resolved external dependencies. A missing set of
+/// dependencies hashes the same as an empty one.
+pub fn hash_external_dependencies(
+    dependencies: Option<&HashSet<turborepo_lockfiles::Package>>,
+) -> Result<String> {
+    let mut dependencies = dependencies
+        .map(|dependencies| dependencies.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    // Sorted the way the Go implementation sorts them
+    dependencies.sort_by_cached_key(|package| format!("{}{}", package.key, package.version));
+    Ok(hash_object(&dependencies))
+}
+
+/// The environment mode a task runs in. In infer mode a task that configures
+/// `passThroughEnv` is strict, and any other task is loose.
+pub fn task_env_mode(global_env_mode: EnvMode, task_definition: &TaskDefinition) -> EnvMode {
+    match global_env_mode {
+        EnvMode::Infer if task_definition.pass_through_env().is_some() => EnvMode::Strict,
+        EnvMode::Infer => EnvMode::Loose,
+        env_mode => env_mode,
+    }
+}
+
+/// The path of a task's log file, relative to the task's workspace
+pub fn log_file(task: &str) -> RelativeUnixPathBuf {
+    RelativeUnixPathBuf::new(format!(".turbo/turbo-{}.log", task))
+        .expect("log file path is relative")
+}
+
+/// The task's outputs as they're hashed. The log file is always an output.
+pub fn hashable_outputs(task: &str, outputs: &TaskOutputs) -> TaskOutputs {
+    let mut inclusions = outputs.inclusions.clone();
+    inclusions.push(log_file(task).into_inner());
+    inclusions.sort();
+    let mut exclusions = outputs.exclusions.clone();
+    exclusions.sort();
+    TaskOutputs {
+        inclusions,
+        exclusions,
+    }
+}
+
+pub struct TaskHashInputs<'a> {
+    pub global_hash: &'a str,
+    pub dependency_hashes: Vec<&'a str>,
+    pub package_dir: RelativeUnixPathBuf,
+    pub file_hashes: &'a BTreeMap<RelativeUnixPathBuf, String>,
+    pub external_deps_hash: &'a str,
+    pub task: &'a str,
+    pub task_definition: &'a TaskDefinition,
+    pub pass_through_args: &'a [String],
+    pub env_vars: &'a DetailedMap,
+    pub env_mode: EnvMode,
+}
+
+struct TaskHashable<'a> {
+    global_hash: &'a str,
+    task_dependency_hashes: Vec<&'a str>,
+    package_dir: &'a RelativeUnixPathBuf,
+    hash_of_files: String,
+    external_deps_hash: &'a str,
+    task: &'a str,
+    outputs: TaskOutputs,
+    pass_through_args: &'a [String],
+    env: &'a [String],
+    resolved_env_vars: Vec<String>,
+    pass_through_env: Option<&'a [String]>,
+    env_mode: EnvMode,
+    // This is _explicitly_ ordered and should not be sorted
+    dot_env: &'a [RelativeUnixPathBuf],
+}
+
+impl<'a> GoFormat for TaskHashable<'a> {
+    fn go_format(&self, out: &mut String) {
+        // The Go implementation hashes a pointer to the struct
+        out.push('&');
+        go_struct(
+            out,
+            &[
+                &self.global_hash,
+                &self.task_dependency_hashes,
+                &self.package_dir,
+                &self.hash_of_files,
+                &self.external_deps_hash,
+                &self.task,
+                &self.outputs,
+                &self.pass_through_args,
+                &self.env,
+                &self.resolved_env_vars,
+                &self.pass_through_env.unwrap_or_default(),
+                &self.env_mode,
+                &self.dot_env,
+            ],
+        );
+    }
+}
+
+impl<'a> TaskHashInputs<'a> {
+    pub fn calculate_task_hash(mut self) -> Result<String> {
+        let pass_through_env = match self.env_mode {
+            // Pass through variables aren't considered in loose mode
+            EnvMode::Loose => None,
+            // Collapse unset and empty in strict mode
+            _ => Some(self.task_definition.pass_through_env().unwrap_or_default()),
+        };
+        self.dependency_hashes.sort();
+
+        Ok(hash_object(&TaskHashable {
+            global_hash: self.global_hash,
+            task_dependency_hashes: self.dependency_hashes,
+            package_dir: &self.package_dir,
+            hash_of_files: hash_object(self.file_hashes),
+            external_deps_hash: self.external_deps_hash,
+            task: self.task,
+            outputs: hashable_outputs(self.task, self.task_definition.outputs()),
+            pass_through_args: self.pass_through_args,
+            env: self.task_definition.env(),
+            resolved_env_vars: self.env_vars.all.to_hashable(),
+            pass_through_env,
+            env_mode: self.env_mode,
+            dot_env: self.task_definition.dot_env(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashSet};
+
+    use turbopath::RelativeUnixPathBuf;
+    use turborepo_env::{BySource, DetailedMap, EnvironmentVariableMap};
+
+    use super::*;
+    use crate::task_graph::BookkeepingTaskDefinition;
+
+    fn definition(value: serde_json::Value) -> TaskDefinition {
+        let definition: BookkeepingTaskDefinition = serde_json::from_value(value).unwrap();
+        TaskDefinition::from(&definition)
+    }
+
+    // Expected hashes are what the Go implementation's hashObject produces
+    #[test]
+    fn test_hash_object_matches_go() {
+        let outputs = TaskOutputs {
+            inclusions: vec!["foo".to_string(), "bar".to_string()],
+            exclusions: vec!["baz".to_string()],
+        };
+        let mut formatted = String::new();
+        outputs.go_format(&mut formatted);
+        assert_eq!(formatted, "{[foo bar] [baz]}");
+        assert_eq!(hash_object(&outputs), "4f336181ac339f76");
+
+        let file_hashes = BTreeMap::from([(
+            RelativeUnixPathBuf::new("src/index.ts").unwrap(),
+            "abc".to_string(),
+        )]);
+        assert_eq!(hash_object(&file_hashes), "bd87efce60d476e7");
+
+        // Hashed as `[{a 1.0.0 true} {b 2.0.0 true}]`
+        let dependencies: HashSet<_> = [
+            turborepo_lockfiles::Package::new("b", "2.0.0"),
+            turborepo_lockfiles::Package::new("a", "1.0.0"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            hash_external_dependencies(Some(&dependencies)).unwrap(),
+            "3b82065c3ab875f9"
+        );
+    }
+
+    #[test]
+    fn test_external_dependencies_hash_is_order_independent() {
+        let a = turborepo_lockfiles::Package::new("a", "1.0.0");
+        let b = turborepo_lockfiles::Package::new("b", "2.0.0");
+        let forward: HashSet<_> = [a.clone(), b.clone()].into_iter().collect();
+        let backward: HashSet<_> = [b, a].into_iter().collect();
+        assert_eq!(
+            hash_external_dependencies(Some(&forward)).unwrap(),
+            hash_external_dependencies(Some(&backward)).unwrap()
+        );
+        assert_eq!(
+            hash_external_dependencies(None).unwrap(),
+            hash_external_dependencies(Some(&HashSet::new())).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_task_hash() {
+        let env_vars = DetailedMap {
+            all: EnvironmentVariableMap::default(),
+            by_source: BySource {
+                explicit: EnvironmentVariableMap::default(),
+                matching: EnvironmentVariableMap::default(),
+            },
+        };
+        let file_hashes = BTreeMap::from([(
+            RelativeUnixPathBuf::new("src/index.ts").unwrap(),
+            "abc".to_string(),
+        )]);
+        let loose = definition(serde_json::json!({ "outputs": ["dist/**"] }));
+        let strict = definition(serde_json::json!({
+            "outputs": ["dist/**"],
+            "passThroughEnv": ["AWS_SECRET"],
+        }));
+        let hash = |task_definition: &TaskDefinition, dependency_hashes: Vec<&'static str>| {
+            TaskHashInputs {
+                global_hash: "global",
+                dependency_hashes,
+                package_dir: RelativeUnixPathBuf::new("packages/ui").unwrap(),
+                file_hashes: &file_hashes,
+                external_deps_hash: "external",
+                task: "build",
+                task_definition,
+                pass_through_args: &[],
+                env_vars: &env_vars,
+                env_mode: task_env_mode(EnvMode::Infer, task_definition),
+            }
+            .calculate_task_hash()
+            .unwrap()
+        };
+
+        let loose_hash = hash(&loose, vec!["a", "b"]);
+        // Hashed as `&{global [a b] packages/ui bd87efce60d476e7 external build
+        // {[.turbo/turbo-build.log dist/**] []} [] [] [] [] loose []}`
+        assert_eq!(loose_hash, "89dd2c4990a0847c");
+        // Dependency hashes are sorted before hashing
+        assert_eq!(loose_hash, hash(&loose, vec!["b", "a"]));
+        // Configuring passThroughEnv switches an inferred task to strict mode
+        assert_ne!(loose_hash, hash(&strict, vec!["a", "b"]));
+    }
+}
@@ -11,11 +11,12 @@ use anyhow::{anyhow, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use const_format::formatcp;
 use dunce::canonicalize as fs_canonicalize;
+use jsonc_parser::{parse_to_serde_value, ParseOptions};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use tiny_gradient::{GradientStr, RGB};
-use tracing::debug;
-use turbo_updater::check_for_updates;
+use tracing::{debug, warn};
+use turbo_updater::{check_for_updates, MismatchAction, UpdatePolicy};
 use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf};
 
 use crate::{
@@ -603,14 +604,16 @@ impl RepoState {
         subscriber: &TurboSubscriber,
         ui: UI,
     ) -> Result<Payload> {
+        let update_policy = update_policy(&self.root)?;
         if let Some(LocalTurboState { bin_path, version }) = &self.local_turbo_state {
-            try_check_for_updates(&shim_args, version);
+            check_version_policy(&update_policy, version)?;
+            try_check_for_updates(&shim_args, version, &update_policy);
             let canonical_local_turbo = fs_canonicalize(bin_path)?;
             Ok(Payload::Rust(
                 self.spawn_local_turbo(&canonical_local_turbo, shim_args),
             ))
         } else {
-            try_check_for_updates(&shim_args, get_version());
+            try_check_for_updates(&shim_args, get_version(), &update_policy);
             // cli::run checks for this env var, rather than an arg, so that we can support
             // calling old versions without passing unknown flags.
             env::set_var(
@@ -714,8 +717,56 @@ fn is_turbo_binary_path_set() -> bool {
     env::var("TURBO_BINARY_PATH").is_ok()
 }
 
-fn try_check_for_updates(args: &ShimArgs, current_version: &str) {
-    if args.should_check_for_update() {
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct RootTurboJson {
+    #[serde(default)]
+    update_policy: UpdatePolicy,
+}
+
+/// Reads the `updatePolicy` from the root turbo.json, which may contain
+/// comments. A missing or unparseable turbo.json is reported when it's loaded
+/// for a command, so here it just means there's no policy, but an invalid
+/// `updatePolicy` is an error rather than being silently ignored.
+fn update_policy(repo_root: &AbsoluteSystemPath) -> Result<UpdatePolicy> {
+    let Some(turbo_json) = fs::read_to_string(repo_root.join_component("turbo.json"))
+        .ok()
+        .and_then(|contents| {
+            parse_to_serde_value(
+                &contents,
+                &ParseOptions {
+                    allow_comments: true,
+                    allow_trailing_commas: true,
+                    allow_loose_object_property_names: false,
+                },
+            )
+            .ok()
+            .flatten()
+        })
+    else {
+        return Ok(UpdatePolicy::default());
+    };
+    let turbo_json: RootTurboJson = serde_json::from_value(turbo_json)
+        .map_err(|e| anyhow!("invalid \"updatePolicy\" in turbo.json: {}", e))?;
+    Ok(turbo_json.update_policy)
+}
+
+/// Checks the local turbo against the repository's allowed `turboVersion`
+/// range, failing only if the policy says to. An invalid range is treated
+/// like a mismatch.
+fn check_version_policy(policy: &UpdatePolicy, version: &str) -> Result<()> {
+    match policy.check_version(version) {
+        Err(e) if policy.on_mismatch == MismatchAction::Warn => {
+            warn!("{}", e);
+            Ok(())
+        }
+        result => Ok(result?),
+    }
+}
+
+fn try_check_for_updates(args: &ShimArgs, current_version: &str, policy: &UpdatePolicy) {
+    // A forced check overrides the repository opting out of notifications
+    if args.should_check_for_update() && (policy.notify_updates() || args.force_update_check) {
         // custom footer for update message
         let footer = format!(
             "Follow {username} for updates: {url}",
@@ -736,6 +787,7 @@ fn try_check_for_updates(args: &ShimArgs, current_version: &str) {
             "https://github.com/vercel/turbo",
             Some(&footer),
             current_version,
+            policy.registry.as_deref(),
             // use default for timeout (800ms)
             None,
             interval,
@@ -794,6 +846,77 @@ mod test {
         (tmp_dir, dir)
     }
 
+    #[test]
+    fn test_update_policy() {
+        let (_tmp, root) = tmp_dir();
+        assert_eq!(update_policy(&root).unwrap(), UpdatePolicy::default());
+
+        root.join_component("turbo.json")
+            .create_with_contents(
+                r#"{
+                    "pipeline": {},
+                    "updatePolicy": {
+                        "turboVersion": "^1.10.0",
+                        "onMismatch": "error",
+                        "registry": "https://npm.example.com"
+                    }
+                }"#,
+            )
+            .unwrap();
+        let policy = update_policy(&root).unwrap();
+        assert_eq!(policy.registry.as_deref(), Some("https://npm.example.com"));
+        assert!(check_version_policy(&policy, "1.10.7").is_ok());
+        assert!(check_version_policy(&policy, "1.9.0").is_err());
+
+        let warn_policy = UpdatePolicy {
+            on_mismatch: MismatchAction::Warn,
+            ..policy
+        };
+        assert!(check_version_policy(&warn_policy, "1.9.0").is_ok());
+
+        let invalid_range = UpdatePolicy {
+            turbo_version: Some("not a range".to_string()),
+            ..warn_policy
+        };
+        assert!(check_version_policy(&invalid_range, "1.10.7").is_ok());
+        let invalid_range = UpdatePolicy {
+            on_mismatch: MismatchAction::Error,
+            ..invalid_range
+        };
+        assert!(check_version_policy(&invalid_range, "1.10.7").is_err());
+    }
+
+    #[test]
+    fn test_update_policy_jsonc() {
+        let (_tmp, root) = tmp_dir();
+        let turbo_json = root.join_component("turbo.json");
+        turbo_json
+            .create_with_contents(
+                r#"{
+                    // only allow the version in the lockfile
+                    "updatePolicy": { "turboVersion": "1.10.7", "onMismatch": "error", },
+                }"#,
+            )
+            .unwrap();
+        let policy = update_policy(&root).unwrap();
+        assert_eq!(policy.turbo_version.as_deref(), Some("1.10.7"));
+        assert_eq!(policy.on_mismatch, MismatchAction::Error);
+
+        turbo_json
+            .create_with_contents(r#"{ "updatePolicy": { "onMismatch": "fail" } }"#)
+            .unwrap();
+        assert!(update_policy(&root).is_err());
+
+        turbo_json
+            .create_with_contents(r#"{ "updatePolicy": { "turboVersions": "^1.10.0" } }"#)
+            .unwrap();
+        assert!(update_policy(&root).is_err());
+
+        // turbo.json itself being unparseable is reported elsewhere
+        turbo_json.create_with_contents("{").unwrap();
+        assert_eq!(update_policy(&root).unwrap(), UpdatePolicy::default());
+    }
+
     #[test]
     fn test_process_potential_turbo_roots() {
         struct TestCase {
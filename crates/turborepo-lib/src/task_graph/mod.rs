@@ -1,10 +1,68 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use thiserror::Error;
+use tracing::warn;
+use turbopath::RelativeUnixPathBuf;
 
-pub type Pipeline = HashMap<String, BookkeepingTaskDefinition>;
+pub type Pipeline = BTreeMap<String, BookkeepingTaskDefinition>;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+const TOPOLOGICAL_PIPELINE_DELIMITER: &str = "^";
+const ENV_PIPELINE_DELIMITER: &str = "$";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    #[error(
+        "You specified \"{value}\" in the \"{key}\" key. You should not prefix your environment \
+         variables with \"$\""
+    )]
+    InvalidEnvPrefix { value: String, key: &'static str },
+    #[error("Invalid \"timeout\" of \"{0}\". Timeouts are durations such as \"90s\" or \"1h30m\"")]
+    InvalidTimeout(String),
+    #[error("Invalid \"cpus\" of 0. A task needs at least 1 cpu to run")]
+    ZeroCpus,
+}
+
+// Tasks that don't configure `cpus` count as a single unit of concurrency
+const DEFAULT_TASK_CPUS: u32 = 1;
+
+// RawTaskDefinition is the shape of a task in turbo.json. Fields are optional
+// so that we can tell a field that was omitted apart from one that was set to
+// its zero value.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawTaskDefinition {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outputs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    depends_on: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inputs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_mode: Option<TaskOutputMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persistent: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pass_through_env: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dot_env: Option<Vec<RelativeUnixPathBuf>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_retries: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpus: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawTaskDefinition", into = "RawTaskDefinition")]
 pub struct BookkeepingTaskDefinition {
     defined_fields: HashSet<String>,
     experimental_fields: HashSet<String>,
@@ -12,37 +70,274 @@ pub struct BookkeepingTaskDefinition {
     task_definition: TaskDefinitionHashable,
 }
 
+impl BookkeepingTaskDefinition {
+    /// A definition for a task that isn't in turbo.json but should still be
+    /// runnable, e.g. a root package.json script in single package mode.
+    /// Caching is explicitly disabled as if it had been configured that way.
+    pub fn uncached() -> Self {
+        Self {
+            defined_fields: HashSet::from(["Cache".to_string()]),
+            task_definition: TaskDefinitionHashable {
+                should_cache: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.defined_fields.contains(field_name) || self.experimental_fields.contains(field_name)
+    }
+}
+
+impl TryFrom<RawTaskDefinition> for BookkeepingTaskDefinition {
+    type Error = Error;
+
+    fn try_from(raw_task: RawTaskDefinition) -> Result<Self, Error> {
+        let mut defined_fields = HashSet::new();
+        let mut task_definition = TaskDefinitionHashable::default();
+
+        if let Some(outputs) = raw_task.outputs {
+            // Record that outputs were actually configured in turbo.json
+            defined_fields.insert("Outputs".to_string());
+            let (mut exclusions, mut inclusions): (Vec<_>, Vec<_>) =
+                outputs.into_iter().partition(|glob| glob.starts_with('!'));
+            for exclusion in &mut exclusions {
+                exclusion.remove(0);
+            }
+            inclusions.sort();
+            exclusions.sort();
+            task_definition.outputs = TaskOutputs {
+                inclusions,
+                exclusions,
+            };
+        }
+
+        match raw_task.cache {
+            Some(cache) => {
+                defined_fields.insert("Cache".to_string());
+                task_definition.should_cache = cache;
+            }
+            None => task_definition.should_cache = true,
+        }
+
+        let mut env_var_dependencies = HashSet::new();
+        if let Some(depends_on) = raw_task.depends_on {
+            defined_fields.insert("DependsOn".to_string());
+            for dependency in depends_on {
+                if let Some(env_var) = dependency.strip_prefix(ENV_PIPELINE_DELIMITER) {
+                    warn!(
+                        "[DEPRECATED] Declaring an environment variable in \"dependsOn\" is \
+                         deprecated, found {}. Use the \"env\" key or use `npx @turbo/codemod \
+                         migrate-env-var-dependencies`.",
+                        dependency
+                    );
+                    defined_fields.insert("Env".to_string());
+                    env_var_dependencies.insert(env_var.to_string());
+                } else if let Some(topo_dependency) =
+                    dependency.strip_prefix(TOPOLOGICAL_PIPELINE_DELIMITER)
+                {
+                    task_definition
+                        .topological_dependencies
+                        .push(topo_dependency.to_string());
+                } else {
+                    task_definition.task_dependencies.push(dependency);
+                }
+            }
+        }
+        task_definition.task_dependencies.sort();
+        task_definition.topological_dependencies.sort();
+
+        if let Some(env) = raw_task.env {
+            defined_fields.insert("Env".to_string());
+            gather_env_vars(env, "env", &mut env_var_dependencies)?;
+        }
+        task_definition.env_var_dependencies = env_var_dependencies.into_iter().collect();
+        task_definition.env_var_dependencies.sort();
+
+        let mut experimental = TaskDefinitionExperiments::default();
+        if let Some(pass_through_env) = raw_task.pass_through_env {
+            defined_fields.insert("PassThroughEnv".to_string());
+            let mut env_var_pass_throughs = HashSet::new();
+            gather_env_vars(
+                pass_through_env,
+                "passThroughEnv",
+                &mut env_var_pass_throughs,
+            )?;
+            let mut pass_through_env = env_var_pass_throughs.into_iter().collect::<Vec<_>>();
+            pass_through_env.sort();
+            experimental.passthrough_env = Some(pass_through_env);
+        }
+
+        if let Some(dot_env) = raw_task.dot_env {
+            defined_fields.insert("DotEnv".to_string());
+            // These are _explicitly_ not sorted, order determines precedence
+            task_definition.dot_env = dot_env;
+        }
+
+        if let Some(inputs) = raw_task.inputs {
+            // Inputs don't need to be sorted, the files they match are sorted
+            // when they're hashed
+            defined_fields.insert("Inputs".to_string());
+            task_definition.inputs = inputs;
+        }
+
+        if let Some(output_mode) = raw_task.output_mode {
+            defined_fields.insert("OutputMode".to_string());
+            task_definition.output_mode = output_mode;
+        }
+
+        if let Some(persistent) = raw_task.persistent {
+            defined_fields.insert("Persistent".to_string());
+            task_definition.persistent = persistent;
+        }
+
+        if let Some(timeout) = raw_task.timeout {
+            defined_fields.insert("Timeout".to_string());
+            parse_timeout(&timeout)?;
+            task_definition.timeout = Some(timeout);
+        }
+
+        if let Some(max_retries) = raw_task.max_retries {
+            defined_fields.insert("MaxRetries".to_string());
+            task_definition.max_retries = max_retries;
+        }
+
+        if let Some(cpus) = raw_task.cpus {
+            defined_fields.insert("Cpus".to_string());
+            if cpus == 0 {
+                return Err(Error::ZeroCpus);
+            }
+            task_definition.cpus = Some(cpus);
+        }
+
+        Ok(Self {
+            defined_fields,
+            experimental_fields: HashSet::new(),
+            experimental,
+            task_definition,
+        })
+    }
+}
+
+impl From<BookkeepingTaskDefinition> for RawTaskDefinition {
+    // Only fields that were configured are written back out, so that
+    // rewriting turbo.json doesn't fill it with defaults
+    fn from(bookkeeping: BookkeepingTaskDefinition) -> Self {
+        let has_field = |field_name: &str| bookkeeping.has_field(field_name);
+        let task_definition = &bookkeeping.task_definition;
+        Self {
+            outputs: has_field("Outputs").then(|| task_definition.outputs.to_globs()),
+            cache: has_field("Cache").then_some(task_definition.should_cache),
+            depends_on: has_field("DependsOn").then(|| {
+                depends_on(
+                    &task_definition.task_dependencies,
+                    &task_definition.topological_dependencies,
+                )
+            }),
+            inputs: has_field("Inputs").then(|| task_definition.inputs.clone()),
+            output_mode: has_field("OutputMode").then_some(task_definition.output_mode),
+            persistent: has_field("Persistent").then_some(task_definition.persistent),
+            env: has_field("Env").then(|| task_definition.env_var_dependencies.clone()),
+            pass_through_env: bookkeeping.experimental.passthrough_env.clone(),
+            dot_env: has_field("DotEnv").then(|| task_definition.dot_env.clone()),
+            timeout: task_definition.timeout.clone(),
+            max_retries: has_field("MaxRetries").then_some(task_definition.max_retries),
+            cpus: task_definition.cpus,
+        }
+    }
+}
+
+// gather_env_vars collects env vars into the provided set, erroring on any
+// that still use the deprecated `$` prefix
+fn gather_env_vars(
+    vars: Vec<String>,
+    key: &'static str,
+    into: &mut HashSet<String>,
+) -> Result<(), Error> {
+    for value in vars {
+        if value.starts_with(ENV_PIPELINE_DELIMITER) {
+            // Hard error to help people specify this correctly during migration.
+            return Err(Error::InvalidEnvPrefix { value, key });
+        }
+        into.insert(value);
+    }
+    Ok(())
+}
+
+// Timeouts use the same duration format as Go, e.g. "90s" or "1h30m"
+fn parse_timeout(timeout: &str) -> Result<Duration, Error> {
+    match go_parse_duration::parse_duration(timeout) {
+        Ok(nanos) if nanos > 0 => Ok(Duration::from_nanos(nanos as u64)),
+        _ => Err(Error::InvalidTimeout(timeout.to_string())),
+    }
+}
+
+fn depends_on(task_dependencies: &[String], topological_dependencies: &[String]) -> Vec<String> {
+    let mut depends_on = task_dependencies
+        .iter()
+        .cloned()
+        .chain(
+            topological_dependencies
+                .iter()
+                .map(|dependency| format!("{TOPOLOGICAL_PIPELINE_DELIMITER}{dependency}")),
+        )
+        .collect::<Vec<_>>();
+    depends_on.sort();
+    depends_on
+}
+
 // A list of config fields in a task definition that are considered
 // experimental. We keep these separated so we can compute a global hash without
 // these.
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 struct TaskDefinitionExperiments {
-    passthrough_env: Vec<String>,
+    passthrough_env: Option<Vec<String>>,
 }
 
 // TaskOutputs represents the patterns for including and excluding files from
 // outputs
-#[derive(Debug, Default, Serialize, Deserialize)]
-struct TaskOutputs {
-    inclusions: Vec<String>,
-    exclusions: Vec<String>,
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskOutputs {
+    pub inclusions: Vec<String>,
+    pub exclusions: Vec<String>,
+}
+
+impl TaskOutputs {
+    // The outputs as they'd be written in turbo.json, with exclusions
+    // prefixed by `!`
+    fn to_globs(&self) -> Vec<String> {
+        let mut globs = self
+            .inclusions
+            .iter()
+            .cloned()
+            .chain(self.exclusions.iter().map(|glob| format!("!{glob}")))
+            .collect::<Vec<_>>();
+        globs.sort();
+        globs
+    }
 }
 
 // TaskOutputMode defines the ways turbo can display task output during a run
-#[derive(Debug, Default, Serialize, Deserialize)]
-enum TaskOutputMode {
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskOutputMode {
     // FullTaskOutput will show all task output
     #[default]
+    #[serde(rename = "full")]
     Full,
     // None will hide all task output
+    #[serde(rename = "none")]
     None,
     // Hash will display turbo-computed task hashes
+    #[serde(rename = "hash-only")]
     Hash,
     // New will show all new task output and turbo-computed task hashes for cached
     // output
+    #[serde(rename = "new-only")]
     New,
     // Error will show task output for failures only; no cache miss/hit messages are
     // emitted
+    #[serde(rename = "errors-only")]
     Error,
 }
 
@@ -50,7 +345,7 @@ enum TaskOutputMode {
 // used downstream for calculating the global hash. We want to exclude
 // experimental fields here because we don't want experimental fields to be part
 // of the global hash.
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 struct TaskDefinitionHashable {
     outputs: TaskOutputs,
     should_cache: bool,
@@ -60,11 +355,15 @@ struct TaskDefinitionHashable {
     inputs: Vec<String>,
     output_mode: TaskOutputMode,
     persistent: bool,
+    dot_env: Vec<RelativeUnixPathBuf>,
+    timeout: Option<String>,
+    max_retries: u32,
+    cpus: Option<u32>,
 }
 
 // task_definition is a representation of the configFile pipeline for further
 // computation.
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct TaskDefinition {
     outputs: TaskOutputs,
     should_cache: bool,
@@ -73,7 +372,7 @@ pub struct TaskDefinition {
     env_var_dependencies: Vec<String>,
 
     // rawTask.PassthroughEnv
-    passthrough_env: Vec<String>,
+    passthrough_env: Option<Vec<String>>,
 
     // TopologicalDependencies are tasks from package dependencies.
     // E.g. "build" is a topological dependency in:
@@ -97,4 +396,253 @@ pub struct TaskDefinition {
     // Persistent indicates whether the Task is expected to exit or not
     // Tasks marked Persistent do not exit (e.g. --watch mode or dev servers)
     persistent: bool,
+
+    // DotEnv is a list of dotenv files, relative to the workspace, whose parsed
+    // contents are included in the task hash
+    dot_env: Vec<RelativeUnixPathBuf>,
+
+    // Timeout is how long the task may run before it's killed, as configured
+    timeout: Option<String>,
+
+    // MaxRetries is how many times a failed or timed out task is retried
+    max_retries: u32,
+
+    // Cpus is how many units of --concurrency the task occupies while it runs
+    cpus: u32,
+}
+
+impl From<&BookkeepingTaskDefinition> for TaskDefinition {
+    fn from(bookkeeping: &BookkeepingTaskDefinition) -> Self {
+        let task_definition = bookkeeping.task_definition.clone();
+        Self {
+            outputs: task_definition.outputs,
+            should_cache: task_definition.should_cache,
+            env_var_dependencies: task_definition.env_var_dependencies,
+            passthrough_env: bookkeeping.experimental.passthrough_env.clone(),
+            topological_dependencies: task_definition.topological_dependencies,
+            task_dependencies: task_definition.task_dependencies,
+            inputs: task_definition.inputs,
+            output_mode: task_definition.output_mode,
+            persistent: task_definition.persistent,
+            dot_env: task_definition.dot_env,
+            timeout: task_definition.timeout,
+            max_retries: task_definition.max_retries,
+            cpus: task_definition.cpus.unwrap_or(DEFAULT_TASK_CPUS),
+        }
+    }
+}
+
+impl TaskDefinition {
+    pub fn outputs(&self) -> &TaskOutputs {
+        &self.outputs
+    }
+
+    pub fn should_cache(&self) -> bool {
+        self.should_cache
+    }
+
+    pub fn env(&self) -> &[String] {
+        &self.env_var_dependencies
+    }
+
+    /// `None` if `passThroughEnv` wasn't configured, which is distinct from
+    /// it being configured as empty
+    pub fn pass_through_env(&self) -> Option<&[String]> {
+        self.passthrough_env.as_deref()
+    }
+
+    pub fn topological_dependencies(&self) -> &[String] {
+        &self.topological_dependencies
+    }
+
+    pub fn task_dependencies(&self) -> &[String] {
+        &self.task_dependencies
+    }
+
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn dot_env(&self) -> &[RelativeUnixPathBuf] {
+        &self.dot_env
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.as_deref().map(|timeout| {
+            parse_timeout(timeout).expect("timeout was validated when turbo.json was parsed")
+        })
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    pub fn cpus(&self) -> u32 {
+        self.cpus
+    }
+}
+
+// TaskDefinitionWithDefaults is how a resolved task definition is displayed,
+// e.g. in a dry run. Unlike RawTaskDefinition, every field is present so that
+// users can see the defaults for the keys they didn't configure.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskDefinitionWithDefaults<'a> {
+    outputs: Vec<String>,
+    cache: bool,
+    depends_on: Vec<String>,
+    inputs: Vec<String>,
+    output_mode: TaskOutputMode,
+    persistent: bool,
+    env: Vec<String>,
+    pass_through_env: Option<Vec<String>>,
+    dot_env: &'a [RelativeUnixPathBuf],
+    timeout: Option<&'a str>,
+    max_retries: u32,
+    cpus: u32,
+}
+
+impl Serialize for TaskDefinition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut inputs = self.inputs.clone();
+        inputs.sort();
+        let mut env = self.env_var_dependencies.clone();
+        env.sort();
+        let pass_through_env = self.passthrough_env.clone().map(|mut pass_through_env| {
+            pass_through_env.sort();
+            pass_through_env
+        });
+        TaskDefinitionWithDefaults {
+            outputs: self.outputs.to_globs(),
+            cache: self.should_cache,
+            depends_on: depends_on(&self.task_dependencies, &self.topological_dependencies),
+            inputs,
+            output_mode: self.output_mode,
+            persistent: self.persistent,
+            env,
+            pass_through_env,
+            dot_env: &self.dot_env,
+            timeout: self.timeout.as_deref(),
+            max_retries: self.max_retries,
+            cpus: self.cpus,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_task_definition() {
+        let task: BookkeepingTaskDefinition = serde_json::from_value(json!({
+            "outputs": ["dist/**", "!dist/cache/**", ".next/**"],
+            "dependsOn": ["^build", "prebuild", "$LEGACY_VAR"],
+            "env": ["NODE_ENV"],
+            "passThroughEnv": ["AWS_SECRET"],
+            "outputMode": "new-only",
+        }))
+        .unwrap();
+        let task_definition = TaskDefinition::from(&task);
+
+        assert_eq!(
+            task_definition.outputs(),
+            &TaskOutputs {
+                inclusions: vec![".next/**".to_string(), "dist/**".to_string()],
+                exclusions: vec!["dist/cache/**".to_string()],
+            }
+        );
+        assert!(task_definition.should_cache);
+        assert_eq!(task_definition.topological_dependencies(), &["build"]);
+        assert_eq!(task_definition.task_dependencies(), &["prebuild"]);
+        assert_eq!(task_definition.env(), &["LEGACY_VAR", "NODE_ENV"]);
+        assert_eq!(
+            task_definition.pass_through_env(),
+            Some(["AWS_SECRET".to_string()].as_slice())
+        );
+        assert_eq!(task_definition.output_mode, TaskOutputMode::New);
+        assert!(!task_definition.persistent);
+    }
+
+    #[test]
+    fn test_env_var_prefix_is_an_error() {
+        let result = serde_json::from_value::<BookkeepingTaskDefinition>(json!({
+            "env": ["$NODE_ENV"],
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_round_trip_only_writes_defined_fields() {
+        let raw = json!({
+            "dependsOn": ["^build"],
+            "outputs": ["!dist/cache/**", "dist/**"],
+        });
+        let task: BookkeepingTaskDefinition = serde_json::from_value(raw.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&task).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_resolved_task_definition_includes_defaults() {
+        let task: BookkeepingTaskDefinition = serde_json::from_value(json!({
+            "dependsOn": ["^build", "lint"],
+            "outputs": ["dist/**", "!dist/cache/**"],
+        }))
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(TaskDefinition::from(&task)).unwrap(),
+            json!({
+                "outputs": ["!dist/cache/**", "dist/**"],
+                "cache": true,
+                "dependsOn": ["^build", "lint"],
+                "inputs": [],
+                "outputMode": "full",
+                "persistent": false,
+                "env": [],
+                "passThroughEnv": null,
+                "dotEnv": [],
+                "timeout": null,
+                "maxRetries": 0,
+                "cpus": 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_resource_limits() {
+        let raw = json!({
+            "timeout": "1h30m",
+            "maxRetries": 2,
+            "cpus": 4,
+        });
+        let task: BookkeepingTaskDefinition = serde_json::from_value(raw.clone()).unwrap();
+        let task_definition = TaskDefinition::from(&task);
+        assert_eq!(
+            task_definition.timeout(),
+            Some(Duration::from_secs(90 * 60))
+        );
+        assert_eq!(task_definition.max_retries(), 2);
+        assert_eq!(task_definition.cpus(), 4);
+        assert_eq!(serde_json::to_value(&task).unwrap(), raw);
+
+        let task: BookkeepingTaskDefinition = serde_json::from_value(json!({})).unwrap();
+        let task_definition = TaskDefinition::from(&task);
+        assert_eq!(task_definition.timeout(), None);
+        assert_eq!(task_definition.max_retries(), 0);
+        assert_eq!(task_definition.cpus(), 1);
+    }
+
+    #[test]
+    fn test_invalid_resource_limits() {
+        for raw in [
+            json!({ "timeout": "soon" }),
+            json!({ "timeout": "0s" }),
+            json!({ "cpus": 0 }),
+        ] {
+            assert!(serde_json::from_value::<BookkeepingTaskDefinition>(raw).is_err());
+        }
+    }
 }
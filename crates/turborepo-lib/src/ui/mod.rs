@@ -4,6 +4,10 @@ use console::{Style, StyledObject};
 use indicatif::{ProgressBar, ProgressStyle};
 use lazy_static::lazy_static;
 
+mod tui;
+
+pub use tui::{TaskReporter, TaskStatus, Tui};
+
 pub fn start_spinner(message: &str) -> ProgressBar {
     let pb = ProgressBar::new_spinner();
     if env::var("CI").is_ok() {
@@ -44,6 +48,17 @@ impl UI {
         env::var("CI").is_ok()
     }
 
+    /// Whether the user can answer prompts. CI is never interactive.
+    pub fn is_interactive(&self) -> bool {
+        !self.is_ci() && atty::is(atty::Stream::Stdout) && atty::is(atty::Stream::Stdin)
+    }
+
+    /// Whether the terminal can show the full screen task UI. It needs an
+    /// interactive terminal.
+    pub fn supports_tui(&self) -> bool {
+        self.is_interactive()
+    }
+
     /// Infer the color choice from environment variables and checking if stdout
     /// is a tty
     pub fn infer() -> Self {
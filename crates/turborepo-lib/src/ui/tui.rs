@@ -0,0 +1,489 @@
+use std::{
+    io::{self, Stdout},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use tokio::sync::Notify;
+
+// How often the screen is redrawn while nothing else is happening
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+const TASK_LIST_WIDTH: u16 = 40;
+const PAGE_SIZE: usize = 10;
+const KEY_HINTS: &str =
+    " ↑/↓ select task · PgUp/PgDn scroll output · End follow output · Ctrl-C stop ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Queued,
+    Running,
+    // The task's outputs were restored from the cache instead of running it
+    Cached,
+    Succeeded,
+    Failed,
+}
+
+impl TaskStatus {
+    fn symbol(&self) -> &'static str {
+        match self {
+            TaskStatus::Queued => "·",
+            TaskStatus::Running => "▶",
+            TaskStatus::Cached => "↺",
+            TaskStatus::Succeeded => "✔",
+            TaskStatus::Failed => "✘",
+        }
+    }
+
+    fn style(&self) -> Style {
+        let color = match self {
+            TaskStatus::Queued => Color::DarkGray,
+            TaskStatus::Running => Color::Yellow,
+            TaskStatus::Cached => Color::Cyan,
+            TaskStatus::Succeeded => Color::Green,
+            TaskStatus::Failed => Color::Red,
+        };
+        Style::default().fg(color)
+    }
+}
+
+#[derive(Debug)]
+enum TuiEvent {
+    Start { task: String },
+    Output { task: String, line: String },
+    End { task: String, status: TaskStatus },
+}
+
+#[derive(Debug)]
+struct TaskPane {
+    task: String,
+    status: TaskStatus,
+    started: Option<Instant>,
+    duration: Option<Duration>,
+    output: Vec<String>,
+}
+
+impl TaskPane {
+    fn elapsed(&self) -> Option<Duration> {
+        self.duration
+            .or_else(|| self.started.map(|started| started.elapsed()))
+    }
+}
+
+/// The state of the terminal UI, kept separate from the terminal so that it
+/// can be rendered to any backend
+#[derive(Debug)]
+struct App {
+    tasks: Vec<TaskPane>,
+    selected: usize,
+    // How many lines above the end of the output the selected pane is
+    // scrolled. 0 follows the output as it arrives.
+    scroll_from_bottom: usize,
+}
+
+impl App {
+    fn new(tasks: Vec<String>) -> Self {
+        Self {
+            tasks: tasks
+                .into_iter()
+                .map(|task| TaskPane {
+                    task,
+                    status: TaskStatus::Queued,
+                    started: None,
+                    duration: None,
+                    output: Vec::new(),
+                })
+                .collect(),
+            selected: 0,
+            scroll_from_bottom: 0,
+        }
+    }
+
+    fn pane_index(&self, task: &str) -> Option<usize> {
+        self.tasks.iter().position(|pane| pane.task == task)
+    }
+
+    fn handle_event(&mut self, event: TuiEvent) {
+        match event {
+            TuiEvent::Start { task } => {
+                let Some(index) = self.pane_index(&task) else {
+                    return;
+                };
+                let pane = &mut self.tasks[index];
+                // A task that starts again is being retried
+                if pane.started.is_some() {
+                    pane.output.push(format!("--- retrying {} ---", task));
+                }
+                pane.status = TaskStatus::Running;
+                pane.started = Some(Instant::now());
+                pane.duration = None;
+                // Move off a task that hasn't started so there's output to show
+                if self.tasks[self.selected].status == TaskStatus::Queued {
+                    self.selected = index;
+                    self.scroll_from_bottom = 0;
+                }
+            }
+            TuiEvent::Output { task, line } => {
+                let Some(index) = self.pane_index(&task) else {
+                    return;
+                };
+                self.tasks[index]
+                    .output
+                    .push(console::strip_ansi_codes(&line).into_owned());
+                // Keep a scrolled pane still while output arrives below it
+                if index == self.selected && self.scroll_from_bottom > 0 {
+                    self.scroll_from_bottom += 1;
+                }
+            }
+            TuiEvent::End { task, status } => {
+                let Some(index) = self.pane_index(&task) else {
+                    return;
+                };
+                let pane = &mut self.tasks[index];
+                pane.status = status;
+                pane.duration = pane.started.map(|started| started.elapsed());
+            }
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        if index < self.tasks.len() && index != self.selected {
+            self.selected = index;
+            self.scroll_from_bottom = 0;
+        }
+    }
+
+    fn scroll_up(&mut self, lines: usize) {
+        let output_len = self
+            .tasks
+            .get(self.selected)
+            .map_or(0, |pane| pane.output.len());
+        self.scroll_from_bottom = (self.scroll_from_bottom + lines).min(output_len);
+    }
+
+    fn scroll_down(&mut self, lines: usize) {
+        self.scroll_from_bottom = self.scroll_from_bottom.saturating_sub(lines);
+    }
+
+    /// Handles a key press, returning true if the user asked to stop the run
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Up | KeyCode::Char('k') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected + 1),
+            KeyCode::PageUp => self.scroll_up(PAGE_SIZE),
+            KeyCode::PageDown => self.scroll_down(PAGE_SIZE),
+            KeyCode::End => self.scroll_from_bottom = 0,
+            _ => {}
+        }
+        false
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(f.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(TASK_LIST_WIDTH), Constraint::Min(0)])
+            .split(rows[0]);
+
+        let items = self
+            .tasks
+            .iter()
+            .map(|pane| {
+                let duration = pane
+                    .elapsed()
+                    .map(|elapsed| format!(" {:.1}s", elapsed.as_secs_f64()))
+                    .unwrap_or_default();
+                ListItem::new(format!(
+                    "{} {}{}",
+                    pane.status.symbol(),
+                    pane.task,
+                    duration
+                ))
+                .style(pane.status.style())
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" Tasks "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.selected));
+        f.render_stateful_widget(list, columns[0], &mut list_state);
+
+        if let Some(pane) = self.tasks.get(self.selected) {
+            // The borders take up two rows
+            let height = columns[1].height.saturating_sub(2) as usize;
+            let bottom = pane.output.len().saturating_sub(self.scroll_from_bottom);
+            let top = bottom.saturating_sub(height);
+            let lines = pane.output[top..bottom]
+                .iter()
+                .map(|line| Line::from(line.as_str()))
+                .collect::<Vec<_>>();
+            let output = Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} ", pane.task)),
+            );
+            f.render_widget(output, columns[1]);
+        }
+
+        f.render_widget(
+            Paragraph::new(KEY_HINTS).style(Style::default().fg(Color::DarkGray)),
+            rows[1],
+        );
+    }
+}
+
+/// A full screen terminal UI that lists a run's tasks with their status, and
+/// shows the output of the selected task.
+///
+/// The UI is drawn from its own thread until every [TuiSender] has been
+/// dropped and [Tui::finish] is called.
+pub struct Tui {
+    sender: Sender<TuiEvent>,
+    interrupted: Arc<Notify>,
+    handle: JoinHandle<io::Result<App>>,
+}
+
+impl Tui {
+    pub fn start(tasks: Vec<String>) -> io::Result<Self> {
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        enable_raw_mode()?;
+        if let Err(e) = execute!(terminal.backend_mut(), EnterAlternateScreen) {
+            disable_raw_mode()?;
+            return Err(e);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let interrupted = Arc::new(Notify::new());
+        let handle = thread::spawn({
+            let interrupted = interrupted.clone();
+            move || {
+                let result = draw(&mut terminal, App::new(tasks), receiver, &interrupted);
+                restore(&mut terminal)?;
+                result
+            }
+        });
+
+        Ok(Self {
+            sender,
+            interrupted,
+            handle,
+        })
+    }
+
+    pub fn sender(&self) -> TuiSender {
+        TuiSender {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Resolves once the user asks to stop the run
+    pub async fn interrupted(&self) {
+        self.interrupted.notified().await
+    }
+
+    /// Closes the UI and restores the terminal. The UI's output is lost with
+    /// the alternate screen, so the output of failed tasks is printed again.
+    pub fn finish(self) -> io::Result<()> {
+        drop(self.sender);
+        let app = self
+            .handle
+            .join()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "terminal UI panicked"))??;
+        for pane in app
+            .tasks
+            .iter()
+            .filter(|pane| pane.status == TaskStatus::Failed)
+        {
+            for line in &pane.output {
+                println!("{}: {}", pane.task, line);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn draw(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    mut app: App,
+    receiver: Receiver<TuiEvent>,
+    interrupted: &Notify,
+) -> io::Result<App> {
+    loop {
+        terminal.draw(|f| app.render(f))?;
+        if event::poll(FRAME_INTERVAL)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && app.handle_key(key) {
+                    interrupted.notify_one();
+                }
+            }
+        }
+        loop {
+            match receiver.try_recv() {
+                Ok(event) => app.handle_event(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(app),
+            }
+        }
+    }
+}
+
+fn restore(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+/// Reports task progress to a [Tui]
+#[derive(Clone)]
+pub struct TuiSender {
+    sender: Sender<TuiEvent>,
+}
+
+impl TuiSender {
+    /// Marks the task as running. If the returned reporter is dropped before
+    /// it's finished, e.g. because the task timed out, the task is marked as
+    /// failed.
+    pub fn start_task(&self, task: &str) -> TaskReporter {
+        // The UI may have already closed, in which case there's nobody to tell
+        let _ = self.sender.send(TuiEvent::Start {
+            task: task.to_string(),
+        });
+        TaskReporter {
+            sender: self.sender.clone(),
+            task: task.to_string(),
+            finished: false,
+        }
+    }
+}
+
+pub struct TaskReporter {
+    sender: Sender<TuiEvent>,
+    task: String,
+    finished: bool,
+}
+
+impl TaskReporter {
+    pub fn output(&self, line: String) {
+        let _ = self.sender.send(TuiEvent::Output {
+            task: self.task.clone(),
+            line,
+        });
+    }
+
+    pub fn finish(mut self, status: TaskStatus) {
+        self.end(status);
+    }
+
+    fn end(&mut self, status: TaskStatus) {
+        self.finished = true;
+        let _ = self.sender.send(TuiEvent::End {
+            task: self.task.clone(),
+            status,
+        });
+    }
+}
+
+impl Drop for TaskReporter {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.end(TaskStatus::Failed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::{App, TaskStatus, TuiEvent};
+
+    fn render(app: &App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(80, 6)).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+            .collect()
+    }
+
+    fn output(task: &str, line: &str) -> TuiEvent {
+        TuiEvent::Output {
+            task: task.to_string(),
+            line: line.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_task_status_and_output() {
+        let mut app = App::new(vec!["ui#build".to_string(), "web#build".to_string()]);
+        app.handle_event(TuiEvent::Start {
+            task: "web#build".to_string(),
+        });
+        app.handle_event(output("web#build", "\u{1b}[32mcompiled\u{1b}[0m"));
+        app.handle_event(TuiEvent::End {
+            task: "web#build".to_string(),
+            status: TaskStatus::Failed,
+        });
+
+        // The first task to start is selected and its output is shown
+        // without colors
+        assert_eq!(app.selected, 1);
+        let screen = render(&app);
+        assert!(screen[1].contains("· ui#build"));
+        assert!(screen[2].contains("✘ web#build 0.0s"));
+        assert!(screen[0].contains(" web#build "));
+        assert!(screen[1].contains("│compiled"));
+        assert!(screen[5].contains("Ctrl-C stop"));
+    }
+
+    #[test]
+    fn test_output_scrolling() {
+        let mut app = App::new(vec!["web#dev".to_string()]);
+        app.handle_event(TuiEvent::Start {
+            task: "web#dev".to_string(),
+        });
+        for i in 0..20 {
+            app.handle_event(output("web#dev", &format!("line {}", i)));
+        }
+        // The pane follows the end of the output, which fits 3 lines
+        let screen = render(&app);
+        assert!(screen[1].contains("line 17"));
+        assert!(screen[3].contains("line 19"));
+
+        app.handle_key(KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE));
+        app.handle_event(output("web#dev", "line 20"));
+        // Scrolled panes stay put while output arrives
+        let screen = render(&app);
+        assert!(screen[1].contains("line 7"));
+        assert!(screen[3].contains("line 9"));
+
+        app.handle_key(KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+        assert!(render(&app)[3].contains("line 20"));
+
+        assert!(app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+    }
+}
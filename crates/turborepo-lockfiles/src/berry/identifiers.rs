@@ -157,6 +157,41 @@ impl<'a> Descriptor<'a> {
         }
     }
 
+    /// Returns the descriptor bound to the given parent locator if the range
+    /// is relative to the package that depends on it. Yarn appends the parent
+    /// locator to these ranges e.g. `portal:../foo` depended on by
+    /// `a@workspace:packages/a` becomes
+    /// `portal:../foo::locator=a%40workspace%3Apackages%2Fa`
+    pub fn bound_to(&self, parent: &Locator) -> Option<Descriptor<'static>> {
+        let protocol = self.protocol()?;
+        if !BOUND_PROTOCOLS.contains(&protocol) || self.range.contains("locator=") {
+            return None;
+        }
+        // Builtin patches don't reference any file relative to the parent
+        if protocol == "patch"
+            && self
+                .range
+                .split_once('#')
+                .map_or(true, |(_, patch)| Locator::is_patch_builtin(patch))
+        {
+            return None;
+        }
+
+        let separator = match self.range.contains("::") {
+            true => "&",
+            false => "::",
+        };
+        let range = format!(
+            "{}{separator}locator={}",
+            self.range,
+            encode_uri_component(&parent.to_string())
+        );
+        Some(Descriptor {
+            ident: self.ident.to_owned(),
+            range: Cow::Owned(range),
+        })
+    }
+
     /// If the descriptor is a patch returns the version that the patch targets
     pub fn primary_version(&self) -> Option<String> {
         let Locator { reference, .. } = Locator::from_patch_reference(&self.range)?;
@@ -191,6 +226,32 @@ impl<'a> fmt::Display for Locator<'a> {
 
 const WORKSPACE_PROTOCOL: &str = "workspace:";
 
+// Protocols where the range is resolved relative to the parent package
+const BOUND_PROTOCOLS: [&str; 5] = ["exec", "file", "link", "patch", "portal"];
+
+// Mirrors Javascript's encodeURIComponent which yarn uses for encoding locators
+fn encode_uri_component(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
 impl<'a> Locator<'a> {
     pub fn new(ident: &'a str, reference: &'a str) -> Result<Self, Error> {
         let ident = Ident::try_from(ident)?;
@@ -416,4 +477,51 @@ mod test {
             assert_eq!(patch_locator, expected, "{}", tc.locator);
         }
     }
+
+    #[test]
+    fn test_encode_uri_component() {
+        assert_eq!(
+            encode_uri_component("berry-patch@workspace:."),
+            "berry-patch%40workspace%3A."
+        );
+        assert_eq!(
+            encode_uri_component("@scope/a@workspace:packages/a"),
+            "%40scope%2Fa%40workspace%3Apackages%2Fa"
+        );
+    }
+
+    #[test]
+    fn test_descriptor_bound_to() {
+        let parent = Locator::try_from("a@workspace:packages/a").unwrap();
+        let bound = |desc: &str| {
+            Descriptor::try_from(desc)
+                .unwrap()
+                .bound_to(&parent)
+                .map(|d| d.to_string())
+        };
+        assert_eq!(
+            bound("foo@portal:../foo").as_deref(),
+            Some("foo@portal:../foo::locator=a%40workspace%3Apackages%2Fa")
+        );
+        assert_eq!(
+            bound("foo@link:../foo").as_deref(),
+            Some("foo@link:../foo::locator=a%40workspace%3Apackages%2Fa")
+        );
+        assert_eq!(
+            bound("lodash@patch:lodash@npm%3A4.17.21#./.yarn/patches/lodash.patch").as_deref(),
+            Some(
+                "lodash@patch:lodash@npm%3A4.17.21#./.yarn/patches/lodash.patch::locator=a%\
+                 40workspace%3Apackages%2Fa"
+            )
+        );
+        assert_eq!(
+            bound("resolve@patch:resolve@^1.22.0#~builtin<compat/resolve>"),
+            None
+        );
+        assert_eq!(bound("foo@npm:^1.0.0"), None);
+        assert_eq!(
+            bound("foo@portal:../foo::locator=b%40workspace%3Apackages%2Fb"),
+            None
+        );
+    }
 }
@@ -38,6 +38,9 @@ pub enum Error {
 // We depend on BTree iteration being sorted for correct serialization
 type Map<K, V> = std::collections::BTreeMap<K, V>;
 
+// Dependencies added to a package by `packageExtensions`
+type ExtensionDependencies<'a> = Map<Locator<'a>, Vec<(&'a str, &'a str)>>;
+
 pub struct BerryLockfile<'a> {
     data: &'a LockfileData,
     resolutions: Map<Descriptor<'a>, Locator<'a>>,
@@ -50,6 +53,8 @@ pub struct BerryLockfile<'a> {
     extensions: HashSet<Descriptor<'static>>,
     // Package overrides
     overrides: Map<Resolution<'a>, &'a str>,
+    // Additional dependencies added to packages via `packageExtensions`
+    package_extensions: ExtensionDependencies<'a>,
 }
 
 // This is the direct representation of the lockfile as it appears on disk.
@@ -88,13 +93,31 @@ struct BerryPackage {
 
 #[derive(Debug, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Clone, Copy)]
 struct DependencyMeta {
+    built: Option<bool>,
     optional: Option<bool>,
     unplugged: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BerryManifest {
     resolutions: Option<Map<String, String>>,
+    // Package extensions are configured in `.yarnrc.yml` instead of package.json
+    #[serde(skip)]
+    package_extensions: Option<Map<String, PackageExtension>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YarnRc {
+    #[serde(default)]
+    package_extensions: Map<String, PackageExtension>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageExtension {
+    #[serde(default)]
+    dependencies: Map<String, String>,
 }
 
 impl<'a> BerryLockfile<'a> {
@@ -132,6 +155,12 @@ impl<'a> BerryLockfile<'a> {
             .transpose()?
             .unwrap_or_default();
 
+        let package_extensions = manifest
+            .and_then(|manifest| manifest.package_extensions.as_ref())
+            .map(|extensions| Self::match_package_extensions(&locator_package, extensions))
+            .transpose()?
+            .unwrap_or_default();
+
         let mut this = Self {
             data: lockfile,
             resolutions: descriptor_locator,
@@ -140,6 +169,7 @@ impl<'a> BerryLockfile<'a> {
             patches,
             overrides,
             extensions: Default::default(),
+            package_extensions,
         };
 
         this.populate_extensions()?;
@@ -153,9 +183,9 @@ impl<'a> BerryLockfile<'a> {
             .keys()
             .filter(|descriptor| matches!(descriptor.protocol(), Some("npm")))
             .collect();
-        for (locator, package) in &self.locator_package {
-            for (name, range) in package.dependencies.iter().flatten() {
-                let mut descriptor = self.resolve_dependency(locator, name, range.as_ref())?;
+        for locator in self.locator_package.keys() {
+            for (name, range) in self.package_dependencies(locator) {
+                let mut descriptor = self.resolve_dependency(locator, name, range)?;
                 if descriptor.protocol().is_none() {
                     if let Some(range) = self.resolver.get(&descriptor) {
                         descriptor.range = range.into();
@@ -173,6 +203,74 @@ impl<'a> BerryLockfile<'a> {
         Ok(())
     }
 
+    // Finds the packages that each of the `packageExtensions` apply to
+    fn match_package_extensions(
+        locator_package: &Map<Locator<'a>, &'a BerryPackage>,
+        extensions: &'a Map<String, PackageExtension>,
+    ) -> Result<ExtensionDependencies<'a>, Error> {
+        let mut package_extensions = ExtensionDependencies::new();
+        for (selector, extension) in extensions {
+            let selector = Descriptor::try_from(selector.as_str())?;
+            let range = Descriptor::strip_protocol(&selector.range);
+            for (locator, package) in locator_package {
+                if locator.ident != selector.ident
+                    || !Self::satisfies(package.version.as_ref(), range)
+                {
+                    continue;
+                }
+                package_extensions
+                    .entry(locator.clone())
+                    .or_default()
+                    .extend(
+                        extension
+                            .dependencies
+                            .iter()
+                            .map(|(name, range)| (name.as_str(), range.as_str())),
+                    );
+            }
+        }
+        Ok(package_extensions)
+    }
+
+    // Yarn matches packageExtensions selectors with npm's range semantics
+    fn satisfies(version: &str, range: &str) -> bool {
+        if range == "*" {
+            return true;
+        }
+        match (
+            node_semver::Version::parse(version),
+            node_semver::Range::parse(range),
+        ) {
+            (Ok(version), Ok(range)) => range.satisfies(&version),
+            _ => version == range,
+        }
+    }
+
+    // All dependencies of a package including any added by `packageExtensions`
+    fn package_dependencies(
+        &self,
+        locator: &Locator<'a>,
+    ) -> impl Iterator<Item = (&'a str, &'a str)> + '_ {
+        let package = self.locator_package.get(locator).copied();
+        let dependencies = package
+            .and_then(|package| package.dependencies.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|(name, range)| (name.as_str(), range.as_ref()));
+        let extensions = self
+            .package_extensions
+            .get(locator)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(move |(name, _)| {
+                !package
+                    .and_then(|package| package.dependencies.as_ref())
+                    .map_or(false, |dependencies| dependencies.contains_key(*name))
+            });
+        dependencies.chain(extensions)
+    }
+
     /// All patch files referenced in the lockfile
     pub fn patches(&self) -> Vec<&Path> {
         self.patches
@@ -249,7 +347,7 @@ impl<'a> BerryLockfile<'a> {
         let mut patches = Map::new();
 
         // Include all workspace packages and their references
-        for (locator, package) in &self.locator_package {
+        for locator in self.locator_package.keys() {
             if workspace_packages
                 .iter()
                 .map(|s| s.as_str())
@@ -257,8 +355,8 @@ impl<'a> BerryLockfile<'a> {
                 .any(|path| locator.is_workspace_path(path))
             {
                 //  We need to track all of the descriptors coming out the workspace
-                for (name, range) in package.dependencies.iter().flatten() {
-                    let dependency = self.resolve_dependency(locator, name, range.as_ref())?;
+                for (name, range) in self.package_dependencies(locator) {
+                    let dependency = self.resolve_dependency(locator, name, range)?;
                     let dep_locator = self
                         .resolutions
                         .get(&dependency)
@@ -274,15 +372,15 @@ impl<'a> BerryLockfile<'a> {
         }
 
         for key in packages {
-            let locator = Locator::try_from(key.as_str())?;
-
-            let package = self
+            let locator = Locator::try_from(key.as_str())?.as_owned();
+            // We need a locator that borrows from the lockfile data
+            let (locator, _) = self
                 .locator_package
-                .get(&locator)
-                .ok_or_else(|| Error::MissingPackageForLocator(locator.as_owned()))?;
+                .get_key_value(&locator)
+                .ok_or_else(|| Error::MissingPackageForLocator(locator.clone()))?;
 
-            for (name, range) in package.dependencies.iter().flatten() {
-                let dependency = self.resolve_dependency(&locator, name, range.as_ref())?;
+            for (name, range) in self.package_dependencies(locator) {
+                let dependency = self.resolve_dependency(locator, name, range)?;
                 let dep_locator = self
                     .resolutions
                     .get(&dependency)
@@ -291,9 +389,17 @@ impl<'a> BerryLockfile<'a> {
             }
 
             // If the package has an associated patch we include it in the subgraph
-            if let Some(patch_locator) = self.patches.get(&locator) {
+            if let Some(patch_locator) = self.patches.get(locator) {
                 patches.insert(locator.as_owned(), patch_locator.clone());
             }
+
+            // A patch also requires the package that it patches
+            if let Some(original) = locator.patched_locator() {
+                let descriptor = Descriptor::from(original.as_owned());
+                if let Some(original_locator) = self.resolutions.get(&descriptor) {
+                    resolutions.insert(descriptor, original_locator.clone());
+                }
+            }
         }
 
         for patch in patches.values() {
@@ -336,6 +442,7 @@ impl<'a> BerryLockfile<'a> {
             resolver: self.resolver.clone(),
             extensions: self.extensions.clone(),
             overrides: self.overrides.clone(),
+            package_extensions: self.package_extensions.clone(),
         })
     }
 
@@ -362,7 +469,26 @@ impl<'a> BerryLockfile<'a> {
             }
         }
 
-        Ok(dependency)
+        Ok(self.bind_dependency(locator, dependency))
+    }
+
+    // Ranges that are relative to a package, e.g. `portal:` or `patch:` with a
+    // local patch file, are bound to the package that declared them. For
+    // dependencies this is the parent package, for overrides coming from
+    // `resolutions` this is the root workspace.
+    fn bind_dependency<'b>(&self, locator: &Locator, dependency: Descriptor<'b>) -> Descriptor<'b> {
+        if self.resolutions.contains_key(&dependency) {
+            return dependency;
+        }
+        let root_workspace = self
+            .locator_package
+            .keys()
+            .find(|locator| locator.is_workspace_path("."));
+        iter::once(locator)
+            .chain(root_workspace)
+            .filter_map(|parent| dependency.bound_to(parent))
+            .find(|bound| self.resolutions.contains_key(bound))
+            .unwrap_or(dependency)
     }
 }
 
@@ -412,21 +538,22 @@ impl<'a> Lockfile for BerryLockfile<'a> {
         let locator =
             Locator::try_from(key).unwrap_or_else(|_| panic!("Was passed invalid locator: {key}"));
 
-        let Some(package) = self.locator_package.get(&locator) else {
+        let Some((locator, _)) = self.locator_package.get_key_value(&locator) else {
             return Ok(None);
         };
 
         let mut map = HashMap::new();
-        for (name, version) in package.dependencies.iter().flatten() {
-            let mut dependency = Descriptor::new(name, version.as_ref()).unwrap();
+        for (name, version) in self.package_dependencies(locator) {
+            let mut dependency = Descriptor::new(name, version).unwrap();
             for (resolution, reference) in &self.overrides {
                 if let Some(override_dependency) =
-                    resolution.reduce_dependency(reference, &dependency, &locator)
+                    resolution.reduce_dependency(reference, &dependency, locator)
                 {
                     dependency = override_dependency;
                     break;
                 }
             }
+            let dependency = self.bind_dependency(locator, dependency);
             map.insert(dependency.ident.to_string(), dependency.range.to_string());
         }
         // For each dependency we need to check if there's an override
@@ -446,7 +573,17 @@ impl BerryManifest {
         I: IntoIterator<Item = (String, String)>,
     {
         let resolutions = Some(resolutions.into_iter().collect());
-        Self { resolutions }
+        Self {
+            resolutions,
+            package_extensions: None,
+        }
+    }
+
+    /// Adds any `packageExtensions` defined in the given `.yarnrc.yml`
+    pub fn with_yarnrc(mut self, contents: &[u8]) -> Result<Self, Error> {
+        let yarnrc: YarnRc = serde_yaml::from_slice(contents)?;
+        self.package_extensions = Some(yarnrc.package_extensions);
+        Ok(self)
     }
 
     pub fn resolutions(&self) -> Option<Result<Map<Resolution, &str>, Error>> {
@@ -466,11 +603,10 @@ pub fn berry_subgraph(
     contents: &[u8],
     workspace_packages: &[String],
     packages: &[String],
-    resolutions: Option<HashMap<String, String>>,
+    manifest: Option<&BerryManifest>,
 ) -> Result<Vec<u8>, Error> {
-    let manifest = resolutions.map(BerryManifest::with_resolutions);
     let data = LockfileData::from_bytes(contents)?;
-    let lockfile = BerryLockfile::new(&data, manifest.as_ref())?;
+    let lockfile = BerryLockfile::new(&data, manifest)?;
     let pruned_lockfile = lockfile.subgraph(workspace_packages, packages)?;
     let new_contents = pruned_lockfile.lockfile()?.to_string().into_bytes();
     Ok(new_contents)
@@ -675,6 +811,7 @@ mod test {
                     .cloned()
                     .collect(),
             ),
+            ..Default::default()
         };
         let lockfile = BerryLockfile::new(&data, Some(&manifest)).unwrap();
 
@@ -708,6 +845,7 @@ mod test {
                 .cloned()
                 .collect(),
             ),
+            ..Default::default()
         };
         let lockfile = BerryLockfile::new(&data, Some(&manifest)).unwrap();
 
@@ -748,6 +886,7 @@ mod test {
                     .cloned()
                     .collect(),
             ),
+            ..Default::default()
         };
         let lockfile = BerryLockfile::new(&data, Some(&manifest)).unwrap();
 
@@ -842,4 +981,152 @@ mod test {
              resolve@patch:resolve@^1.22.2#~builtin<compat/resolve>"
         );
     }
+
+    fn protocols_manifest() -> BerryManifest {
+        BerryManifest::with_resolutions([(
+            "lodash@^4.17.21".to_string(),
+            "patch:lodash@npm%3A4.17.21#./.yarn/patches/lodash-npm-4.17.21-6382451519.patch"
+                .to_string(),
+        )])
+        .with_yarnrc(
+            b"nodeLinker: node-modules
+packageExtensions:
+  \"debug@*\":
+    dependencies:
+      ms: ^2.1.2
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_portal_and_link_resolution() {
+        let data = LockfileData::from_bytes(include_bytes!("../../fixtures/berry-protocols.lock"))
+            .unwrap();
+        let lockfile = BerryLockfile::new(&data, None).unwrap();
+
+        assert_eq!(
+            lockfile
+                .resolve_package("packages/a", "foo", "portal:../../external/foo")
+                .unwrap(),
+            Some(Package::new(
+                "foo@portal:../../external/foo::locator=a%40workspace%3Apackages%2Fa",
+                "0.0.0-use.local"
+            ))
+        );
+        assert_eq!(
+            lockfile
+                .resolve_package("packages/b", "bar", "link:../../external/bar")
+                .unwrap(),
+            Some(Package::new(
+                "bar@link:../../external/bar::locator=b%40workspace%3Apackages%2Fb",
+                "0.0.0-use.local"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_package_extensions() {
+        let data = LockfileData::from_bytes(include_bytes!("../../fixtures/berry-protocols.lock"))
+            .unwrap();
+        let lockfile = BerryLockfile::new(&data, None).unwrap();
+        assert_eq!(
+            lockfile.all_dependencies("debug@npm:4.3.4").unwrap(),
+            Some(HashMap::new())
+        );
+
+        let manifest = protocols_manifest();
+        let lockfile = BerryLockfile::new(&data, Some(&manifest)).unwrap();
+        assert_eq!(
+            lockfile.all_dependencies("debug@npm:4.3.4").unwrap(),
+            Some(
+                [("ms".to_string(), "^2.1.2".to_string())]
+                    .into_iter()
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_package_extension_ranges() {
+        for range in [
+            "^4.3.0 || ^5.0.0",
+            ">=4.0.0 <5.0.0",
+            "4.0.0 - 4.3.4",
+            "4.x",
+            "*",
+        ] {
+            assert!(BerryLockfile::satisfies("4.3.4", range), "{range}");
+        }
+        for range in ["^3.0.0 || ^5.0.0", ">=4.3.5 <5", "~4.2.0"] {
+            assert!(!BerryLockfile::satisfies("4.3.4", range), "{range}");
+        }
+    }
+
+    #[test]
+    fn test_protocols_subgraph() {
+        let data = LockfileData::from_bytes(include_bytes!("../../fixtures/berry-protocols.lock"))
+            .unwrap();
+        let manifest = protocols_manifest();
+        let lockfile = BerryLockfile::new(&data, Some(&manifest)).unwrap();
+
+        let closure = transitive_closure(
+            &lockfile,
+            "packages/a",
+            [
+                ("debug", "^4.3.4"),
+                ("esbuild", "^0.17.19"),
+                ("foo", "portal:../../external/foo"),
+                ("lodash", "^4.17.21"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        )
+        .unwrap();
+        let mut packages = closure.into_iter().map(|pkg| pkg.key).collect::<Vec<_>>();
+        packages.sort();
+        assert_eq!(
+            packages,
+            vec![
+                "debug@npm:4.3.4",
+                "esbuild@npm:0.17.19",
+                "foo@portal:../../external/foo::locator=a%40workspace%3Apackages%2Fa",
+                "lodash@npm:4.17.21",
+                "ms@npm:2.1.2",
+            ]
+        );
+
+        let subgraph = lockfile
+            .subgraph(&["packages/a".into()], &packages)
+            .unwrap();
+        let contents = subgraph.lockfile().unwrap().to_string();
+        let expected_keys = [
+            "\"a@workspace:packages/a\":",
+            "\"debug@npm:^4.3.4\":",
+            "\"esbuild@npm:^0.17.19\":",
+            "\"foo@portal:../../external/foo::locator=a%40workspace%3Apackages%2Fa\":",
+            "\"lodash@npm:4.17.21\":",
+            "\"lodash@patch:lodash@npm%3A4.17.21#./.yarn/patches/lodash-npm-4.17.21-6382451519.\
+             patch::locator=test%40workspace%3A.\":",
+            "\"ms@npm:^2.1.2\":",
+            "\"test@workspace:.\":",
+        ];
+        for key in expected_keys {
+            assert!(contents.contains(key), "missing {key} in {contents}");
+        }
+        assert!(!contents.contains("bar@link"));
+        assert!(contents.contains(
+            "  dependenciesMeta:
+    esbuild:
+      built: false"
+        ));
+    }
+
+    #[test]
+    fn test_protocols_roundtrip() {
+        let contents = include_str!("../../fixtures/berry-protocols.lock");
+        let lockfile = LockfileData::from_bytes(contents.as_bytes()).unwrap();
+        assert_eq!(contents, lockfile.to_string());
+    }
 }
@@ -147,28 +147,29 @@ where
     let mut string = String::new();
     let mut first = true;
 
-    let mut add_line = |dependency: &str, field: &str| {
+    for (dependency, meta) in metadata {
+        let dependency = dependency.as_ref();
+        let fields = [
+            ("built", meta.built),
+            ("optional", meta.optional),
+            ("unplugged", meta.unplugged),
+        ];
+        // Each dependency should only be written once with all of its fields
+        if fields.iter().all(|(_, value)| value.is_none()) {
+            continue;
+        }
+
         if !first {
             string.push('\n');
         }
-
-        string.push_str(&format!(
-            "    {}:\n      {}: true",
-            wrap_string(dependency),
-            wrap_string(field)
-        ));
+        string.push_str(&format!("    {}:", wrap_string(dependency)));
+        for (field, value) in fields {
+            if let Some(value) = value {
+                string.push_str(&format!("\n      {}: {value}", wrap_string(field)));
+            }
+        }
 
         first = false;
-    };
-
-    for (dependency, meta) in metadata {
-        let dependency = dependency.as_ref();
-        if meta.optional.unwrap_or_default() {
-            add_line(dependency, "optional");
-        }
-        if meta.unplugged.unwrap_or_default() {
-            add_line(dependency, "unplugged");
-        }
     }
 
     string
@@ -241,4 +242,34 @@ mod test {
         let serailized = lockfile.to_string();
         assert!(serailized.contains(&format!("? {long_key}\n")));
     }
+
+    #[test]
+    fn test_dependencies_meta_display() {
+        let meta = [
+            (
+                "esbuild",
+                DependencyMeta {
+                    built: Some(false),
+                    optional: None,
+                    unplugged: Some(true),
+                },
+            ),
+            (
+                "fsevents",
+                DependencyMeta {
+                    built: None,
+                    optional: Some(true),
+                    unplugged: None,
+                },
+            ),
+        ];
+        assert_eq!(
+            stringify_dependencies_meta(meta.iter().map(|(k, v)| (k, v))),
+            "    esbuild:
+      built: false
+      unplugged: true
+    fsevents:
+      optional: true"
+        );
+    }
 }
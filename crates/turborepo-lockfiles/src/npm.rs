@@ -14,10 +14,12 @@ type Map<K, V> = std::collections::BTreeMap<K, V>;
 pub struct NpmLockfile {
     #[serde(rename = "lockfileVersion")]
     lockfile_version: i32,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
     packages: Map<String, NpmPackage>,
-    // We parse this so it doesn't end up in 'other' and we don't need to worry
-    // about accidentally serializing it.
-    #[serde(skip_serializing, default)]
+    // Only used by lockfileVersion 1, later versions also include this field for
+    // backwards compatibility, but we clear it on load so it doesn't get
+    // accidentally serialized.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
     dependencies: Map<String, Value>,
     // We want to reserialize any additional fields, but we don't use them
     // we keep them as raw values to avoid describing the correct schema.
@@ -44,6 +46,20 @@ struct NpmPackage {
     other: Map<String, Value>,
 }
 
+// An entry in the nested 'dependencies' tree used by lockfileVersion 1
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct NpmV1Dependency {
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolved: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    requires: Map<String, String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    dependencies: Map<String, NpmV1Dependency>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
 impl Lockfile for NpmLockfile {
     fn resolve_package(
         &self,
@@ -103,19 +119,134 @@ impl Lockfile for NpmLockfile {
 
 impl NpmLockfile {
     pub fn load(content: &[u8]) -> Result<Self, Error> {
-        let lockfile: NpmLockfile = serde_json::from_slice(content)?;
+        let mut lockfile: NpmLockfile = serde_json::from_slice(content)?;
+
+        if lockfile.lockfile_version <= 1 {
+            // lockfileVersion 1 only has the nested 'dependencies' tree so we
+            // flatten it into the same path based keys that 'packages' uses.
+            lockfile.packages = lockfile.v1_packages()?;
+            return Ok(lockfile);
+        }
 
-        // We don't support lockfiles without 'packages' as older versions
-        // required reading through the contents of node_modules in order
-        // to resolve dependencies.
+        // Lockfiles generated by npm 7+ without 'packages' would require
+        // reading through the contents of node_modules in order to resolve
+        // dependencies.
         // See https://github.com/npm/cli/blob/9609e9eed87c735f0319ac0af265f4d406cbf800/workspaces/arborist/lib/shrinkwrap.js#L674
-        if lockfile.lockfile_version <= 1
-            || (lockfile.packages.is_empty() && !lockfile.dependencies.is_empty())
-        {
-            Err(Error::UnsupportedNpmVersion)
-        } else {
-            Ok(lockfile)
+        if lockfile.packages.is_empty() && !lockfile.dependencies.is_empty() {
+            return Err(Error::UnsupportedNpmVersion);
+        }
+        lockfile.dependencies.clear();
+
+        Ok(lockfile)
+    }
+
+    fn is_v1(&self) -> bool {
+        self.lockfile_version <= 1
+    }
+
+    fn v1_packages(&self) -> Result<Map<String, NpmPackage>, Error> {
+        fn flatten(
+            prefix: &str,
+            dependencies: Map<String, NpmV1Dependency>,
+            packages: &mut Map<String, NpmPackage>,
+        ) {
+            for (name, dependency) in dependencies {
+                let key = format!("{prefix}node_modules/{name}");
+                let NpmV1Dependency {
+                    version,
+                    resolved,
+                    requires,
+                    dependencies,
+                    other,
+                } = dependency;
+                packages.insert(
+                    key.clone(),
+                    NpmPackage {
+                        version,
+                        resolved,
+                        dependencies: requires,
+                        dev_dependencies: Map::new(),
+                        peer_dependencies: Map::new(),
+                        optional_dependencies: Map::new(),
+                        other,
+                    },
+                );
+                flatten(&format!("{key}/"), dependencies, packages);
+            }
+        }
+
+        let dependencies = self
+            .dependencies
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), serde_json::from_value(value.clone())?)))
+            .collect::<Result<Map<String, NpmV1Dependency>, Error>>()?;
+
+        let mut packages = Map::new();
+        // lockfileVersion 1 has no entry for the root package so we synthesize one
+        // in order for the root workspace to be resolvable.
+        let root_other = ["name"]
+            .into_iter()
+            .filter_map(|field| Some((field.to_string(), self.other.get(field)?.clone())))
+            .collect();
+        packages.insert(
+            "".into(),
+            NpmPackage {
+                version: self
+                    .other
+                    .get("version")
+                    .and_then(|version| version.as_str())
+                    .map(|version| version.to_string()),
+                resolved: None,
+                dependencies: Map::new(),
+                dev_dependencies: Map::new(),
+                peer_dependencies: Map::new(),
+                optional_dependencies: Map::new(),
+                other: root_other,
+            },
+        );
+        flatten("", dependencies, &mut packages);
+
+        Ok(packages)
+    }
+
+    // Rebuilds the nested lockfileVersion 1 'dependencies' tree from 'packages'
+    fn v1_dependencies(packages: &Map<String, NpmPackage>) -> Result<Map<String, Value>, Error> {
+        let mut dependencies: Map<String, NpmV1Dependency> = Map::new();
+        // Since keys are sorted a parent will always be visited before its children
+        for (key, pkg) in packages {
+            let mut names = key
+                .split("node_modules/")
+                .map(|segment| segment.trim_end_matches('/'))
+                .peekable();
+            // Only entries that are installed into node_modules are part of the tree
+            if names.next() != Some("") || names.peek().is_none() {
+                continue;
+            }
+            let mut names: Vec<_> = names.collect();
+            let name = names.pop().expect("checked that key has a name");
+            let mut parent = &mut dependencies;
+            for ancestor in names {
+                parent = &mut parent
+                    .get_mut(ancestor)
+                    .ok_or_else(|| Error::MissingPackage(key.clone()))?
+                    .dependencies;
+            }
+            parent.insert(
+                name.to_string(),
+                NpmV1Dependency {
+                    version: pkg.version.clone(),
+                    resolved: pkg.resolved.clone(),
+                    requires: pkg.dependencies.clone(),
+                    dependencies: Map::new(),
+                    other: pkg.other.clone(),
+                },
+            );
         }
+
+        dependencies
+            .into_iter()
+            .map(|(name, dependency)| Ok((name, serde_json::to_value(dependency)?)))
+            .collect()
     }
 
     fn get_package(&self, package: impl AsRef<str>) -> Result<&NpmPackage, Error> {
@@ -139,6 +270,18 @@ impl NpmLockfile {
             pruned_packages.insert("".into(), root.clone());
         }
         for workspace in workspace_packages {
+            if self.is_v1() {
+                // lockfileVersion 1 doesn't have entries for workspaces, only for
+                // any `file:` links to them.
+                let link = format!("file:{workspace}");
+                for (key, entry) in &self.packages {
+                    if entry.version.as_deref() == Some(&link) {
+                        pruned_packages.insert(key.clone(), entry.clone());
+                    }
+                }
+                continue;
+            }
+
             let pkg = self.get_package(workspace)?;
             pruned_packages.insert(workspace.to_string(), pkg.clone());
 
@@ -149,6 +292,16 @@ impl NpmLockfile {
                 }
             }
         }
+
+        if self.is_v1() {
+            return Ok(Self {
+                lockfile_version: self.lockfile_version,
+                dependencies: Self::v1_dependencies(&pruned_packages)?,
+                packages: Map::default(),
+                other: self.other.clone(),
+            });
+        }
+
         Ok(Self {
             lockfile_version: 3,
             packages: pruned_packages,
@@ -433,4 +586,133 @@ mod test {
         assert!(closures.get("packages/c").unwrap().is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_v1_resolve_package() -> Result<(), Error> {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock-v1.json"))?;
+        let tests = [
+            ("chalk", "node_modules/chalk", "4.1.2"),
+            (
+                "node_modules/chalk/node_modules/ansi-styles",
+                "node_modules/chalk/node_modules/ansi-styles",
+                "4.3.0",
+            ),
+            ("ui", "node_modules/ui", "file:packages/ui"),
+        ];
+
+        for (name, key, version) in &tests {
+            let pkg = lockfile.resolve_package("", name, "")?;
+            assert_eq!(pkg, Some(Package::new(*key, *version)));
+        }
+        assert!(lockfile.resolve_package("", "react", "")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1_all_dependencies() -> Result<(), Error> {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock-v1.json"))?;
+        let tests = [
+            (
+                "node_modules/chalk",
+                vec![
+                    "node_modules/chalk/node_modules/ansi-styles",
+                    "node_modules/supports-color",
+                ],
+            ),
+            (
+                "node_modules/chalk/node_modules/ansi-styles",
+                vec!["node_modules/color-convert"],
+            ),
+            ("node_modules/color-name", vec![]),
+        ];
+
+        for (key, expected) in &tests {
+            let deps = lockfile.all_dependencies(key)?.unwrap();
+            let mut actual_keys: Vec<_> = deps.keys().collect();
+            actual_keys.sort();
+            assert_eq!(&actual_keys, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1_subgraph() -> Result<(), Error> {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock-v1.json"))?;
+        let closure = crate::transitive_closure(
+            &lockfile,
+            "",
+            vec![("chalk".to_string(), "^4.1.2".to_string())]
+                .into_iter()
+                .collect(),
+        )?;
+        let mut packages: Vec<_> = closure.into_iter().map(|pkg| pkg.key).collect();
+        packages.sort();
+
+        let pruned = lockfile.subgraph(&["packages/ui".into()], &packages)?;
+        let serialized = serde_json::to_value(&pruned)?;
+        assert_eq!(serialized["lockfileVersion"], 1);
+        assert!(serialized.get("packages").is_none());
+        let dependencies = serialized["dependencies"].as_object().unwrap();
+        let mut names: Vec<_> = dependencies.keys().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "chalk",
+                "color-convert",
+                "color-name",
+                "has-flag",
+                "supports-color",
+                "ui"
+            ]
+        );
+        assert_eq!(
+            dependencies["chalk"]["dependencies"]["ansi-styles"]["requires"]["color-convert"],
+            "^2.0.1"
+        );
+        assert_eq!(
+            dependencies["color-name"]["integrity"],
+            "sha512-dOy+3AuW3a2wNbZHIuMZpTcgjGuLU/uBL/\
+             ubcZF9OXbDo8ff4O8yVp5Bf0efS8uEoYo5q4Fx7dY9OgQGXgAsQA=="
+        );
+
+        // The pruned lockfile should be readable and equivalent
+        let reloaded = NpmLockfile::load(&serde_json::to_vec(&pruned)?)?;
+        assert_eq!(
+            reloaded.resolve_package("", "ansi-styles", "")?,
+            None,
+            "nested packages shouldn't be hoisted"
+        );
+        assert_eq!(
+            reloaded.all_dependencies("node_modules/chalk")?,
+            lockfile.all_dependencies("node_modules/chalk")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shrinkwrap() -> Result<(), Error> {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-shrinkwrap.json"))?;
+        let closure = crate::transitive_closure(
+            &lockfile,
+            "",
+            vec![("chalk".to_string(), "^4.1.2".to_string())]
+                .into_iter()
+                .collect(),
+        )?;
+        assert_eq!(closure.len(), 6);
+        assert!(closure.contains(&Package::new("node_modules/has-flag", "4.0.0")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_dependencies_not_serialized() -> Result<(), Error> {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock.json"))?;
+        let serialized = serde_json::to_value(lockfile)?;
+        assert!(serialized.get("dependencies").is_none());
+        Ok(())
+    }
 }
@@ -6,10 +6,11 @@ use std::{
 };
 
 use camino::Utf8Path;
+use serde::{Deserialize, Serialize};
 
 use crate::{IntoUnix, PathError, RelativeUnixPath};
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
 pub struct RelativeUnixPathBuf(pub(crate) String);
 
 impl Display for RelativeUnixPathBuf {
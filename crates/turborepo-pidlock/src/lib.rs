@@ -2,7 +2,7 @@ use std::{
     convert::TryInto,
     fs,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
 };
 
@@ -21,6 +21,9 @@ pub enum PidlockError {
     /// The lock is already owned by a running process
     #[error("already owned")]
     AlreadyOwned,
+    /// A lock file could not be removed
+    #[error("unable to remove lock at \"{0}\"")]
+    RemoveFailed(PathBuf),
 }
 
 /// A result from a Pidlock operation
@@ -70,6 +73,188 @@ fn process_exists(pid: i32) -> bool {
     }
 }
 
+// Start times are derived from clocks that may be adjusted while a process is
+// running, so we allow some leeway when comparing them. Reusing a pid within
+// this window is not something we expect to happen in practice.
+const START_TIME_TOLERANCE_MS: u64 = 1000;
+
+/// Returns when a process was started in milliseconds since the Unix epoch.
+#[cfg(target_os = "linux")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The executable name is wrapped in parens and can itself contain spaces or
+    // parens so we only look at the fields after it. The start time is the 22nd
+    // field and is measured in clock ticks since boot.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let ticks: u64 = fields.split_whitespace().nth(19)?.parse().ok()?;
+    let boot_time: u64 = fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return None;
+    }
+    Some(boot_time * 1000 + ticks * 1000 / ticks_per_second as u64)
+}
+
+/// Returns when a process was started in milliseconds since the Unix epoch.
+#[cfg(target_os = "macos")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    let mut info: libc::proc_bsdinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+    let result = unsafe {
+        libc::proc_pidinfo(
+            pid as libc::c_int,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            &mut info as *mut libc::proc_bsdinfo as *mut libc::c_void,
+            size,
+        )
+    };
+    if result != size {
+        return None;
+    }
+    Some(info.pbi_start_tvsec * 1000 + info.pbi_start_tvusec / 1000)
+}
+
+/// Returns when a process was started in milliseconds since the Unix epoch.
+#[cfg(target_os = "windows")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, FILETIME},
+        System::Threading::{GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    };
+    // Difference between the FILETIME epoch (1601-01-01) and the Unix epoch in
+    // 100ns intervals
+    const UNIX_EPOCH_INTERVALS: u64 = 116_444_736_000_000_000;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle == 0 {
+            return None;
+        }
+        let empty = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut creation, mut exit, mut kernel, mut user) = (empty, empty, empty, empty);
+        let result = GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user);
+        CloseHandle(handle);
+        if result == 0 {
+            return None;
+        }
+        let intervals = ((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64;
+        intervals
+            .checked_sub(UNIX_EPOCH_INTERVALS)
+            .map(|intervals| intervals / 10_000)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn process_start_time(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// The process recorded in a lock file.
+///
+/// Lock files start with the pid on its own line so that readers that only
+/// understand plain pid files keep working, any additional details follow as
+/// `key=value` lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockOwner {
+    pub pid: u32,
+    /// Milliseconds since the Unix epoch at which the process started
+    pub start_time: Option<u64>,
+    /// Version of the binary that holds the lock
+    pub version: Option<String>,
+    /// Socket the lock owner is listening on
+    pub socket: Option<PathBuf>,
+}
+
+impl LockOwner {
+    fn current(version: Option<String>, socket: Option<PathBuf>) -> Self {
+        let pid = process::id();
+        LockOwner {
+            pid,
+            start_time: process_start_time(pid),
+            version,
+            socket,
+        }
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let pid = lines.next()?.trim().parse::<i32>().ok()?;
+        let mut owner = LockOwner {
+            pid: pid.try_into().ok()?,
+            start_time: None,
+            version: None,
+            socket: None,
+        };
+        for line in lines {
+            match line.split_once('=') {
+                Some(("start_time", start_time)) => owner.start_time = start_time.parse().ok(),
+                Some(("version", version)) => owner.version = Some(version.to_string()),
+                Some(("socket", socket)) => owner.socket = Some(PathBuf::from(socket)),
+                // ignore anything we don't understand so that newer lock files can
+                // still be read by older versions
+                _ => {}
+            }
+        }
+        Some(owner)
+    }
+
+    fn serialize(&self) -> String {
+        let mut contents = self.pid.to_string();
+        if let Some(start_time) = self.start_time {
+            contents.push_str(&format!("\nstart_time={start_time}"));
+        }
+        if let Some(version) = &self.version {
+            contents.push_str(&format!("\nversion={version}"));
+        }
+        if let Some(socket) = &self.socket {
+            contents.push_str(&format!("\nsocket={}", socket.display()));
+        }
+        contents.push('\n');
+        contents
+    }
+
+    /// Returns true if the recorded process is still running. If the lock file
+    /// has a start time, the running process must have started at the same
+    /// time to guard against the pid being reused by an unrelated process.
+    pub fn is_running(&self) -> bool {
+        let Ok(pid) = i32::try_from(self.pid) else {
+            return false;
+        };
+        if !process_exists(pid) {
+            return false;
+        }
+        match (self.start_time, process_start_time(self.pid)) {
+            (Some(recorded), Some(actual)) => recorded.abs_diff(actual) <= START_TIME_TOLERANCE_MS,
+            // We can't tell if the pid has been reused so we err on the side of
+            // assuming the lock is still held
+            _ => true,
+        }
+    }
+}
+
+/// The state of a lock file on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockFileState {
+    /// There is no lock file
+    Unlocked,
+    /// The lock is held by a running process
+    Owned(LockOwner),
+    /// The lock file refers to a process that is no longer running
+    Stale(LockOwner),
+    /// The lock file exists but its contents can't be read
+    Corrupted,
+}
+
 /// A pid-centered lock. A lock is considered "acquired" when a file exists on
 /// disk at the path specified, containing the process id of the locking
 /// process.
@@ -80,6 +265,10 @@ pub struct Pidlock {
     path: PathBuf,
     /// Current state of the Pidlock
     state: PidlockState,
+    /// Version to record in the lock file
+    version: Option<String>,
+    /// Socket to record in the lock file
+    socket: Option<PathBuf>,
 }
 
 impl Pidlock {
@@ -89,9 +278,28 @@ impl Pidlock {
             pid: process::id(),
             path,
             state: PidlockState::New,
+            version: None,
+            socket: None,
         }
     }
 
+    /// Record the version of the locking binary in the lock file.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Record the socket the locking process listens on in the lock file.
+    pub fn with_socket(mut self, socket: impl Into<PathBuf>) -> Self {
+        self.socket = Some(socket.into());
+        self
+    }
+
+    /// The path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Acquire a lock.
     pub fn acquire(&mut self) -> PidlockResult {
         match self.state {
@@ -101,9 +309,18 @@ impl Pidlock {
             }
         }
 
-        // acquiring something with a valid owner is an error
-        if self.get_owner().is_some() {
-            return Err(PidlockError::AlreadyOwned);
+        // acquiring something with a valid owner is an error, but a lock left
+        // behind by a process that is gone can be safely taken over
+        match self.file_state() {
+            LockFileState::Owned(_) => return Err(PidlockError::AlreadyOwned),
+            LockFileState::Stale(owner) => {
+                warn!(
+                    "removing stale pid file at {:?} owned by {}",
+                    self.path, owner.pid
+                );
+                self.remove_stale(&owner)?;
+            }
+            LockFileState::Unlocked | LockFileState::Corrupted => {}
         }
 
         if let Some(p) = self.path.parent() {
@@ -121,8 +338,9 @@ impl Pidlock {
                 return Err(PidlockError::LockExists(self.path.clone()));
             }
         };
-        file.write_all(&format!("{}", self.pid).into_bytes()[..])
-            .unwrap();
+        let owner = LockOwner::current(self.version.clone(), self.socket.clone());
+        debug_assert_eq!(owner.pid, self.pid);
+        file.write_all(owner.serialize().as_bytes()).unwrap();
 
         self.state = PidlockState::Acquired;
         Ok(())
@@ -149,34 +367,70 @@ impl Pidlock {
     }
 
     /// Gets the owner of this lockfile, returning the pid. If the lock file
-    /// doesn't exist, or the specified pid is not a valid process id on the
-    /// system, it clears it.
+    /// doesn't exist, or the specified pid is not a running process that
+    /// matches the one that acquired the lock, `None` is returned.
     pub fn get_owner(&self) -> Option<u32> {
-        let mut file = match fs::OpenOptions::new().read(true).open(self.path.clone()) {
-            Ok(file) => file,
-            Err(_) => {
-                return None;
+        match self.file_state() {
+            LockFileState::Owned(owner) => Some(owner.pid),
+            LockFileState::Stale(_) => {
+                warn!("stale pid file at {:?}", self.path);
+                None
             }
-        };
+            LockFileState::Corrupted => {
+                warn!("corrupted/invalid pid file at {:?}", self.path);
+                None
+            }
+            LockFileState::Unlocked => None,
+        }
+    }
+
+    /// Reads the lock file and determines whether it is held by a running
+    /// process.
+    pub fn file_state(&self) -> LockFileState {
+        match self.read_owner() {
+            Some(Some(owner)) if owner.is_running() => LockFileState::Owned(owner),
+            Some(Some(owner)) => LockFileState::Stale(owner),
+            Some(None) => LockFileState::Corrupted,
+            None => LockFileState::Unlocked,
+        }
+    }
+
+    /// Removes the lock file if it isn't held by a running process, returning
+    /// the state of the lock file prior to removal. A lock that is owned by a
+    /// running process is never removed.
+    pub fn clear_stale(&self) -> Result<LockFileState, PidlockError> {
+        let state = self.file_state();
+        match &state {
+            LockFileState::Stale(owner) => self.remove_stale(owner)?,
+            LockFileState::Corrupted => fs::remove_file(&self.path)
+                .map_err(|_| PidlockError::RemoveFailed(self.path.clone()))?,
+            LockFileState::Owned(_) | LockFileState::Unlocked => {}
+        }
+        Ok(state)
+    }
+
+    // Returns `None` if there's no lock file and `Some(None)` if it can't be
+    // parsed
+    fn read_owner(&self) -> Option<Option<LockOwner>> {
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .open(self.path.clone())
+            .ok()?;
 
         let mut contents = String::new();
         if file.read_to_string(&mut contents).is_err() {
-            warn!("corrupted/invalid pid file at {:?}", self.path);
-            return None;
+            return Some(None);
         }
+        Some(LockOwner::parse(&contents))
+    }
 
-        match contents.trim().parse::<i32>() {
-            Ok(pid) if process_exists(pid) => {
-                Some(pid.try_into().expect("if a pid exists it is a valid u32"))
-            }
-            Ok(_) => {
-                warn!("stale pid file at {:?}", self.path);
-                None
-            }
-            Err(_) => {
-                warn!("nonnumeric pid file at {:?}", self.path);
-                None
-            }
+    // Only remove the lock file if it still refers to the stale owner, this
+    // avoids removing a lock that another process acquired after we read it.
+    fn remove_stale(&self, stale: &LockOwner) -> PidlockResult {
+        match self.read_owner() {
+            Some(Some(owner)) if &owner == stale => fs::remove_file(&self.path)
+                .map_err(|_| PidlockError::RemoveFailed(self.path.clone())),
+            _ => Ok(()),
         }
     }
 }
@@ -191,11 +445,11 @@ impl Drop for Pidlock {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, io::Write, path::PathBuf};
+    use std::{fs, io::Write, path::PathBuf, process};
 
     use rand::{distributions::Alphanumeric, thread_rng, Rng};
 
-    use super::{Pidlock, PidlockError, PidlockState};
+    use super::{LockFileState, LockOwner, Pidlock, PidlockError, PidlockState};
 
     // This was removed from the library itself, but retained here
     // to assert backwards compatibility with std::process::id
@@ -297,6 +551,19 @@ mod tests {
         assert!(!pidfile.locked());
     }
 
+    // A pid for a process that has exited and been reaped
+    fn dead_pid() -> u32 {
+        // The test binary exits immediately when asked to list tests
+        let mut child = process::Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(process::Stdio::null())
+            .spawn()
+            .expect("unable to spawn process");
+        let pid = child.id();
+        child.wait().unwrap();
+        pid
+    }
+
     #[test]
     fn test_stale_pid() {
         let (_tmp, path) = make_pid_path();
@@ -306,13 +573,98 @@ mod tests {
             .open(path.clone())
             .expect("Could not open file for writing");
 
-        file.write_all(&format!("{}", thread_rng().gen::<i32>()).into_bytes()[..])
+        file.write_all(&format!("{}", dead_pid()).into_bytes()[..])
             .unwrap();
 
         drop(file);
 
         let mut pidfile = Pidlock::new(path.clone());
-        assert_eq!(pidfile.acquire(), Err(PidlockError::LockExists(path)));
+        pidfile.acquire().unwrap();
+        assert_eq!(pidfile.get_owner(), Some(getpid()));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    #[test]
+    fn test_reused_pid() {
+        let (_tmp, path) = make_pid_path();
+        // Our pid is running, but it didn't start at the recorded time so it must
+        // have been reused
+        fs::write(&path, format!("{}\nstart_time=1000\n", getpid())).unwrap();
+
+        let mut pidfile = Pidlock::new(path.clone());
+        assert!(matches!(pidfile.file_state(), LockFileState::Stale(_)));
+        assert_eq!(pidfile.get_owner(), None);
+        pidfile.acquire().unwrap();
+        assert!(matches!(pidfile.file_state(), LockFileState::Owned(_)));
+    }
+
+    #[test]
+    fn test_legacy_pid_file() {
+        let (_tmp, path) = make_pid_path();
+        fs::write(&path, format!("{}", getpid())).unwrap();
+
+        let mut pidfile = Pidlock::new(path);
+        assert_eq!(pidfile.get_owner(), Some(getpid()));
+        assert_eq!(pidfile.acquire(), Err(PidlockError::AlreadyOwned));
+    }
+
+    #[test]
+    fn test_lock_file_contents() {
+        let (_tmp, path) = make_pid_path();
+        let mut pidfile = Pidlock::new(path.clone())
+            .with_version("1.2.3")
+            .with_socket("/tmp/turbod.sock");
+        pidfile.acquire().unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().next(), Some(getpid().to_string().as_str()));
+
+        let LockFileState::Owned(owner) = Pidlock::new(path).file_state() else {
+            panic!("expected lock to be owned");
+        };
+        assert_eq!(owner.pid, getpid());
+        assert_eq!(owner.version.as_deref(), Some("1.2.3"));
+        assert_eq!(owner.socket, Some(PathBuf::from("/tmp/turbod.sock")));
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+        assert!(owner.start_time.is_some());
+    }
+
+    #[test]
+    fn test_parse_ignores_unknown_keys() {
+        assert_eq!(
+            LockOwner::parse("123\nstart_time=456\nfuture=value\nversion=1.0.0\n"),
+            Some(LockOwner {
+                pid: 123,
+                start_time: Some(456),
+                version: Some("1.0.0".to_string()),
+                socket: None,
+            })
+        );
+        assert_eq!(LockOwner::parse("-1"), None);
+        assert_eq!(LockOwner::parse(""), None);
+    }
+
+    #[test]
+    fn test_clear_stale() {
+        let (_tmp, path) = make_pid_path();
+        let pidfile = Pidlock::new(path.clone());
+        assert_eq!(pidfile.clear_stale(), Ok(LockFileState::Unlocked));
+
+        fs::write(&path, format!("{}\nversion=1.0.0", dead_pid())).unwrap();
+        assert!(matches!(
+            pidfile.clear_stale(),
+            Ok(LockFileState::Stale(LockOwner { version: Some(version), .. })) if version == "1.0.0"
+        ));
+        assert!(!path.exists());
+
+        fs::write(&path, "not a pid").unwrap();
+        assert_eq!(pidfile.clear_stale(), Ok(LockFileState::Corrupted));
+        assert!(!path.exists());
+
+        let mut owner = Pidlock::new(path.clone());
+        owner.acquire().unwrap();
+        assert!(matches!(pidfile.clear_stale(), Ok(LockFileState::Owned(_))));
+        assert!(path.exists());
     }
 
     #[test]
@@ -0,0 +1,99 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The result of the last update check against a version source
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedCheck {
+    // Seconds since the unix epoch
+    pub checked_at: u64,
+    // The url that was checked, so that changing the registry invalidates the
+    // cached result
+    pub source: String,
+    pub latest_version: Option<String>,
+}
+
+impl CachedCheck {
+    pub fn new(source: String, latest_version: Option<String>, now: SystemTime) -> Self {
+        Self {
+            checked_at: now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            source,
+            latest_version,
+        }
+    }
+
+    pub fn is_stale(&self, interval: Duration, now: SystemTime) -> bool {
+        let checked_at = UNIX_EPOCH + Duration::from_secs(self.checked_at);
+        now.duration_since(checked_at)
+            .map_or(false, |elapsed| elapsed >= interval)
+    }
+}
+
+/// Stores the last check for each package and channel in a JSON file
+#[derive(Debug, Clone)]
+pub struct VersionCache {
+    path: PathBuf,
+}
+
+impl VersionCache {
+    pub fn new(dir: &Path, package_name: &str, tag: &str) -> Self {
+        Self {
+            path: dir.join(format!("{}-{}.json", package_name, tag)),
+        }
+    }
+
+    /// A cache in the user's cache directory, if they have one
+    pub fn in_user_cache_dir(package_name: &str, tag: &str) -> Option<Self> {
+        let dir = dirs_next::cache_dir()?.join("turbo").join("update-check");
+        Some(Self::new(&dir, package_name, tag))
+    }
+
+    /// The last check, if there was one and the cache can be read
+    pub fn read(&self) -> Option<CachedCheck> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn write(&self, check: &CachedCheck) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string(check)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_version_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VersionCache::new(&dir.path().join("nested"), "turbo", "latest");
+        assert_eq!(cache.read(), None);
+
+        let now = SystemTime::now();
+        let check = CachedCheck::new(
+            "https://registry.example.com/turbo/latest".to_string(),
+            Some("1.10.7".to_string()),
+            now,
+        );
+        cache.write(&check).unwrap();
+        assert_eq!(cache.read(), Some(check.clone()));
+        // Channels are cached separately
+        assert_eq!(
+            VersionCache::new(&dir.path().join("nested"), "turbo", "canary").read(),
+            None
+        );
+
+        let day = Duration::from_secs(60 * 60 * 24);
+        assert!(!check.is_stale(day, now));
+        assert!(check.is_stale(day, now + day));
+        assert!(check.is_stale(Duration::ZERO, now));
+    }
+}
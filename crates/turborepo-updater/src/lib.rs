@@ -1,14 +1,18 @@
-use std::{fmt, time::Duration};
+use std::{
+    fmt, thread,
+    time::{Duration, SystemTime},
+};
 
 use console::style;
 use semver::Version as SemVerVersion;
 use serde::Deserialize;
 use thiserror::Error as ThisError;
-use update_informer::{
-    http_client::{GenericHttpClient, HeaderMap, HttpClient},
-    Check, Package, Registry, Result as UpdateResult,
-};
 
+use crate::cache::{CachedCheck, VersionCache};
+pub use crate::policy::{MismatchAction, UpdatePolicy, VersionPolicyError};
+
+mod cache;
+mod policy;
 mod ui;
 
 // 800ms
@@ -47,53 +51,73 @@ impl fmt::Display for VersionTag {
     }
 }
 
-struct NPMRegistry;
-
-impl Registry for NPMRegistry {
-    const NAME: &'static str = "npm-registry";
-    fn get_latest_version<T: HttpClient>(
-        http: GenericHttpClient<T>,
-        pkg: &Package,
-    ) -> UpdateResult<Option<String>> {
-        // determine tag to request
-        let tag = get_tag_from_version(&pkg.version().semver().pre);
-        // since we're overloading tag within name, we need to split it back out
-        let full_name = pkg.to_string();
-        let split_name: Vec<&str> = full_name.split('/').collect();
-        let name = split_name[1];
-        let url = format!(
+/// The url to look up the newest version of a package on a channel. npm
+/// registries serve a package's manifest for each dist-tag.
+fn version_url(registry: Option<&str>, package_name: &str, tag: &VersionTag) -> String {
+    match registry {
+        Some(registry) => format!(
+            "{registry}/{name}/{tag}",
+            registry = registry.trim_end_matches('/'),
+            name = package_name,
+            tag = tag
+        ),
+        None => format!(
             "https://turbo.build/api/binaries/version?name={name}&tag={tag}",
-            name = name,
+            name = package_name,
             tag = tag
-        );
-
-        let result: NpmVersionData = http.get(&url)?;
-        Ok(Some(result.version))
+        ),
     }
 }
 
-// Source https://github.com/mgrachev/update-informer/blob/main/src/http_client/reqwest.rs
-// Vendored here until update-informer allows us to control tls implementation
-pub struct ReqwestHttpClient;
-
-impl HttpClient for ReqwestHttpClient {
-    fn get<T: serde::de::DeserializeOwned>(
-        url: &str,
-        timeout: Duration,
-        headers: HeaderMap,
-    ) -> Result<T, Box<dyn std::error::Error>> {
-        let mut req = reqwest::blocking::Client::builder()
-            .timeout(timeout)
-            .build()?
-            .get(url);
-
-        for (key, value) in headers {
-            req = req.header(key, value);
-        }
-
-        let json = req.send()?.json()?;
+fn fetch_latest_version(url: &str, timeout: Duration) -> Result<String, reqwest::Error> {
+    let data: NpmVersionData = reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()?
+        .get(url)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(data.version)
+}
 
-        Ok(json)
+/// The newest version we know of. A previous result is used whenever there
+/// is one, and refreshed in the background once it's older than `interval`,
+/// so that slow or offline networks never hold up a command. Only the first
+/// check against a source, or a forced check with an `interval` of zero,
+/// waits for the network.
+fn latest_version(
+    cache: Option<VersionCache>,
+    url: String,
+    timeout: Duration,
+    interval: Duration,
+) -> Option<String> {
+    let now = SystemTime::now();
+    let cached = cache
+        .as_ref()
+        .and_then(|cache| cache.read())
+        .filter(|cached| cached.source == url && !interval.is_zero());
+    match cached {
+        Some(cached) => {
+            if cached.is_stale(interval, now) {
+                // The thread is abandoned if the command finishes first
+                thread::spawn(move || {
+                    if let (Some(cache), Ok(version)) = (cache, fetch_latest_version(&url, timeout))
+                    {
+                        let _ = cache.write(&CachedCheck::new(url, Some(version), now));
+                    }
+                });
+            }
+            cached.latest_version
+        }
+        None => {
+            let version = fetch_latest_version(&url, timeout).ok();
+            // Failures are cached too, so an offline machine only waits once
+            // per interval
+            if let Some(cache) = cache {
+                let _ = cache.write(&CachedCheck::new(url, version.clone(), now));
+            }
+            version
+        }
     }
 }
 
@@ -119,6 +143,7 @@ pub fn check_for_updates(
     github_repo: &str,
     footer: Option<&str>,
     current_version: &str,
+    registry: Option<&str>,
     timeout: Option<Duration>,
     interval: Option<Duration>,
 ) -> Result<(), UpdateNotifierError> {
@@ -127,21 +152,19 @@ pub fn check_for_updates(
         return Ok(());
     }
 
-    // we want notifications per channel (latest, canary, etc) so we need to ensure
-    // we have one cached latest version per channel. UpdateInformer does not
-    // support this out of the box, so we hack it into the name by overloading
-    // owner (in the supported owner/name format) to be channel/name.
+    // we want notifications per channel (latest, canary, etc) so we cache one
+    // latest version per channel
     let parsed_version = SemVerVersion::parse(current_version)?;
     let tag = get_tag_from_version(&parsed_version.pre);
-    let package_name = format!("{}/{}", tag, package_name);
+    let url = version_url(registry, package_name, &tag);
+    let cache = VersionCache::in_user_cache_dir(package_name, &tag.to_string());
 
     let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
     let interval = interval.unwrap_or(DEFAULT_INTERVAL);
-    let informer = update_informer::new(NPMRegistry, package_name, current_version)
-        .http_client(ReqwestHttpClient)
-        .timeout(timeout)
-        .interval(interval);
-    if let Ok(Some(version)) = informer.check_version() {
+    let latest_version = latest_version(cache, url, timeout, interval)
+        .and_then(|version| SemVerVersion::parse(&version).ok())
+        .filter(|version| version > &parsed_version);
+    if let Some(version) = latest_version {
         let latest_version = version.to_string();
         // TODO: make this package manager aware
         let update_cmd = style("npx @turbo/codemod update").cyan().bold();
@@ -0,0 +1,112 @@
+use node_semver::{Range, Version};
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug, PartialEq)]
+pub enum VersionPolicyError {
+    #[error("Invalid \"turboVersion\" range \"{range}\" in \"updatePolicy\": {message}")]
+    InvalidRange { range: String, message: String },
+    #[error("Invalid turbo version \"{version}\": {message}")]
+    InvalidVersion { version: String, message: String },
+    #[error(
+        "turbo {version} is outside of the range \"{range}\" allowed by this repository's \
+         \"updatePolicy\". Install a turbo version within that range"
+    )]
+    OutsideRange { version: String, range: String },
+}
+
+/// What to do when the local turbo is outside of the allowed range, or the
+/// range itself is invalid
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MismatchAction {
+    #[default]
+    Warn,
+    Error,
+}
+
+/// The `updatePolicy` from a repository's root turbo.json
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdatePolicy {
+    /// An npm style range of the turbo versions the repository allows to be
+    /// installed locally, e.g. "^1.10.0" or ">=1.10.0 <1.11.0"
+    pub turbo_version: Option<String>,
+    #[serde(default)]
+    pub on_mismatch: MismatchAction,
+    /// The npm registry to look for new turbo versions in, e.g. a private
+    /// mirror. Defaults to turbo.build.
+    pub registry: Option<String>,
+    /// Set to false to never show update notifications for the repository
+    pub notify_updates: Option<bool>,
+}
+
+impl UpdatePolicy {
+    /// Checks that `version` is within the allowed `turboVersion` range. Any
+    /// version is allowed if there is no range.
+    pub fn check_version(&self, version: &str) -> Result<(), VersionPolicyError> {
+        let Some(range) = &self.turbo_version else {
+            return Ok(());
+        };
+        let parsed_range = Range::parse(range).map_err(|e| VersionPolicyError::InvalidRange {
+            range: range.clone(),
+            message: e.to_string(),
+        })?;
+        let parsed_version =
+            Version::parse(version).map_err(|e| VersionPolicyError::InvalidVersion {
+                version: version.to_string(),
+                message: e.to_string(),
+            })?;
+        if parsed_range.satisfies(&parsed_version) {
+            Ok(())
+        } else {
+            Err(VersionPolicyError::OutsideRange {
+                version: version.to_string(),
+                range: range.clone(),
+            })
+        }
+    }
+
+    pub fn notify_updates(&self) -> bool {
+        self.notify_updates.unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_version() {
+        let policy: UpdatePolicy =
+            serde_json::from_str(r#"{"turboVersion": ">=1.10.0 <1.11.0", "onMismatch": "error"}"#)
+                .unwrap();
+        assert_eq!(policy.on_mismatch, MismatchAction::Error);
+        assert!(policy.notify_updates());
+
+        assert_eq!(policy.check_version("1.10.7"), Ok(()));
+        assert_eq!(
+            policy.check_version("1.9.3"),
+            Err(VersionPolicyError::OutsideRange {
+                version: "1.9.3".to_string(),
+                range: ">=1.10.0 <1.11.0".to_string(),
+            })
+        );
+        assert!(matches!(
+            policy.check_version("latest"),
+            Err(VersionPolicyError::InvalidVersion { .. })
+        ));
+
+        // Without a range every version is allowed
+        assert_eq!(UpdatePolicy::default().check_version("0.0.1"), Ok(()));
+
+        let invalid = UpdatePolicy {
+            turbo_version: Some("not a range".to_string()),
+            ..UpdatePolicy::default()
+        };
+        assert!(matches!(
+            invalid.check_version("1.10.7"),
+            Err(VersionPolicyError::InvalidRange { .. })
+        ));
+    }
+}
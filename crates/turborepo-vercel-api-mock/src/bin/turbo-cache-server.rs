@@ -0,0 +1,95 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use url::Url;
+use vercel_api_mock::cache::{
+    start_cache_server, CacheServerConfig, DiskStorage, S3Config, S3Storage, TokenGrant,
+    DEFAULT_MAX_ARTIFACT_SIZE,
+};
+
+/// A self-hosted remote cache for turbo
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Args {
+    /// Address to listen on
+    #[clap(long, env = "TURBO_CACHE_ADDR", default_value = "127.0.0.1:8000")]
+    addr: SocketAddr,
+    /// Tokens that are allowed to access the cache in the form
+    /// <token>:<team>[,<team>...]. Requests without a team are scoped to the
+    /// first team.
+    #[clap(
+        long = "token",
+        env = "TURBO_CACHE_TOKENS",
+        value_delimiter = ' ',
+        required = true
+    )]
+    tokens: Vec<TokenGrant>,
+    /// Require uploaded artifacts to be signed with this key
+    #[clap(long, env = "TURBO_REMOTE_CACHE_SIGNATURE_KEY", hide_env_values = true)]
+    signature_key: Option<String>,
+    /// Maximum artifact size in bytes
+    #[clap(long, default_value_t = DEFAULT_MAX_ARTIFACT_SIZE)]
+    max_artifact_size: usize,
+    /// Directory to store artifacts in
+    #[clap(long, default_value = ".turbo-cache", conflicts_with = "s3_bucket")]
+    storage_dir: PathBuf,
+    /// Store artifacts in this S3 bucket instead of on disk
+    #[clap(long, env = "TURBO_CACHE_S3_BUCKET")]
+    s3_bucket: Option<String>,
+    /// Endpoint of the S3 compatible service
+    #[clap(
+        long,
+        env = "TURBO_CACHE_S3_ENDPOINT",
+        default_value = "https://s3.amazonaws.com"
+    )]
+    s3_endpoint: Url,
+    #[clap(long, env = "AWS_REGION", default_value = "us-east-1")]
+    s3_region: String,
+    /// Prefix for all object keys
+    #[clap(long, env = "TURBO_CACHE_S3_PREFIX", default_value = "")]
+    s3_prefix: String,
+    /// Use path style addressing for buckets
+    #[clap(long, env = "TURBO_CACHE_S3_PATH_STYLE")]
+    s3_path_style: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let args = Args::parse();
+    let config = CacheServerConfig {
+        tokens: args.tokens,
+        signature_key: args.signature_key.map(String::into_bytes),
+        max_artifact_size: args.max_artifact_size,
+    };
+
+    info!("listening on {}", args.addr);
+    match args.s3_bucket {
+        Some(bucket) => {
+            let access_key_id = std::env::var("AWS_ACCESS_KEY_ID")
+                .map_err(|_| anyhow!("AWS_ACCESS_KEY_ID must be set to use S3 storage"))?;
+            let secret_access_key = std::env::var("AWS_SECRET_ACCESS_KEY")
+                .map_err(|_| anyhow!("AWS_SECRET_ACCESS_KEY must be set to use S3 storage"))?;
+            let storage = S3Storage::new(S3Config {
+                endpoint: args.s3_endpoint,
+                bucket,
+                region: args.s3_region,
+                access_key_id,
+                secret_access_key,
+                prefix: args.s3_prefix,
+                path_style: args.s3_path_style,
+            });
+            start_cache_server(args.addr, storage, config).await
+        }
+        None => {
+            let storage = DiskStorage::new(args.storage_dir);
+            start_cache_server(args.addr, storage, config).await
+        }
+    }
+}
@@ -0,0 +1,759 @@
+//! A remote cache server that speaks the same `/v8/artifacts` protocol as the
+//! Vercel Remote Cache. Unlike the rest of this crate it isn't a mock, it
+//! persists artifacts to a pluggable [`ArtifactStorage`] and only serves
+//! requests for the teams a token has been granted access to.
+
+mod s3;
+mod storage;
+
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use ring::{constant_time::verify_slices_are_equal, digest};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+use turborepo_api_client::{CachingStatus, CachingStatusResponse};
+use turborepo_cache::signature_authentication::ArtifactSignatureAuthenticator;
+
+pub use self::{
+    s3::{S3Config, S3Storage},
+    storage::{
+        Artifact, ArtifactMetadata, ArtifactStorage, DiskStorage, EventSummary, StorageError,
+    },
+};
+
+pub const DEFAULT_MAX_ARTIFACT_SIZE: usize = 100 * 1024 * 1024;
+
+const ARTIFACT_DURATION_HEADER: &str = "x-artifact-duration";
+const ARTIFACT_TAG_HEADER: &str = "x-artifact-tag";
+
+/// A token and the teams it is allowed to read and write artifacts for.
+///
+/// Requests that don't specify a team are scoped to the first team.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenGrant {
+    pub token: String,
+    pub teams: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TokenGrantError {
+    #[error("expected a token grant in the form <token>:<team>[,<team>...]")]
+    MissingTeams,
+    #[error("token cannot be empty")]
+    EmptyToken,
+    #[error("invalid team \"{0}\", teams may only contain alphanumeric characters, '-' and '_'")]
+    InvalidTeam(String),
+}
+
+impl FromStr for TokenGrant {
+    type Err = TokenGrantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, teams) = s.rsplit_once(':').ok_or(TokenGrantError::MissingTeams)?;
+        if token.is_empty() {
+            return Err(TokenGrantError::EmptyToken);
+        }
+        let teams = teams
+            .split(',')
+            .map(|team| match is_valid_key(team) {
+                true => Ok(team.to_string()),
+                false => Err(TokenGrantError::InvalidTeam(team.to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            token: token.to_string(),
+            teams,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheServerConfig {
+    pub tokens: Vec<TokenGrant>,
+    /// If set, every uploaded artifact must have an `x-artifact-tag` signed
+    /// with this key.
+    pub signature_key: Option<Vec<u8>>,
+    pub max_artifact_size: usize,
+}
+
+impl Default for CacheServerConfig {
+    fn default() -> Self {
+        Self {
+            tokens: Vec::new(),
+            signature_key: None,
+            max_artifact_size: DEFAULT_MAX_ARTIFACT_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEvent {
+    source: String,
+    event: String,
+    #[serde(default)]
+    duration: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamParams {
+    team_id: Option<String>,
+    slug: Option<String>,
+}
+
+// Errors are returned in the same shape as the Vercel API so existing clients
+// can surface them
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", message)
+    }
+
+    fn not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", "artifact not found")
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(err: StorageError) -> Self {
+        error!("artifact storage error: {}", err);
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "unable to access artifact storage",
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({ "code": self.code, "message": self.message })),
+        )
+            .into_response()
+    }
+}
+
+struct CacheServer {
+    storage: Box<dyn ArtifactStorage>,
+    config: CacheServerConfig,
+    // Recording events reads the stored summary and writes it back, so
+    // concurrent updates are serialized to avoid losing any
+    events_lock: Mutex<()>,
+}
+
+impl CacheServer {
+    // Returns the team the request is scoped to
+    fn authorize(&self, headers: &HeaderMap, params: &TeamParams) -> Result<String, ApiError> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "unauthorized",
+                    "missing bearer token",
+                )
+            })?;
+        // Tokens are compared by digest so the comparison takes the same time
+        // regardless of how long either token is
+        let token = digest::digest(&digest::SHA256, token.as_bytes());
+        let grant = self
+            .config
+            .tokens
+            .iter()
+            .find(|grant| {
+                let granted = digest::digest(&digest::SHA256, grant.token.as_bytes());
+                verify_slices_are_equal(granted.as_ref(), token.as_ref()).is_ok()
+            })
+            .ok_or_else(|| ApiError::forbidden("invalid token"))?;
+
+        match params.team_id.as_deref().or(params.slug.as_deref()) {
+            Some(team) if grant.teams.iter().any(|granted| granted == team) => Ok(team.to_string()),
+            Some(team) => Err(ApiError::forbidden(format!(
+                "token does not have access to team {team}"
+            ))),
+            None => Ok(grant.teams[0].clone()),
+        }
+    }
+
+    fn verify_tag(
+        &self,
+        team: &str,
+        hash: &str,
+        body: &[u8],
+        tag: Option<&str>,
+    ) -> Result<(), ApiError> {
+        let Some(signature_key) = &self.config.signature_key else {
+            return Ok(());
+        };
+        let tag = tag.ok_or_else(|| {
+            ApiError::bad_request(format!("{ARTIFACT_TAG_HEADER} header is required"))
+        })?;
+        let authenticator = ArtifactSignatureAuthenticator::new(
+            team.as_bytes().to_vec(),
+            Some(signature_key.clone()),
+        );
+        match authenticator.validate(hash.as_bytes(), body, tag) {
+            Ok(true) => Ok(()),
+            _ => Err(ApiError::bad_request("invalid artifact verification tag")),
+        }
+    }
+}
+
+type SharedServer = Arc<CacheServer>;
+
+// Hashes and teams are used as path segments by storage backends so we limit
+// them to a safe set of characters
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 128
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn validate_hash(hash: &str) -> Result<(), ApiError> {
+    match is_valid_key(hash) {
+        true => Ok(()),
+        false => Err(ApiError::bad_request(format!(
+            "invalid artifact hash {hash}"
+        ))),
+    }
+}
+
+async fn status(
+    State(server): State<SharedServer>,
+    Query(params): Query<TeamParams>,
+    headers: HeaderMap,
+) -> Result<Json<CachingStatusResponse>, ApiError> {
+    server.authorize(&headers, &params)?;
+    Ok(Json(CachingStatusResponse {
+        status: CachingStatus::Enabled,
+    }))
+}
+
+async fn get_artifact(
+    State(server): State<SharedServer>,
+    Path(hash): Path<String>,
+    Query(params): Query<TeamParams>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let team = server.authorize(&headers, &params)?;
+    validate_hash(&hash)?;
+    let artifact = server
+        .storage
+        .get(&team, &hash)
+        .await?
+        .ok_or_else(ApiError::not_found)?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/octet-stream"),
+    );
+    response_headers.insert(
+        ARTIFACT_DURATION_HEADER,
+        HeaderValue::from(artifact.metadata.duration),
+    );
+    if let Some(tag) = artifact
+        .metadata
+        .tag
+        .and_then(|tag| HeaderValue::from_str(&tag).ok())
+    {
+        response_headers.insert(ARTIFACT_TAG_HEADER, tag);
+    }
+    Ok((response_headers, artifact.body).into_response())
+}
+
+async fn artifact_exists(
+    State(server): State<SharedServer>,
+    Path(hash): Path<String>,
+    Query(params): Query<TeamParams>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    let team = server.authorize(&headers, &params)?;
+    validate_hash(&hash)?;
+    match server.storage.exists(&team, &hash).await? {
+        true => Ok(StatusCode::OK),
+        false => Err(ApiError::not_found()),
+    }
+}
+
+async fn put_artifact(
+    State(server): State<SharedServer>,
+    Path(hash): Path<String>,
+    Query(params): Query<TeamParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let team = server.authorize(&headers, &params)?;
+    validate_hash(&hash)?;
+
+    let duration = match headers.get(ARTIFACT_DURATION_HEADER) {
+        Some(duration) => duration
+            .to_str()
+            .ok()
+            .and_then(|duration| duration.parse().ok())
+            .ok_or_else(|| {
+                ApiError::bad_request(format!("invalid {ARTIFACT_DURATION_HEADER} header"))
+            })?,
+        None => 0,
+    };
+    let tag = headers
+        .get(ARTIFACT_TAG_HEADER)
+        .map(|tag| {
+            tag.to_str()
+                .map_err(|_| ApiError::bad_request(format!("invalid {ARTIFACT_TAG_HEADER} header")))
+        })
+        .transpose()?;
+    server.verify_tag(&team, &hash, &body, tag)?;
+
+    debug!(
+        "storing artifact {} for {} ({} bytes)",
+        hash,
+        team,
+        body.len()
+    );
+    server
+        .storage
+        .put(
+            &team,
+            &hash,
+            Artifact {
+                body,
+                metadata: ArtifactMetadata {
+                    duration,
+                    tag: tag.map(|tag| tag.to_string()),
+                },
+            },
+        )
+        .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "urls": [format!("{team}/{hash}")] })),
+    )
+        .into_response())
+}
+
+async fn record_events(
+    State(server): State<SharedServer>,
+    Query(params): Query<TeamParams>,
+    headers: HeaderMap,
+    Json(events): Json<Vec<CacheEvent>>,
+) -> Result<StatusCode, ApiError> {
+    let team = server.authorize(&headers, &params)?;
+
+    let mut summary = EventSummary::default();
+    for event in events {
+        let is_hit = match event.event.to_ascii_uppercase().as_str() {
+            "HIT" => true,
+            "MISS" => false,
+            other => return Err(ApiError::bad_request(format!("unknown event {other}"))),
+        };
+        let counter = match (event.source.to_ascii_uppercase().as_str(), is_hit) {
+            ("LOCAL", true) => &mut summary.local_hits,
+            ("LOCAL", false) => &mut summary.local_misses,
+            ("REMOTE", true) => &mut summary.remote_hits,
+            ("REMOTE", false) => &mut summary.remote_misses,
+            (other, _) => return Err(ApiError::bad_request(format!("unknown source {other}"))),
+        };
+        *counter += 1;
+        if is_hit {
+            summary.time_saved += event.duration;
+        }
+    }
+
+    let _guard = server.events_lock.lock().await;
+    let mut team_summary = server.storage.get_events(&team).await?;
+    team_summary.local_hits += summary.local_hits;
+    team_summary.local_misses += summary.local_misses;
+    team_summary.remote_hits += summary.remote_hits;
+    team_summary.remote_misses += summary.remote_misses;
+    team_summary.time_saved += summary.time_saved;
+    server.storage.put_events(&team, team_summary).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_events(
+    State(server): State<SharedServer>,
+    Query(params): Query<TeamParams>,
+    headers: HeaderMap,
+) -> Result<Json<EventSummary>, ApiError> {
+    let team = server.authorize(&headers, &params)?;
+    Ok(Json(server.storage.get_events(&team).await?))
+}
+
+pub fn cache_router(storage: impl ArtifactStorage, config: CacheServerConfig) -> Router {
+    let max_artifact_size = config.max_artifact_size;
+    let server = Arc::new(CacheServer {
+        storage: Box::new(storage),
+        config,
+        events_lock: Mutex::default(),
+    });
+
+    Router::new()
+        .route("/v8/artifacts/status", get(status))
+        .route("/v8/artifacts/events", get(get_events).post(record_events))
+        .route(
+            "/v8/artifacts/:hash",
+            get(get_artifact).head(artifact_exists).put(put_artifact),
+        )
+        .layer(DefaultBodyLimit::max(max_artifact_size))
+        .with_state(server)
+}
+
+pub async fn start_cache_server(
+    addr: SocketAddr,
+    storage: impl ArtifactStorage,
+    config: CacheServerConfig,
+) -> Result<()> {
+    let app = cache_router(storage, config);
+    axum_server::bind(addr)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+    use turborepo_cache::signature_authentication::ArtifactSignatureAuthenticator;
+
+    use super::*;
+
+    const TOKEN: &str = "secret";
+
+    fn config() -> CacheServerConfig {
+        CacheServerConfig {
+            tokens: vec![
+                "secret:team_a,team_b".parse().unwrap(),
+                "other:team_c".parse().unwrap(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    async fn send(
+        router: &Router,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        headers: &[(&str, &str)],
+        body: impl Into<Body>,
+    ) -> (StatusCode, HeaderMap, Bytes) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(body.into()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, headers, body)
+    }
+
+    #[test]
+    fn test_parse_token_grant() {
+        assert_eq!(
+            "secret:team_a,team-b".parse(),
+            Ok(TokenGrant {
+                token: "secret".to_string(),
+                teams: vec!["team_a".to_string(), "team-b".to_string()],
+            })
+        );
+        assert_eq!(
+            "secret".parse::<TokenGrant>(),
+            Err(TokenGrantError::MissingTeams)
+        );
+        assert_eq!(
+            ":team".parse::<TokenGrant>(),
+            Err(TokenGrantError::EmptyToken)
+        );
+        assert_eq!(
+            "secret:../team".parse::<TokenGrant>(),
+            Err(TokenGrantError::InvalidTeam("../team".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_artifact_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = cache_router(DiskStorage::new(dir.path()), config());
+
+        let (status, _, _) = send(&router, "GET", "/v8/artifacts/abc", Some(TOKEN), &[], "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _, _) = send(&router, "HEAD", "/v8/artifacts/abc", Some(TOKEN), &[], "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _, body) = send(
+            &router,
+            "PUT",
+            "/v8/artifacts/abc",
+            Some(TOKEN),
+            &[(ARTIFACT_DURATION_HEADER, "350")],
+            "artifact",
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body, r#"{"urls":["team_a/abc"]}"#);
+
+        let (status, headers, body) =
+            send(&router, "GET", "/v8/artifacts/abc", Some(TOKEN), &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "artifact");
+        assert_eq!(headers[ARTIFACT_DURATION_HEADER], "350");
+        assert!(headers.get(ARTIFACT_TAG_HEADER).is_none());
+
+        let (status, _, _) = send(&router, "HEAD", "/v8/artifacts/abc", Some(TOKEN), &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_team_scoping() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = cache_router(DiskStorage::new(dir.path()), config());
+
+        let (status, _, _) = send(
+            &router,
+            "PUT",
+            "/v8/artifacts/abc?teamId=team_b",
+            Some(TOKEN),
+            &[],
+            "artifact",
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        // Artifacts are only visible to the team they were uploaded for
+        let (status, _, _) = send(
+            &router,
+            "GET",
+            "/v8/artifacts/abc?slug=team_b",
+            Some(TOKEN),
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = send(&router, "GET", "/v8/artifacts/abc", Some(TOKEN), &[], "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _, _) = send(
+            &router,
+            "GET",
+            "/v8/artifacts/abc?teamId=team_b",
+            Some("other"),
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _, _) = send(
+            &router,
+            "GET",
+            "/v8/artifacts/abc?teamId=team_b",
+            Some("secret-but-longer"),
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _, _) = send(
+            &router,
+            "GET",
+            "/v8/artifacts/abc?teamId=team_b",
+            Some("wrong"),
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _, _) = send(
+            &router,
+            "GET",
+            "/v8/artifacts/abc?teamId=team_b",
+            None,
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _, _) =
+            send(&router, "GET", "/v8/artifacts/status", Some(TOKEN), &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_signature_verification() {
+        let dir = tempfile::tempdir().unwrap();
+        let signature_key = b"signature-key".to_vec();
+        let router = cache_router(
+            DiskStorage::new(dir.path()),
+            CacheServerConfig {
+                signature_key: Some(signature_key.clone()),
+                ..config()
+            },
+        );
+
+        let (status, _, _) = send(
+            &router,
+            "PUT",
+            "/v8/artifacts/abc",
+            Some(TOKEN),
+            &[],
+            "artifact",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let wrong_team_tag =
+            ArtifactSignatureAuthenticator::new(b"team_b".to_vec(), Some(signature_key.clone()))
+                .generate_tag(b"abc", b"artifact")
+                .unwrap();
+        let (status, _, _) = send(
+            &router,
+            "PUT",
+            "/v8/artifacts/abc",
+            Some(TOKEN),
+            &[(ARTIFACT_TAG_HEADER, &wrong_team_tag)],
+            "artifact",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let tag = ArtifactSignatureAuthenticator::new(b"team_a".to_vec(), Some(signature_key))
+            .generate_tag(b"abc", b"artifact")
+            .unwrap();
+        let (status, _, _) = send(
+            &router,
+            "PUT",
+            "/v8/artifacts/abc",
+            Some(TOKEN),
+            &[(ARTIFACT_TAG_HEADER, &tag)],
+            "artifact",
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let (_, headers, _) = send(&router, "GET", "/v8/artifacts/abc", Some(TOKEN), &[], "").await;
+        assert_eq!(headers[ARTIFACT_TAG_HEADER], tag.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = cache_router(DiskStorage::new(dir.path()), config());
+
+        let events = json!([
+            { "sessionId": "session", "source": "LOCAL", "event": "hit", "hash": "a", "duration": 100 },
+            { "sessionId": "session", "source": "REMOTE", "event": "HIT", "hash": "b", "duration": 50 },
+            { "sessionId": "session", "source": "REMOTE", "event": "MISS", "hash": "c" },
+        ]);
+        for _ in 0..2 {
+            let (status, _, _) = send(
+                &router,
+                "POST",
+                "/v8/artifacts/events",
+                Some(TOKEN),
+                &[("content-type", "application/json")],
+                events.to_string(),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let (_, _, body) = send(&router, "GET", "/v8/artifacts/events", Some(TOKEN), &[], "").await;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            json!({
+                "localHits": 2,
+                "localMisses": 0,
+                "remoteHits": 2,
+                "remoteMisses": 2,
+                "timeSaved": 300
+            })
+        );
+
+        // Events are persisted by the storage backend
+        let router = cache_router(DiskStorage::new(dir.path()), config());
+        let (_, _, body) = send(&router, "GET", "/v8/artifacts/events", Some(TOKEN), &[], "").await;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["localHits"],
+            2
+        );
+
+        let (_, _, body) = send(
+            &router,
+            "GET",
+            "/v8/artifacts/events?teamId=team_b",
+            Some(TOKEN),
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["localHits"],
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = cache_router(DiskStorage::new(dir.path()), config());
+        let (status, _, _) = send(
+            &router,
+            "PUT",
+            "/v8/artifacts/..%2F..%2Fescape",
+            Some(TOKEN),
+            &[],
+            "artifact",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,220 @@
+use std::{fs, io, path::PathBuf};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid artifact metadata: {0}")]
+    Metadata(#[from] serde_json::Error),
+    #[error("request to storage failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("storage responded with {status}: {body}")]
+    UnexpectedResponse {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    #[error("invalid artifact metadata header {0}")]
+    InvalidHeader(&'static str),
+}
+
+/// Everything the cache protocol stores alongside an artifact body
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactMetadata {
+    /// Time in milliseconds it took to produce the artifact
+    pub duration: u64,
+    /// Signature tag supplied by the client, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// Aggregated cache usage reported by clients via `/v8/artifacts/events`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSummary {
+    pub local_hits: u64,
+    pub local_misses: u64,
+    pub remote_hits: u64,
+    pub remote_misses: u64,
+    /// Total milliseconds saved by cache hits
+    pub time_saved: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub body: Bytes,
+    pub metadata: ArtifactMetadata,
+}
+
+/// A place for the cache server to keep artifacts.
+///
+/// Artifacts are namespaced by team, callers are responsible for validating
+/// that `team` and `hash` are safe to use as path segments.
+#[async_trait]
+pub trait ArtifactStorage: Send + Sync + 'static {
+    async fn get(&self, team: &str, hash: &str) -> Result<Option<Artifact>, StorageError>;
+
+    async fn exists(&self, team: &str, hash: &str) -> Result<bool, StorageError>;
+
+    async fn put(&self, team: &str, hash: &str, artifact: Artifact) -> Result<(), StorageError>;
+
+    /// Returns the cache usage recorded for `team`, which is empty if nothing
+    /// has been recorded yet.
+    async fn get_events(&self, team: &str) -> Result<EventSummary, StorageError>;
+
+    async fn put_events(&self, team: &str, events: EventSummary) -> Result<(), StorageError>;
+}
+
+/// Name events are stored under within a team. Hashes can't contain a '.' so
+/// this never collides with an artifact or its metadata.
+pub(crate) const EVENTS_KEY: &str = ".events.json";
+
+/// Stores artifacts in a local directory as `<root>/<team>/<hash>` with
+/// metadata written next to the body in `<hash>.json`. Events are kept in
+/// `<root>/<team>/.events.json`.
+#[derive(Debug, Clone)]
+pub struct DiskStorage {
+    root: PathBuf,
+}
+
+impl DiskStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn artifact_path(&self, team: &str, hash: &str) -> PathBuf {
+        self.root.join(team).join(hash)
+    }
+
+    fn metadata_path(&self, team: &str, hash: &str) -> PathBuf {
+        self.root.join(team).join(format!("{hash}.json"))
+    }
+}
+
+#[async_trait]
+impl ArtifactStorage for DiskStorage {
+    async fn get(&self, team: &str, hash: &str) -> Result<Option<Artifact>, StorageError> {
+        let body = match tokio::fs::read(self.artifact_path(team, hash)).await {
+            Ok(body) => body,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let metadata = match tokio::fs::read(self.metadata_path(team, hash)).await {
+            Ok(metadata) => serde_json::from_slice(&metadata)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => ArtifactMetadata::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(Artifact {
+            body: body.into(),
+            metadata,
+        }))
+    }
+
+    async fn exists(&self, team: &str, hash: &str) -> Result<bool, StorageError> {
+        Ok(tokio::fs::try_exists(self.artifact_path(team, hash)).await?)
+    }
+
+    async fn put(&self, team: &str, hash: &str, artifact: Artifact) -> Result<(), StorageError> {
+        let artifact_path = self.artifact_path(team, hash);
+        let metadata_path = self.metadata_path(team, hash);
+        let metadata = serde_json::to_vec(&artifact.metadata)?;
+        // Writes happen on a blocking thread since we want to write to a temporary
+        // file and rename it so concurrent readers never observe a partial artifact
+        tokio::task::spawn_blocking(move || -> Result<(), StorageError> {
+            let dir = artifact_path
+                .parent()
+                .expect("artifact path always has a team directory");
+            fs::create_dir_all(dir)?;
+            write_atomic(&metadata_path, &metadata)?;
+            write_atomic(&artifact_path, &artifact.body)?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    }
+
+    async fn get_events(&self, team: &str) -> Result<EventSummary, StorageError> {
+        match tokio::fs::read(self.root.join(team).join(EVENTS_KEY)).await {
+            Ok(events) => Ok(serde_json::from_slice(&events)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(EventSummary::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put_events(&self, team: &str, events: EventSummary) -> Result<(), StorageError> {
+        let dir = self.root.join(team);
+        let events = serde_json::to_vec(&events)?;
+        tokio::task::spawn_blocking(move || -> Result<(), StorageError> {
+            fs::create_dir_all(&dir)?;
+            write_atomic(&dir.join(EVENTS_KEY), &events)?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    }
+}
+
+fn write_atomic(path: &std::path::Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    io::Write::write_all(&mut file, contents)?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_disk_storage_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = DiskStorage::new(dir.path());
+        let artifact = Artifact {
+            body: Bytes::from_static(b"artifact body"),
+            metadata: ArtifactMetadata {
+                duration: 42,
+                tag: Some("tag".to_string()),
+            },
+        };
+
+        assert!(!storage.exists("team", "abc").await.unwrap());
+        assert_eq!(storage.get("team", "abc").await.unwrap(), None);
+
+        storage.put("team", "abc", artifact.clone()).await.unwrap();
+
+        assert!(storage.exists("team", "abc").await.unwrap());
+        assert_eq!(storage.get("team", "abc").await.unwrap(), Some(artifact));
+        assert!(!storage.exists("other-team", "abc").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_disk_storage_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = DiskStorage::new(dir.path());
+        let events = EventSummary {
+            local_hits: 1,
+            time_saved: 100,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            storage.get_events("team").await.unwrap(),
+            EventSummary::default()
+        );
+        storage.put_events("team", events).await.unwrap();
+        assert_eq!(storage.get_events("team").await.unwrap(), events);
+        // Events survive the storage being recreated
+        let storage = DiskStorage::new(dir.path());
+        assert_eq!(storage.get_events("team").await.unwrap(), events);
+        assert_eq!(
+            storage.get_events("other-team").await.unwrap(),
+            EventSummary::default()
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod cache;
+
 use std::net::SocketAddr;
 
 use anyhow::Result;
@@ -58,8 +58,8 @@ use thiserror::Error;
 
 #[cfg(feature = "walk")]
 pub use crate::walk::{
-    FileIterator, FilterTarget, FilterTree, LinkBehavior, Negation, Walk, WalkBehavior, WalkEntry,
-    WalkError,
+    DirectoryMatcher, FileIterator, FilterTarget, FilterTree, LinkBehavior, Negation, Walk,
+    WalkBehavior, WalkEntry, WalkError,
 };
 pub use crate::{
     capture::MatchedText,
@@ -922,6 +922,22 @@ impl<'t> Glob<'t> {
         walk::walk(self, directory, behavior)
     }
 
+    /// Gets a [`DirectoryMatcher`] that determines whether directory trees can
+    /// contain matches for this [`Glob`].
+    ///
+    /// This is useful when matching many [`Glob`]s against a single traversal
+    /// of a directory tree rather than [walking][`Glob::walk`] each [`Glob`].
+    ///
+    /// [`DirectoryMatcher`]: crate::DirectoryMatcher
+    /// [`Glob`]: crate::Glob
+    /// [`Glob::walk`]: crate::Glob::walk
+    #[cfg(feature = "walk")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "walk")))]
+    pub fn directory_matcher(&self) -> DirectoryMatcher {
+        DirectoryMatcher::new(self.tree.as_ref().tokens())
+            .expect("failed to compile glob sub-expressions")
+    }
+
     /// Gets **non-error** [`Diagnostic`]s.
     ///
     /// This function requires a receiving [`Glob`] and so does not report
@@ -2044,6 +2060,32 @@ mod tests {
         assert_eq!(format!("{}", glob), "");
     }
 
+    #[cfg(feature = "walk")]
+    #[test]
+    fn directory_matcher_prunes_unmatched_components() {
+        let matcher = Glob::new("/root/packages/*/dist/**")
+            .unwrap()
+            .directory_matcher();
+
+        assert!(matcher.may_contain_matches(Path::new("/root")));
+        assert!(matcher.may_contain_matches(Path::new("/root/packages/a")));
+        assert!(matcher.may_contain_matches(Path::new("/root/packages/a/dist/b/c")));
+        assert!(!matcher.may_contain_matches(Path::new("/root/apps")));
+        assert!(!matcher.may_contain_matches(Path::new("/root/packages/a/src")));
+    }
+
+    #[cfg(feature = "walk")]
+    #[test]
+    fn directory_matcher_prunes_below_bounded_glob() {
+        let matcher = Glob::new("/root/*/package.json")
+            .unwrap()
+            .directory_matcher();
+
+        assert!(matcher.may_contain_matches(Path::new("/root/a")));
+        assert!(!matcher.may_contain_matches(Path::new("/root/a/package.json")));
+        assert!(!matcher.may_contain_matches(Path::new("/root/a/node_modules")));
+    }
+
     #[test]
     fn partition_glob_with_variant_expression_text() {
         let (prefix, glob) = Glob::new("**/file.ext").unwrap().partition();
@@ -595,20 +595,7 @@ impl<'g> Walk<'g> {
         I: IntoIterator<Item = &'t Token<'t>>,
         I::IntoIter: Clone,
     {
-        let mut regexes = Vec::new();
-        for component in token::components(tokens) {
-            if component
-                .tokens()
-                .iter()
-                .any(|token| token.has_component_boundary())
-            {
-                // Stop at component boundaries, such as tree wildcards or any
-                // boundary within a group token.
-                break;
-            }
-            regexes.push(Glob::compile(component.tokens().iter().copied())?);
-        }
-        Ok(regexes)
+        DirectoryMatcher::new(tokens).map(|matcher| matcher.components)
     }
 
     /// Clones any borrowed data into an owning instance.
@@ -903,6 +890,80 @@ impl<'e> WalkEntry<'e> {
     }
 }
 
+/// Matches directory paths against the leading components of a [`Glob`].
+///
+/// A `DirectoryMatcher` applies the same pruning as [`Walk`], but can be used
+/// by traversals that are not driven by [`Glob::walk`], such as a single
+/// traversal shared by many [`Glob`]s. Like [`Walk`], only components up to the
+/// first component boundary (e.g., a tree wildcard) are considered.
+///
+/// [`Glob`]: crate::Glob
+/// [`Glob::walk`]: crate::Glob::walk
+/// [`Walk`]: crate::Walk
+#[cfg_attr(docsrs, doc(cfg(feature = "walk")))]
+#[derive(Clone, Debug)]
+pub struct DirectoryMatcher {
+    components: Vec<Regex>,
+    // `true` if every component of the glob has a pattern, in which case paths
+    // with more components than the glob can never match.
+    is_bounded: bool,
+}
+
+impl DirectoryMatcher {
+    pub(crate) fn new<'t, I>(tokens: I) -> Result<Self, CompileError>
+    where
+        I: IntoIterator<Item = &'t Token<'t>>,
+        I::IntoIter: Clone,
+    {
+        let mut components = Vec::new();
+        let mut is_bounded = true;
+        for component in token::components(tokens) {
+            if component
+                .tokens()
+                .iter()
+                .any(|token| token.has_component_boundary())
+            {
+                is_bounded = false;
+                break;
+            }
+            components.push(Glob::compile(component.tokens().iter().copied())?);
+        }
+        Ok(DirectoryMatcher {
+            components,
+            is_bounded,
+        })
+    }
+
+    /// Returns `false` if neither the directory at `path` nor any path beneath
+    /// it can match the [`Glob`].
+    ///
+    /// Paths are compared component-wise from their root, so `path` must be
+    /// expressed the same way as the [`Glob`] (e.g., absolute paths for an
+    /// absolute [`Glob`]).
+    ///
+    /// [`Glob`]: crate::Glob
+    pub fn may_contain_matches(&self, path: &Path) -> bool {
+        let mut depth = 0;
+        for component in path
+            .components()
+            .filter(|c| !matches!(c, Component::RootDir))
+            .filter_map(|component| match component {
+                Component::Normal(component) => Some(CandidatePath::from(component)),
+                Component::Prefix(component) => Some(CandidatePath::from(component.as_os_str())),
+                _ => None,
+            })
+        {
+            match self.components.get(depth) {
+                Some(pattern) if !pattern.is_match(component.as_ref()) => return false,
+                Some(_) => {}
+                None => break,
+            }
+            depth += 1;
+        }
+        !self.is_bounded || depth < self.components.len()
+    }
+}
+
 pub fn walk<'g>(
     glob: &'g Glob<'_>,
     directory: impl AsRef<Path>,
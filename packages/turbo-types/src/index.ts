@@ -3,4 +3,5 @@ export type {
   Schema,
   Pipeline,
   RemoteCache,
+  UpdatePolicy,
 } from "./types/config";
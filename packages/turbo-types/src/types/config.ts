@@ -110,6 +110,14 @@ export interface RootSchema extends BaseSchema {
    * @default {}
    */
   remoteCache?: RemoteCache;
+
+  /**
+   * Configuration options that control which versions of turbo can run in this
+   * repository and how turbo checks for updates.
+   *
+   * @default {}
+   */
+  updatePolicy?: UpdatePolicy;
 }
 
 export interface Pipeline {
@@ -262,6 +270,39 @@ export interface RemoteCache {
   signature?: boolean;
 }
 
+export interface UpdatePolicy {
+  /**
+   * An npm style range of the turbo versions that may be installed locally,
+   * e.g. "^1.10.0" or ">=1.10.0 <1.11.0". Any version is allowed when unset.
+   *
+   * @default null
+   */
+  turboVersion?: null | string;
+
+  /**
+   * What to do when the local turbo is outside of `turboVersion`, or
+   * `turboVersion` is not a valid range. "warn" prints a warning and continues,
+   * "error" exits without running the command.
+   *
+   * @default "warn"
+   */
+  onMismatch?: "warn" | "error";
+
+  /**
+   * The npm registry to check for new turbo versions, e.g. a private mirror.
+   *
+   * @default null
+   */
+  registry?: null | string;
+
+  /**
+   * Set to `false` to never show update notifications in this repository.
+   *
+   * @default true
+   */
+  notifyUpdates?: boolean;
+}
+
 export type OutputMode =
   | "full"
   | "hash-only"